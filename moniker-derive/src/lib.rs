@@ -1,43 +1,97 @@
 #![recursion_limit = "128"]
 
+#[macro_use]
 extern crate quote;
 extern crate syn;
 #[macro_use]
 extern crate synstructure;
 extern crate proc_macro2;
 
-use synstructure::{BindStyle, Structure};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::Token;
+use synstructure::{AddBounds, BindStyle, Structure};
+
+decl_derive!([BoundTerm, attributes(moniker)] => bound_term_derive);
+
+fn bound_term_derive(s: Structure) -> proc_macro2::TokenStream {
+    try_bound_term_derive(s).unwrap_or_else(compile_error)
+}
+
+fn try_bound_term_derive(s: Structure) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let options = parse_container_options(&s.ast().attrs)?;
+    let mirror = s.ast().ident.clone();
+    let remote_ast = options.remote.as_ref().map(|_| remote_ast(s.ast()));
+    let mut s = match remote_ast {
+        Some(ref ast) => Structure::try_new(ast)?,
+        None => s,
+    };
+    add_bounds(&mut s, options.bound.clone());
+    filter_ignored(&mut s)?;
+    if options.transparent {
+        check_transparent(&s)?;
+    }
+    span_bindings(&mut s);
+    let ident_ty = options.ident_ty();
+    let import_moniker = import_moniker(options.krate.clone());
+    let bound_term = quote! { moniker::BoundTerm::<#ident_ty> };
 
-decl_derive!([BoundTerm] => bound_term_derive);
+    let variant_options = s
+        .variants()
+        .iter()
+        .map(|v| parse_variant_options(&s, v))
+        .collect::<Result<Vec<_>, _>>()?;
 
-fn bound_term_derive(mut s: Structure) -> proc_macro2::TokenStream {
     s.bind_with(|_| BindStyle::Ref);
     let term_eq_body = {
-        let body = s.variants().iter().fold(quote!(), |acc, v| {
+        let variants = <_>::zip(s.variants().iter(), &variant_options);
+        let body = variants.fold(quote!(), |acc, (v, variant_options)| {
             // Create two sets of bindings, one for the lhs, and another for the rhs
             let mut lhs = v.clone();
             let mut rhs = v.clone();
-            lhs.binding_name(|_, i| {
-                syn::Ident::new(
-                    &format!("__binding_lhs_{}", i),
-                    proc_macro2::Span::call_site(),
-                )
+            lhs.binding_name(|field, i| {
+                syn::Ident::new(&format!("__binding_lhs_{}", i), field.ty.span())
             });
-            rhs.binding_name(|_, i| {
-                syn::Ident::new(
-                    &format!("__binding_rhs_{}", i),
-                    proc_macro2::Span::call_site(),
-                )
+            rhs.binding_name(|field, i| {
+                syn::Ident::new(&format!("__binding_rhs_{}", i), field.ty.span())
             });
 
             let lhs_pat = lhs.pat();
             let rhs_pat = rhs.pat();
 
+            if variant_options.is_ac() {
+                let lhs_fields = ac_fields(&lhs, variant_options);
+                let rhs_fields = ac_fields(&rhs, variant_options);
+                let arm_body = if variant_options.commutative {
+                    quote! { moniker::unordered_term_eq::<#ident_ty, _>(#lhs_fields, #rhs_fields) }
+                } else {
+                    quote! {{
+                        let __lhs_fields = #lhs_fields;
+                        let __rhs_fields = #rhs_fields;
+                        __lhs_fields.len() == __rhs_fields.len()
+                            && <_>::zip(__lhs_fields.iter(), __rhs_fields.iter())
+                                .all(|(__lhs, __rhs)| #bound_term::term_eq(*__lhs, *__rhs))
+                    }}
+                };
+                return quote! { #acc (&#lhs_pat, &#rhs_pat) => #arm_body, };
+            }
+
             // build up the alpha-equality expression for this variant
             let arm_body = <_>::zip(lhs.bindings().iter(), rhs.bindings()).fold(
                 quote!(true),
                 |acc, (lhs, rhs)| {
-                    quote! { #acc && moniker::BoundTerm::<String>::term_eq(#lhs, #rhs) }
+                    if is_constant(lhs.ast()) {
+                        return quote! { #acc && #lhs == #rhs };
+                    }
+                    if is_unordered(lhs.ast()) {
+                        let term_eq = quote_spanned! { field_span(lhs) =>
+                            moniker::unordered_term_eq::<#ident_ty, _>(#lhs, #rhs)
+                        };
+                        return quote! { #acc && #term_eq };
+                    }
+                    let term_eq = field_fn(lhs, &bound_term, "term_eq", "term_eq");
+                    let term_eq = quote_spanned! { field_span(lhs) => #term_eq(#lhs, #rhs) };
+                    quote! { #acc && #term_eq }
                 },
             );
 
@@ -51,78 +105,407 @@ fn bound_term_derive(mut s: Structure) -> proc_macro2::TokenStream {
         }
     };
 
+    let term_eq_check_body = {
+        let is_enum = match s.ast().data {
+            syn::Data::Enum(_) => true,
+            syn::Data::Struct(_) | syn::Data::Union(_) => false,
+        };
+        let alpha_mismatch = quote! { moniker::AlphaMismatch::<#ident_ty> };
+        let value_mismatch = quote! {
+            #alpha_mismatch::new(moniker::Mismatched::Value, moniker::Mismatched::Value)
+        };
+
+        let variants = <_>::zip(s.variants().iter(), &variant_options);
+        let body = variants.fold(quote!(), |acc, (v, variant_options)| {
+            // Create two sets of bindings, one for the lhs, and another for the rhs
+            let mut lhs = v.clone();
+            let mut rhs = v.clone();
+            lhs.binding_name(|field, i| {
+                syn::Ident::new(&format!("__binding_lhs_{}", i), field.ty.span())
+            });
+            rhs.binding_name(|field, i| {
+                syn::Ident::new(&format!("__binding_rhs_{}", i), field.ty.span())
+            });
+
+            let lhs_pat = lhs.pat();
+            let rhs_pat = rhs.pat();
+
+            if variant_options.is_ac() {
+                let lhs_fields = ac_fields(&lhs, variant_options);
+                let rhs_fields = ac_fields(&rhs, variant_options);
+                let label = v.ast().ident.to_string();
+                let arm_body = if variant_options.commutative {
+                    quote! {
+                        if moniker::unordered_term_eq::<#ident_ty, _>(#lhs_fields, #rhs_fields) {
+                            Ok(())
+                        } else {
+                            Err(#value_mismatch.within(moniker::PathSegment::Field(#label)))
+                        }
+                    }
+                } else {
+                    // Report the position of the first mismatch in the
+                    // flattened fields
+                    quote! {{
+                        let __lhs_fields = #lhs_fields;
+                        let __rhs_fields = #rhs_fields;
+                        if __lhs_fields.len() != __rhs_fields.len() {
+                            return Err(#alpha_mismatch::new(
+                                moniker::Mismatched::Len(__lhs_fields.len()),
+                                moniker::Mismatched::Len(__rhs_fields.len()),
+                            ).within(moniker::PathSegment::Field(#label)));
+                        }
+                        let __fields = <_>::zip(__lhs_fields.iter(), __rhs_fields.iter());
+                        for (__index, (__lhs, __rhs)) in __fields.enumerate() {
+                            #bound_term::term_eq_check(*__lhs, *__rhs).map_err(|__mismatch| {
+                                __mismatch
+                                    .within(moniker::PathSegment::Index(__index))
+                                    .within(moniker::PathSegment::Field(#label))
+                            })?;
+                        }
+                        Ok(())
+                    }}
+                };
+                return quote! { #acc (&#lhs_pat, &#rhs_pat) => #arm_body, };
+            }
+
+            // check each field in turn, recording the field in the path to the
+            // first mismatch that is found
+            let arm_body = <_>::zip(lhs.bindings().iter(), rhs.bindings()).fold(
+                quote!(),
+                |acc, (lhs, rhs)| {
+                    let check = if is_constant(lhs.ast()) {
+                        quote! { if #lhs == #rhs { Ok(()) } else { Err(#value_mismatch) } }
+                    } else if is_unordered(lhs.ast()) {
+                        quote_spanned! { field_span(lhs) =>
+                            if moniker::unordered_term_eq::<#ident_ty, _>(#lhs, #rhs) {
+                                Ok(())
+                            } else {
+                                Err(#value_mismatch)
+                            }
+                        }
+                    } else {
+                        let term_eq_check =
+                            field_fn(lhs, &bound_term, "term_eq_check", "term_eq_check");
+                        quote_spanned! { field_span(lhs) => #term_eq_check(#lhs, #rhs) }
+                    };
+
+                    // Transparent types are invisible in the path
+                    if options.transparent {
+                        return quote! { #acc (#check)?; };
+                    }
+                    let label = field_label(v, lhs, is_enum);
+                    quote! {
+                        #acc (#check).map_err(|__mismatch| {
+                            __mismatch.within(moniker::PathSegment::Field(#label))
+                        })?;
+                    }
+                },
+            );
+
+            quote! { #acc (&#lhs_pat, &#rhs_pat) => { #arm_body Ok(()) }, }
+        });
+
+        // Avoid the 'unreachable match' warning for types with zero or one variants
+        match s.variants().len() {
+            0 | 1 => body,
+            _ => {
+                let ident = &s.ast().ident;
+                let term_ty = match options.remote {
+                    None => quote! { Self },
+                    Some(_) => {
+                        let (_, ty_generics, _) = s.ast().generics.split_for_impl();
+                        quote! { __Remote #ty_generics }
+                    },
+                };
+                let variant_names = s.variants().iter().fold(quote!(), |acc, v| {
+                    let variant = v.ast().ident;
+                    let name = variant.to_string();
+                    quote! { #acc #ident::#variant { .. } => #name, }
+                });
+                quote! {
+                    #body
+                    (__lhs, __rhs) => {
+                        let __variant_name = |__term: &#term_ty| match *__term { #variant_names };
+                        Err(#alpha_mismatch::new(
+                            moniker::Mismatched::Variant(__variant_name(__lhs)),
+                            moniker::Mismatched::Variant(__variant_name(__rhs)),
+                        ))
+                    },
+                }
+            },
+        }
+    };
+
+    // Constant fields contain no variables or binders
+    s.filter(|bi| !is_constant(bi.ast()));
+
     s.bind_with(|_| BindStyle::RefMut);
     let close_term_body = s.each(|bi| {
-        quote!{ moniker::BoundTerm::<String>::close_term(#bi, __state, __on_free); }
+        let close_term = field_fn(bi, &bound_term, "close_term", "close_term");
+        quote_spanned! { field_span(bi) => #close_term(#bi, __state, __on_free); }
     });
     let open_term_body = s.each(|bi| {
-        quote!{ moniker::BoundTerm::<String>::open_term(#bi, __state, __on_bound); }
+        let open_term = field_fn(bi, &bound_term, "open_term", "open_term");
+        quote_spanned! { field_span(bi) => #open_term(#bi, __state, __on_bound); }
     });
 
     s.bind_with(|_| BindStyle::Ref);
     let visit_vars_body = s.each(|bi| {
-        quote!{ moniker::BoundTerm::<String>::visit_vars(#bi, __on_var); }
+        let visit_vars = field_fn(bi, &bound_term, "visit_vars", "visit_term_vars");
+        quote_spanned! { field_span(bi) => #visit_vars(#bi, __on_var); }
+    });
+    let try_visit_vars_body = s.each(|bi| {
+        let try_visit_vars = field_fn(bi, &bound_term, "try_visit_vars", "try_visit_term_vars");
+        quote_spanned! { field_span(bi) => #try_visit_vars(#bi, __on_var)?; }
     });
     s.bind_with(|_| BindStyle::RefMut);
     let visit_mut_vars_body = s.each(|bi| {
-        quote!{ moniker::BoundTerm::<String>::visit_mut_vars(#bi, __on_var); }
+        let visit_mut_vars = field_fn(bi, &bound_term, "visit_mut_vars", "visit_mut_term_vars");
+        quote_spanned! { field_span(bi) => #visit_mut_vars(#bi, __on_var); }
+    });
+    s.bind_with(|_| BindStyle::Ref);
+    let visit_vars_with_binders_body = s.each(|bi| {
+        let visit_vars_with_binders = field_fn(
+            bi,
+            &bound_term,
+            "visit_vars_with_binders",
+            "visit_term_vars_with_binders",
+        );
+        quote_spanned! { field_span(bi) =>
+            #visit_vars_with_binders(#bi, __binders, __on_var);
+        }
     });
+    let visit_nodes_body =
+        visit_nodes_body(&s, options.transparent, &bound_term, "visit_term_nodes");
+    let visit_scopes_body =
+        visit_scopes_body(&s, options.transparent, &bound_term, "visit_term_scopes");
+
+    if let Some(remote) = options.remote {
+        let (_, ty_generics, _) = s.ast().generics.split_for_impl();
+        let fns = quote! {
+            pub fn term_eq(__self: &__Remote #ty_generics, other: &__Remote #ty_generics) -> bool {
+                moniker::maybe_grow_stack(|| match (__self, other) { #term_eq_body })
+            }
+
+            pub fn term_eq_check(
+                __self: &__Remote #ty_generics,
+                other: &__Remote #ty_generics,
+            ) -> Result<(), moniker::AlphaMismatch<#ident_ty>> {
+                moniker::maybe_grow_stack(|| match (__self, other) { #term_eq_check_body })
+            }
+
+            pub fn close_term(
+                __self: &mut __Remote #ty_generics,
+                __state: moniker::ScopeState,
+                __on_free: &impl moniker::OnFreeFn<#ident_ty>,
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #close_term_body })
+            }
+
+            pub fn open_term(
+                __self: &mut __Remote #ty_generics,
+                __state: moniker::ScopeState,
+                __on_bound: &impl moniker::OnBoundFn<#ident_ty>,
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #open_term_body })
+            }
+
+            pub fn visit_term_vars(
+                __self: &__Remote #ty_generics,
+                __on_var: &mut impl FnMut(&moniker::Var<#ident_ty>),
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #visit_vars_body })
+            }
+
+            pub fn try_visit_term_vars<__B>(
+                __self: &__Remote #ty_generics,
+                __on_var: &mut impl FnMut(&moniker::Var<#ident_ty>) -> ::std::ops::ControlFlow<__B>,
+            ) -> ::std::ops::ControlFlow<__B> {
+                moniker::maybe_grow_stack(|| {
+                    match *__self { #try_visit_vars_body }
+                    ::std::ops::ControlFlow::Continue(())
+                })
+            }
+
+            pub fn visit_mut_term_vars(
+                __self: &mut __Remote #ty_generics,
+                __on_var: &mut impl FnMut(&mut moniker::Var<#ident_ty>),
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #visit_mut_vars_body })
+            }
+
+            pub fn visit_term_vars_with_binders(
+                __self: &__Remote #ty_generics,
+                __binders: &mut moniker::BinderStack<#ident_ty>,
+                __on_var: &mut impl FnMut(
+                    &moniker::BinderStack<#ident_ty>,
+                    &moniker::Var<#ident_ty>,
+                ),
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #visit_vars_with_binders_body })
+            }
+
+            pub fn visit_term_nodes(
+                __self: &__Remote #ty_generics,
+                __depth: usize,
+                __on_node: &mut impl FnMut(usize),
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #visit_nodes_body })
+            }
 
-    s.gen_impl(quote! {
-        extern crate moniker;
+            pub fn visit_term_scopes(
+                __self: &__Remote #ty_generics,
+                __path: &mut Vec<moniker::PathSegment<#ident_ty>>,
+                __binders: &mut moniker::BinderStack<#ident_ty>,
+                __on_scope: &mut impl FnMut(
+                    &[moniker::PathSegment<#ident_ty>],
+                    &moniker::BinderStack<#ident_ty>,
+                    &[moniker::Binder<#ident_ty>],
+                ),
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #visit_scopes_body })
+            }
+        };
+        let bound = quote! { moniker::BoundTerm<#ident_ty> };
+        return Ok(remote_impl(
+            &s,
+            &mirror,
+            &remote,
+            bound,
+            options.bound,
+            import_moniker,
+            fns,
+        ));
+    }
 
-        gen impl moniker::BoundTerm<String> for @Self {
+    Ok(s.gen_impl(quote! {
+        #import_moniker
+
+        gen impl moniker::BoundTerm<#ident_ty> for @Self {
             fn term_eq(&self, other: &Self) -> bool {
-                match (self, other) { #term_eq_body }
+                moniker::maybe_grow_stack(|| match (self, other) { #term_eq_body })
+            }
+
+            fn term_eq_check(
+                &self,
+                other: &Self,
+            ) -> Result<(), moniker::AlphaMismatch<#ident_ty>> {
+                moniker::maybe_grow_stack(|| match (self, other) { #term_eq_check_body })
             }
 
             fn close_term(
                 &mut self,
                 __state: moniker::ScopeState,
-                __on_free: &impl moniker::OnFreeFn<String>,
+                __on_free: &impl moniker::OnFreeFn<#ident_ty>,
             ) {
-                match *self { #close_term_body }
+                moniker::maybe_grow_stack(|| match *self { #close_term_body })
             }
 
             fn open_term(
                 &mut self,
                 __state: moniker::ScopeState,
-                __on_bound: &impl moniker::OnBoundFn<String>,
+                __on_bound: &impl moniker::OnBoundFn<#ident_ty>,
+            ) {
+                moniker::maybe_grow_stack(|| match *self { #open_term_body })
+            }
+
+            fn visit_vars(&self, __on_var: &mut impl FnMut(&moniker::Var<#ident_ty>)) {
+                moniker::maybe_grow_stack(|| match *self { #visit_vars_body })
+            }
+
+            fn try_visit_vars<__B>(
+                &self,
+                __on_var: &mut impl FnMut(&moniker::Var<#ident_ty>) -> ::std::ops::ControlFlow<__B>,
+            ) -> ::std::ops::ControlFlow<__B> {
+                moniker::maybe_grow_stack(|| {
+                    match *self { #try_visit_vars_body }
+                    ::std::ops::ControlFlow::Continue(())
+                })
+            }
+
+            fn visit_mut_vars(&mut self, __on_var: &mut impl FnMut(&mut moniker::Var<#ident_ty>)) {
+                moniker::maybe_grow_stack(|| match *self { #visit_mut_vars_body })
+            }
+
+            fn visit_vars_with_binders(
+                &self,
+                __binders: &mut moniker::BinderStack<#ident_ty>,
+                __on_var: &mut impl FnMut(
+                    &moniker::BinderStack<#ident_ty>,
+                    &moniker::Var<#ident_ty>,
+                ),
             ) {
-                match *self { #open_term_body }
+                moniker::maybe_grow_stack(|| match *self { #visit_vars_with_binders_body })
             }
 
-            fn visit_vars(&self, __on_var: &mut impl FnMut(&moniker::Var<String>)) {
-                match *self { #visit_vars_body }
+            fn visit_nodes(&self, __depth: usize, __on_node: &mut impl FnMut(usize)) {
+                moniker::maybe_grow_stack(|| match *self { #visit_nodes_body })
             }
 
-            fn visit_mut_vars(&mut self, __on_var: &mut impl FnMut(&mut moniker::Var<String>)) {
-                match *self { #visit_mut_vars_body }
+            fn visit_scopes(
+                &self,
+                __path: &mut Vec<moniker::PathSegment<#ident_ty>>,
+                __binders: &mut moniker::BinderStack<#ident_ty>,
+                __on_scope: &mut impl FnMut(
+                    &[moniker::PathSegment<#ident_ty>],
+                    &moniker::BinderStack<#ident_ty>,
+                    &[moniker::Binder<#ident_ty>],
+                ),
+            ) {
+                moniker::maybe_grow_stack(|| match *self { #visit_scopes_body })
             }
         }
-    })
+    }))
 }
 
-decl_derive!([BoundPattern] => bound_pattern_derive);
+decl_derive!([BoundPattern, attributes(moniker)] => bound_pattern_derive);
+
+fn bound_pattern_derive(s: Structure) -> proc_macro2::TokenStream {
+    try_bound_pattern_derive(s).unwrap_or_else(compile_error)
+}
+
+fn try_bound_pattern_derive(s: Structure) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let options = parse_container_options(&s.ast().attrs)?;
+    let mirror = s.ast().ident.clone();
+    let remote_ast = options.remote.as_ref().map(|_| remote_ast(s.ast()));
+    let mut s = match remote_ast {
+        Some(ref ast) => Structure::try_new(ast)?,
+        None => s,
+    };
+    add_bounds(&mut s, options.bound.clone());
+    filter_ignored(&mut s)?;
+    if options.transparent {
+        check_transparent(&s)?;
+    }
+    // The order of the binders in a pattern determines the indices of the
+    // variables that refer to them, so it can't be ignored
+    let unordered = s
+        .variants()
+        .iter()
+        .flat_map(|v| v.bindings())
+        .find(|bi| is_unordered(bi.ast()));
+    if let Some(binding) = unordered {
+        return Err(syn::Error::new_spanned(
+            binding.ast(),
+            "unordered fields are only supported in terms, not in patterns",
+        ));
+    }
+    span_bindings(&mut s);
+    let ident_ty = options.ident_ty();
+    let import_moniker = import_moniker(options.krate.clone());
+    let bound_pattern = quote! { moniker::BoundPattern::<#ident_ty> };
 
-fn bound_pattern_derive(mut s: Structure) -> proc_macro2::TokenStream {
     s.bind_with(|_| BindStyle::Ref);
     let pattern_eq_body = {
         let body = s.variants().iter().fold(quote!(), |acc, v| {
             // Create two sets of bindings, one for the lhs, and another for the rhs
             let mut lhs = v.clone();
             let mut rhs = v.clone();
-            lhs.binding_name(|_, i| {
-                syn::Ident::new(
-                    &format!("__binding_lhs_{}", i),
-                    proc_macro2::Span::call_site(),
-                )
+            lhs.binding_name(|field, i| {
+                syn::Ident::new(&format!("__binding_lhs_{}", i), field.ty.span())
             });
-            rhs.binding_name(|_, i| {
-                syn::Ident::new(
-                    &format!("__binding_rhs_{}", i),
-                    proc_macro2::Span::call_site(),
-                )
+            rhs.binding_name(|field, i| {
+                syn::Ident::new(&format!("__binding_rhs_{}", i), field.ty.span())
             });
 
             let lhs_pat = lhs.pat();
@@ -132,7 +515,14 @@ fn bound_pattern_derive(mut s: Structure) -> proc_macro2::TokenStream {
             let arm_body = <_>::zip(lhs.bindings().iter(), rhs.bindings()).fold(
                 quote!(true),
                 |acc, (lhs, rhs)| {
-                    quote! { #acc && moniker::BoundPattern::<String>::pattern_eq(#lhs, #rhs) }
+                    if is_constant(lhs.ast()) {
+                        return quote! { #acc && #lhs == #rhs };
+                    }
+                    let pattern_eq = field_fn(lhs, &bound_pattern, "pattern_eq", "pattern_eq");
+                    let pattern_eq = quote_spanned! { field_span(lhs) =>
+                        #pattern_eq(#lhs, #rhs)
+                    };
+                    quote! { #acc && #pattern_eq }
                 },
             );
 
@@ -146,77 +536,2287 @@ fn bound_pattern_derive(mut s: Structure) -> proc_macro2::TokenStream {
         }
     };
 
+    // Constant fields contain no variables or binders
+    s.filter(|bi| !is_constant(bi.ast()));
+
     s.bind_with(|_| BindStyle::RefMut);
     let close_pattern_body = s.each(|bi| {
-        quote!{ moniker::BoundPattern::<String>::close_pattern(#bi, __state, __on_free); }
+        let close_pattern = field_fn(bi, &bound_pattern, "close_pattern", "close_pattern");
+        quote_spanned! { field_span(bi) => #close_pattern(#bi, __state, __on_free); }
     });
     let open_pattern_body = s.each(|bi| {
-        quote!{ moniker::BoundPattern::<String>::open_pattern(#bi, __state, __on_bound); }
+        let open_pattern = field_fn(bi, &bound_pattern, "open_pattern", "open_pattern");
+        quote_spanned! { field_span(bi) => #open_pattern(#bi, __state, __on_bound); }
     });
 
     s.bind_with(|_| BindStyle::Ref);
     let visit_vars_body = s.each(|bi| {
-        quote!{ moniker::BoundPattern::<String>::visit_vars(#bi, __on_var); }
+        let visit_vars = field_fn(bi, &bound_pattern, "visit_vars", "visit_pattern_vars");
+        quote_spanned! { field_span(bi) => #visit_vars(#bi, __on_var); }
+    });
+    let try_visit_vars_body = s.each(|bi| {
+        let try_visit_vars = field_fn(
+            bi,
+            &bound_pattern,
+            "try_visit_vars",
+            "try_visit_pattern_vars",
+        );
+        quote_spanned! { field_span(bi) => #try_visit_vars(#bi, __on_var)?; }
     });
     s.bind_with(|_| BindStyle::RefMut);
     let visit_mut_vars_body = s.each(|bi| {
-        quote!{ moniker::BoundPattern::<String>::visit_mut_vars(#bi, __on_var); }
+        let visit_mut_vars = field_fn(
+            bi,
+            &bound_pattern,
+            "visit_mut_vars",
+            "visit_mut_pattern_vars",
+        );
+        quote_spanned! { field_span(bi) => #visit_mut_vars(#bi, __on_var); }
     });
+    s.bind_with(|_| BindStyle::Ref);
+    let visit_vars_with_binders_body = s.each(|bi| {
+        let visit_vars_with_binders = field_fn(
+            bi,
+            &bound_pattern,
+            "visit_vars_with_binders",
+            "visit_pattern_vars_with_binders",
+        );
+        quote_spanned! { field_span(bi) =>
+            #visit_vars_with_binders(#bi, __binders, __on_var);
+        }
+    });
+    let visit_nodes_body = visit_nodes_body(
+        &s,
+        options.transparent,
+        &bound_pattern,
+        "visit_pattern_nodes",
+    );
+    let visit_scopes_body = visit_scopes_body(
+        &s,
+        options.transparent,
+        &bound_pattern,
+        "visit_pattern_scopes",
+    );
 
     s.bind_with(|_| BindStyle::Ref);
     let visit_binders_body = s.each(|bi| {
-        quote!{ moniker::BoundPattern::<String>::visit_binders(#bi, __on_binder); }
+        let visit_binders = field_fn(bi, &bound_pattern, "visit_binders", "visit_binders");
+        quote_spanned! { field_span(bi) => #visit_binders(#bi, __on_binder); }
     });
     s.bind_with(|_| BindStyle::RefMut);
     let visit_mut_binders_body = s.each(|bi| {
-        quote!{ moniker::BoundPattern::<String>::visit_mut_binders(#bi, __on_binder); }
+        let visit_mut_binders =
+            field_fn(bi, &bound_pattern, "visit_mut_binders", "visit_mut_binders");
+        quote_spanned! { field_span(bi) => #visit_mut_binders(#bi, __on_binder); }
+    });
+    let order_binders_body = s.each(|bi| {
+        let order_binders = field_fn(bi, &bound_pattern, "order_binders", "order_binders");
+        quote_spanned! { field_span(bi) => #order_binders(#bi, __body); }
     });
 
-    s.gen_impl(quote! {
-        extern crate moniker;
+    if let Some(remote) = options.remote {
+        let (_, ty_generics, _) = s.ast().generics.split_for_impl();
+        let fns = quote! {
+            pub fn pattern_eq(
+                __self: &__Remote #ty_generics,
+                other: &__Remote #ty_generics,
+            ) -> bool {
+                moniker::maybe_grow_stack(|| match (__self, other) { #pattern_eq_body })
+            }
+
+            pub fn close_pattern(
+                __self: &mut __Remote #ty_generics,
+                __state: moniker::ScopeState,
+                __on_free: &impl moniker::OnFreeFn<#ident_ty>,
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #close_pattern_body })
+            }
+
+            pub fn open_pattern(
+                __self: &mut __Remote #ty_generics,
+                __state: moniker::ScopeState,
+                __on_bound: &impl moniker::OnBoundFn<#ident_ty>,
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #open_pattern_body })
+            }
+
+            pub fn visit_pattern_vars(
+                __self: &__Remote #ty_generics,
+                __on_var: &mut impl FnMut(&moniker::Var<#ident_ty>),
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #visit_vars_body })
+            }
+
+            pub fn try_visit_pattern_vars<__B>(
+                __self: &__Remote #ty_generics,
+                __on_var: &mut impl FnMut(&moniker::Var<#ident_ty>) -> ::std::ops::ControlFlow<__B>,
+            ) -> ::std::ops::ControlFlow<__B> {
+                moniker::maybe_grow_stack(|| {
+                    match *__self { #try_visit_vars_body }
+                    ::std::ops::ControlFlow::Continue(())
+                })
+            }
+
+            pub fn visit_mut_pattern_vars(
+                __self: &mut __Remote #ty_generics,
+                __on_var: &mut impl FnMut(&mut moniker::Var<#ident_ty>),
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #visit_mut_vars_body })
+            }
+
+            pub fn visit_pattern_vars_with_binders(
+                __self: &__Remote #ty_generics,
+                __binders: &mut moniker::BinderStack<#ident_ty>,
+                __on_var: &mut impl FnMut(
+                    &moniker::BinderStack<#ident_ty>,
+                    &moniker::Var<#ident_ty>,
+                ),
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #visit_vars_with_binders_body })
+            }
+
+            pub fn visit_pattern_nodes(
+                __self: &__Remote #ty_generics,
+                __depth: usize,
+                __on_node: &mut impl FnMut(usize),
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #visit_nodes_body })
+            }
+
+            pub fn visit_pattern_scopes(
+                __self: &__Remote #ty_generics,
+                __path: &mut Vec<moniker::PathSegment<#ident_ty>>,
+                __binders: &mut moniker::BinderStack<#ident_ty>,
+                __on_scope: &mut impl FnMut(
+                    &[moniker::PathSegment<#ident_ty>],
+                    &moniker::BinderStack<#ident_ty>,
+                    &[moniker::Binder<#ident_ty>],
+                ),
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #visit_scopes_body })
+            }
+
+            pub fn visit_binders(
+                __self: &__Remote #ty_generics,
+                __on_binder: &mut impl FnMut(&moniker::Binder<#ident_ty>),
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #visit_binders_body })
+            }
+
+            pub fn visit_mut_binders(
+                __self: &mut __Remote #ty_generics,
+                __on_binder: &mut impl FnMut(&mut moniker::Binder<#ident_ty>),
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #visit_mut_binders_body })
+            }
+
+            pub fn order_binders(
+                __self: &mut __Remote #ty_generics,
+                __body: &impl moniker::BoundTerm<#ident_ty>,
+            ) {
+                moniker::maybe_grow_stack(|| match *__self { #order_binders_body })
+            }
+        };
+        let bound = quote! { moniker::BoundPattern<#ident_ty> };
+        return Ok(remote_impl(
+            &s,
+            &mirror,
+            &remote,
+            bound,
+            options.bound,
+            import_moniker,
+            fns,
+        ));
+    }
+
+    Ok(s.gen_impl(quote! {
+        #import_moniker
 
-        gen impl moniker::BoundPattern<String> for @Self {
+        gen impl moniker::BoundPattern<#ident_ty> for @Self {
             fn pattern_eq(&self, other: &Self) -> bool {
-                match (self, other) { #pattern_eq_body }
+                moniker::maybe_grow_stack(|| match (self, other) { #pattern_eq_body })
             }
 
             fn close_pattern(
                 &mut self,
                 __state: moniker::ScopeState,
-                __on_free: &impl moniker::OnFreeFn<String>,
+                __on_free: &impl moniker::OnFreeFn<#ident_ty>,
             ) {
-                match *self { #close_pattern_body }
+                moniker::maybe_grow_stack(|| match *self { #close_pattern_body })
             }
 
             fn open_pattern(
                 &mut self,
                 __state: moniker::ScopeState,
-                __on_bound: &impl moniker::OnBoundFn<String>,
+                __on_bound: &impl moniker::OnBoundFn<#ident_ty>,
             ) {
-                match *self { #open_pattern_body }
+                moniker::maybe_grow_stack(|| match *self { #open_pattern_body })
             }
 
-            fn visit_vars(&self, __on_var: &mut impl FnMut(&moniker::Var<String>)) {
-                match *self { #visit_vars_body }
+            fn visit_vars(&self, __on_var: &mut impl FnMut(&moniker::Var<#ident_ty>)) {
+                moniker::maybe_grow_stack(|| match *self { #visit_vars_body })
+            }
+
+            fn try_visit_vars<__B>(
+                &self,
+                __on_var: &mut impl FnMut(&moniker::Var<#ident_ty>) -> ::std::ops::ControlFlow<__B>,
+            ) -> ::std::ops::ControlFlow<__B> {
+                moniker::maybe_grow_stack(|| {
+                    match *self { #try_visit_vars_body }
+                    ::std::ops::ControlFlow::Continue(())
+                })
             }
 
             fn visit_mut_vars(
                 &mut self,
-                __on_var: &mut impl FnMut(&mut moniker::Var<String>),
+                __on_var: &mut impl FnMut(&mut moniker::Var<#ident_ty>),
+            ) {
+                moniker::maybe_grow_stack(|| match *self { #visit_mut_vars_body })
+            }
+
+            fn visit_vars_with_binders(
+                &self,
+                __binders: &mut moniker::BinderStack<#ident_ty>,
+                __on_var: &mut impl FnMut(
+                    &moniker::BinderStack<#ident_ty>,
+                    &moniker::Var<#ident_ty>,
+                ),
+            ) {
+                moniker::maybe_grow_stack(|| match *self { #visit_vars_with_binders_body })
+            }
+
+            fn visit_nodes(&self, __depth: usize, __on_node: &mut impl FnMut(usize)) {
+                moniker::maybe_grow_stack(|| match *self { #visit_nodes_body })
+            }
+
+            fn visit_scopes(
+                &self,
+                __path: &mut Vec<moniker::PathSegment<#ident_ty>>,
+                __binders: &mut moniker::BinderStack<#ident_ty>,
+                __on_scope: &mut impl FnMut(
+                    &[moniker::PathSegment<#ident_ty>],
+                    &moniker::BinderStack<#ident_ty>,
+                    &[moniker::Binder<#ident_ty>],
+                ),
             ) {
-                match *self { #visit_mut_vars_body }
+                moniker::maybe_grow_stack(|| match *self { #visit_scopes_body })
             }
 
-            fn visit_binders(&self, __on_binder: &mut impl FnMut(&moniker::Binder<String>)) {
-                match *self { #visit_binders_body }
+            fn visit_binders(&self, __on_binder: &mut impl FnMut(&moniker::Binder<#ident_ty>)) {
+                moniker::maybe_grow_stack(|| match *self { #visit_binders_body })
             }
 
             fn visit_mut_binders(
                 &mut self,
-                __on_binder: &mut impl FnMut(&mut moniker::Binder<String>),
+                __on_binder: &mut impl FnMut(&mut moniker::Binder<#ident_ty>),
             ) {
-                match *self { #visit_mut_binders_body }
+                moniker::maybe_grow_stack(|| match *self { #visit_mut_binders_body })
+            }
+
+            fn order_binders(&mut self, __body: &impl moniker::BoundTerm<#ident_ty>) {
+                moniker::maybe_grow_stack(|| match *self { #order_binders_body })
             }
         }
-    })
+    }))
+}
+
+decl_derive!([Alpha, attributes(moniker)] => alpha_derive);
+
+fn alpha_derive(s: Structure) -> proc_macro2::TokenStream {
+    try_alpha_derive(s).unwrap_or_else(compile_error)
+}
+
+fn try_alpha_derive(s: Structure) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let options = parse_container_options(&s.ast().attrs)?;
+    if options.skip_term && options.skip_pattern {
+        return Err(syn::Error::new_spanned(
+            &s.ast().ident,
+            "cannot skip both `BoundTerm` and `BoundPattern`",
+        ));
+    }
+
+    let mut impls = quote!();
+    if !options.skip_term {
+        impls.extend(try_bound_term_derive(s.clone())?);
+    }
+    if !options.skip_pattern {
+        impls.extend(try_bound_pattern_derive(s)?);
+    }
+    Ok(impls)
+}
+
+decl_derive!([Subst, attributes(subst, moniker)] => subst_derive);
+
+fn subst_derive(s: Structure) -> proc_macro2::TokenStream {
+    try_subst_derive(s).unwrap_or_else(compile_error)
+}
+
+fn try_subst_derive(mut s: Structure) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let options = parse_container_options(&s.ast().attrs)?;
+    check_not_remote(&options)?;
+    let ident_ty = options.ident_ty();
+    let import_moniker = import_moniker(options.krate);
+    span_bindings(&mut s);
+    for variant in s.variants() {
+        for binding in variant.bindings() {
+            parse_field_options(&binding.ast().attrs)?;
+        }
+    }
+
+    let self_ty = {
+        let ident = &s.ast().ident;
+        let (_, ty_generics, _) = s.ast().generics.split_for_impl();
+        quote! { #ident #ty_generics }
+    };
+
+    // The types of the terms that can be substituted for variables, defaulting
+    // to the type that we are deriving for
+    let mut replacements = Vec::new();
+    let mut bound = None;
+    for attr in &s.ast().attrs {
+        if attr.path().is_ident("subst") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("replacement") {
+                    let lit = meta.value()?.parse::<syn::LitStr>()?;
+                    replacements.push(lit.parse::<syn::Type>()?);
+                    Ok(())
+                } else if meta.path.is_ident("bound") {
+                    parse_bound(&meta, &mut bound)
+                } else {
+                    Err(meta.error("expected `replacement = \"...\"` or `bound = \"...\"`"))
+                }
+            })?;
+        }
+    }
+    add_bounds(&mut s, bound);
+
+    // Find the fields that hold variables that may be substituted
+    let mut var_fields = Vec::new();
+    let is_enum = match s.ast().data {
+        syn::Data::Enum(_) => true,
+        syn::Data::Struct(_) | syn::Data::Union(_) => false,
+    };
+    for (variant_index, variant) in s.variants().iter().enumerate() {
+        let mut is_var_variant = false;
+        // NOTE: The attributes of a struct's only variant are the attributes of
+        // the struct itself
+        for attr in variant.ast().attrs.iter().filter(|_| is_enum) {
+            if attr.path().is_ident("subst") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("var") {
+                        is_var_variant = true;
+                        Ok(())
+                    } else {
+                        Err(meta.error("expected `var`"))
+                    }
+                })?;
+            }
+        }
+        if is_var_variant && variant.bindings().len() != 1 {
+            return Err(syn::Error::new_spanned(
+                variant.ast().ident,
+                "variable variants must have exactly one field",
+            ));
+        }
+
+        for (field_index, binding) in variant.bindings().iter().enumerate() {
+            let mut is_var_field = is_var_variant;
+            for attr in &binding.ast().attrs {
+                if attr.path().is_ident("subst") {
+                    attr.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("var") {
+                            is_var_field = true;
+                            Ok(())
+                        } else {
+                            Err(meta.error("expected `var`"))
+                        }
+                    })?;
+                }
+            }
+            if is_var_field {
+                var_fields.push((variant_index, field_index));
+            }
+        }
+    }
+
+    // Transparent types are variables whenever their only field is
+    if options.transparent {
+        check_transparent(&s)?;
+        let field_index = s.variants()[0]
+            .bindings()
+            .iter()
+            .position(|bi| !is_ignored(bi.ast()))
+            .unwrap();
+        if !var_fields.contains(&(0, field_index)) {
+            var_fields.push((0, field_index));
+        }
+    }
+
+    let replacements = replacements_or_self(replacements, &s.ast().ident);
+
+    // Variables are replaced by cloning the replacement term
+    if replacements.iter().any(Option::is_none) && !var_fields.is_empty() {
+        s.add_where_predicate(syn::parse_quote! { #self_ty: Clone });
+    }
+
+    // Terms that are substituted with some other type of term rely on the
+    // replacement type to replace its own variables, so check that it does
+    let mut impls = quote!();
+    if !var_fields.is_empty() {
+        for replacement in replacements.iter().flatten() {
+            s.add_where_predicate(syn::parse_quote! {
+                #replacement: moniker::SubstVar<#ident_ty>
+            });
+        }
+        if replacements.iter().any(Option::is_none) {
+            let ident = &s.ast().ident;
+            let (impl_generics, ty_generics, where_clause) = s.ast().generics.split_for_impl();
+            impls.extend(quote! {
+                impl #impl_generics moniker::SubstVar<#ident_ty> for #ident #ty_generics
+                #where_clause
+                {}
+            });
+        }
+    }
+
+    s.bind_with(|_| BindStyle::Ref);
+    for replacement in replacements {
+        let replacement_ty = match replacement {
+            Some(ref ty) => quote! { #ty },
+            None => self_ty.clone(),
+        };
+
+        let as_var_fn = if var_fields.is_empty() {
+            quote!()
+        } else {
+            let mut variant_index = 0;
+            let as_var_body = s.each_variant(|v| {
+                let var = v
+                    .bindings()
+                    .iter()
+                    .enumerate()
+                    .find(|&(i, _)| var_fields.contains(&(variant_index, i)))
+                    .map(|(_, bi)| bi);
+                variant_index += 1;
+                match var {
+                    Some(bi) => quote_spanned! { field_span(bi) =>
+                        moniker::Subst::<#ident_ty, #replacement_ty>::as_var(#bi)
+                    },
+                    None => quote! { None },
+                }
+            });
+
+            quote! {
+                fn as_var(&self) -> Option<&moniker::Var<#ident_ty>> {
+                    match *self { #as_var_body }
+                }
+            }
+        };
+
+        // Only replace this term if it has the same type as the replacement
+        let replace_self = if replacement.is_none() && !var_fields.is_empty() {
+            quote! {
+                if let Some(__var) = moniker::Subst::<#ident_ty, #replacement_ty>::as_var(self) {
+                    if __name == __var {
+                        return Clone::clone(__replacement);
+                    }
+                }
+            }
+        } else {
+            quote!()
+        };
+
+        let replace_self_substs = if replacement.is_none() && !var_fields.is_empty() {
+            quote! {
+                if let Some(__var) = moniker::Subst::<#ident_ty, #replacement_ty>::as_var(self) {
+                    if let Some(&(_, ref __replacement)) =
+                        __mappings.iter().find(|&&(ref __name, _)| __name == __var)
+                    {
+                        return Clone::clone(__replacement);
+                    }
+                }
+            }
+        } else {
+            quote!()
+        };
+
+        let subst_body = s.each_variant(|v| {
+            let bindings = v.bindings();
+            v.construct(|field, i| {
+                let bi = &bindings[i];
+                if is_ignored(field) || is_constant(field) {
+                    quote! { Clone::clone(#bi) }
+                } else {
+                    quote_spanned! { field.ty.span() =>
+                        moniker::Subst::<#ident_ty, #replacement_ty>::subst(
+                            #bi,
+                            __name,
+                            __replacement,
+                        )
+                    }
+                }
+            })
+        });
+        let substs_body = s.each_variant(|v| {
+            let bindings = v.bindings();
+            v.construct(|field, i| {
+                let bi = &bindings[i];
+                if is_ignored(field) || is_constant(field) {
+                    quote! { Clone::clone(#bi) }
+                } else {
+                    quote_spanned! { field.ty.span() =>
+                        moniker::Subst::<#ident_ty, #replacement_ty>::substs(#bi, __mappings)
+                    }
+                }
+            })
+        });
+
+        // Only replace this term in place if it has the same type as the
+        // replacement, otherwise substitute into each of the fields in place
+        let (replace_self_mut, replace_self_substs_mut) =
+            if replacement.is_none() && !var_fields.is_empty() {
+                (
+                    quote! {
+                        let __is_replaced =
+                            match moniker::Subst::<#ident_ty, #replacement_ty>::as_var(self) {
+                                Some(__var) => __name == __var,
+                                None => false,
+                            };
+                        if __is_replaced {
+                            *self = Clone::clone(__replacement);
+                            return;
+                        }
+                    },
+                    quote! {
+                        let __replacement =
+                            match moniker::Subst::<#ident_ty, #replacement_ty>::as_var(self) {
+                                Some(__var) => __mappings
+                                    .iter()
+                                    .find(|&&(ref __name, _)| __name == __var)
+                                    .map(|&(_, ref __replacement)| __replacement),
+                                None => None,
+                            };
+                        if let Some(__replacement) = __replacement {
+                            *self = Clone::clone(__replacement);
+                            return;
+                        }
+                    },
+                )
+            } else {
+                (quote!(), quote!())
+            };
+
+        // Only rebuild this term if one of its fields was changed, rebuilding
+        // the other fields using `Subst::subst` so that they share their
+        // subterms with the original term
+        let changed_body = |subst_changed: proc_macro2::TokenStream,
+                            subst_unchanged: proc_macro2::TokenStream,
+                            args: proc_macro2::TokenStream| {
+            s.each_variant(|v| {
+                let bindings = v.bindings();
+                let changed = |i: usize| {
+                    syn::Ident::new(&format!("__changed_{}", i), proc_macro2::Span::call_site())
+                };
+                let mut lets = quote!();
+                let mut unchanged = quote! { true };
+                for (i, bi) in bindings.iter().enumerate() {
+                    if is_ignored(bi.ast()) || is_constant(bi.ast()) {
+                        continue;
+                    }
+                    let changed = changed(i);
+                    lets.extend(quote_spanned! { bi.ast().ty.span() =>
+                        let #changed =
+                            moniker::Subst::<#ident_ty, #replacement_ty>::#subst_changed(
+                                #bi,
+                                #args
+                            );
+                    });
+                    unchanged.extend(quote! { && #changed.is_none() });
+                }
+                let construct = v.construct(|field, i| {
+                    let bi = &bindings[i];
+                    if is_ignored(field) || is_constant(field) {
+                        quote! { Clone::clone(#bi) }
+                    } else {
+                        let changed = changed(i);
+                        quote_spanned! { field.ty.span() =>
+                            #changed.unwrap_or_else(|| {
+                                moniker::Subst::<#ident_ty, #replacement_ty>::#subst_unchanged(
+                                    #bi,
+                                    #args
+                                )
+                            })
+                        }
+                    }
+                });
+
+                quote! {
+                    #lets
+                    if #unchanged { None } else { Some(#construct) }
+                }
+            })
+        };
+        let subst_changed_body = changed_body(
+            quote!(subst_changed),
+            quote!(subst),
+            quote!(__name, __replacement),
+        );
+        let substs_changed_body =
+            changed_body(quote!(substs_changed), quote!(substs), quote!(__mappings));
+
+        let (replace_self_changed, replace_self_substs_changed) =
+            if replacement.is_none() && !var_fields.is_empty() {
+                (
+                    quote! {
+                        let __var = moniker::Subst::<#ident_ty, #replacement_ty>::as_var(self);
+                        if let Some(__var) = __var {
+                            if __name == __var {
+                                return Some(Clone::clone(__replacement));
+                            }
+                        }
+                    },
+                    quote! {
+                        let __var = moniker::Subst::<#ident_ty, #replacement_ty>::as_var(self);
+                        if let Some(__var) = __var {
+                            if let Some(&(_, ref __replacement)) =
+                                __mappings.iter().find(|&&(ref __name, _)| __name == __var)
+                            {
+                                return Some(Clone::clone(__replacement));
+                            }
+                        }
+                    },
+                )
+            } else {
+                (quote!(), quote!())
+            };
+
+        s.bind_with(|_| BindStyle::RefMut);
+        let subst_mut_body = s.each(|bi| {
+            if is_ignored(bi.ast()) || is_constant(bi.ast()) {
+                quote!()
+            } else {
+                quote_spanned! { bi.ast().ty.span() =>
+                    moniker::Subst::<#ident_ty, #replacement_ty>::subst_mut(
+                        #bi,
+                        __name,
+                        __replacement,
+                    );
+                }
+            }
+        });
+        let substs_mut_body = s.each(|bi| {
+            if is_ignored(bi.ast()) || is_constant(bi.ast()) {
+                quote!()
+            } else {
+                quote_spanned! { bi.ast().ty.span() =>
+                    moniker::Subst::<#ident_ty, #replacement_ty>::substs_mut(#bi, __mappings);
+                }
+            }
+        });
+        s.bind_with(|_| BindStyle::Ref);
+
+        impls.extend(quote! {
+            gen impl moniker::Subst<#ident_ty, #replacement_ty> for @Self {
+                #as_var_fn
+
+                fn subst<__V: PartialEq<moniker::Var<#ident_ty>>>(
+                    &self,
+                    __name: &__V,
+                    __replacement: &#replacement_ty,
+                ) -> Self {
+                    moniker::maybe_grow_stack(|| {
+                        #replace_self
+                        match *self { #subst_body }
+                    })
+                }
+
+                fn substs<__V: PartialEq<moniker::Var<#ident_ty>>>(
+                    &self,
+                    __mappings: &[(__V, #replacement_ty)],
+                ) -> Self {
+                    moniker::maybe_grow_stack(|| {
+                        #replace_self_substs
+                        match *self { #substs_body }
+                    })
+                }
+
+                fn subst_mut<__V: PartialEq<moniker::Var<#ident_ty>>>(
+                    &mut self,
+                    __name: &__V,
+                    __replacement: &#replacement_ty,
+                ) {
+                    moniker::maybe_grow_stack(|| {
+                        #replace_self_mut
+                        match *self { #subst_mut_body }
+                    })
+                }
+
+                fn substs_mut<__V: PartialEq<moniker::Var<#ident_ty>>>(
+                    &mut self,
+                    __mappings: &[(__V, #replacement_ty)],
+                ) {
+                    moniker::maybe_grow_stack(|| {
+                        #replace_self_substs_mut
+                        match *self { #substs_mut_body }
+                    })
+                }
+
+                fn subst_changed<__V: PartialEq<moniker::Var<#ident_ty>>>(
+                    &self,
+                    __name: &__V,
+                    __replacement: &#replacement_ty,
+                ) -> Option<Self> {
+                    moniker::maybe_grow_stack(|| {
+                        #replace_self_changed
+                        match *self { #subst_changed_body }
+                    })
+                }
+
+                fn substs_changed<__V: PartialEq<moniker::Var<#ident_ty>>>(
+                    &self,
+                    __mappings: &[(__V, #replacement_ty)],
+                ) -> Option<Self> {
+                    moniker::maybe_grow_stack(|| {
+                        #replace_self_substs_changed
+                        match *self { #substs_changed_body }
+                    })
+                }
+            }
+        });
+    }
+
+    Ok(s.gen_impl(quote! {
+        #import_moniker
+
+        #impls
+    }))
+}
+
+decl_derive!([Instantiate, attributes(instantiate, moniker)] => instantiate_derive);
+
+fn instantiate_derive(s: Structure) -> proc_macro2::TokenStream {
+    try_instantiate_derive(s).unwrap_or_else(compile_error)
+}
+
+fn try_instantiate_derive(mut s: Structure) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let options = parse_container_options(&s.ast().attrs)?;
+    check_not_remote(&options)?;
+    let ident_ty = options.ident_ty();
+    let import_moniker = import_moniker(options.krate);
+    span_bindings(&mut s);
+    for variant in s.variants() {
+        for binding in variant.bindings() {
+            parse_field_options(&binding.ast().attrs)?;
+        }
+    }
+
+    let self_ty = {
+        let ident = &s.ast().ident;
+        let (_, ty_generics, _) = s.ast().generics.split_for_impl();
+        quote! { #ident #ty_generics }
+    };
+
+    // The types of the solutions of the metavariables, defaulting to the type
+    // that we are deriving for
+    let mut replacements = Vec::new();
+    let mut bound = None;
+    for attr in &s.ast().attrs {
+        if attr.path().is_ident("instantiate") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("replacement") {
+                    let lit = meta.value()?.parse::<syn::LitStr>()?;
+                    replacements.push(lit.parse::<syn::Type>()?);
+                    Ok(())
+                } else if meta.path.is_ident("bound") {
+                    parse_bound(&meta, &mut bound)
+                } else {
+                    Err(meta.error("expected `replacement = \"...\"` or `bound = \"...\"`"))
+                }
+            })?;
+        }
+    }
+    add_bounds(&mut s, bound);
+
+    // Find the fields that hold metavariables that may be instantiated
+    let mut meta_fields = Vec::new();
+    let is_enum = match s.ast().data {
+        syn::Data::Enum(_) => true,
+        syn::Data::Struct(_) | syn::Data::Union(_) => false,
+    };
+    for (variant_index, variant) in s.variants().iter().enumerate() {
+        let mut is_meta_variant = false;
+        // NOTE: The attributes of a struct's only variant are the attributes of
+        // the struct itself
+        for attr in variant.ast().attrs.iter().filter(|_| is_enum) {
+            if attr.path().is_ident("instantiate") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("meta") {
+                        is_meta_variant = true;
+                        Ok(())
+                    } else {
+                        Err(meta.error("expected `meta`"))
+                    }
+                })?;
+            }
+        }
+        if is_meta_variant && variant.bindings().len() != 1 {
+            return Err(syn::Error::new_spanned(
+                variant.ast().ident,
+                "metavariable variants must have exactly one field",
+            ));
+        }
+
+        for (field_index, binding) in variant.bindings().iter().enumerate() {
+            let mut is_meta_field = is_meta_variant;
+            for attr in &binding.ast().attrs {
+                if attr.path().is_ident("instantiate") {
+                    attr.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("meta") {
+                            is_meta_field = true;
+                            Ok(())
+                        } else {
+                            Err(meta.error("expected `meta`"))
+                        }
+                    })?;
+                }
+            }
+            if is_meta_field {
+                meta_fields.push((variant_index, field_index));
+            }
+        }
+    }
+
+    // Transparent types are metavariables whenever their only field is
+    if options.transparent {
+        check_transparent(&s)?;
+        let field_index = s.variants()[0]
+            .bindings()
+            .iter()
+            .position(|bi| !is_ignored(bi.ast()))
+            .unwrap();
+        if !meta_fields.contains(&(0, field_index)) {
+            meta_fields.push((0, field_index));
+        }
+    }
+
+    let replacements = replacements_or_self(replacements, &s.ast().ident);
+
+    // Metavariables are replaced by cloning their solutions
+    if replacements.iter().any(Option::is_none) && !meta_fields.is_empty() {
+        s.add_where_predicate(syn::parse_quote! { #self_ty: Clone });
+    }
+
+    s.bind_with(|_| BindStyle::Ref);
+
+    let mut impls = quote!();
+    for replacement in replacements {
+        let replacement_ty = match replacement {
+            Some(ref ty) => quote! { #ty },
+            None => self_ty.clone(),
+        };
+        let instantiate = quote! { moniker::Instantiate::<#ident_ty, #replacement_ty> };
+
+        let as_meta_fn = if meta_fields.is_empty() {
+            quote!()
+        } else {
+            let mut variant_index = 0;
+            let as_meta_body = s.each_variant(|v| {
+                let meta = v
+                    .bindings()
+                    .iter()
+                    .enumerate()
+                    .find(|&(i, _)| meta_fields.contains(&(variant_index, i)))
+                    .map(|(_, bi)| bi);
+                variant_index += 1;
+                match meta {
+                    Some(bi) => quote_spanned! { field_span(bi) => #instantiate::as_meta(#bi) },
+                    None => quote! { None },
+                }
+            });
+
+            quote! {
+                fn as_meta(&self) -> Option<&moniker::MetaVar<#ident_ty>> {
+                    match *self { #as_meta_body }
+                }
+            }
+        };
+
+        // Only replace this term if it has the same type as the solutions
+        let replace_self = if replacement.is_none() && !meta_fields.is_empty() {
+            quote! {
+                if let Some(__meta) = #instantiate::as_meta(self) {
+                    if let Some(__solution) = __solutions.get(__meta) {
+                        return Clone::clone(__solution);
+                    }
+                }
+            }
+        } else {
+            quote!()
+        };
+
+        let mut visit = s.clone();
+        visit.filter(|bi| !is_ignored(bi.ast()) && !is_constant(bi.ast()));
+        let visit_metas_body = visit.each(|bi| {
+            quote_spanned! { field_span(bi) => #instantiate::visit_metas(#bi, __on_meta); }
+        });
+
+        let instantiate_body = s.each_variant(|v| {
+            let bindings = v.bindings();
+            v.construct(|field, i| {
+                let bi = &bindings[i];
+                if is_ignored(field) || is_constant(field) {
+                    quote! { Clone::clone(#bi) }
+                } else {
+                    quote_spanned! { field.ty.span() =>
+                        #instantiate::instantiate(#bi, __solutions)
+                    }
+                }
+            })
+        });
+
+        impls.extend(quote! {
+            gen impl moniker::Instantiate<#ident_ty, #replacement_ty> for @Self {
+                #as_meta_fn
+
+                fn visit_metas(
+                    &self,
+                    __on_meta: &mut impl FnMut(&moniker::MetaVar<#ident_ty>),
+                ) {
+                    match *self { #visit_metas_body }
+                }
+
+                fn instantiate(
+                    &self,
+                    __solutions: &::std::collections::HashMap<
+                        moniker::MetaVar<#ident_ty>,
+                        #replacement_ty,
+                    >,
+                ) -> Self {
+                    #replace_self
+                    match *self { #instantiate_body }
+                }
+            }
+        });
+    }
+
+    Ok(s.gen_impl(quote! {
+        #import_moniker
+
+        #impls
+    }))
+}
+
+decl_derive!([Zip, attributes(zip, moniker)] => zip_derive);
+
+fn zip_derive(s: Structure) -> proc_macro2::TokenStream {
+    try_zip_derive(s).unwrap_or_else(compile_error)
+}
+
+fn try_zip_derive(mut s: Structure) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let options = parse_container_options(&s.ast().attrs)?;
+    check_not_remote(&options)?;
+    let ident_ty = options.ident_ty();
+    let import_moniker = import_moniker(options.krate);
+    span_bindings(&mut s);
+    for variant in s.variants() {
+        for binding in variant.bindings() {
+            parse_field_options(&binding.ast().attrs)?;
+        }
+    }
+    if options.transparent {
+        check_transparent(&s)?;
+    }
+
+    let self_ty = {
+        let ident = &s.ast().ident;
+        let (_, ty_generics, _) = s.ast().generics.split_for_impl();
+        quote! { #ident #ty_generics }
+    };
+
+    // The types of the subterms that are handed to the zipper, defaulting to
+    // the type that we are deriving for
+    let mut replacements = Vec::new();
+    let mut bound = None;
+    for attr in &s.ast().attrs {
+        if attr.path().is_ident("zip") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("replacement") {
+                    let lit = meta.value()?.parse::<syn::LitStr>()?;
+                    replacements.push(lit.parse::<syn::Type>()?);
+                    Ok(())
+                } else if meta.path.is_ident("bound") {
+                    parse_bound(&meta, &mut bound)
+                } else {
+                    Err(meta.error("expected `replacement = \"...\"` or `bound = \"...\"`"))
+                }
+            })?;
+        }
+    }
+    add_bounds(&mut s, bound);
+
+    let replacements = replacements_or_self(replacements, &s.ast().ident);
+
+    s.bind_with(|_| BindStyle::Ref);
+
+    let mut impls = quote!();
+    for replacement in replacements {
+        let replacement_ty = match replacement {
+            Some(ref ty) => quote! { #ty },
+            None => self_ty.clone(),
+        };
+        let zip = quote! { moniker::Zip::<#ident_ty, #replacement_ty> };
+
+        // Only hand this term to the zipper if it has the same type as the
+        // zipped subterms
+        let zip_with_fn = if replacement.is_none() {
+            quote! {
+                fn zip_with(
+                    &self,
+                    __other: &Self,
+                    __zipper: &mut impl moniker::Zipper<#ident_ty, #replacement_ty>,
+                ) -> Option<Self> {
+                    moniker::Zipper::zip_terms(__zipper, self, __other)
+                }
+            }
+        } else {
+            quote!()
+        };
+
+        let zip_structure_body = {
+            let body = s.variants().iter().fold(quote!(), |acc, v| {
+                // Create two sets of bindings, one for the lhs, and another for the rhs
+                let mut lhs = v.clone();
+                let mut rhs = v.clone();
+                lhs.binding_name(|field, i| {
+                    syn::Ident::new(&format!("__binding_lhs_{}", i), field.ty.span())
+                });
+                rhs.binding_name(|field, i| {
+                    syn::Ident::new(&format!("__binding_rhs_{}", i), field.ty.span())
+                });
+
+                let lhs_pat = lhs.pat();
+                let rhs_pat = rhs.pat();
+
+                // Constant fields must be equal for the terms to be zipped
+                let checks = <_>::zip(lhs.bindings().iter(), rhs.bindings())
+                    .filter(|(lhs, _)| is_constant(lhs.ast()))
+                    .fold(quote!(), |acc, (lhs, rhs)| {
+                        quote! { #acc if #lhs != #rhs { return None; } }
+                    });
+
+                let construct = v.construct(|field, i| {
+                    let lhs = &lhs.bindings()[i];
+                    let rhs = &rhs.bindings()[i];
+                    if is_ignored(field) || is_constant(field) {
+                        quote! { Clone::clone(#lhs) }
+                    } else {
+                        quote_spanned! { field.ty.span() =>
+                            #zip::zip_with(#lhs, #rhs, __zipper)?
+                        }
+                    }
+                });
+
+                quote! { #acc (&#lhs_pat, &#rhs_pat) => { #checks Some(#construct) }, }
+            });
+
+            // Avoid the 'unreachable match' warning for types with zero or one variants
+            match s.variants().len() {
+                0 | 1 => body,
+                _ => quote! { #body (_, _) => None },
+            }
+        };
+
+        impls.extend(quote! {
+            gen impl moniker::Zip<#ident_ty, #replacement_ty> for @Self {
+                #zip_with_fn
+
+                fn zip_structure(
+                    &self,
+                    __other: &Self,
+                    __zipper: &mut impl moniker::Zipper<#ident_ty, #replacement_ty>,
+                ) -> Option<Self> {
+                    match (self, __other) { #zip_structure_body }
+                }
+            }
+        });
+    }
+
+    Ok(s.gen_impl(quote! {
+        #import_moniker
+
+        #impls
+    }))
+}
+
+decl_derive!([AlphaHash, attributes(moniker)] => alpha_hash_derive);
+
+fn alpha_hash_derive(s: Structure) -> proc_macro2::TokenStream {
+    try_alpha_hash_derive(s).unwrap_or_else(compile_error)
+}
+
+fn try_alpha_hash_derive(mut s: Structure) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let options = parse_container_options(&s.ast().attrs)?;
+    let ident_ty = options.ident_ty();
+    check_not_remote(&options)?;
+    add_bounds(&mut s, options.bound);
+    filter_ignored(&mut s)?;
+    if options.transparent {
+        check_transparent(&s)?;
+    }
+    span_bindings(&mut s);
+    let import_moniker = import_moniker(options.krate);
+    let is_enum = match s.ast().data {
+        syn::Data::Enum(_) => true,
+        syn::Data::Struct(_) | syn::Data::Union(_) => false,
+    };
+
+    let variant_options = s
+        .variants()
+        .iter()
+        .map(|v| parse_variant_options(&s, v))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    s.bind_with(|_| BindStyle::Ref);
+    let variants = <_>::zip(s.variants().iter(), &variant_options).enumerate();
+    let alpha_hash_body = variants.fold(quote!(), |acc, (i, (v, variant_options))| {
+        let pat = v.pat();
+        // Hash the position of the variant, so that different variants with
+        // the same fields hash differently
+        let discriminant = if is_enum {
+            quote! { ::std::hash::Hasher::write_usize(__state, #i); }
+        } else {
+            quote!()
+        };
+
+        if variant_options.is_ac() {
+            let ac_fields = ac_fields(v, variant_options);
+            let fields = if variant_options.commutative {
+                quote! { moniker::unordered_alpha_hash::<#ident_ty, _, _>(#ac_fields, __state); }
+            } else {
+                quote! {
+                    let __fields = #ac_fields;
+                    ::std::hash::Hasher::write_usize(__state, __fields.len());
+                    for __field in __fields {
+                        moniker::AlphaHash::<#ident_ty>::alpha_hash(__field, __state);
+                    }
+                }
+            };
+            return quote! { #acc #pat => { #discriminant #fields }, };
+        }
+
+        let fields = v.bindings().iter().fold(quote!(), |acc, bi| {
+            let alpha_hash = if is_constant(bi.ast()) {
+                quote_spanned! { field_span(bi) => ::std::hash::Hash::hash(#bi, __state); }
+            } else if is_unordered(bi.ast()) {
+                quote_spanned! { field_span(bi) =>
+                    moniker::unordered_alpha_hash::<#ident_ty, _, _>(#bi, __state);
+                }
+            } else {
+                quote_spanned! { field_span(bi) =>
+                    moniker::AlphaHash::<#ident_ty>::alpha_hash(#bi, __state);
+                }
+            };
+            quote! { #acc #alpha_hash }
+        });
+        quote! { #acc #pat => { #discriminant #fields }, }
+    });
+
+    Ok(s.gen_impl(quote! {
+        #import_moniker
+
+        gen impl moniker::AlphaHash<#ident_ty> for @Self {
+            fn alpha_hash<__H: ::std::hash::Hasher>(&self, __state: &mut __H) {
+                match *self { #alpha_hash_body }
+            }
+        }
+    }))
+}
+
+decl_derive!([AlphaOrd, attributes(moniker)] => alpha_ord_derive);
+
+fn alpha_ord_derive(s: Structure) -> proc_macro2::TokenStream {
+    try_alpha_ord_derive(s).unwrap_or_else(compile_error)
+}
+
+fn try_alpha_ord_derive(mut s: Structure) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let options = parse_container_options(&s.ast().attrs)?;
+    let ident_ty = options.ident_ty();
+    check_not_remote(&options)?;
+    add_bounds(&mut s, options.bound);
+    filter_ignored(&mut s)?;
+    if options.transparent {
+        check_transparent(&s)?;
+    }
+    let import_moniker = import_moniker(options.krate);
+
+    let variant_options = s
+        .variants()
+        .iter()
+        .map(|v| parse_variant_options(&s, v))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    s.bind_with(|_| BindStyle::Ref);
+    let alpha_cmp_body = {
+        let variants = <_>::zip(s.variants().iter(), &variant_options);
+        let body = variants.fold(quote!(), |acc, (v, variant_options)| {
+            // Create two sets of bindings, one for the lhs, and another for the rhs
+            let mut lhs = v.clone();
+            let mut rhs = v.clone();
+            lhs.binding_name(|field, i| {
+                syn::Ident::new(&format!("__binding_lhs_{}", i), field.ty.span())
+            });
+            rhs.binding_name(|field, i| {
+                syn::Ident::new(&format!("__binding_rhs_{}", i), field.ty.span())
+            });
+
+            let lhs_pat = lhs.pat();
+            let rhs_pat = rhs.pat();
+
+            if variant_options.is_ac() {
+                let lhs_fields = ac_fields(&lhs, variant_options);
+                let rhs_fields = ac_fields(&rhs, variant_options);
+                let arm_body = if variant_options.commutative {
+                    quote! {
+                        moniker::unordered_alpha_cmp::<#ident_ty, _>(#lhs_fields, #rhs_fields)
+                    }
+                } else {
+                    quote! {
+                        moniker::AlphaOrd::<#ident_ty>::alpha_cmp(
+                            &(#lhs_fields)[..],
+                            &(#rhs_fields)[..],
+                        )
+                    }
+                };
+                return quote! { #acc (&#lhs_pat, &#rhs_pat) => #arm_body, };
+            }
+
+            // compare the fields lexicographically
+            let arm_body = <_>::zip(lhs.bindings().iter(), rhs.bindings()).fold(
+                quote!(::std::cmp::Ordering::Equal),
+                |acc, (lhs, rhs)| {
+                    let alpha_cmp = if is_constant(lhs.ast()) {
+                        quote_spanned! { field_span(lhs) => ::std::cmp::Ord::cmp(#lhs, #rhs) }
+                    } else if is_unordered(lhs.ast()) {
+                        quote_spanned! { field_span(lhs) =>
+                            moniker::unordered_alpha_cmp::<#ident_ty, _>(#lhs, #rhs)
+                        }
+                    } else {
+                        quote_spanned! { field_span(lhs) =>
+                            moniker::AlphaOrd::<#ident_ty>::alpha_cmp(#lhs, #rhs)
+                        }
+                    };
+                    quote! { #acc.then_with(|| #alpha_cmp) }
+                },
+            );
+
+            quote! { #acc (&#lhs_pat, &#rhs_pat) => #arm_body, }
+        });
+
+        // Avoid the 'unreachable match' warning for types with zero or one variants
+        match s.variants().len() {
+            0 | 1 => body,
+            _ => {
+                // Order different variants by their positions in the definition
+                let ident = &s.ast().ident;
+                let variants = s.variants().iter().enumerate();
+                let variant_indices = variants.fold(quote!(), |acc, (i, v)| {
+                    let variant = v.ast().ident;
+                    quote! { #acc #ident::#variant { .. } => #i, }
+                });
+                quote! {
+                    #body
+                    (__lhs, __rhs) => {
+                        let __variant_index = |__term: &Self| match *__term { #variant_indices };
+                        ::std::cmp::Ord::cmp(&__variant_index(__lhs), &__variant_index(__rhs))
+                    },
+                }
+            },
+        }
+    };
+
+    Ok(s.gen_impl(quote! {
+        #import_moniker
+
+        gen impl moniker::AlphaOrd<#ident_ty> for @Self {
+            fn alpha_cmp(&self, other: &Self) -> ::std::cmp::Ordering {
+                match (self, other) { #alpha_cmp_body }
+            }
+        }
+    }))
+}
+
+decl_derive!([MapVars, attributes(moniker)] => map_vars_derive);
+
+fn map_vars_derive(s: Structure) -> proc_macro2::TokenStream {
+    try_map_vars_derive(s).unwrap_or_else(compile_error)
+}
+
+fn try_map_vars_derive(mut s: Structure) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let options = parse_container_options(&s.ast().attrs)?;
+    let ident_ty = options.ident_ty();
+    check_not_remote(&options)?;
+    add_bounds(&mut s, options.bound);
+    for variant in s.variants() {
+        for binding in variant.bindings() {
+            parse_field_options(&binding.ast().attrs)?;
+        }
+    }
+    if options.transparent {
+        check_transparent(&s)?;
+    }
+    span_bindings(&mut s);
+    let import_moniker = import_moniker(options.krate);
+
+    s.bind_with(|_| BindStyle::Ref);
+    let map_vars_body = s.each_variant(|v| {
+        let bindings = v.bindings();
+        v.construct(|field, i| {
+            let bi = &bindings[i];
+            if is_ignored(field) || is_constant(field) {
+                quote! { Clone::clone(#bi) }
+            } else {
+                quote_spanned! { field.ty.span() =>
+                    moniker::MapVars::<#ident_ty>::map_vars(#bi, __on_var, __on_binder)
+                }
+            }
+        })
+    });
+
+    Ok(s.gen_impl(quote! {
+        #import_moniker
+
+        gen impl moniker::MapVars<#ident_ty> for @Self {
+            fn map_vars(
+                &self,
+                __on_var: &mut impl FnMut(&moniker::Var<#ident_ty>) -> moniker::Var<#ident_ty>,
+                __on_binder: &mut impl FnMut(
+                    &moniker::Binder<#ident_ty>,
+                ) -> moniker::Binder<#ident_ty>,
+            ) -> Self {
+                match *self { #map_vars_body }
+            }
+        }
+    }))
+}
+
+decl_derive!([Visit, attributes(visit, moniker)] => visit_derive);
+
+fn visit_derive(s: Structure) -> proc_macro2::TokenStream {
+    try_visit_derive(s).unwrap_or_else(compile_error)
+}
+
+fn try_visit_derive(mut s: Structure) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let options = parse_container_options(&s.ast().attrs)?;
+    check_not_remote(&options)?;
+    add_bounds(&mut s, options.bound);
+    let moniker_path = match options.krate {
+        Some(ref path) => quote! { #path },
+        None => quote! { ::moniker },
+    };
+    let import_moniker = import_moniker(options.krate);
+    for variant in s.variants() {
+        for binding in variant.bindings() {
+            parse_field_options(&binding.ast().attrs)?;
+        }
+    }
+
+    // The visitor trait to call, generating a new one with a hook for each
+    // variant if one was not supplied
+    let mut visitor = None;
+    for attr in &s.ast().attrs {
+        if attr.path().is_ident("visit") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("visitor") {
+                    let lit = meta.value()?.parse::<syn::LitStr>()?;
+                    visitor = Some(lit.parse::<syn::Path>()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `visitor = \"...\"`"))
+                }
+            })?;
+        }
+    }
+
+    // Transparent types delegate to the visitor of their only field, rather
+    // than calling hooks of their own
+    if options.transparent {
+        check_transparent(&s)?;
+        if visitor.is_some() {
+            return Err(syn::Error::new_spanned(
+                &s.ast().ident,
+                "transparent types can not be given a visitor",
+            ));
+        }
+        let predicates = s.variants()[0]
+            .bindings()
+            .iter()
+            .filter(|bi| !is_ignored(bi.ast()) && !is_constant(bi.ast()))
+            .map(|bi| {
+                let ty = &bi.ast().ty;
+                syn::parse_quote! { #ty: moniker::Visit<__V> }
+            })
+            .collect::<Vec<syn::WherePredicate>>();
+        for predicate in predicates {
+            s.add_where_predicate(predicate);
+        }
+    }
+
+    let (visitor_bound, visitor_trait) = match visitor {
+        Some(path) => (Some(quote! { #path }), None),
+        None if options.transparent => (None, None),
+        None => {
+            let ast = s.ast();
+            let ident = &ast.ident;
+            let vis = &ast.vis;
+            let trait_ident = syn::Ident::new(&format!("{}Visitor", ident), ident.span());
+            let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+            let hooks = s.variants().iter().map(|v| {
+                let hook = hook_ident(v);
+                let doc = match v.prefix {
+                    Some(prefix) => {
+                        format!(" Called when visiting `{}::{}`", prefix, v.ast().ident)
+                    },
+                    None => format!(" Called when visiting `{}`", v.ast().ident),
+                };
+                let params = v.bindings().iter().map(|bi| {
+                    let ty = &bi.ast().ty;
+                    quote! { _: &#ty }
+                });
+                quote! {
+                    #[doc = #doc]
+                    fn #hook(&mut self, #(#params),*) {}
+                }
+            });
+
+            let doc = format!(
+                " A visitor for `{}`, with a hook for each of its variants",
+                ident
+            );
+            let visitor_trait = quote! {
+                #[doc = #doc]
+                #vis trait #trait_ident #impl_generics : #moniker_path::ScopeVisitor #where_clause {
+                    #(#hooks)*
+                }
+            };
+
+            (
+                Some(quote! { #trait_ident #ty_generics }),
+                Some(visitor_trait),
+            )
+        },
+    };
+
+    s.bind_with(|_| BindStyle::Ref);
+    let has_hooks = visitor_trait.is_some();
+    let visit_body = s.each_variant(|v| {
+        let hook_call = match visitor_bound {
+            Some(ref visitor_path) if has_hooks => {
+                let hook = hook_ident(v);
+                let bindings = v.bindings();
+                quote! { <__V as #visitor_path>::#hook(__visitor, #(#bindings),*); }
+            },
+            _ => quote!(),
+        };
+        let walk = v
+            .bindings()
+            .iter()
+            .filter(|bi| !is_ignored(bi.ast()) && !is_constant(bi.ast()))
+            .map(|bi| {
+                quote_spanned! { field_span(bi) =>
+                    moniker::Visit::<__V>::visit(#bi, __visitor);
+                }
+            });
+
+        quote! {
+            #hook_call
+            #(#walk)*
+        }
+    });
+
+    let visitor_bound = match visitor_bound {
+        Some(visitor_path) => quote! { #visitor_path + ?Sized },
+        None => quote! { ?Sized },
+    };
+    let visit_impl = s.gen_impl(quote! {
+        #import_moniker
+
+        gen impl<__V: #visitor_bound> moniker::Visit<__V> for @Self {
+            fn visit(&self, __visitor: &mut __V) {
+                match *self { #visit_body }
+            }
+        }
+    });
+
+    Ok(quote! {
+        #visitor_trait
+        #visit_impl
+    })
+}
+
+decl_derive!([DisplayTerm, attributes(moniker)] => display_term_derive);
+
+fn display_term_derive(s: Structure) -> proc_macro2::TokenStream {
+    try_display_term_derive(s).unwrap_or_else(compile_error)
+}
+
+fn try_display_term_derive(mut s: Structure) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let options = parse_container_options(&s.ast().attrs)?;
+    let ident_ty = options.ident_ty();
+    check_not_remote(&options)?;
+    add_bounds(&mut s, options.bound);
+    filter_ignored(&mut s)?;
+    if options.transparent {
+        check_transparent(&s)?;
+    }
+    span_bindings(&mut s);
+    let import_moniker = import_moniker(options.krate);
+
+    s.bind_with(|_| BindStyle::Ref);
+    let transparent = options.transparent;
+    let fmt_term_body = s.each_variant(|v| {
+        // Transparent types are displayed as their only field
+        if transparent {
+            let bi = &v.bindings()[0];
+            return quote_spanned! { field_span(bi) =>
+                moniker::DisplayTerm::<#ident_ty>::fmt_term(#bi, __env, __f)
+            };
+        }
+
+        let name = v.ast().ident.to_string();
+        let fields = v.bindings().iter().enumerate().map(|(i, bi)| {
+            let label = match bi.ast().ident {
+                Some(ref ident) if i == 0 => format!("{}: ", ident),
+                Some(ref ident) => format!(", {}: ", ident),
+                None if i == 0 => String::new(),
+                None => String::from(", "),
+            };
+            // Constant fields contain no variables, so we can use their
+            // debug representation
+            let fmt_field = if is_constant(bi.ast()) {
+                quote! { write!(__f, "{:?}", #bi)?; }
+            } else {
+                quote_spanned! { field_span(bi) =>
+                    moniker::DisplayTerm::<#ident_ty>::fmt_term(#bi, __env, __f)?;
+                }
+            };
+            quote! {
+                __f.write_str(#label)?;
+                #fmt_field
+            }
+        });
+
+        let (open, close) = match *v.ast().fields {
+            _ if v.bindings().is_empty() => (String::new(), String::new()),
+            syn::Fields::Named(_) => (String::from(" { "), String::from(" }")),
+            syn::Fields::Unnamed(_) | syn::Fields::Unit => (String::from("("), String::from(")")),
+        };
+
+        quote! {
+            __f.write_str(#name)?;
+            __f.write_str(#open)?;
+            #(#fields)*
+            __f.write_str(#close)
+        }
+    });
+
+    Ok(s.gen_impl(quote! {
+        #import_moniker
+
+        gen impl moniker::DisplayTerm<#ident_ty> for @Self {
+            fn fmt_term(
+                &self,
+                __env: &mut moniker::DisplayEnv<#ident_ty>,
+                __f: &mut ::std::fmt::Formatter,
+            ) -> ::std::fmt::Result {
+                match *self { #fmt_term_body }
+            }
+        }
+    }))
+}
+
+decl_derive!([ArbitraryTerm, attributes(moniker)] => arbitrary_term_derive);
+
+fn arbitrary_term_derive(s: Structure) -> proc_macro2::TokenStream {
+    try_arbitrary_term_derive(s).unwrap_or_else(compile_error)
+}
+
+fn try_arbitrary_term_derive(mut s: Structure) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let options = parse_container_options(&s.ast().attrs)?;
+    check_not_remote(&options)?;
+    if let Some(ref ident) = options.ident {
+        return Err(syn::Error::new_spanned(
+            ident,
+            "arbitrary terms can only be generated for `String` identifiers",
+        ));
+    }
+    add_bounds(&mut s, options.bound);
+    for variant in s.variants() {
+        for binding in variant.bindings() {
+            parse_field_options(&binding.ast().attrs)?;
+        }
+    }
+    if options.transparent {
+        check_transparent(&s)?;
+    }
+    let import_moniker = import_moniker(options.krate);
+
+    let variants = s.variants().iter().map(|v| {
+        // Ignored fields are filled in with their default values, and
+        // constant fields are generated using their `Arbitrary` impls
+        let mut strategies = Vec::new();
+        let mut fields = Vec::new();
+        for (i, bi) in v.bindings().iter().enumerate() {
+            if is_ignored(bi.ast()) {
+                continue;
+            }
+            let ty = &bi.ast().ty;
+            let field = syn::Ident::new(&format!("__field_{}", i), field_span(bi));
+            let strategy = if is_constant(bi.ast()) {
+                quote_spanned! { field_span(bi) =>
+                    Some(moniker::proptest::strategy::Strategy::boxed(
+                        moniker::proptest::arbitrary::any::<#ty>(),
+                    ))
+                }
+            } else {
+                quote_spanned! { field_span(bi) =>
+                    <#ty as moniker::ArbitraryTerm>::arbitrary_term(&__env)
+                }
+            };
+            strategies.push(strategy);
+            fields.push(field);
+        }
+
+        let construct = v.construct(|field, i| {
+            if is_ignored(field) {
+                quote! { ::std::default::Default::default() }
+            } else {
+                let field = syn::Ident::new(&format!("__field_{}", i), field.ty.span());
+                quote! { #field }
+            }
+        });
+
+        if fields.is_empty() {
+            return quote! {
+                __variants.push(moniker::proptest::strategy::Strategy::boxed(
+                    moniker::proptest::strategy::LazyJust::new(|| #construct),
+                ));
+            };
+        }
+
+        // Combine the strategies of the fields into nested pairs, so that we
+        // aren't limited by the size of the tuples that implement `Strategy`
+        let strategy = fields.iter().rev().fold(
+            quote! { moniker::proptest::strategy::Just(()) },
+            |rest, field| quote! { (#field, #rest) },
+        );
+        let pattern = fields
+            .iter()
+            .rev()
+            .fold(quote! { () }, |rest, field| quote! { (#field, #rest) });
+
+        quote! {
+            if let (#(Some(#fields),)*) = (#(#strategies,)*) {
+                __variants.push(moniker::proptest::strategy::Strategy::boxed(
+                    moniker::proptest::strategy::Strategy::prop_map(
+                        #strategy,
+                        |#pattern| #construct,
+                    ),
+                ));
+            }
+        }
+    });
+
+    // Transparent types don't count towards the depth of the generated terms
+    let env = if options.transparent {
+        quote! { let __env = __env.clone(); }
+    } else {
+        quote! { let __env = __env.deeper()?; }
+    };
+
+    let arbitrary_term_impl = s.gen_impl(quote! {
+        #import_moniker
+
+        gen impl moniker::ArbitraryTerm for @Self {
+            fn arbitrary_term(
+                __env: &moniker::ArbitraryEnv,
+            ) -> Option<moniker::proptest::strategy::BoxedStrategy<Self>> {
+                #env
+                let mut __variants = Vec::new();
+                #(#variants)*
+                match __variants.len() {
+                    0 => None,
+                    _ => Some(moniker::proptest::strategy::Strategy::boxed(
+                        moniker::proptest::strategy::Union::new(__variants),
+                    )),
+                }
+            }
+        }
+    });
+
+    let message = format!(
+        "no `{}` could be generated in the environment, try increasing its depth",
+        s.ast().ident,
+    );
+    s.add_bounds(AddBounds::None);
+    s.add_where_predicate(syn::parse_quote! { Self: moniker::ArbitraryTerm });
+    let arbitrary_impl = s.gen_impl(quote! {
+        #import_moniker
+
+        gen impl moniker::proptest::arbitrary::Arbitrary for @Self {
+            type Parameters = moniker::ArbitraryEnv;
+            type Strategy = moniker::proptest::strategy::BoxedStrategy<Self>;
+
+            fn arbitrary_with(__env: moniker::ArbitraryEnv) -> Self::Strategy {
+                <Self as moniker::ArbitraryTerm>::arbitrary_term(&__env).expect(#message)
+            }
+        }
+    });
+
+    Ok(quote! {
+        #arbitrary_term_impl
+        #arbitrary_impl
+    })
+}
+
+/// The name of the visitor hook for a variant, eg. `visit_app` for `Expr::App`
+fn hook_ident(v: &synstructure::VariantInfo) -> syn::Ident {
+    let ident = &v.ast().ident;
+    let mut name = String::from("visit_");
+    for (i, ch) in ident.to_string().chars().enumerate() {
+        if ch.is_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.extend(ch.to_lowercase());
+    }
+    syn::Ident::new(&name, ident.span())
+}
+
+/// Options that can be set on types using `#[moniker(...)]` attributes
+#[derive(Default)]
+struct ContainerOptions {
+    /// Where predicates that replace the inferred bounds
+    bound: Option<Vec<syn::WherePredicate>>,
+    /// The path to the `moniker` crate, for when it is re-exported from
+    /// another crate
+    krate: Option<syn::Path>,
+    /// Don't derive `BoundTerm` when deriving `Alpha`
+    skip_term: bool,
+    /// Don't derive `BoundPattern` when deriving `Alpha`
+    skip_pattern: bool,
+    /// Delegate to the only field of a struct, treating the struct as if it
+    /// were the type of that field
+    transparent: bool,
+    /// The path to a type from another crate that this type mirrors
+    remote: Option<syn::Path>,
+    /// The type of the identifiers used in variables, defaulting to `String`
+    ident: Option<syn::Type>,
+}
+
+impl ContainerOptions {
+    /// The type of the identifiers used in variables
+    fn ident_ty(&self) -> proc_macro2::TokenStream {
+        match self.ident {
+            None => quote! { String },
+            Some(ref ident) => quote! { #ident },
+        }
+    }
+}
+
+fn parse_container_options(attrs: &[syn::Attribute]) -> Result<ContainerOptions, syn::Error> {
+    let mut options = ContainerOptions::default();
+    for attr in attrs {
+        if attr.path().is_ident("moniker") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("bound") {
+                    parse_bound(&meta, &mut options.bound)
+                } else if meta.path.is_ident("crate") {
+                    let lit = meta.value()?.parse::<syn::LitStr>()?;
+                    options.krate = Some(lit.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("skip_term") {
+                    options.skip_term = true;
+                    Ok(())
+                } else if meta.path.is_ident("skip_pattern") {
+                    options.skip_pattern = true;
+                    Ok(())
+                } else if meta.path.is_ident("transparent") {
+                    options.transparent = true;
+                    Ok(())
+                } else if meta.path.is_ident("remote") {
+                    let lit = meta.value()?.parse::<syn::LitStr>()?;
+                    options.remote = Some(lit.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("ident") {
+                    let lit = meta.value()?.parse::<syn::LitStr>()?;
+                    options.ident = Some(lit.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error(concat!(
+                        "expected `bound = \"...\"`, `crate = \"...\"`, `ident = \"...\"`, ",
+                        "`remote = \"...\"`, `skip_term`, `skip_pattern`, or `transparent`",
+                    )))
+                }
+            })?;
+        }
+    }
+    Ok(options)
+}
+
+fn parse_bound(
+    meta: &syn::meta::ParseNestedMeta,
+    predicates: &mut Option<Vec<syn::WherePredicate>>,
+) -> Result<(), syn::Error> {
+    let lit = meta.value()?.parse::<syn::LitStr>()?;
+    let bound = lit.parse_with(Punctuated::<_, Token![,]>::parse_terminated)?;
+    predicates.get_or_insert_with(Vec::new).extend(bound);
+    Ok(())
+}
+
+/// Add the bounds required by the generated impls
+///
+/// By default each type parameter must implement the trait being derived. We
+/// avoid bounding the field types, because this would result in an overflow
+/// when evaluating the bounds of recursive types. The inferred bounds can be
+/// replaced using a container attribute, for example:
+///
+/// ```rust,ignore
+/// #[moniker(bound = "T: BoundTerm<String> + Clone")]
+/// ```
+fn add_bounds(s: &mut Structure, predicates: Option<Vec<syn::WherePredicate>>) {
+    match predicates {
+        None => {
+            s.add_bounds(AddBounds::Generics);
+        },
+        Some(predicates) => {
+            s.add_bounds(AddBounds::None);
+            for predicate in predicates {
+                s.add_where_predicate(predicate);
+            }
+        },
+    }
+}
+
+/// A copy of a mirror type that refers to the remote type through an import
+///
+/// This lets us match on the variants of the remote type using the fields of
+/// the mirror type.
+fn remote_ast(ast: &syn::DeriveInput) -> syn::DeriveInput {
+    let mut remote_ast = ast.clone();
+    remote_ast.ident = syn::Ident::new("__Remote", ast.ident.span());
+    remote_ast
+}
+
+/// Generate functions on a mirror type that work with the remote type
+///
+/// The orphan rule prevents us from implementing the traits for types from
+/// other crates, so fields of the remote type must be marked with
+/// `#[moniker(with = "...")]` to call these functions instead.
+fn remote_impl(
+    s: &Structure,
+    mirror: &syn::Ident,
+    remote: &syn::Path,
+    bound: proc_macro2::TokenStream,
+    predicates: Option<Vec<syn::WherePredicate>>,
+    import_moniker: proc_macro2::TokenStream,
+    fns: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let generics = &s.ast().generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let mut where_clause = where_clause
+        .cloned()
+        .unwrap_or_else(|| syn::parse_quote!(where));
+    match predicates {
+        None => where_clause.predicates.extend(generics.type_params().map(
+            |param| -> syn::WherePredicate {
+                let ident = &param.ident;
+                syn::parse_quote! { #ident: #bound }
+            },
+        )),
+        Some(predicates) => where_clause.predicates.extend(predicates),
+    }
+
+    quote! {
+        const _: () = {
+            #import_moniker
+
+            use #remote as __Remote;
+
+            impl #impl_generics #mirror #ty_generics #where_clause {
+                #fns
+            }
+        };
+    }
+}
+
+/// Bring the `moniker` crate into scope for the generated impls
+fn import_moniker(krate: Option<syn::Path>) -> proc_macro2::TokenStream {
+    match krate {
+        None => quote! { extern crate moniker; },
+        Some(path) => quote! { use #path as moniker; },
+    }
+}
+
+/// Options that can be set on fields using `#[moniker(...)]` attributes
+#[derive(Default)]
+struct FieldOptions {
+    /// Skip the field when comparing for alpha equality and when traversing
+    /// variables, as if it were wrapped in an `Ignore`
+    ignore: bool,
+    /// Compare the field using `PartialEq`, and skip it when traversing
+    /// variables, because it does not contain any variables or binders
+    constant: bool,
+    /// Call the functions generated for a remote type on the field, rather
+    /// than the methods of the trait
+    with: Option<syn::Path>,
+    /// Compare the elements of a collection for alpha equality in any order
+    unordered: bool,
+}
+
+fn parse_field_options(attrs: &[syn::Attribute]) -> Result<FieldOptions, syn::Error> {
+    let mut options = FieldOptions::default();
+    for attr in attrs {
+        if attr.path().is_ident("moniker") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("ignore") {
+                    options.ignore = true;
+                    Ok(())
+                } else if meta.path.is_ident("constant") {
+                    options.constant = true;
+                    Ok(())
+                } else if meta.path.is_ident("with") {
+                    let lit = meta.value()?.parse::<syn::LitStr>()?;
+                    options.with = Some(lit.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("unordered") {
+                    options.unordered = true;
+                    Ok(())
+                } else {
+                    Err(meta
+                        .error("expected `ignore`, `constant`, `unordered`, or `with = \"...\"`"))
+                }
+            })?;
+        }
+    }
+    Ok(options)
+}
+
+/// Options that can be set on the variants of enums using `#[moniker(...)]`
+/// attributes
+#[derive(Default)]
+struct VariantOptions {
+    /// Compare the fields of the variant for alpha equality in any order
+    commutative: bool,
+    /// Flatten nested uses of the variant before comparing its fields, using
+    /// the given function to get at the enum that each field refers to
+    associative: Option<syn::Path>,
+}
+
+impl VariantOptions {
+    /// Whether the fields of the variant are compared modulo commutativity or
+    /// associativity, rather than one by one
+    fn is_ac(&self) -> bool {
+        self.commutative || self.associative.is_some()
+    }
+}
+
+fn parse_variant_options(
+    s: &Structure,
+    variant: &synstructure::VariantInfo,
+) -> Result<VariantOptions, syn::Error> {
+    let mut options = VariantOptions::default();
+    match s.ast().data {
+        syn::Data::Enum(_) => {},
+        // NOTE: The attributes of a struct's only variant are the attributes
+        // of the struct itself
+        syn::Data::Struct(_) | syn::Data::Union(_) => return Ok(options),
+    }
+    for attr in variant.ast().attrs {
+        if attr.path().is_ident("moniker") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("commutative") {
+                    options.commutative = true;
+                    Ok(())
+                } else if meta.path.is_ident("associative") {
+                    let lit = meta.value()?.parse::<syn::LitStr>()?;
+                    options.associative = Some(lit.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `commutative` or `associative = \"...\"`"))
+                }
+            })?;
+        }
+    }
+    if options.is_ac() {
+        for binding in variant.bindings() {
+            let field_options = parse_field_options(&binding.ast().attrs)?;
+            if field_options.constant || field_options.unordered || field_options.with.is_some() {
+                return Err(syn::Error::new_spanned(
+                    binding.ast(),
+                    "the fields of commutative or associative variants can't be marked with \
+                     `constant`, `unordered`, or `with`",
+                ));
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// An expression that collects references to the fields of a commutative or
+/// associative variant into a `Vec`
+///
+/// The fields of associative variants are flattened, replacing each field
+/// that refers to the same variant with the fields of that variant, so that
+/// `Par(Par(a, b), c)` and `Par(a, Par(b, c))` both result in `[a, b, c]`.
+fn ac_fields(v: &synstructure::VariantInfo, options: &VariantOptions) -> proc_macro2::TokenStream {
+    let bindings = v.bindings();
+    let as_term = match options.associative {
+        None => return quote! { vec![#(#bindings),*] },
+        Some(ref as_term) => as_term,
+    };
+
+    let mut nested = v.clone();
+    nested.binding_name(|field, i| syn::Ident::new(&format!("__nested_{}", i), field.ty.span()));
+    let nested_pat = nested.pat();
+    // Push the fields in reverse, so that they are popped off in order
+    let push_fields = bindings.iter().rev();
+    let push_nested = nested.bindings().iter().rev();
+
+    quote! {{
+        let mut __stack = Vec::new();
+        #(__stack.push(#push_fields);)*
+        let mut __fields = Vec::new();
+        while let Some(__field) = __stack.pop() {
+            #[allow(unreachable_patterns)]
+            match *#as_term(__field) {
+                #nested_pat => { #(__stack.push(#push_nested);)* },
+                _ => __fields.push(__field),
+            }
+        }
+        __fields
+    }}
+}
+
+/// The function to call on a field, which will be one of the functions
+/// generated for a remote type if the field is marked with
+/// `#[moniker(with = "...")]`, and otherwise the method of the trait
+fn field_fn(
+    bi: &synstructure::BindingInfo,
+    trait_path: &proc_macro2::TokenStream,
+    method: &str,
+    remote_fn: &str,
+) -> proc_macro2::TokenStream {
+    let with = parse_field_options(&bi.ast().attrs)
+        .ok()
+        .and_then(|options| options.with);
+    match with {
+        None => {
+            let method = syn::Ident::new(method, field_span(bi));
+            quote_spanned! { field_span(bi) => #trait_path::#method }
+        },
+        Some(with) => {
+            let remote_fn = syn::Ident::new(remote_fn, field_span(bi));
+            quote_spanned! { field_span(bi) => #with::#remote_fn }
+        },
+    }
+}
+
+/// The body of `visit_nodes`, which visits each value of the type as a node,
+/// with its fields one level deeper, unless the type is transparent
+fn visit_nodes_body(
+    s: &Structure,
+    transparent: bool,
+    trait_path: &proc_macro2::TokenStream,
+    remote_fn: &str,
+) -> proc_macro2::TokenStream {
+    s.each_variant(|v| {
+        let depth = if transparent {
+            quote!(__depth)
+        } else {
+            quote!(__depth + 1)
+        };
+        let visit_fields = v.bindings().iter().map(|bi| {
+            let visit_nodes = field_fn(bi, trait_path, "visit_nodes", remote_fn);
+            quote_spanned! { field_span(bi) => #visit_nodes(#bi, #depth, __on_node); }
+        });
+        if transparent {
+            quote! { #(#visit_fields)* }
+        } else {
+            quote! { __on_node(__depth); #(#visit_fields)* }
+        }
+    })
+}
+
+/// The body of `visit_scopes`, which records each field in the path to the
+/// scopes that it contains, unless the type is transparent
+fn visit_scopes_body(
+    s: &Structure,
+    transparent: bool,
+    trait_path: &proc_macro2::TokenStream,
+    remote_fn: &str,
+) -> proc_macro2::TokenStream {
+    let is_enum = match s.ast().data {
+        syn::Data::Enum(_) => true,
+        syn::Data::Struct(_) | syn::Data::Union(_) => false,
+    };
+    s.each_variant(|v| {
+        let visit_fields = v.bindings().iter().map(|bi| {
+            let visit_scopes = field_fn(bi, trait_path, "visit_scopes", remote_fn);
+            let visit_field = quote_spanned! { field_span(bi) =>
+                #visit_scopes(#bi, __path, __binders, __on_scope);
+            };
+            if transparent {
+                return visit_field;
+            }
+            let label = field_label(v, bi, is_enum);
+            quote! {
+                __path.push(moniker::PathSegment::Field(#label));
+                #visit_field
+                __path.pop();
+            }
+        });
+        quote! { #(#visit_fields)* }
+    })
+}
+
+/// The label of a field in the path to a mismatch, like `App.0` or `Let.body`
+fn field_label(
+    variant: &synstructure::VariantInfo,
+    bi: &synstructure::BindingInfo,
+    is_enum: bool,
+) -> String {
+    let field = match bi.ast().ident {
+        Some(ref ident) => ident.to_string(),
+        None => variant
+            .ast()
+            .fields
+            .iter()
+            .position(|field| std::ptr::eq(field, bi.ast()))
+            .unwrap()
+            .to_string(),
+    };
+    if is_enum {
+        format!("{}.{}", variant.ast().ident, field)
+    } else {
+        field
+    }
+}
+
+fn is_ignored(field: &syn::Field) -> bool {
+    parse_field_options(&field.attrs)
+        .map(|options| options.ignore)
+        .unwrap_or(false)
+}
+
+fn is_constant(field: &syn::Field) -> bool {
+    parse_field_options(&field.attrs)
+        .map(|options| options.constant)
+        .unwrap_or(false)
+}
+
+fn is_unordered(field: &syn::Field) -> bool {
+    parse_field_options(&field.attrs)
+        .map(|options| options.unordered)
+        .unwrap_or(false)
+}
+
+/// Check that a type marked with `#[moniker(transparent)]` is a struct with
+/// exactly one field that is not ignored
+fn check_transparent(s: &Structure) -> Result<(), syn::Error> {
+    let ast = s.ast();
+    match ast.data {
+        syn::Data::Struct(_) => {},
+        syn::Data::Enum(_) | syn::Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &ast.ident,
+                "only structs can be transparent",
+            ));
+        },
+    }
+
+    let fields = s.variants()[0]
+        .bindings()
+        .iter()
+        .filter(|bi| !is_ignored(bi.ast()))
+        .count();
+    if fields != 1 {
+        return Err(syn::Error::new_spanned(
+            &ast.ident,
+            "transparent structs must have exactly one field that is not ignored",
+        ));
+    }
+    Ok(())
+}
+
+/// Mirrors of remote types are only supported by the `BoundTerm` and
+/// `BoundPattern` derives
+fn check_not_remote(options: &ContainerOptions) -> Result<(), syn::Error> {
+    match options.remote {
+        None => Ok(()),
+        Some(ref remote) => Err(syn::Error::new_spanned(
+            remote,
+            "remote types are only supported when deriving `BoundTerm` or `BoundPattern`",
+        )),
+    }
+}
+
+/// Remove the bindings of fields marked with `#[moniker(ignore)]`
+fn filter_ignored(s: &mut Structure) -> Result<(), syn::Error> {
+    for variant in s.variants() {
+        for binding in variant.bindings() {
+            parse_field_options(&binding.ast().attrs)?;
+        }
+    }
+    s.filter(|bi| !is_ignored(bi.ast()));
+    Ok(())
+}
+
+/// Returns the replacement types, with `None` standing in for the type that
+/// the trait is being derived for
+fn replacements_or_self(
+    replacements: Vec<syn::Type>,
+    ident: &syn::Ident,
+) -> Vec<Option<syn::Type>> {
+    if replacements.is_empty() {
+        return vec![None];
+    }
+
+    replacements
+        .into_iter()
+        .map(|ty| match ty {
+            syn::Type::Path(ref path) if path.qself.is_none() && path.path.is_ident(ident) => None,
+            syn::Type::Path(ref path) if path.qself.is_none() && path.path.is_ident("Self") => None,
+            ty => Some(ty),
+        })
+        .collect()
+}
+
+/// Give the bindings the spans of their fields, for better error messages
+fn span_bindings(s: &mut Structure) {
+    s.binding_name(|field, i| syn::Ident::new(&format!("__binding_{}", i), field.ty.span()));
+}
+
+/// The span of the type of a binding's field
+///
+/// We use this for the method calls on each field, so that if the type of the
+/// field does not implement the required trait, the error will point at the
+/// offending field, rather than at the derive.
+fn field_span(bi: &synstructure::BindingInfo) -> proc_macro2::Span {
+    bi.ast().ty.span()
+}
+
+/// Convert an error into a `compile_error!` invocation
+///
+/// We avoid `syn::Error::to_compile_error`, because it refers to
+/// `::core::compile_error`, which can't be resolved from crates using the 2015
+/// edition.
+fn compile_error(error: syn::Error) -> proc_macro2::TokenStream {
+    error
+        .into_iter()
+        .map(|error| {
+            let message = error.to_string();
+            quote_spanned! { error.span() => compile_error!(#message); }
+        })
+        .collect()
 }
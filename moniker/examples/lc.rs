@@ -1,12 +1,24 @@
 //! An example of using the `moniker` library to implement the untyped lambda
 //! calculus
 
+#[cfg(feature = "lasso")]
+extern crate lasso;
 #[macro_use]
 extern crate moniker;
+#[cfg(feature = "proptest")]
+#[macro_use]
+extern crate proptest;
+#[cfg(feature = "string-interner")]
+extern crate string_interner;
 
-use moniker::{Binder, Scope, Var};
+use moniker::{Binder, DisplayTerm, Scope, Subst, Var};
 use std::rc::Rc;
 
+#[cfg(all(test, feature = "proptest"))]
+use moniker::{ArbitraryEnv, FreeVar};
+#[cfg(all(test, feature = "proptest"))]
+use proptest::arbitrary::{any, any_with};
+
 /// Expressions
 ///
 /// ```text
@@ -14,9 +26,13 @@ use std::rc::Rc;
 ///     | \x => e    anonymous functions
 ///     | e₁ e₂      function application
 /// ````
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, AlphaHash, AlphaOrd, BoundTerm, DisplayTerm, MapVars, Subst, Visit, Zip)]
+#[cfg_attr(feature = "proptest", derive(ArbitraryTerm))]
+#[subst(replacement = "RcExpr")]
+#[zip(replacement = "RcExpr")]
 pub enum Expr {
     /// Variables
+    #[subst(var)]
     Var(Var<String>),
     /// Lambda expressions
     Lam(Scope<Binder<String>, RcExpr>),
@@ -24,34 +40,23 @@ pub enum Expr {
     App(RcExpr, RcExpr),
 }
 
+/// The pointer that subexpressions are stored behind
+///
+/// This can be replaced with `Arc` to share expressions between threads.
+type Ptr<T> = Rc<T>;
+
 /// Reference counted expressions
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, AlphaHash, AlphaOrd, BoundTerm, DisplayTerm, MapVars, Subst, Visit, Zip)]
+#[cfg_attr(feature = "proptest", derive(ArbitraryTerm))]
+#[moniker(transparent)]
 pub struct RcExpr {
-    pub inner: Rc<Expr>,
+    pub inner: Ptr<Expr>,
 }
 
 impl From<Expr> for RcExpr {
     fn from(src: Expr) -> RcExpr {
         RcExpr {
-            inner: Rc::new(src),
-        }
-    }
-}
-
-impl RcExpr {
-    // FIXME: auto-derive this somehow!
-    fn subst<N: PartialEq<Var<String>>>(&self, name: &N, replacement: &RcExpr) -> RcExpr {
-        match *self.inner {
-            Expr::Var(ref var) if name == var => replacement.clone(),
-            Expr::Var(_) => self.clone(),
-            Expr::Lam(ref scope) => RcExpr::from(Expr::Lam(Scope {
-                unsafe_pattern: scope.unsafe_pattern.clone(),
-                unsafe_body: scope.unsafe_body.subst(name, replacement),
-            })),
-            Expr::App(ref fun, ref arg) => RcExpr::from(Expr::App(
-                fun.subst(name, replacement),
-                arg.subst(name, replacement),
-            )),
+            inner: Ptr::new(src),
         }
     }
 }
@@ -61,10 +66,7 @@ pub fn eval(expr: &RcExpr) -> RcExpr {
     match *expr.inner {
         Expr::Var(_) | Expr::Lam(_) => expr.clone(),
         Expr::App(ref fun, ref arg) => match *eval(fun).inner {
-            Expr::Lam(ref scope) => {
-                let (binder, body) = scope.clone().unbind();
-                eval(&body.subst(&binder, &eval(arg)))
-            },
+            Expr::Lam(ref scope) => eval(&scope.clone().instantiate(&eval(arg))),
             _ => expr.clone(),
         },
     }
@@ -89,4 +91,2107 @@ fn test_eval() {
     assert_term_eq!(eval(&expr), RcExpr::from(Expr::Var(Var::Free(y.clone()))));
 }
 
+#[test]
+fn test_eval_capture_avoiding() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let y1 = FreeVar::fresh_named("y");
+    let y2 = FreeVar::fresh_named("y");
+
+    // expr = (\x -> \y -> x) y
+    let expr = RcExpr::from(Expr::App(
+        RcExpr::from(Expr::Lam(Scope::new(
+            Binder(x.clone()),
+            RcExpr::from(Expr::Lam(Scope::new(
+                Binder(y1.clone()),
+                RcExpr::from(Expr::Var(Var::Free(x.clone()))),
+            ))),
+        ))),
+        RcExpr::from(Expr::Var(Var::Free(y2.clone()))),
+    ));
+
+    // The free `y` must not be captured by the inner binder
+    assert_term_eq!(
+        eval(&expr),
+        RcExpr::from(Expr::Lam(Scope::new(
+            Binder(y1.clone()),
+            RcExpr::from(Expr::Var(Var::Free(y2.clone()))),
+        ))),
+    );
+}
+
+#[test]
+fn test_free_vars() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+
+    // expr = (\x -> x y) x
+    let expr = RcExpr::from(Expr::App(
+        RcExpr::from(Expr::Lam(Scope::new(
+            Binder(x.clone()),
+            RcExpr::from(Expr::App(
+                RcExpr::from(Expr::Var(Var::Free(x.clone()))),
+                RcExpr::from(Expr::Var(Var::Free(y.clone()))),
+            )),
+        ))),
+        RcExpr::from(Expr::Var(Var::Free(x.clone()))),
+    ));
+
+    assert_eq!(expr.free_vars(), [x, y].iter().cloned().collect());
+}
+
+#[test]
+fn test_replace_var() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // expr = \y => x
+    let expr = RcExpr::from(Expr::Lam(Scope::new(Binder(y.clone()), var(&x))));
+    let expr = expr.replace_var(&x, &var(&y));
+
+    // The `y` that replaced `x` must not be captured by the binder
+    assert_eq!(expr.free_vars(), [y.clone()].iter().cloned().collect());
+    let z = FreeVar::fresh_named("z");
+    let expected = RcExpr::from(Expr::Lam(Scope::new(Binder(z), var(&y))));
+    assert_term_eq!(expr, expected);
+}
+
+#[test]
+fn test_substitution() {
+    use moniker::{BoundTerm, FreeVar, Substitution};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let app = |f, a| RcExpr::from(Expr::App(f, a));
+
+    // s1 = [x := y z]
+    let mut s1 = Substitution::new();
+    assert!(s1.insert(x.clone(), app(var(&y), var(&z))).is_none());
+    // s2 = [y := z, z := x]
+    let s2 = vec![(y.clone(), var(&z)), (z.clone(), var(&x))]
+        .into_iter()
+        .collect::<Substitution<_, _>>();
+
+    // The mappings are applied simultaneously
+    assert_term_eq!(s2.apply(&app(var(&y), var(&z))), app(var(&z), var(&x)));
+
+    // s2 ∘ s1 = [x := z x, y := z, z := x]
+    let composed = s2.compose(&s1);
+    assert_eq!(composed.len(), 3);
+    assert_term_eq!(composed.get(&x).unwrap().clone(), app(var(&z), var(&x)));
+    let expr = app(var(&x), var(&y));
+    assert_term_eq!(composed.apply(&expr), s2.apply(&s1.apply(&expr)));
+
+    // Restricting to the free variables of a term
+    let restricted = composed.restrict(&var(&y).free_vars());
+    assert_eq!(restricted.len(), 1);
+    assert!(restricted.contains(&y));
+    assert!(!restricted.contains(&x));
+}
+
+#[test]
+fn test_subst_mut() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \y => x y
+    let mut expr = RcExpr::from(Expr::Lam(Scope::new(
+        Binder(y.clone()),
+        RcExpr::from(Expr::App(var(&x), var(&y))),
+    )));
+    let expected = expr.subst(&x, &var(&z));
+
+    // Nodes that are not shared are substituted into in place
+    let root = Rc::as_ptr(&expr.inner);
+    expr.subst_mut(&x, &var(&z));
+    assert_eq!(Rc::as_ptr(&expr.inner), root);
+    assert_term_eq!(expr, expected);
+
+    // Shared nodes are rebuilt, leaving the other references untouched
+    let shared = expr.clone();
+    expr.substs_mut(&[(z.clone(), var(&x))]);
+    assert!(!Rc::ptr_eq(&expr.inner, &shared.inner));
+    assert_term_eq!(shared, expected);
+    assert_term_eq!(expr, expected.subst(&z, &var(&x)));
+
+    // Shared nodes are left shared if the substitution would not change them
+    let shared = expr.clone();
+    expr.subst_mut(&y, &var(&z));
+    assert!(Rc::ptr_eq(&expr.inner, &shared.inner));
+    expr.substs_mut(&[(y.clone(), var(&z))]);
+    assert!(Rc::ptr_eq(&expr.inner, &shared.inner));
+}
+
+#[test]
+fn test_subst_preserves_sharing() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // (\y => y z) x
+    let fun = RcExpr::from(Expr::Lam(Scope::new(
+        Binder(y.clone()),
+        RcExpr::from(Expr::App(var(&y), var(&z))),
+    )));
+    let expr = RcExpr::from(Expr::App(fun.clone(), var(&x)));
+
+    // Substituting a variable that does not appear returns the original term
+    assert!(Rc::ptr_eq(&expr.subst(&y, &var(&z)).inner, &expr.inner));
+    assert!(Rc::ptr_eq(
+        &expr.substs(&[(y.clone(), var(&z))]).inner,
+        &expr.inner
+    ));
+
+    // Only the spine leading to the substituted variable is rebuilt
+    let replaced = expr.subst(&x, &var(&y));
+    assert!(!Rc::ptr_eq(&replaced.inner, &expr.inner));
+    match *replaced.inner {
+        Expr::App(ref replaced_fun, _) => assert!(Rc::ptr_eq(&replaced_fun.inner, &fun.inner)),
+        _ => panic!("expected an application"),
+    }
+    assert_term_eq!(replaced, RcExpr::from(Expr::App(fun.clone(), var(&y))));
+}
+
+#[test]
+fn test_rename_free_var() {
+    use moniker::{BoundPattern, BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \x => x y
+    let lam = |fv: &FreeVar<String>| {
+        RcExpr::from(Expr::Lam(Scope::new(
+            Binder(x.clone()),
+            RcExpr::from(Expr::App(var(&x), var(fv))),
+        )))
+    };
+
+    // Renaming a free variable
+    let mut expr = lam(&y);
+    expr.rename_free_var(&y, &z);
+    assert_term_eq!(expr, lam(&z));
+
+    // Renaming a bound variable has no effect until it is unbound
+    expr.rename_free_var(&x, &y);
+    assert_term_eq!(expr, lam(&z));
+
+    let scope = match *expr.inner {
+        Expr::Lam(ref scope) => scope.clone(),
+        _ => panic!("expected a lambda"),
+    };
+    let (mut binder, mut body) = scope.unbind();
+    let old = binder.0.clone();
+    let w = FreeVar::fresh_named("w");
+    binder.rename_free_var(&old, &w);
+    body.rename_free_var(&old, &w);
+    assert_eq!(binder, Binder(w.clone()));
+    assert_term_eq!(body, RcExpr::from(Expr::App(var(&w), var(&z))));
+    assert_term_eq!(RcExpr::from(Expr::Lam(Scope::new(binder, body))), lam(&z));
+}
+
+#[test]
+fn test_fold_with_binders() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // expr = \x => \y => x y z
+    let expr = RcExpr::from(Expr::Lam(Scope::new(
+        Binder(x.clone()),
+        RcExpr::from(Expr::Lam(Scope::new(
+            Binder(y.clone()),
+            RcExpr::from(Expr::App(
+                RcExpr::from(Expr::App(var(&x), var(&y))),
+                var(&z),
+            )),
+        ))),
+    )));
+
+    // For each variable, record the binder it refers to and the binders in scope
+    let vars = expr.fold_with_binders(Vec::new(), |mut vars, binders, var| {
+        let binder = match *var {
+            Var::Free(_) => None,
+            Var::Bound(ref bound_var) => binders.lookup(bound_var).cloned(),
+        };
+        vars.push((binder, binders.binders().cloned().collect::<Vec<_>>()));
+        vars
+    });
+
+    let in_scope = vec![Binder(y.clone()), Binder(x.clone())];
+    assert_eq!(
+        vars,
+        vec![
+            (Some(Binder(x.clone())), in_scope.clone()),
+            (Some(Binder(y.clone())), in_scope.clone()),
+            (None, in_scope),
+        ],
+    );
+}
+
+#[test]
+fn test_free_vars_ordered() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // expr = y (\x -> x z) y x
+    let expr = RcExpr::from(Expr::App(
+        RcExpr::from(Expr::App(
+            RcExpr::from(Expr::App(
+                var(&y),
+                RcExpr::from(Expr::Lam(Scope::new(
+                    Binder(x.clone()),
+                    RcExpr::from(Expr::App(var(&x), var(&z))),
+                ))),
+            )),
+            var(&y),
+        )),
+        var(&x),
+    ));
+
+    assert_eq!(expr.free_vars_ordered(), vec![y, z, x]);
+}
+
+#[test]
+fn test_count_occurrences() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // expr = (\x -> x y) x y
+    let expr = RcExpr::from(Expr::App(
+        RcExpr::from(Expr::App(
+            RcExpr::from(Expr::Lam(Scope::new(
+                Binder(x.clone()),
+                RcExpr::from(Expr::App(var(&x), var(&y))),
+            ))),
+            var(&x),
+        )),
+        var(&y),
+    ));
+
+    assert_eq!(expr.count_occurrences(&x), 1);
+    assert_eq!(expr.count_occurrences(&y), 2);
+    assert_eq!(expr.count_occurrences(&z), 0);
+
+    let counts = expr.occurrence_counts();
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts[&x], 1);
+    assert_eq!(counts[&y], 2);
+}
+
+#[test]
+fn test_is_closed() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \x => x
+    let id = RcExpr::from(Expr::Lam(Scope::new(Binder(x.clone()), var(&x))));
+    assert!(id.is_closed());
+
+    // \x => x y
+    let open = RcExpr::from(Expr::Lam(Scope::new(
+        Binder(x.clone()),
+        RcExpr::from(Expr::App(var(&x), var(&y))),
+    )));
+    assert!(!open.is_closed());
+}
+
+#[test]
+fn test_try_visit_vars() {
+    use moniker::{BoundTerm, FreeVar};
+    use std::ops::ControlFlow;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // y (x x)
+    let expr = RcExpr::from(Expr::App(
+        var(&y),
+        RcExpr::from(Expr::App(var(&x), var(&x))),
+    ));
+
+    let mut visited = 0;
+    let found = expr.try_visit_vars(&mut |var| {
+        visited += 1;
+        match *var {
+            Var::Free(ref free_var) if *free_var == y => ControlFlow::Break(free_var.clone()),
+            _ => ControlFlow::Continue(()),
+        }
+    });
+    assert_eq!(found, ControlFlow::Break(y.clone()));
+    assert_eq!(visited, 1);
+
+    let mut visited = 0;
+    let found = expr.try_visit_vars(&mut |_| {
+        visited += 1;
+        ControlFlow::<()>::Continue(())
+    });
+    assert_eq!(found, ControlFlow::Continue(()));
+    assert_eq!(visited, 3);
+}
+
+#[test]
+fn test_term_size_and_depth() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // y
+    assert_eq!(var(&y).term_size(), 1);
+    assert_eq!(var(&y).term_depth(), 1);
+
+    // \x => x
+    let id = RcExpr::from(Expr::Lam(Scope::new(Binder(x.clone()), var(&x))));
+    assert_eq!(id.term_size(), 2);
+    assert_eq!(id.term_depth(), 2);
+
+    // (\x => x) y
+    let app = RcExpr::from(Expr::App(id, var(&y)));
+    assert_eq!(app.term_size(), 4);
+    assert_eq!(app.term_depth(), 3);
+}
+
+#[test]
+fn test_shadowing() {
+    use moniker::{BoundTerm, FreeVar, PathSegment, Shadowed, Shadowing};
+
+    let x1 = FreeVar::fresh_named("x");
+    let x2 = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let lam =
+        |fv: &FreeVar<String>, body| RcExpr::from(Expr::Lam(Scope::new(Binder(fv.clone()), body)));
+
+    // \x => \y => x
+    assert_eq!(lam(&x1, lam(&y, var(&x1))).shadowing(&[]), vec![]);
+
+    // \x => \x => x
+    assert_eq!(
+        lam(&x1, lam(&x2, var(&x2))).shadowing(&[]),
+        vec![Shadowing {
+            path: vec![
+                PathSegment::Field("Lam.0"),
+                PathSegment::Body(vec![Binder(x1.clone())]),
+                PathSegment::Field("Lam.0"),
+            ],
+            binder: Binder(x2.clone()),
+            shadowed: Shadowed::Binder(Binder(x1.clone())),
+        }],
+    );
+
+    // \x => x, where `x` is already defined
+    assert_eq!(
+        lam(&x2, var(&x2)).shadowing(&[x1.clone()]),
+        vec![Shadowing {
+            path: vec![PathSegment::Field("Lam.0")],
+            binder: Binder(x2.clone()),
+            shadowed: Shadowed::FreeVar(x1.clone()),
+        }],
+    );
+}
+
+#[test]
+fn test_term_eq_optional_binders() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    type Lam = Scope<Option<Binder<String>>, RcExpr>;
+
+    // Anonymous parameters bind nothing, so the body's variables stay free
+    let anon = Lam::new(None, var(&x));
+    let named = Lam::new(Some(Binder(y.clone())), var(&x));
+    let binding = Lam::new(Some(Binder(x.clone())), var(&x));
+
+    assert_eq!(anon.free_vars(), [x.clone()].iter().cloned().collect());
+    assert!(binding.free_vars().is_empty());
+    assert!(!anon.term_eq(&named));
+    assert_term_eq!(anon, Lam::new(None, var(&x)));
+}
+
+#[test]
+fn test_map_body() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \x => x y
+    let scope = Scope::new(Binder(x.clone()), RcExpr::from(Expr::App(var(&x), var(&y))));
+
+    // Free variables can be substituted without unbinding the scope
+    assert_term_eq!(
+        scope.map_body(|body| body.subst(&y, &var(&z))),
+        Scope::new(Binder(x.clone()), RcExpr::from(Expr::App(var(&x), var(&z)))),
+    );
+}
+
+#[test]
+fn test_unbind3() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let lam = |fv: &FreeVar<String>| Scope::new(Binder(fv.clone()), var(fv));
+
+    let (binder1, body1, binder2, body2, binder3, body3) =
+        lam(&x).unbind3(lam(&y), lam(&z)).unwrap();
+
+    // The same fresh name is used for each of the scopes
+    assert_eq!(binder1, binder2);
+    assert_eq!(binder1, binder3);
+    assert_term_eq!(body1, var(&binder1.0));
+    assert_term_eq!(body2, var(&binder1.0));
+    assert_term_eq!(body3, var(&binder1.0));
+}
+
+#[test]
+fn test_unbind_all() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let lam = |fv: &FreeVar<String>| Scope::new(Binder(fv.clone()), var(fv));
+
+    let unbound = Scope::unbind_all(vec![lam(&x), lam(&y), lam(&x), lam(&y)]).unwrap();
+
+    assert_eq!(unbound.len(), 4);
+    let fresh = unbound[0].0.clone();
+    for (binder, body) in unbound {
+        assert_eq!(binder, fresh);
+        assert_term_eq!(body, var(&fresh.0));
+    }
+}
+
+#[test]
+fn test_unbind_with() {
+    use moniker::{FreeVar, RawId, UniqueId};
+
+    let x = FreeVar::fresh_named("x");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \x => x
+    let scope = Scope::new(Binder(x.clone()), var(&x));
+
+    // Use a counter to number the fresh variables deterministically
+    let unbind = |scope: Scope<Binder<String>, RcExpr>| {
+        let mut next_id = RawId::max_value();
+        scope.unbind_with(|binder| {
+            next_id -= 1;
+            Binder(FreeVar {
+                unique_id: UniqueId::from_raw(next_id),
+                pretty_name: binder.0.pretty_name.clone(),
+            })
+        })
+    };
+
+    let (binder1, body1) = unbind(scope.clone());
+    let (binder2, body2) = unbind(scope);
+    assert_eq!(binder1.0.unique_id.to_raw(), RawId::max_value() - 1);
+    assert_eq!(binder1, binder2);
+    assert_term_eq!(body1, var(&binder1.0));
+    assert_term_eq!(body2, var(&binder1.0));
+}
+
+#[cfg(feature = "u64-ids")]
+#[test]
+fn test_stable_name_supply() {
+    use moniker::{FreeVar, NameSupply, Scope, StableNameSupply, UniqueId};
+
+    let x = FreeVar::fresh_named("x");
+    let elaborate = |key: &str| {
+        let mut supply = StableNameSupply::new("src/main.lc").child(key);
+        // \x => x
+        let scope = Scope::new(
+            Binder(x.clone()),
+            RcExpr::from(Expr::Var(Var::Free(x.clone()))),
+        );
+        let (binder, _) = scope.unbind_from(&mut supply);
+        (binder.0, supply.next_id())
+    };
+
+    // Elaborating the same definition again results in the same variables
+    let (binder1, id1) = elaborate("main::id");
+    let (binder2, id2) = elaborate("main::id");
+    assert_eq!(binder1, binder2);
+    assert_eq!(id1, id2);
+    assert_ne!(binder1.unique_id, id1);
+
+    // Other definitions get other variables
+    let (binder3, _) = elaborate("main::const");
+    assert_ne!(binder1, binder3);
+
+    // The ids are kept apart from the ones generated by the other supplies
+    assert!(binder1.unique_id.to_raw() >> (UniqueId::BITS - 2) == 0b11);
+}
+
+#[test]
+fn test_compare_vars_with_strings() {
+    use moniker::{Binder, FreeVar, Scope, Var};
+
+    let x = FreeVar::from("x");
+    assert!(x == "x");
+    assert!(x != "y");
+    assert!(x == *"x");
+    assert!(FreeVar::<String>::fresh_unnamed() != "x");
+
+    // Converting the same string twice results in different variables
+    assert_ne!(x, FreeVar::from("x"));
+
+    assert!(Binder::from("y") == "y");
+    assert!(Var::from("z") == "z");
+
+    // Bound variables are compared using the name hints of their binders
+    let scope = Scope::new(Binder(x.clone()), RcExpr::from(Expr::Var(Var::Free(x))));
+    let (_, body) = scope.into_raw_parts();
+    match *body.inner {
+        Expr::Var(ref var) => assert!(*var == "x"),
+        ref expr => panic!("expected a variable, found {:?}", expr),
+    }
+}
+
+#[test]
+fn test_fresh_unnamed() {
+    use moniker::{with_isolated_ids, FreeVar};
+
+    with_isolated_ids(|| {
+        let temp = FreeVar::<String>::fresh_unnamed();
+        assert_eq!(temp.pretty_name, None);
+        assert_eq!(temp.to_string(), "$0");
+
+        // Attaching a name later on keeps the variable the same
+        let named = temp.clone().with_pretty_name("t");
+        assert_eq!(named, temp);
+        assert_eq!(named.to_string(), "t");
+    });
+}
+
+#[test]
+fn test_display_vars() {
+    use moniker::{with_isolated_ids, Binder, FreeVar, Var};
+
+    with_isolated_ids(|| {
+        let x1 = FreeVar::<String>::fresh_named("x");
+        let x2 = FreeVar::<String>::fresh_named("x");
+        let temp = FreeVar::<String>::fresh_unnamed();
+
+        // Suffixes are only added to tell apart variables with the same hint
+        assert_eq!(x2.to_string(), "x");
+        assert_eq!(Var::Free(x1.clone()).to_string(), "x1");
+        assert_eq!(Binder(temp.clone()).to_string(), "$0");
+
+        // Each variable keeps its name, however it is displayed
+        assert_eq!(Binder(x2.clone()).to_string(), "x");
+        assert_eq!(Var::Free(x2.clone()).to_string(), "x");
+        assert_eq!(x1.to_string(), "x1");
+        assert_eq!(Var::Free(temp.clone()).to_string(), "$0");
+
+        // Hints that clash with the names chosen for other variables are
+        // also disambiguated
+        let x3 = FreeVar::<String>::fresh_named("x1");
+        assert_eq!(Binder(x3).to_string(), "x11");
+
+        // Isolated calls choose their names separately
+        with_isolated_ids(|| assert_eq!(x1.to_string(), "x"));
+        assert_eq!(x1.to_string(), "x1");
+    });
+}
+
+#[test]
+fn test_bound_var_pretty_name() {
+    use moniker::{Binder, FreeVar, Scope, Var};
+
+    let x = FreeVar::fresh_named("x");
+
+    // \x => x
+    let scope = Scope::new(
+        Binder(x.clone()),
+        RcExpr::from(Expr::Var(Var::Free(x.clone()))),
+    );
+
+    // The bound variable remembers the name of its binder
+    let (_, body) = scope.into_raw_parts();
+    match *body.inner {
+        Expr::Var(Var::Bound(ref bound_var)) => {
+            assert_eq!(bound_var.pretty_name, Some("x".to_owned()));
+            assert_eq!(bound_var.to_string(), "x@0.0");
+            assert!(format!("{:?}", bound_var).contains("\"x\""));
+        },
+        ref expr => panic!("expected a bound variable, found {:?}", expr),
+    }
+}
+
+#[test]
+fn test_with_isolated_ids() {
+    use moniker::{with_isolated_ids, FreeVar, UniqueId};
+
+    let names = || {
+        let x = FreeVar::<String>::fresh_named("x");
+        let y = FreeVar::<String>::fresh_named("y");
+        format!("{}{} {}{}", x, x.unique_id, y, y.unique_id)
+    };
+
+    // Each call numbers its ids from zero
+    assert_eq!(with_isolated_ids(names), "x0 y1");
+    assert_eq!(with_isolated_ids(names), "x0 y1");
+
+    // Nested calls restore the outer numbering once they return
+    let ids = with_isolated_ids(|| {
+        let first = UniqueId::new();
+        let inner = with_isolated_ids(UniqueId::new);
+        let second = UniqueId::new();
+        (first.to_raw(), inner.to_raw(), second.to_raw())
+    });
+    assert_eq!(ids, (0, 0, 1));
+}
+
+#[test]
+fn test_unbind_from() {
+    use moniker::{FreeVar, NameSupply, RawId, SeededNameSupply, UniqueId};
+
+    let x = FreeVar::fresh_named("x");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    // The ids are numbered below a tag that keeps them apart from the ids
+    // generated by the other supplies
+    let seeded = |number: RawId| UniqueId::from_raw(number | 0b10 << (UniqueId::BITS - 2));
+
+    // \x => x
+    let scope = Scope::new(Binder(x.clone()), var(&x));
+
+    // Supplies with the same seed generate the same binders
+    let mut supply1 = SeededNameSupply::new(1000);
+    let mut supply2 = SeededNameSupply::new(1000);
+    let (binder1, body1) = scope.clone().unbind_from(&mut supply1);
+    let (binder2, body2) = scope.unbind_from(&mut supply2);
+    assert_eq!(binder1.0.unique_id, seeded(1000));
+    assert_eq!(binder1, binder2);
+    assert_eq!(binder1.0.pretty_name, Some(String::from("x")));
+    assert_term_eq!(body1, var(&binder1.0));
+    assert_term_eq!(body2, var(&binder1.0));
+
+    // Later ids carry on from where the supply left off
+    assert_eq!(supply1.next_id(), seeded(1001));
+    let y = FreeVar::<String>::fresh_from(&mut supply1, None);
+    assert_eq!(y.unique_id, seeded(1002));
+    let Binder(y) = Binder::fresh_from(&mut supply2, Some(String::from("y")));
+    assert_eq!(y.unique_id, seeded(1001));
+
+    // The other ways of unbinding a scope can draw from a supply too
+    let mut supply = SeededNameSupply::new(0);
+    let scope = Scope::new(Binder(x.clone()), var(&x));
+    assert_eq!(
+        scope.unbind_ref_from(&mut supply).binders[0].0.unique_id,
+        seeded(0)
+    );
+    let unbound = scope.clone().unbind_lazy_from(&mut supply);
+    assert_eq!(
+        unbound.into_fresh_from(&mut supply).0 .0.unique_id,
+        seeded(1)
+    );
+    let nested = Scope::new_nested(Binder(x.clone()), Binder(y.clone()), var(&x));
+    let (Binder(x1), Binder(y1), _) = nested.unbind_nested_from(&mut supply);
+    assert_eq!((x1.unique_id, y1.unique_id), (seeded(2), seeded(3)));
+}
+
+#[test]
+fn test_unique_ids_across_threads() {
+    use std::collections::HashSet;
+    use std::thread;
+
+    use moniker::{IdStrategy, UniqueId};
+
+    let threads = (0..4)
+        .map(|_| thread::spawn(|| (0..2000).map(|_| UniqueId::new()).collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+
+    let mut ids = HashSet::new();
+    for thread in threads {
+        for id in thread.join().unwrap() {
+            assert!(ids.insert(id), "id {} was generated twice", id);
+        }
+    }
+
+    let strategy = if cfg!(feature = "thread-local-ids") {
+        IdStrategy::ThreadLocal
+    } else {
+        IdStrategy::Atomic
+    };
+    assert_eq!(UniqueId::STRATEGY, strategy);
+}
+
+#[test]
+fn test_unique_id_config() {
+    use std::mem;
+
+    use moniker::{IdOverflow, RawId, UniqueId};
+
+    let bits = if cfg!(feature = "u64-ids") { 64 } else { 32 };
+    assert_eq!(UniqueId::BITS, bits);
+    assert_eq!(mem::size_of::<UniqueId>() * 8, bits as usize);
+    assert_eq!(mem::size_of::<RawId>() * 8, bits as usize);
+
+    let overflow = if cfg!(feature = "wrapping-ids") {
+        IdOverflow::Wrap
+    } else {
+        IdOverflow::Panic
+    };
+    assert_eq!(UniqueId::OVERFLOW, overflow);
+
+    // Every generated id has been issued
+    let id = UniqueId::new();
+    assert!(id.to_raw() < UniqueId::issued());
+}
+
+#[test]
+fn test_unbind_ref() {
+    use moniker::{FreeVar, ScopeState};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \x => x y
+    let scope = Scope::new(Binder(x.clone()), RcExpr::from(Expr::App(var(&x), var(&y))));
+    let unbound = scope.unbind_ref();
+    let fresh = unbound.binders[0].clone();
+    assert_ne!(fresh, Binder(x.clone()));
+
+    // The body is borrowed from the scope, so bound variables are looked up
+    match *unbound.body.inner {
+        Expr::App(ref fun, _) => match *fun.inner {
+            Expr::Var(Var::Bound(ref bound_var)) => {
+                assert_eq!(
+                    unbound.lookup(ScopeState::new(), bound_var),
+                    Some(fresh.0.clone())
+                );
+            },
+            _ => panic!("expected a bound variable"),
+        },
+        _ => panic!("expected an application"),
+    }
+
+    let (binder, body) = unbound.into_owned();
+    assert_eq!(binder, fresh);
+    assert_term_eq!(body, RcExpr::from(Expr::App(var(&fresh.0), var(&y))));
+}
+
+#[test]
+fn test_unbind_lazy() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \x => x y
+    let scope = Scope::new(Binder(x.clone()), RcExpr::from(Expr::App(var(&x), var(&y))));
+
+    // Rebinding the body reuses the original binder
+    let mut unbound = scope.clone().unbind_lazy();
+    assert_eq!(*unbound.pattern(), Binder(x.clone()));
+    assert_term_eq!(*unbound.body(), RcExpr::from(Expr::App(var(&x), var(&y))));
+    *unbound.body_mut() = unbound.body().subst(&y, &var(&z));
+    let rebound = unbound.rebind();
+    assert_eq!(rebound.clone().into_raw_parts().0, Binder(x.clone()));
+    assert_term_eq!(
+        rebound,
+        Scope::new(Binder(x.clone()), RcExpr::from(Expr::App(var(&x), var(&z)))),
+    );
+
+    // The binder is freshened if the body escapes
+    let (binder, body) = scope.clone().unbind_lazy().into_fresh();
+    assert_ne!(binder, Binder(x.clone()));
+    assert_term_eq!(body, RcExpr::from(Expr::App(var(&binder.0), var(&y))));
+
+    // The binder is freshened up front if it would capture a free variable
+    let scope = scope.subst(&y, &var(&x));
+    let unbound = scope.unbind_lazy();
+    let binder = unbound.pattern().clone();
+    assert_ne!(binder, Binder(x.clone()));
+    assert_term_eq!(
+        *unbound.body(),
+        RcExpr::from(Expr::App(var(&binder.0), var(&x)))
+    );
+    assert_eq!(unbound.into_fresh().0, binder);
+}
+
+#[test]
+fn test_instantiate() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let lam = |fv: &FreeVar<String>, body| Scope::new(Binder(fv.clone()), body);
+
+    // \x => \x => x
+    let scope = lam(&x, RcExpr::from(Expr::Lam(lam(&x, var(&x)))));
+
+    // Only the variables bound by the outer scope are replaced
+    assert_term_eq!(
+        scope.instantiate(&var(&y)),
+        RcExpr::from(Expr::Lam(lam(&x, var(&x)))),
+    );
+
+    // \x => x x
+    let scope = lam(&x, RcExpr::from(Expr::App(var(&x), var(&x))));
+
+    // The argument may refer to the original binder
+    assert_term_eq!(
+        scope.instantiate(&var(&x)),
+        RcExpr::from(Expr::App(var(&x), var(&x))),
+    );
+
+    // \x => y, with x substituted for y
+    let z = FreeVar::fresh_named("z");
+    let scope = lam(&x, var(&y)).subst(&y, &var(&x));
+
+    // Free occurrences of the binder in the body are left alone
+    assert_term_eq!(scope.instantiate(&var(&z)), var(&x));
+}
+
+#[test]
+fn test_nested_scopes() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \x => \y => x y z, and \x => \x => x, where the inner binder shadows
+    // the outer one
+    let body = RcExpr::from(Expr::App(
+        var(&x),
+        RcExpr::from(Expr::App(var(&y), var(&z))),
+    ));
+    for (inner, body) in vec![(y.clone(), body), (x.clone(), var(&x))] {
+        let nested = Scope::new_nested(Binder(x.clone()), Binder(inner.clone()), body.clone());
+        let expected = Scope::new(Binder(x.clone()), Scope::new(Binder(inner), body));
+        assert_term_eq!(nested, expected);
+
+        let (Binder(x1), Binder(y1), body1) = nested.unbind_nested();
+        let (Binder(x2), inner) = expected.unbind();
+        let (Binder(y2), mut body2) = inner.unbind();
+        body2.rename_free_var(&x2, &x1);
+        body2.rename_free_var(&y2, &y1);
+        assert_term_eq!(body1, body2);
+    }
+}
+
+#[test]
+fn test_instantiate_suspended() {
+    use moniker::{BoundTerm, FreeVar, Suspended};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // (\x => x y) z ~> z y
+    let scope = Scope::new(Binder(x.clone()), RcExpr::from(Expr::App(var(&x), var(&y))));
+    let body = scope.instantiate_suspended(var(&z));
+    assert_eq!(body.pending().len(), 1);
+    assert_term_eq!(body.force(), RcExpr::from(Expr::App(var(&z), var(&y))));
+
+    // Later substitutions are applied to the earlier replacements, so the
+    // term is only traversed once when it is forced
+    let body = body.subst(z.clone(), var(&x)).subst(y.clone(), var(&z));
+    assert_eq!(body.pending().len(), 3);
+    assert!(body.free_vars().contains(&x));
+    assert!(!body.free_vars().contains(&y));
+    assert_term_eq!(
+        body.into_forced(),
+        RcExpr::from(Expr::App(var(&x), var(&z)))
+    );
+
+    let body = Suspended::new(var(&x));
+    assert_term_eq!(
+        body.subst(x.clone(), var(&y))
+            .subst(x.clone(), var(&z))
+            .force(),
+        var(&y)
+    );
+
+    // \x => y, with x substituted for y
+    let scope = Scope::new(Binder(x.clone()), var(&y)).subst(&y, &var(&x));
+
+    // Free occurrences of the binder in the body are left alone
+    let body = scope.instantiate_suspended(var(&z));
+    assert_term_eq!(body.force(), var(&x));
+
+    // Forcing through a shared reference only applies the substitutions once
+    assert!(Rc::ptr_eq(&body.force_ref().inner, &body.force_ref().inner));
+}
+
+#[test]
+fn test_shift() {
+    use moniker::{BinderIndex, BoundTerm, BoundVar, FreeVar, ScopeOffset};
+
+    let y = FreeVar::fresh_named("y");
+    let bound = |scope, binder| {
+        RcExpr::from(Expr::Var(Var::Bound(BoundVar::new(
+            ScopeOffset(scope),
+            BinderIndex(binder),
+            None,
+        ))))
+    };
+    let lam = |body| RcExpr::from(Expr::Lam(Scope::from_raw_parts(Binder(y.clone()), body)));
+
+    // \y => y @0.0
+    let mut expr = lam(RcExpr::from(Expr::App(bound(0, 0), bound(1, 0))));
+    expr.shift(2);
+
+    // \y => y @2.0
+    let expected = lam(RcExpr::from(Expr::App(bound(0, 0), bound(3, 0))));
+    assert_term_eq!(expr, expected);
+
+    let var: BoundVar<String> = BoundVar::new(ScopeOffset(1), BinderIndex(0), None);
+    let shifted = var.shift(2);
+    assert_eq!(shifted.scope(), ScopeOffset(3));
+    assert_eq!(shifted.binder(), BinderIndex(0));
+}
+
+#[test]
+fn test_ann_binder() {
+    use moniker::{AnnBinder, BoundTerm, FreeVar};
+
+    /// The number of times that a parameter is used
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    enum Multiplicity {
+        Zero,
+        One,
+    }
+
+    type Lam = Scope<AnnBinder<String, Multiplicity>, RcExpr>;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \x => x, with different annotations on the parameter
+    let lam1 = Lam::new(AnnBinder::new(x.clone(), Multiplicity::One), var(&x));
+    let lam2 = Lam::new(AnnBinder::new(y.clone(), Multiplicity::Zero), var(&y));
+
+    // The annotations are ignored when comparing terms
+    assert!(lam1.term_eq(&lam2));
+
+    // But are preserved when unbinding
+    let (param, body) = lam1.unbind();
+    assert_eq!(param.annotation, Multiplicity::One);
+    assert_term_eq!(body, var(&param.binder.0));
+}
+
+#[test]
+fn test_spanned() {
+    use moniker::{BoundTerm, FreeVar, Spanned};
+
+    type Span = (usize, usize);
+    type Lam = Scope<Spanned<Span, Binder<String>>, Spanned<Span, RcExpr>>;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \x => x, at different locations in the source
+    let lam1 = Lam::new(
+        Spanned::new((1, 2), Binder(x.clone())),
+        Spanned::new((6, 7), var(&x)),
+    );
+    let lam2 = Lam::new(
+        Spanned::new((3, 4), Binder(y.clone())),
+        Spanned::new((8, 9), var(&y)),
+    );
+
+    // The spans are ignored when comparing terms
+    assert!(lam1.term_eq(&lam2));
+
+    // But are preserved when unbinding
+    let (binder, body) = lam1.unbind();
+    assert_eq!(binder.span, (1, 2));
+    assert_eq!(body.span, (6, 7));
+    assert_term_eq!(body.inner, var(&binder.inner.0));
+
+    // And when substituting
+    let body = body.subst(&binder.inner.0, &var(&y));
+    assert_eq!(body.span, (6, 7));
+    assert_term_eq!(body.inner, var(&y));
+}
+
+#[test]
+fn test_closed() {
+    use moniker::{BoundTerm, Closed, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \x => x
+    let id = RcExpr::from(Expr::Lam(Scope::new(Binder(x.clone()), var(&x))));
+    let id = Closed::new(id).unwrap();
+    assert!(id.free_vars().is_empty());
+
+    // Substituting into a closed term leaves it untouched
+    assert_term_eq!(id.subst(&x, &var(&y)), id);
+
+    // \x => y
+    let open = RcExpr::from(Expr::Lam(Scope::new(Binder(x.clone()), var(&y))));
+    let open = Closed::new(open).unwrap_err();
+
+    // Binding the free variables closes the term
+    let closed = Closed::abstract_over(open);
+    assert!(closed.free_vars().is_empty());
+    let (binders, body) = closed.into_inner().unbind();
+    assert_eq!(binders.len(), 1);
+    let lam = RcExpr::from(Expr::Lam(Scope::new(Binder(x.clone()), var(&binders[0].0))));
+    assert_term_eq!(body, lam);
+}
+
+#[test]
+fn test_fv() {
+    use moniker::{BoundTerm, FreeVar, Fv};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // x y
+    let app = Fv::new(RcExpr::from(Expr::App(var(&x), var(&y))));
+    assert!(app.contains_free_var(&x));
+    assert!(!app.contains_free_var(&z));
+
+    // Substituting a variable that does not appear shares the original term
+    let same = app.subst(&z, &var(&x));
+    assert!(Rc::ptr_eq(&same.inner, &app.inner));
+
+    // Substituting a variable that appears updates the cache
+    let replaced = app.subst(&x, &var(&z));
+    assert_term_eq!(
+        replaced.into_inner(),
+        RcExpr::from(Expr::App(var(&z), var(&y)))
+    );
+    assert!(!app.subst(&x, &var(&z)).contains_free_var(&x));
+    assert!(app.subst(&x, &var(&z)).contains_free_var(&z));
+
+    // Binding and unbinding a variable keeps the cache up to date
+    let scope = Scope::new(Binder(x.clone()), app.clone());
+    let (_, closed) = scope.clone().into_raw_parts();
+    assert!(!closed.contains_free_var(&x));
+    assert!(closed.contains_free_var(&y));
+    let (Binder(x1), body) = scope.unbind();
+    assert!(body.contains_free_var(&x1));
+    assert_eq!(body.free_vars(), body.inner.free_vars());
+}
+
+#[test]
+fn test_hashed() {
+    use moniker::{BoundTerm, FreeVar, Hashed};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let lam = |fv: &FreeVar<String>, body| Expr::Lam(Scope::new(Binder(fv.clone()), body));
+
+    // \x => x and \y => y are alpha equivalent, so they have the same hash
+    let id_x = Hashed::new(lam(&x, var(&x)));
+    let id_y = Hashed::new(lam(&y, var(&y)));
+    let konst = Hashed::new(lam(&x, var(&y)));
+    assert_eq!(id_x.hash_value(), id_y.hash_value());
+    assert!(id_x.term_eq(&id_y));
+    assert!(!id_x.term_eq(&konst));
+
+    // Substituting updates the cached hash
+    let replaced = konst.subst(&y, &var(&z));
+    assert_eq!(
+        replaced.hash_value(),
+        Hashed::new(lam(&x, var(&z))).hash_value()
+    );
+
+    // Binding and unbinding variables keeps the cached hash up to date
+    let app = Hashed::new(RcExpr::from(Expr::App(var(&x), var(&y))));
+    let scope = Scope::new(Binder(x.clone()), app.clone());
+    let (_, closed) = scope.clone().into_raw_parts();
+    assert_eq!(
+        closed.hash_value(),
+        Hashed::new(closed.clone().into_inner()).hash_value()
+    );
+    assert_ne!(closed.hash_value(), app.hash_value());
+    let (Binder(x1), body) = scope.unbind();
+    assert_eq!(
+        body.hash_value(),
+        Hashed::new(body.clone().into_inner()).hash_value()
+    );
+    assert_term_eq!(
+        body.into_inner(),
+        RcExpr::from(Expr::App(var(&x1), var(&y)))
+    );
+}
+
+#[test]
+fn test_hash_cons() {
+    use moniker::{FreeVar, HashCons};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let lam = |fv: &FreeVar<String>, body| Expr::Lam(Scope::new(Binder(fv.clone()), body));
+
+    let mut store = HashCons::new();
+
+    // \x => x and \y => y are alpha equivalent, so they share the same node
+    let id_x = store.intern(lam(&x, var(&x)));
+    let id_y = store.intern(lam(&y, var(&y)));
+    assert_eq!(id_x, id_y);
+    assert_eq!(store.len(), 1);
+
+    // \x => y is different
+    let konst = store.intern(lam(&x, var(&y)));
+    assert_ne!(id_x, konst);
+    assert_eq!(store.len(), 2);
+
+    // Terms are removed from the store once they are no longer used
+    drop(konst);
+    assert_eq!(store.len(), 1);
+}
+
+#[test]
+fn test_term_eq_shared() {
+    use moniker::{BinderStack, BoundTerm, OnBoundFn, OnFreeFn, PathSegment, ScopeState};
+
+    /// A term that is never equal to anything, not even itself
+    #[derive(Debug, Clone)]
+    struct Opaque;
+
+    impl BoundTerm<String> for Opaque {
+        fn term_eq(&self, _: &Opaque) -> bool {
+            false
+        }
+
+        fn close_term(&mut self, _: ScopeState, _: &impl OnFreeFn<String>) {}
+        fn open_term(&mut self, _: ScopeState, _: &impl OnBoundFn<String>) {}
+        fn visit_vars(&self, _: &mut impl FnMut(&Var<String>)) {}
+        fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<String>)) {}
+        fn visit_vars_with_binders(
+            &self,
+            _: &mut BinderStack<String>,
+            _: &mut impl FnMut(&BinderStack<String>, &Var<String>),
+        ) {
+        }
+        fn visit_scopes(
+            &self,
+            _: &mut Vec<PathSegment<String>>,
+            _: &mut BinderStack<String>,
+            _: &mut impl FnMut(&[PathSegment<String>], &BinderStack<String>, &[Binder<String>]),
+        ) {
+        }
+        fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
+    }
+
+    // Shared terms are compared by pointer, without looking inside them
+    let shared = Rc::new(Opaque);
+    assert!(shared.term_eq(&shared.clone()));
+    assert!(shared.term_eq_check(&shared.clone()).is_ok());
+    assert!(!shared.term_eq(&Rc::new(Opaque)));
+}
+
+#[test]
+fn test_with_stack_size() {
+    use moniker::{with_stack_size, BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+
+    // A term that is too deep to drop on the default stack
+    let is_closed = with_stack_size(512 * 1024 * 1024, || {
+        let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+        let deep = || (0..100_000).fold(var(&x), |expr, _| RcExpr::from(Expr::App(expr, var(&x))));
+        assert!(deep().term_eq(&deep()));
+        Scope::new(Binder(x.clone()), deep()).is_closed()
+    });
+
+    assert!(is_closed);
+}
+
+#[cfg(feature = "stacker")]
+#[test]
+fn test_deep_terms() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let deep = |fv: &FreeVar<String>| {
+        (0..50_000).fold(var(fv), |expr, _| RcExpr::from(Expr::App(expr, var(fv))))
+    };
+
+    // Dropping a deep term recurses, so it is taken apart one node at a time
+    fn drop_deep(expr: RcExpr) {
+        let mut exprs = vec![expr];
+        while let Some(expr) = exprs.pop() {
+            match Rc::try_unwrap(expr.inner) {
+                Ok(Expr::App(fun, arg)) => exprs.extend(vec![fun, arg]),
+                Ok(Expr::Lam(scope)) => exprs.push(scope.into_raw_parts().1),
+                Ok(Expr::Var(_)) | Err(_) => {},
+            }
+        }
+    }
+
+    // A term that is too deep to traverse on the default stack without
+    // growing it
+    let (open, closed) = (deep(&x), deep(&x));
+    assert!(open.term_eq(&closed));
+    assert!(!open.is_closed());
+    assert_eq!(open.free_vars().len(), 1);
+
+    let lam = RcExpr::from(Expr::Lam(Scope::new(Binder(x.clone()), closed)));
+    assert!(lam.is_closed());
+
+    let (replaced, expected) = (open.subst(&x, &var(&y)), deep(&y));
+    assert!(replaced.term_eq(&expected));
+    assert!(!replaced.free_vars().contains(&x));
+
+    drop_deep(open);
+    drop_deep(lam);
+    drop_deep(replaced);
+    drop_deep(expected);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_term_eq_and_free_vars() {
+    use moniker::{par_free_vars, par_term_eq, BoundTerm, FreeVar};
+
+    // Shared between threads, so this uses scopes over plain variables rather
+    // than reference counted expressions. There are enough definitions for
+    // them to be split between threads.
+    let free_vars = (0..1000)
+        .map(|_| FreeVar::fresh_named("x"))
+        .collect::<Vec<FreeVar<String>>>();
+    let defs = |binder: &str| {
+        free_vars
+            .chunks(2)
+            .map(|pair| {
+                let b = FreeVar::fresh_named(binder);
+                let body = vec![Var::Free(b.clone()), Var::Free(pair[0].clone())];
+                Scope::new(Binder(b), body)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let lhs = defs("a");
+    let rhs = defs("b");
+    assert!(par_term_eq(&lhs, &rhs));
+    assert!(!par_term_eq(&lhs, &rhs[1..]));
+    assert!(!par_term_eq(&lhs[1..], &rhs[..rhs.len() - 1]));
+
+    assert_eq!(par_free_vars(&lhs), lhs.free_vars());
+    assert_eq!(par_free_vars(&lhs).len(), 500);
+    assert_eq!(par_free_vars(&lhs[..10]), lhs[..10].free_vars());
+}
+
+#[test]
+fn test_named_data() {
+    use moniker::{with_isolated_ids, Binder, BoundTerm, FreeVar, Named, Scope, Var};
+
+    let x = FreeVar::<Named<String, &str>>::fresh_with_data("x", "line 1");
+
+    // \x => x
+    let scope = Scope::new(Binder(x.clone()), Var::Free(x.clone()));
+
+    // The data is kept after binding and unbinding the variable
+    let (Binder(binder), body) = scope.unbind();
+    assert_eq!(binder.data(), Some(&"line 1"));
+    match body {
+        Var::Free(ref free_var) => assert_eq!(free_var.data(), Some(&"line 1")),
+        Var::Bound(_) => panic!("expected a free variable"),
+    }
+    assert_eq!(with_isolated_ids(|| binder.to_string()), "x");
+
+    // The data is ignored when comparing names
+    let name1 = Named::new(String::from("y"), "line 2");
+    let name2 = Named::new(String::from("y"), "line 3");
+    assert_eq!(name1, name2);
+    assert!(Var::Free(x.clone()).term_eq(&Var::Free(x)));
+}
+
+#[test]
+fn test_sorted_vars() {
+    use std::rc::Rc;
+
+    use moniker::{Binder, BoundTerm, Embed, FreeVar, OtherSort, Scope, Sorted, Subst, Var};
+
+    enum TermSort {}
+    enum TypeSort {}
+
+    type TermName = Sorted<String, TermSort>;
+    type TypeName = Sorted<String, TypeSort>;
+
+    #[derive(Debug, Clone, BoundTerm)]
+    #[moniker(ident = "TypeName")]
+    enum Type {
+        Var(Var<TypeName>),
+    }
+
+    type Ann = Embed<OtherSort<TypeName, Rc<Type>>>;
+
+    #[derive(Debug, Clone, BoundTerm, Subst)]
+    #[moniker(ident = "TermName")]
+    enum Term {
+        #[subst(var)]
+        Var(Var<TermName>),
+        Lam(Scope<(Binder<TermName>, Ann), Rc<Term>>),
+    }
+
+    // The term and type variables have the same name, but different sorts
+    let x = FreeVar::<TermName>::fresh_named("a");
+    let a = FreeVar::<TypeName>::fresh_named("a");
+    let ann =
+        |a: &FreeVar<TypeName>| Embed(OtherSort::new(Rc::new(Type::Var(Var::Free(a.clone())))));
+    let lam = |a: &FreeVar<TypeName>| {
+        Term::Lam(Scope::new(
+            (Binder(x.clone()), ann(a)),
+            Rc::new(Term::Var(Var::Free(x.clone()))),
+        ))
+    };
+
+    // \(a : a) => a
+    let term = lam(&a);
+    assert!(term.is_closed());
+    assert!(term.term_eq(&lam(&a)));
+    assert!(!term.term_eq(&lam(&FreeVar::fresh_named("a"))));
+
+    // Unbinding only freshens the term variable
+    let ((binder, Embed(ann)), body) = match term {
+        Term::Lam(scope) => scope.unbind(),
+        Term::Var(_) => unreachable!(),
+    };
+    assert_ne!(binder.0, x);
+    assert!(ann.term.free_vars().contains(&a));
+
+    // Substituting for the term variable leaves the annotation alone
+    let y = FreeVar::fresh_named("y");
+    let body = body.subst(&binder.0, &Term::Var(Var::Free(y.clone())));
+    assert!(body.free_vars().contains(&y));
+    assert!(ann.term.free_vars().contains(&a));
+}
+
+#[test]
+fn test_arc_str_names() {
+    use std::sync::Arc;
+    use std::thread;
+
+    use moniker::{Binder, BoundTerm, FreeVar, Scope, Subst, Var};
+
+    #[derive(Debug, Clone, BoundTerm, Subst)]
+    #[moniker(ident = "Arc<str>")]
+    enum Term {
+        #[subst(var)]
+        Var(Var<Arc<str>>),
+        Lam(Scope<Binder<Arc<str>>, Arc<Term>>),
+        Lit(Arc<str>),
+    }
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let x = FreeVar::<Arc<str>>::fresh_named("x");
+    let var = |fv: &FreeVar<Arc<str>>| Arc::new(Term::Var(Var::Free(fv.clone())));
+
+    // \x => x
+    let term = Term::Lam(Scope::new(Binder(x.clone()), var(&x)));
+    assert_send_sync(&term);
+
+    // Cloning the variable shares the name
+    let x_clone = x.clone();
+    assert!(Arc::ptr_eq(
+        x.pretty_name.as_ref().unwrap(),
+        x_clone.pretty_name.as_ref().unwrap(),
+    ));
+
+    let other = thread::spawn(move || match term {
+        Term::Lam(scope) => {
+            let (binder, body) = scope.unbind();
+            let lit = Term::Lit(Arc::from("hello"));
+            body.subst(&binder.0, &lit)
+        },
+        _ => unreachable!(),
+    });
+    let result = other.join().unwrap();
+
+    assert!(result.term_eq(&Arc::new(Term::Lit(Arc::from("hello")))));
+}
+
+#[test]
+fn test_custom_interner() {
+    use std::collections::HashMap;
+
+    use moniker::{FreeVar, GlobalInterner, Interner, Symbol};
+
+    // An interner that numbers its strings, and is freed when it is dropped
+    #[derive(Default)]
+    struct Names {
+        ids: HashMap<String, usize>,
+        names: Vec<String>,
+    }
+
+    impl Interner for Names {
+        type Symbol = usize;
+
+        fn intern(&mut self, name: &str) -> usize {
+            let names = &mut self.names;
+            *self.ids.entry(name.to_owned()).or_insert_with(|| {
+                names.push(name.to_owned());
+                names.len() - 1
+            })
+        }
+
+        fn resolve(&self, symbol: usize) -> &str {
+            &self.names[symbol]
+        }
+    }
+
+    let mut names = Names::default();
+    let x1 = FreeVar::fresh_interned(&mut names, "x");
+    let x2 = FreeVar::fresh_interned(&mut names, "x");
+    let y = FreeVar::fresh_interned(&mut names, "y");
+
+    assert_eq!(x1.pretty_name, x2.pretty_name);
+    assert_ne!(x1, x2);
+    assert_eq!(names.names.len(), 2);
+    assert_eq!(x1.resolve_name(&names), Some("x"));
+    assert_eq!(y.resolve_name(&names), Some("y"));
+    assert_eq!(FreeVar::<usize>::fresh_unnamed().resolve_name(&names), None);
+
+    let z = FreeVar::fresh_interned(&mut GlobalInterner, "z");
+    assert_eq!(z.pretty_name, Some(Symbol::intern("z")));
+    assert_eq!(z.resolve_name(&GlobalInterner), Some("z"));
+}
+
+#[cfg(feature = "lasso")]
+#[test]
+fn test_lasso_interner() {
+    use lasso::Rodeo;
+    use moniker::{BoundTerm, FreeVar};
+
+    let mut names = Rodeo::default();
+    let x = FreeVar::fresh_interned(&mut names, "x");
+    let y = FreeVar::fresh_interned(&mut names, "y");
+    assert_eq!(x.pretty_name, Some(names.get_or_intern("x")));
+    assert_eq!(x.resolve_name(&names), Some("x"));
+
+    // \x => x y, named by the interner's keys
+    let scope = Scope::new(Binder(x.clone()), vec![Var::Free(x), Var::Free(y.clone())]);
+    let (Binder(x1), body) = scope.unbind();
+    assert_eq!(x1.resolve_name(&names), Some("x"));
+    assert!(body.term_eq(&vec![Var::Free(x1), Var::Free(y)]));
+}
+
+#[cfg(feature = "string-interner")]
+#[test]
+fn test_string_interner() {
+    use moniker::{BoundTerm, FreeVar};
+    use string_interner::DefaultStringInterner;
+
+    let mut names = DefaultStringInterner::default();
+    let x = FreeVar::fresh_interned(&mut names, "x");
+    let y = FreeVar::fresh_interned(&mut names, "y");
+    assert_eq!(x.pretty_name, Some(names.get_or_intern("x")));
+    assert_eq!(x.resolve_name(&names), Some("x"));
+
+    // \x => x y, named by the interner's symbols
+    let scope = Scope::new(Binder(x.clone()), vec![Var::Free(x), Var::Free(y.clone())]);
+    let (Binder(x1), body) = scope.unbind();
+    assert_eq!(x1.resolve_name(&names), Some("x"));
+    assert!(body.term_eq(&vec![Var::Free(x1), Var::Free(y)]));
+}
+
+#[test]
+fn test_fresh_name() {
+    use std::collections::HashSet;
+
+    use moniker::fresh_name;
+
+    let mut used = HashSet::new();
+    assert_eq!(fresh_name("x", &used), "x");
+
+    used.insert("x".to_owned());
+    used.insert("x2".to_owned());
+    assert_eq!(fresh_name("x", &used), "x1");
+
+    used.insert("x1".to_owned());
+    assert_eq!(fresh_name("x", &used), "x3");
+    assert_eq!(fresh_name("y", &used), "y");
+}
+
+#[test]
+fn test_display_term() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let x_inner = FreeVar::fresh_named("x");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // expr = \x => \y => x y
+    let expr = RcExpr::from(Expr::Lam(Scope::new(
+        Binder(x.clone()),
+        RcExpr::from(Expr::Lam(Scope::new(
+            Binder(y.clone()),
+            RcExpr::from(Expr::App(var(&x), var(&y))),
+        ))),
+    )));
+
+    assert_eq!(
+        expr.display_term().to_string(),
+        "Lam(<x> Lam(<y> App(Var(x), Var(y))))",
+    );
+
+    // The inner binder only needs to be renamed if the outer one is used
+    // within its body
+    let shadow = |body: &FreeVar<String>| {
+        RcExpr::from(Expr::Lam(Scope::new(
+            Binder(x.clone()),
+            RcExpr::from(Expr::Lam(Scope::new(Binder(x_inner.clone()), var(body)))),
+        )))
+    };
+
+    assert_eq!(
+        shadow(&x_inner).display_term().to_string(),
+        "Lam(<x> Lam(<x> Var(x)))"
+    );
+    assert_eq!(
+        shadow(&x).display_term().to_string(),
+        "Lam(<x> Lam(<x1> Var(x)))"
+    );
+
+    // Free variables are only disambiguated if they would clash, and
+    // generated variables are numbered in the order that they appear
+    let temp1 = FreeVar::fresh_unnamed();
+    let temp2 = FreeVar::fresh_unnamed();
+    let app = |fun, arg| RcExpr::from(Expr::App(fun, arg));
+    let expr = app(
+        app(var(&x_inner), var(&temp2)),
+        RcExpr::from(Expr::Lam(Scope::new(
+            Binder(y.clone()),
+            app(app(var(&x), var(&temp1)), var(&y)),
+        ))),
+    );
+
+    assert_eq!(
+        expr.display_term().to_string(),
+        "App(App(Var(x), Var($0)), Lam(<y> App(App(Var(x1), Var($1)), Var(y))))",
+    );
+}
+
+#[cfg(feature = "proptest")]
+proptest! {
+    #[test]
+    fn test_arbitrary_closed(expr in any::<RcExpr>()) {
+        use moniker::BoundTerm;
+
+        // Every variable refers to an enclosing binder, so substitution has
+        // no effect on the generated terms
+        let x = FreeVar::fresh_named("x");
+        let replacement = RcExpr::from(Expr::Var(Var::Free(x.clone())));
+
+        prop_assert!(expr.free_vars().is_empty());
+        prop_assert!(RcExpr::term_eq(&expr.subst(&x, &replacement), &expr));
+    }
+
+    #[test]
+    fn test_arbitrary_open(
+        expr in any_with::<RcExpr>(ArbitraryEnv::open(vec![FreeVar::fresh_named("x")])),
+    ) {
+        use moniker::BoundTerm;
+
+        // Only the free variable of the environment may appear in the
+        // generated terms
+        prop_assert!(expr.free_vars().len() <= 1);
+    }
+}
+
+#[test]
+fn test_alpha_normalize() {
+    use moniker::{FreeVar, MapVars};
+    use std::collections::HashSet;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let lam = |binder: &FreeVar<String>, body: &FreeVar<String>| {
+        Scope::new(Binder(binder.clone()), Var::Free(body.clone()))
+    };
+
+    // \x => x and \y => y only differ in the ids of their binders
+    assert_ne!(lam(&x, &x), lam(&y, &y));
+    assert_eq!(lam(&x, &x).alpha_normalize(), lam(&y, &y).alpha_normalize());
+
+    // Free variables are left alone
+    assert_ne!(lam(&x, &y).alpha_normalize(), lam(&x, &z).alpha_normalize());
+
+    let mut cache = HashSet::new();
+    cache.insert(lam(&x, &x).alpha_normalize());
+    assert!(cache.contains(&lam(&z, &z).alpha_normalize()));
+    assert!(!cache.contains(&lam(&x, &z).alpha_normalize()));
+}
+
+#[test]
+fn test_uniquify() {
+    use moniker::{FreeVar, MapVars};
+
+    let x = FreeVar::fresh_named("x");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // expr = (\x => x) (\x => x)
+    let id = RcExpr::from(Expr::Lam(Scope::new(Binder(x.clone()), var(&x))));
+    let expr = RcExpr::from(Expr::App(id.clone(), id));
+
+    let (uniquified, renaming) = expr.uniquify_with_renaming();
+    assert_term_eq!(uniquified, expr);
+    assert_eq!(renaming.len(), 2);
+    assert_eq!(renaming[0].0, Binder(x.clone()));
+    assert_eq!(renaming[1].0, Binder(x.clone()));
+    assert_ne!(renaming[0].1, renaming[1].1);
+
+    // The binders are kept when unbinding with `unbind_with`
+    let binders = match *uniquified.inner {
+        Expr::App(ref fun, ref arg) => match (&*fun.inner, &*arg.inner) {
+            (Expr::Lam(fun), Expr::Lam(arg)) => {
+                let (fun_binder, _) = fun.clone().unbind_with(|binder| binder.clone());
+                let (arg_binder, _) = arg.clone().unbind_with(|binder| binder.clone());
+                vec![fun_binder, arg_binder]
+            },
+            _ => panic!("expected lambdas"),
+        },
+        _ => panic!("expected an application"),
+    };
+    assert_eq!(binders, vec![renaming[0].1.clone(), renaming[1].1.clone()]);
+}
+
+#[test]
+fn test_term_eq_check() {
+    use moniker::{BoundTerm, FreeVar, Mismatched, PathSegment};
+
+    let a = FreeVar::fresh_named("a");
+    let b = FreeVar::fresh_named("b");
+    let x = FreeVar::fresh_named("x");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let lam = |fv: &FreeVar<String>, body: RcExpr| {
+        RcExpr::from(Expr::Lam(Scope::new(Binder(fv.clone()), body)))
+    };
+    let app = |fun: RcExpr, arg: RcExpr| RcExpr::from(Expr::App(fun, arg));
+
+    // \x => x a ~ \x => x b
+    let lhs = lam(&x, app(var(&x), var(&a)));
+    let rhs = lam(&x, app(var(&x), var(&b)));
+    let mismatch = lhs.term_eq_check(&rhs).unwrap_err();
+
+    assert_eq!(
+        mismatch.path,
+        vec![
+            PathSegment::Field("Lam.0"),
+            PathSegment::Body(vec![Binder(x.clone())]),
+            PathSegment::Field("App.1"),
+            PathSegment::Field("Var.0"),
+        ],
+    );
+    assert_eq!(mismatch.lhs, Mismatched::Var(Var::Free(a.clone())));
+    assert_eq!(mismatch.rhs, Mismatched::Var(Var::Free(b.clone())));
+    assert_eq!(
+        mismatch.to_string(),
+        format!(
+            "at `Lam.0 > body of x > App.1 > Var.0`: `{}` differs from `{}`",
+            a, b
+        ),
+    );
+
+    // \x => x ~ \x => x a
+    let mismatch = lam(&x, var(&x)).term_eq_check(&rhs).unwrap_err();
+    assert_eq!(mismatch.lhs, Mismatched::Variant("Var"));
+    assert_eq!(mismatch.rhs, Mismatched::Variant("App"));
+
+    assert!(lhs.term_eq_check(&lhs.clone()).is_ok());
+}
+
+#[test]
+#[should_panic(expected = "diff: at `App.0`: `Lam` differs from `Var`")]
+fn test_assert_term_eq_diff() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let lam = RcExpr::from(Expr::Lam(Scope::new(Binder(x.clone()), var(&x))));
+
+    assert_term_eq!(
+        RcExpr::from(Expr::App(lam, var(&x))),
+        RcExpr::from(Expr::App(var(&x), var(&x))),
+    );
+}
+
+#[test]
+fn test_alpha_match() {
+    use moniker::{FreeVar, Zip};
+
+    let a = FreeVar::fresh_named("a");
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let lam = |fv: &FreeVar<String>, body: RcExpr| {
+        RcExpr::from(Expr::Lam(Scope::new(Binder(fv.clone()), body)))
+    };
+    let app = |fun: RcExpr, arg: RcExpr| RcExpr::from(Expr::App(fun, arg));
+
+    // template = (\x => x a) a
+    let template = app(lam(&x, app(var(&x), var(&a))), var(&a));
+    // subject = (\y => y (\z => z)) (\x => x)
+    let subject = app(lam(&y, app(var(&y), lam(&z, var(&z)))), lam(&x, var(&x)));
+
+    let substitution = template.alpha_match(&subject, &[a.clone()]).unwrap();
+    assert_eq!(substitution.len(), 1);
+    assert_term_eq!(substitution.get(&a).unwrap().clone(), lam(&x, var(&x)));
+    assert_term_eq!(substitution.apply(&template), subject);
+
+    // Each occurrence of a hole must match the same term
+    let subject = app(lam(&y, app(var(&y), var(&z))), var(&y));
+    assert!(template.alpha_match(&subject, &[a.clone()]).is_none());
+
+    // Holes can't capture variables that are bound outside of them
+    let template = lam(&x, var(&a));
+    assert!(template
+        .alpha_match(&lam(&y, var(&y)), &[a.clone()])
+        .is_none());
+    assert!(template
+        .alpha_match(&lam(&y, var(&z)), &[a.clone()])
+        .is_some());
+
+    // Free variables that are not holes must match exactly
+    assert!(template.alpha_match(&lam(&y, var(&z)), &[]).is_none());
+    assert!(template.alpha_match(&lam(&y, var(&a)), &[]).is_some());
+}
+
+#[test]
+fn test_anti_unify() {
+    use moniker::{FreeVar, Zip};
+
+    let a = FreeVar::fresh_named("a");
+    let b = FreeVar::fresh_named("b");
+    let f = FreeVar::fresh_named("f");
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let lam = |fv: &FreeVar<String>, body: RcExpr| {
+        RcExpr::from(Expr::Lam(Scope::new(Binder(fv.clone()), body)))
+    };
+    let app = |fun: RcExpr, arg: RcExpr| RcExpr::from(Expr::App(fun, arg));
+
+    // lhs = \x => f (x a) a
+    let lhs = lam(&x, app(app(var(&f), app(var(&x), var(&a))), var(&a)));
+    // rhs = \y => f (y b) b
+    let rhs = lam(&y, app(app(var(&f), app(var(&y), var(&b))), var(&b)));
+
+    let (term, lhs_substitution, rhs_substitution) = lhs.anti_unify(&rhs, |fv| var(&fv)).unwrap();
+    assert_eq!(lhs_substitution.len(), 1);
+    assert_eq!(rhs_substitution.len(), 1);
+
+    // term = \x => f (x g) g
+    let (g, _) = lhs_substitution.iter().next().unwrap();
+    assert_term_eq!(
+        term,
+        lam(&x, app(app(var(&f), app(var(&x), var(g))), var(g))),
+    );
+    assert_term_eq!(lhs_substitution.apply(&term), lhs);
+    assert_term_eq!(rhs_substitution.apply(&term), rhs);
+
+    // Subterms that refer to bound variables are generalized at their scope
+    let lhs = app(var(&f), lam(&x, var(&x)));
+    let rhs = app(var(&f), lam(&x, app(var(&x), var(&x))));
+
+    let (term, lhs_substitution, rhs_substitution) = lhs.anti_unify(&rhs, |fv| var(&fv)).unwrap();
+    let (g, _) = lhs_substitution.iter().next().unwrap();
+    assert_term_eq!(term, app(var(&f), var(g)));
+    assert_term_eq!(lhs_substitution.apply(&term), lhs);
+    assert_term_eq!(rhs_substitution.apply(&term), rhs);
+}
+
+#[test]
+fn test_unify() {
+    use moniker::{FreeVar, UnifyError, Zip};
+
+    let a = FreeVar::fresh_named("?a");
+    let b = FreeVar::fresh_named("?b");
+    let f = FreeVar::fresh_named("f");
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let lam = |fv: &FreeVar<String>, body: RcExpr| {
+        RcExpr::from(Expr::Lam(Scope::new(Binder(fv.clone()), body)))
+    };
+    let app = |fun: RcExpr, arg: RcExpr| RcExpr::from(Expr::App(fun, arg));
+    let metas = [a.clone(), b.clone()];
+
+    // \x => ?a x ~ \y => f y
+    let lhs = lam(&x, app(var(&a), var(&x)));
+    let rhs = lam(&y, app(var(&f), var(&y)));
+    let substitution = lhs.unify(&rhs, &metas).unwrap();
+    assert_term_eq!(substitution.get(&a).unwrap().clone(), var(&f));
+    assert_term_eq!(substitution.apply(&lhs), rhs);
+
+    // ?a ?b ~ ?b (\x => x)
+    let lhs = app(var(&a), var(&b));
+    let rhs = app(var(&b), lam(&x, var(&x)));
+    let substitution = lhs.unify(&rhs, &metas).unwrap();
+    assert_term_eq!(substitution.get(&a).unwrap().clone(), lam(&x, var(&x)));
+    assert_term_eq!(substitution.get(&b).unwrap().clone(), lam(&x, var(&x)));
+    assert_term_eq!(substitution.apply(&lhs), substitution.apply(&rhs));
+
+    // ?a ~ f ?a
+    let result = var(&a).unify(&app(var(&f), var(&a)), &metas);
+    assert_eq!(result.unwrap_err(), UnifyError::Occurs(a.clone()));
+
+    // \x => ?a ~ \y => y
+    let result = lam(&x, var(&a)).unify(&lam(&y, var(&y)), &metas);
+    assert_eq!(result.unwrap_err(), UnifyError::Escape(a.clone()));
+
+    // f ?a ~ \x => x
+    let result = app(var(&f), var(&a)).unify(&lam(&x, var(&x)), &metas);
+    assert_eq!(result.unwrap_err(), UnifyError::Mismatch);
+}
+
+#[test]
+fn test_alpha_hash() {
+    use moniker::{AlphaHash, BoundTerm, FreeVar};
+    use std::collections::HashMap;
+    use std::hash::{Hash, Hasher};
+
+    /// Expressions that are compared and hashed up to alpha equivalence
+    struct Key(RcExpr);
+
+    impl PartialEq for Key {
+        fn eq(&self, other: &Key) -> bool {
+            self.0.term_eq(&other.0)
+        }
+    }
+
+    impl Eq for Key {}
+
+    impl Hash for Key {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.0.alpha_hash(state);
+        }
+    }
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let lam =
+        |fv: &FreeVar<String>, body| RcExpr::from(Expr::Lam(Scope::new(Binder(fv.clone()), body)));
+
+    let mut sizes = HashMap::new();
+    // \x => x
+    sizes.insert(Key(lam(&x, var(&x))), 2);
+    // \x => y
+    sizes.insert(Key(lam(&x, var(&y))), 2);
+
+    // \y => y
+    assert_eq!(sizes.get(&Key(lam(&y, var(&y)))), Some(&2));
+    // \y => x
+    assert_eq!(sizes.get(&Key(lam(&y, var(&x)))), None);
+    assert_eq!(sizes.len(), 2);
+}
+
+#[test]
+fn test_alpha_ord() {
+    use moniker::{AlphaOrd, BoundTerm, FreeVar};
+    use std::cmp::Ordering;
+    use std::collections::BTreeSet;
+
+    /// Expressions that are compared and ordered up to alpha equivalence
+    struct Key(RcExpr);
+
+    impl PartialEq for Key {
+        fn eq(&self, other: &Key) -> bool {
+            self.0.term_eq(&other.0)
+        }
+    }
+
+    impl Eq for Key {}
+
+    impl PartialOrd for Key {
+        fn partial_cmp(&self, other: &Key) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Key {
+        fn cmp(&self, other: &Key) -> Ordering {
+            self.0.alpha_cmp(&other.0)
+        }
+    }
+
+    // Generate the variables in the opposite order to their names
+    let y = FreeVar::fresh_named("y");
+    let x = FreeVar::fresh_named("x");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let lam =
+        |fv: &FreeVar<String>, body| RcExpr::from(Expr::Lam(Scope::new(Binder(fv.clone()), body)));
+
+    let mut exprs = BTreeSet::new();
+    // \x => y
+    exprs.insert(Key(lam(&x, var(&y))));
+    // \x => x
+    exprs.insert(Key(lam(&x, var(&x))));
+    // \y => y
+    exprs.insert(Key(lam(&y, var(&y))));
+    // y
+    exprs.insert(Key(var(&y)));
+    // x
+    exprs.insert(Key(var(&x)));
+
+    let exprs = exprs.into_iter().map(|Key(expr)| expr).collect::<Vec<_>>();
+    assert_eq!(exprs.len(), 4);
+    assert_term_eq!(exprs[0], var(&x));
+    assert_term_eq!(exprs[1], var(&y));
+    assert_term_eq!(exprs[2], lam(&x, var(&y)));
+    assert_term_eq!(exprs[3], lam(&x, var(&x)));
+}
+
+#[test]
+fn test_map_vars() {
+    use moniker::{FreeVar, MapVars};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // expr = \x => x y
+    let expr = RcExpr::from(Expr::Lam(Scope::new(
+        Binder(x.clone()),
+        RcExpr::from(Expr::App(var(&x), var(&y))),
+    )));
+
+    // Rename the free variables and binders, leaving the bound variables
+    // referring to the same binders
+    let y_renamed = FreeVar::fresh_named("z");
+    let renamed = expr.map_vars(
+        &mut |var| match *var {
+            Var::Free(ref free_var) if *free_var == y => Var::Free(y_renamed.clone()),
+            ref var => var.clone(),
+        },
+        &mut |_| Binder(FreeVar::fresh_named("w")),
+    );
+
+    assert_term_eq!(
+        renamed,
+        RcExpr::from(Expr::Lam(Scope::new(
+            Binder(x.clone()),
+            RcExpr::from(Expr::App(var(&x), var(&y_renamed))),
+        ))),
+    );
+    assert_eq!(
+        renamed.display_term().to_string(),
+        "Lam(<w> App(Var(w), Var(z)))"
+    );
+}
+
+#[test]
+fn test_visit() {
+    use moniker::{FreeVar, ScopeVisitor, Visit};
+
+    /// Counts the applications, and the maximum depth of nested lambdas
+    #[derive(Default)]
+    struct Counter {
+        apps: usize,
+        depth: usize,
+        max_depth: usize,
+    }
+
+    impl ScopeVisitor for Counter {
+        fn enter_scope(&mut self) {
+            self.depth += 1;
+            self.max_depth = usize::max(self.depth, self.max_depth);
+        }
+
+        fn exit_scope(&mut self) {
+            self.depth -= 1;
+        }
+    }
+
+    impl ExprVisitor for Counter {
+        fn visit_app(&mut self, _: &RcExpr, _: &RcExpr) {
+            self.apps += 1;
+        }
+    }
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+
+    // expr = (\x -> \y -> x y) (\x -> x)
+    let expr = RcExpr::from(Expr::App(
+        RcExpr::from(Expr::Lam(Scope::new(
+            Binder(x.clone()),
+            RcExpr::from(Expr::Lam(Scope::new(
+                Binder(y.clone()),
+                RcExpr::from(Expr::App(
+                    RcExpr::from(Expr::Var(Var::Free(x.clone()))),
+                    RcExpr::from(Expr::Var(Var::Free(y.clone()))),
+                )),
+            ))),
+        ))),
+        RcExpr::from(Expr::Lam(Scope::new(
+            Binder(x.clone()),
+            RcExpr::from(Expr::Var(Var::Free(x.clone()))),
+        ))),
+    ));
+
+    let mut counter = Counter::default();
+    expr.visit(&mut counter);
+
+    assert_eq!(counter.apps, 2);
+    assert_eq!(counter.depth, 0);
+    assert_eq!(counter.max_depth, 2);
+}
+
 fn main() {}
@@ -0,0 +1,144 @@
+//! An example of using the `moniker` library to implement the untyped lambda
+//! calculus, with the expressions allocated in an arena
+
+#[macro_use]
+extern crate moniker;
+
+use moniker::{ArenaRef, Binder, FreeVar, Scope, Var};
+
+#[cfg(test)]
+use moniker::Arena;
+
+/// Expressions
+///
+/// ```text
+/// e ::= x          variables
+///     | \x => e    anonymous functions
+///     | e₁ e₂      function application
+/// ````
+#[derive(Debug, Clone, BoundTerm)]
+pub enum Expr<'a> {
+    /// Variables
+    Var(Var<String>),
+    /// Lambda expressions
+    Lam(Scope<Binder<String>, ExprRef<'a>>),
+    /// Function application
+    App(ExprRef<'a>, ExprRef<'a>),
+}
+
+/// References to expressions in the arena
+pub type ExprRef<'a> = ArenaRef<'a, Expr<'a>>;
+
+/// Evaluate an expression into its normal form
+pub fn eval<'a>(expr: ExprRef<'a>) -> ExprRef<'a> {
+    match *ArenaRef::get(&expr) {
+        Expr::Var(_) | Expr::Lam(_) => expr,
+        Expr::App(fun, arg) => match *ArenaRef::get(&eval(fun)) {
+            Expr::Lam(ref scope) => {
+                let (Binder(free_var), body) = scope.clone().unbind();
+                eval(subst(body, &free_var, eval(arg)))
+            },
+            _ => expr,
+        },
+    }
+}
+
+/// Replace the free occurrences of `free_var` in `expr` with `replacement`,
+/// allocating the rebuilt nodes in the arena
+fn subst<'a>(
+    expr: ExprRef<'a>,
+    free_var: &FreeVar<String>,
+    replacement: ExprRef<'a>,
+) -> ExprRef<'a> {
+    let arena = ArenaRef::arena(&expr);
+    match *ArenaRef::get(&expr) {
+        Expr::Var(Var::Free(ref other)) if other == free_var => replacement,
+        Expr::Var(_) => expr,
+        Expr::Lam(ref scope) => {
+            let scope = scope
+                .clone()
+                .map_body(|body| subst(body, free_var, replacement));
+            arena.alloc_ref(Expr::Lam(scope))
+        },
+        Expr::App(fun, arg) => arena.alloc_ref(Expr::App(
+            subst(fun, free_var, replacement),
+            subst(arg, free_var, replacement),
+        )),
+    }
+}
+
+#[test]
+fn test_eval() {
+    let arena = Arena::new();
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| arena.alloc_ref(Expr::Var(Var::Free(fv.clone())));
+
+    // expr = (\x -> x) y
+    let id = arena.alloc_ref(Expr::Lam(Scope::new(Binder(x.clone()), var(&x))));
+    let expr = arena.alloc_ref(Expr::App(id, var(&y)));
+
+    assert_term_eq!(eval(expr), var(&y));
+}
+
+#[test]
+fn test_unbind_allocates_in_arena() {
+    use moniker::BoundTerm;
+
+    let arena = Arena::new();
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| arena.alloc_ref(Expr::Var(Var::Free(fv.clone())));
+
+    // \x => x y
+    let body = arena.alloc_ref(Expr::App(var(&x), var(&y)));
+    let scope = Scope::new(Binder(x.clone()), body);
+
+    // Binding the body rebuilt its nodes, leaving the original untouched
+    assert!(body.free_vars().contains(&x));
+    assert!(!scope.clone().into_raw_parts().1.free_vars().contains(&x));
+
+    let len = arena.len();
+    let (Binder(x1), body1) = scope.unbind();
+    assert!(arena.len() > len);
+    assert_term_eq!(body1, arena.alloc_ref(Expr::App(var(&x1), var(&y))));
+}
+
+#[test]
+fn test_bind_only_allocates_changed_nodes() {
+    use moniker::BoundTerm;
+
+    let arena = Arena::new();
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| arena.alloc_ref(Expr::Var(Var::Free(fv.clone())));
+
+    // (x y) (y y)
+    let y_y = arena.alloc_ref(Expr::App(var(&y), var(&y)));
+    let body = arena.alloc_ref(Expr::App(arena.alloc_ref(Expr::App(var(&x), var(&y))), y_y));
+
+    // Nothing is allocated when none of the variables are bound
+    let len = arena.len();
+    let (_, unchanged) = Scope::new(Binder(z.clone()), body).into_raw_parts();
+    assert!(ArenaRef::ptr_eq(&unchanged, &body));
+    let mut renamed = body;
+    renamed.rename_free_var(&z, &x);
+    assert!(ArenaRef::ptr_eq(&renamed, &body));
+    assert_eq!(arena.len(), len);
+
+    // Only `x` and the applications containing it are copied
+    let (_, closed) = Scope::new(Binder(x.clone()), body).into_raw_parts();
+    assert_eq!(arena.len(), len + 3);
+    match *ArenaRef::get(&closed) {
+        Expr::App(_, ref arg) => assert!(ArenaRef::ptr_eq(arg, &y_y)),
+        _ => panic!("expected an application"),
+    }
+
+    let len = arena.len();
+    renamed.rename_free_var(&x, &z);
+    assert_eq!(arena.len(), len + 3);
+    assert!(renamed.free_vars().contains(&z));
+}
+
+fn main() {}
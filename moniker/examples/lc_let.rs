@@ -3,8 +3,13 @@
 
 #[macro_use]
 extern crate moniker;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 
-use moniker::{Binder, Embed, Nest, Scope, Var};
+use moniker::{Binder, Embed, Nest, Scope, Subst, Var};
 use std::rc::Rc;
 
 /// Expressions
@@ -15,21 +20,29 @@ use std::rc::Rc;
 ///     | e₁ e₂                         function application
 ///     | let x₁=e₁, ..., xₙ=eₙ in e    nested let bindings
 /// ````
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[subst(replacement = "RcExpr")]
 pub enum Expr {
     /// Variables
+    #[subst(var)]
     Var(Var<String>),
     /// Lambda expressions
     Lam(Scope<Binder<String>, RcExpr>),
     /// Function application
     App(RcExpr, RcExpr),
     /// Nested let bindings
-    Let(Scope<Nest<(Binder<String>, Embed<RcExpr>)>, RcExpr>),
+    Let(Scope<Nest<Binding>, RcExpr>),
 }
 
+/// A let binding, from a variable to the expression that it is bound to
+pub type Binding = (Binder<String>, Embed<RcExpr>);
+
 /// Reference counted expressions
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RcExpr {
+    #[subst(var)]
     pub inner: Rc<Expr>,
 }
 
@@ -41,38 +54,6 @@ impl From<Expr> for RcExpr {
     }
 }
 
-impl RcExpr {
-    // FIXME: auto-derive this somehow!
-    fn substs<N: PartialEq<Var<String>>>(&self, mappings: &[(N, RcExpr)]) -> RcExpr {
-        match *self.inner {
-            Expr::Var(ref var) => match mappings.iter().find(|&(name, _)| name == var) {
-                Some((_, ref replacement)) => replacement.clone(),
-                None => self.clone(),
-            },
-            Expr::Lam(ref scope) => RcExpr::from(Expr::Lam(Scope {
-                unsafe_pattern: scope.unsafe_pattern.clone(),
-                unsafe_body: scope.unsafe_body.substs(mappings),
-            })),
-            Expr::App(ref fun, ref arg) => {
-                RcExpr::from(Expr::App(fun.substs(mappings), arg.substs(mappings)))
-            },
-            Expr::Let(ref scope) => RcExpr::from(Expr::Let(Scope {
-                unsafe_pattern: Nest {
-                    unsafe_patterns: scope
-                        .unsafe_pattern
-                        .unsafe_patterns
-                        .iter()
-                        .map(|&(ref n, Embed(ref value))| {
-                            (n.clone(), Embed(value.substs(mappings)))
-                        })
-                        .collect(),
-                },
-                unsafe_body: scope.unsafe_body.substs(mappings),
-            })),
-        }
-    }
-}
-
 /// Evaluate an expression into its normal form
 pub fn eval(expr: &RcExpr) -> RcExpr {
     match *expr.inner {
@@ -159,4 +140,199 @@ fn test_eval_let() {
     assert_term_eq!(eval(&expr), RcExpr::from(Expr::Var(Var::Free(y.clone()))),);
 }
 
+#[test]
+fn test_visit_vars_with_binders_nest() {
+    use moniker::{BinderStack, BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let a = FreeVar::fresh_named("a");
+    let b = FreeVar::fresh_named("b");
+    let c = FreeVar::fresh_named("c");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // expr =
+    //      let a = \x -> x
+    //          b = a
+    //          c = a b
+    //      in c
+    let expr = RcExpr::from(Expr::Let(Scope::new(
+        Nest::new(vec![
+            (
+                Binder(a.clone()),
+                Embed(RcExpr::from(Expr::Lam(Scope::new(
+                    Binder(x.clone()),
+                    var(&x),
+                )))),
+            ),
+            (Binder(b.clone()), Embed(var(&a))),
+            (
+                Binder(c.clone()),
+                Embed(RcExpr::from(Expr::App(var(&a), var(&b)))),
+            ),
+        ]),
+        var(&c),
+    )));
+
+    // Each bound variable should refer to the binder it was created from
+    let mut resolved = Vec::new();
+    expr.visit_vars_with_binders(&mut BinderStack::new(), &mut |binders, var| {
+        if let Var::Bound(ref bound_var) = *var {
+            let binder = binders.lookup(bound_var).expect("unbound variable");
+            resolved.push(binder.0.clone());
+        }
+    });
+
+    assert_eq!(resolved, vec![x, a.clone(), a, b, c]);
+}
+
+#[test]
+fn test_term_eq_rebind() {
+    use moniker::{BoundTerm, FreeVar, Rebind};
+
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    // scope = let x = y in let z = x in z
+    let scope = |x: &FreeVar<String>, z: &FreeVar<String>, y: &FreeVar<String>| {
+        Scope::<Rebind<Binding, Binding>, RcExpr>::new(
+            Rebind::new(
+                (Binder(x.clone()), Embed(var(y))),
+                (Binder(z.clone()), Embed(var(x))),
+            ),
+            var(z),
+        )
+    };
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+
+    // The right binding refers to the binder of the left one
+    assert_term_eq!(scope(&x, &z, &y), scope(&FreeVar::fresh_named("a"), &z, &y));
+    assert_eq!(
+        scope(&x, &z, &y).free_vars(),
+        [y.clone()].iter().cloned().collect()
+    );
+
+    let (rebind, body) = scope(&x, &z, &y).unbind();
+    let ((x, Embed(x_value)), (z, Embed(z_value))) = rebind.unrebind();
+    assert_term_eq!(x_value, var(&y));
+    assert_term_eq!(z_value, var(&x.0));
+    assert_term_eq!(body, var(&z.0));
+}
+
+#[test]
+fn test_nest_push_concat() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let w = FreeVar::fresh_named("w");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let binding = |fv: &FreeVar<String>, value| (Binder(fv.clone()), Embed(value));
+
+    // let x = w; y = x; z = y
+    let bindings = vec![
+        binding(&x, var(&w)),
+        binding(&y, var(&x)),
+        binding(&z, var(&y)),
+    ];
+    let expected = Nest::new(bindings.clone());
+
+    let mut pushed = Nest::new(Vec::new());
+    assert!(pushed.is_empty());
+    for binding in bindings.clone() {
+        pushed.push(binding);
+    }
+    assert_eq!(pushed.len(), 3);
+    assert_pattern_eq!(pushed, expected);
+
+    let lhs = Nest::new(bindings[..1].to_vec());
+    let rhs = Nest::new(bindings[1..].to_vec());
+    let concatenated = lhs.concat(rhs);
+    assert_pattern_eq!(concatenated, expected);
+
+    // The first binding refers to a free variable, but the rest of the
+    // bindings refer to earlier binders
+    let free_vars = concatenated
+        .iter()
+        .map(|&(_, Embed(ref value))| value.free_vars().len())
+        .collect::<Vec<_>>();
+    assert_eq!(free_vars, [1, 0, 0]);
+
+    let scope = Scope::new(concatenated, var(&z));
+    assert_eq!(scope.free_vars(), [w.clone()].iter().cloned().collect());
+    let (nest, body) = scope.unbind();
+    let unnested = nest.unnest();
+    assert_term_eq!((unnested[2].1).0, var(&(unnested[1].0).0));
+    assert_term_eq!(body, var(&(unnested[2].0).0));
+}
+
+#[test]
+fn test_embed_access() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    let (binder, mut value): (Binder<String>, Embed<RcExpr>) =
+        (Binder(x.clone()), Embed::from(var(&y)));
+
+    // Embedded terms can be accessed without destructuring them
+    assert_eq!(value.free_vars(), [y.clone()].iter().cloned().collect());
+    assert_term_eq!(*value.as_ref(), var(&y));
+
+    *value = RcExpr::from(Expr::App(var(&y), var(&y)));
+
+    // let x = y y in x
+    let expr = RcExpr::from(Expr::Let(Scope::new(
+        Nest::new(vec![(binder, value)]),
+        var(&x),
+    )));
+    let (bindings, _) = match *expr.inner {
+        Expr::Let(ref scope) => scope.clone().unbind(),
+        _ => panic!("expected a let expression"),
+    };
+    let (_, value) = bindings.unnest().remove(0);
+    assert_term_eq!(
+        value.into_inner(),
+        RcExpr::from(Expr::App(var(&y), var(&y)))
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serialize_canonical() {
+    use moniker::FreeVar;
+
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    // expr = let x = \y -> y in x
+    let expr = |x: &FreeVar<String>, y: &FreeVar<String>| {
+        RcExpr::from(Expr::Let(Scope::new(
+            Nest::new(vec![(
+                Binder(x.clone()),
+                Embed(RcExpr::from(Expr::Lam(Scope::new(
+                    Binder(y.clone()),
+                    var(y),
+                )))),
+            )]),
+            var(x),
+        )))
+    };
+
+    // Alpha equivalent terms serialize identically, regardless of the ids
+    // and names of their binders
+    let expr1 = expr(&FreeVar::fresh_named("x"), &FreeVar::fresh_named("y"));
+    let expr2 = expr(&FreeVar::fresh_named("a"), &FreeVar::fresh_unnamed());
+    let json = serde_json::to_string(&expr1).unwrap();
+    assert_eq!(json, serde_json::to_string(&expr2).unwrap());
+
+    let round_trip = serde_json::from_str::<RcExpr>(&json).unwrap();
+    assert_term_eq!(round_trip, expr1);
+    assert_eq!(serde_json::to_string(&round_trip).unwrap(), json);
+
+    // Free variables have no canonical form
+    assert!(serde_json::to_string(&var(&FreeVar::fresh_named("x"))).is_err());
+}
+
 fn main() {}
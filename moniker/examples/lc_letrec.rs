@@ -1,10 +1,11 @@
 //! An example of using the `moniker` library to implement the untyped lambda
 //! calculus with mutually recursive bindings.
 
+#[allow(unused_imports)]
 #[macro_use]
 extern crate moniker;
 
-use moniker::{Binder, BoundTerm, Embed, Rec, Scope, Var};
+use moniker::{Binder, BoundTerm, Embed, Rec, Scope, Subst, Var};
 use std::rc::Rc;
 
 /// Expressions
@@ -15,21 +16,27 @@ use std::rc::Rc;
 ///     | e₁ e₂                         function application
 ///     | let x₁=e₁, ..., xₙ=eₙ in e    mutually recursive let bindings
 /// ````
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
+#[subst(replacement = "RcExpr")]
 pub enum Expr {
     /// Variables
+    #[subst(var)]
     Var(Var<String>),
     /// Lambda expressions
     Lam(Scope<Binder<String>, RcExpr>),
     /// Function application
     App(RcExpr, RcExpr),
     /// Mutually recursive let bindings
-    LetRec(Scope<Rec<Vec<(Binder<String>, Embed<RcExpr>)>>, RcExpr>),
+    LetRec(Scope<Rec<Vec<Binding>>, RcExpr>),
 }
 
+/// A recursive binding, from a variable to the expression that it is bound to
+pub type Binding = (Binder<String>, Embed<RcExpr>);
+
 /// Reference counted expressions
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
 pub struct RcExpr {
+    #[subst(var)]
     pub inner: Rc<Expr>,
 }
 
@@ -41,37 +48,6 @@ impl From<Expr> for RcExpr {
     }
 }
 
-impl RcExpr {
-    // FIXME: auto-derive this somehow!
-    fn subst<N: PartialEq<Var<String>>>(&self, name: &N, replacement: &RcExpr) -> RcExpr {
-        match *self.inner {
-            Expr::Var(ref var) if name == var => replacement.clone(),
-            Expr::Var(_) => self.clone(),
-            Expr::Lam(ref scope) => RcExpr::from(Expr::Lam(Scope {
-                unsafe_pattern: scope.unsafe_pattern.clone(),
-                unsafe_body: scope.unsafe_body.subst(name, replacement),
-            })),
-            Expr::App(ref fun, ref arg) => RcExpr::from(Expr::App(
-                fun.subst(name, replacement),
-                arg.subst(name, replacement),
-            )),
-            Expr::LetRec(ref scope) => RcExpr::from(Expr::LetRec(Scope {
-                unsafe_pattern: Rec {
-                    unsafe_pattern: scope
-                        .unsafe_pattern
-                        .unsafe_pattern
-                        .iter()
-                        .map(|&(ref n, Embed(ref value))| {
-                            (n.clone(), Embed(value.subst(name, replacement)))
-                        })
-                        .collect(),
-                },
-                unsafe_body: scope.unsafe_body.subst(name, replacement),
-            })),
-        }
-    }
-}
-
 /// Evaluate an expression into its normal form
 pub fn eval(expr: &RcExpr) -> RcExpr {
     match *expr.inner {
@@ -160,4 +136,31 @@ fn test_eval_let_rec() {
     assert_term_eq!(eval(&expr), RcExpr::from(Expr::Var(Var::Free(x1.clone()))));
 }
 
+#[test]
+fn test_term_eq_let_rec() {
+    use moniker::FreeVar;
+
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    // expr = let f = f_value, g = g_value in f
+    let expr = |f: &FreeVar<String>, g: &FreeVar<String>, f_value, g_value| {
+        RcExpr::from(Expr::LetRec(Scope::new(
+            Rec::new(vec![
+                (Binder(f.clone()), Embed(var(f_value))),
+                (Binder(g.clone()), Embed(var(g_value))),
+            ]),
+            var(f),
+        )))
+    };
+
+    let f = FreeVar::fresh_named("f");
+    let g = FreeVar::fresh_named("g");
+    let a = FreeVar::fresh_named("a");
+    let b = FreeVar::fresh_named("b");
+
+    // The recursive references are bound by the definitions themselves
+    assert!(expr(&f, &g, &g, &f).free_vars().is_empty());
+    assert_term_eq!(expr(&f, &g, &g, &f), expr(&a, &b, &b, &a));
+    assert!(!expr(&f, &g, &g, &f).term_eq(&expr(&a, &b, &a, &b)));
+}
+
 fn main() {}
@@ -4,7 +4,7 @@
 #[macro_use]
 extern crate moniker;
 
-use moniker::{Binder, Scope, Var};
+use moniker::{Binder, Scope, Subst, Var};
 use std::rc::Rc;
 
 /// Expressions
@@ -14,9 +14,11 @@ use std::rc::Rc;
 ///     | \(x₁, ..., xₙ) => e   anonymous functions
 ///     | e (e₁, ..., eₙ)       function application
 /// ````
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
+#[subst(replacement = "RcExpr")]
 pub enum Expr {
     /// Variables
+    #[subst(var)]
     Var(Var<String>),
     /// Lambda expressions
     Lam(Scope<Vec<Binder<String>>, RcExpr>),
@@ -25,8 +27,9 @@ pub enum Expr {
 }
 
 /// Reference counted expressions
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
 pub struct RcExpr {
+    #[subst(var)]
     pub inner: Rc<Expr>,
 }
 
@@ -38,26 +41,6 @@ impl From<Expr> for RcExpr {
     }
 }
 
-impl RcExpr {
-    // FIXME: auto-derive this somehow!
-    fn substs<N: PartialEq<Var<String>>>(&self, mappings: &[(N, RcExpr)]) -> RcExpr {
-        match *self.inner {
-            Expr::Var(ref var) => match mappings.iter().find(|&(name, _)| name == var) {
-                Some((_, ref replacement)) => replacement.clone(),
-                None => self.clone(),
-            },
-            Expr::Lam(ref scope) => RcExpr::from(Expr::Lam(Scope {
-                unsafe_pattern: scope.unsafe_pattern.clone(),
-                unsafe_body: scope.unsafe_body.substs(mappings),
-            })),
-            Expr::App(ref fun, ref args) => RcExpr::from(Expr::App(
-                fun.substs(mappings),
-                args.iter().map(|arg| arg.substs(mappings)).collect(),
-            )),
-        }
-    }
-}
-
 #[derive(Debug, Clone)]
 pub enum EvalError {
     ArgumentCountMismatch { expected: usize, given: usize },
@@ -68,22 +51,15 @@ pub fn eval(expr: &RcExpr) -> Result<RcExpr, EvalError> {
     match *expr.inner {
         Expr::Var(_) | Expr::Lam(_) => Ok(expr.clone()),
         Expr::App(ref fun, ref args) => match *eval(fun)?.inner {
+            Expr::Lam(ref scope) if scope.binder_count() != args.len() => {
+                Err(EvalError::ArgumentCountMismatch {
+                    expected: scope.binder_count(),
+                    given: args.len(),
+                })
+            },
             Expr::Lam(ref scope) => {
-                let (binders, body) = scope.clone().unbind();
-
-                if binders.len() != args.len() {
-                    Err(EvalError::ArgumentCountMismatch {
-                        expected: binders.len(),
-                        given: args.len(),
-                    })
-                } else {
-                    let mappings = <_>::zip(
-                        binders.into_iter(),
-                        args.iter().map(|arg| eval(arg).unwrap()),
-                    ).collect::<Vec<_>>();
-
-                    eval(&body.substs(&mappings))
-                }
+                let args = args.iter().map(eval).collect::<Result<Vec<_>, _>>()?;
+                eval(&scope.clone().instantiates(args))
             },
             _ => Ok(expr.clone()),
         },
@@ -144,4 +120,430 @@ fn test_eval_const_rhs() {
     );
 }
 
+#[test]
+fn test_substs_simultaneous() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // (x (y))[x := y, y := x]
+    let expr = RcExpr::from(Expr::App(var(&x), vec![var(&y)]));
+    let mappings = [(x.clone(), var(&y)), (y.clone(), var(&x))];
+
+    assert_term_eq!(
+        expr.substs(&mappings),
+        RcExpr::from(Expr::App(var(&y), vec![var(&x)])),
+    );
+}
+
+#[test]
+fn test_abstract_over() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // x (y, z)
+    let expr = RcExpr::from(Expr::App(var(&x), vec![var(&y), var(&z)]));
+
+    // \(z, x) => x (y, z)
+    let scope = Scope::abstract_over(vec![z.clone(), x.clone()], expr.clone());
+    assert_eq!(scope.free_vars(), Some(y.clone()).into_iter().collect());
+    assert_term_eq!(
+        scope,
+        Scope::new(vec![Binder(z.clone()), Binder(x.clone())], expr)
+    );
+}
+
+#[test]
+fn test_instantiates() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let a = FreeVar::fresh_named("a");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \(x, y) => y (x)
+    let scope = Scope::new(
+        vec![Binder(x.clone()), Binder(y.clone())],
+        RcExpr::from(Expr::App(var(&y), vec![var(&x)])),
+    );
+
+    assert_term_eq!(
+        scope.clone().instantiates(vec![var(&a), var(&x)]),
+        RcExpr::from(Expr::App(var(&x), vec![var(&a)])),
+    );
+
+    // \(x, y) => a (x), with y substituted for a
+    let scope = Scope::new(
+        vec![Binder(x.clone()), Binder(y.clone())],
+        RcExpr::from(Expr::App(var(&a), vec![var(&x)])),
+    )
+    .subst(&a, &var(&y));
+
+    // Free occurrences of the binders in the body are left alone
+    assert_term_eq!(
+        scope.instantiates(vec![var(&a), var(&a)]),
+        RcExpr::from(Expr::App(var(&y), vec![var(&a)])),
+    );
+}
+
+#[test]
+#[should_panic(expected = "cannot instantiate a scope with differing numbers of arguments")]
+fn test_instantiates_arity_mismatch() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \(x) => x
+    let scope = Scope::new(vec![Binder(x.clone())], var(&x));
+    scope.instantiates(vec![var(&x), var(&x)]);
+}
+
+#[test]
+fn test_binder_hints() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh(None);
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \(x, _) => x
+    let scope = Scope::new(vec![Binder(x.clone()), Binder(y.clone())], var(&x));
+
+    assert_eq!(scope.binder_count(), 2);
+    assert_eq!(scope.binder_hints(), vec![Some("x".to_owned()), None]);
+}
+
+#[test]
+fn test_binder_usage() {
+    use moniker::{FreeVar, Usage};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \(x, y, z) => x(y, y)
+    let scope = Scope::new(
+        vec![Binder(x.clone()), Binder(y.clone()), Binder(z.clone())],
+        RcExpr::from(Expr::App(var(&x), vec![var(&y), var(&y)])),
+    );
+    assert_eq!(
+        scope.binder_usage(),
+        vec![Usage::Once, Usage::Many, Usage::Unused]
+    );
+
+    // \(x) => \(y) => y
+    let inner = RcExpr::from(Expr::Lam(Scope::new(vec![Binder(y.clone())], var(&y))));
+    let scope = Scope::new(vec![Binder(x.clone())], inner);
+    assert_eq!(scope.binder_usage(), vec![Usage::Unused]);
+}
+
+#[test]
+fn test_try_new_duplicate_binders() {
+    use moniker::{DuplicateBinderError, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let binders =
+        |fvs: &[&FreeVar<String>]| fvs.iter().map(|&fv| Binder(fv.clone())).collect::<Vec<_>>();
+
+    // \(x, y) => x
+    assert!(Scope::try_new(binders(&[&x, &y]), var(&x)).is_ok());
+
+    // \(x, y, x) => x
+    assert_eq!(
+        Scope::try_new(binders(&[&x, &y, &x]), var(&x)).unwrap_err(),
+        DuplicateBinderError {
+            binder: Binder(x.clone()),
+            first: 0,
+            second: 2,
+        },
+    );
+
+    // The first binder wins when the pattern is not checked
+    let scope = Scope::new(binders(&[&x, &y, &x]), var(&x));
+    let (params, body) = scope.unbind();
+    assert_term_eq!(body, var(&params[0].0));
+}
+
+#[test]
+fn test_term_eq_array_binders() {
+    use moniker::{BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // Patterns with a fixed number of binders can be stored in arrays
+    let pair1: Scope<[Binder<String>; 2], RcExpr> =
+        Scope::new([Binder(x.clone()), Binder(y.clone())], var(&x));
+    let pair2: Scope<[Binder<String>; 2], RcExpr> =
+        Scope::new([Binder(y.clone()), Binder(x.clone())], var(&y));
+    let pair3: Scope<[Binder<String>; 2], RcExpr> =
+        Scope::new([Binder(x.clone()), Binder(y.clone())], var(&y));
+
+    assert!(pair1.term_eq(&pair2));
+    assert!(!pair1.term_eq(&pair3));
+    assert_term_eq!(pair1.subst(&x, &var(&y)), pair1);
+}
+
+#[test]
+fn test_small_vec_binders() {
+    use moniker::{BoundTerm, FreeVar, SmallVec};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let binders = |fvs: &[&FreeVar<String>]| -> SmallVec<Binder<String>> {
+        fvs.iter().map(|&fv| Binder(fv.clone())).collect()
+    };
+
+    // \(x, y) => y
+    let pair1 = Scope::new(binders(&[&x, &y]), var(&y));
+    // \(y, x) => x
+    let pair2 = Scope::new(binders(&[&y, &x]), var(&x));
+    // \(x, y, z) => y
+    let triple = Scope::new(binders(&[&x, &y, &z]), var(&y));
+
+    assert!(pair1.term_eq(&pair2));
+    assert!(!pair1.term_eq(&triple));
+
+    // Up to two binders are stored inline, even after they are freshened
+    let (params, body) = pair1.unbind();
+    assert!(params.is_inline());
+    assert_term_eq!(body, var(&params[1].0));
+
+    let (params, body) = triple.unbind();
+    assert!(!params.is_inline());
+    assert_eq!(params.len(), 3);
+    assert_term_eq!(body, var(&params[1].0));
+}
+
+#[test]
+fn test_many_binders() {
+    use moniker::{BinderTable, BoundTerm, FreeVar, OnFreeFn, ScopeState};
+
+    // Large patterns are closed using a lookup table
+    let fvs = (0..500)
+        .map(|i| FreeVar::fresh_named(format!("x{}", i)))
+        .collect::<Vec<FreeVar<String>>>();
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let body = fvs.iter().rev().fold(var(&fvs[0]), |acc, fv| {
+        RcExpr::from(Expr::App(acc, vec![var(fv)]))
+    });
+
+    let lam = Scope::new(fvs.iter().cloned().map(Binder).collect::<Vec<_>>(), body);
+    assert!(lam.is_closed());
+
+    let (binders, body) = lam.clone().unbind();
+    assert_eq!(binders.len(), 500);
+    let rebound = Scope::new(binders.clone(), body.clone());
+    assert_term_eq!(rebound, lam);
+    assert_eq!(body.free_vars().len(), 500);
+
+    // Repeated binders are looked up in the same way as with a vector
+    let repeated = vec![
+        Binder(fvs[0].clone()),
+        Binder(fvs[1].clone()),
+        Binder(fvs[0].clone()),
+    ];
+    let table = BinderTable::new(&repeated);
+    for fv in &fvs[..2] {
+        assert_eq!(
+            OnFreeFn::call(&table, ScopeState::new(), fv),
+            OnFreeFn::call(&repeated, ScopeState::new(), fv),
+        );
+    }
+    assert_eq!(OnFreeFn::call(&table, ScopeState::new(), &fvs[2]), None);
+}
+
+#[test]
+fn test_scope_multiple_bodies() {
+    use moniker::FreeVar;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // A single pattern, binding `x` and `y` in each of the bodies
+    let scope = Scope::new(
+        vec![Binder(x.clone()), Binder(y.clone())],
+        vec![var(&x), RcExpr::from(Expr::App(var(&y), vec![var(&z)]))],
+    );
+
+    // Projecting a body refers to the freshened binders of the pattern
+    let (binders, body) = scope.clone().unbind_body(1);
+    assert_term_eq!(
+        body,
+        RcExpr::from(Expr::App(var(&binders[1].0), vec![var(&z)])),
+    );
+
+    // Each body is unbound with the same fresh names
+    let applied =
+        scope.map_bodies(|binders, body| RcExpr::from(Expr::App(body, vec![var(&binders[0].0)])));
+    let (binders, bodies) = applied.unbind();
+    assert_term_eq!(
+        bodies,
+        vec![
+            RcExpr::from(Expr::App(var(&binders[0].0), vec![var(&binders[0].0)])),
+            RcExpr::from(Expr::App(
+                RcExpr::from(Expr::App(var(&binders[1].0), vec![var(&z)])),
+                vec![var(&binders[0].0)],
+            )),
+        ],
+    );
+}
+
+#[test]
+fn test_term_eq_bind_set() {
+    use moniker::{BindSet, BoundTerm, FreeVar};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // The order of the binders in a set does not matter
+    let set1 = Scope::new(
+        BindSet::new(vec![Binder(x.clone()), Binder(y.clone())]),
+        var(&x),
+    );
+    let set2 = Scope::new(
+        BindSet::new(vec![Binder(y.clone()), Binder(x.clone())]),
+        var(&x),
+    );
+    let set3 = Scope::new(
+        BindSet::new(vec![Binder(x.clone()), Binder(y.clone())]),
+        var(&y),
+    );
+
+    assert!(set1.term_eq(&set2));
+    assert!(set1.term_eq(&set3));
+
+    // But the body can still tell the binders apart
+    let set4 = Scope::new(
+        BindSet::new(vec![Binder(x.clone()), Binder(y.clone())]),
+        RcExpr::from(Expr::App(var(&x), vec![var(&y)])),
+    );
+    let set5 = Scope::new(
+        BindSet::new(vec![Binder(x.clone()), Binder(y.clone())]),
+        RcExpr::from(Expr::App(var(&x), vec![var(&x)])),
+    );
+
+    assert!(!set4.term_eq(&set5));
+
+    // Unlike with vectors of binders
+    let vec1 = Scope::new(vec![Binder(x.clone()), Binder(y.clone())], var(&x));
+    let vec2 = Scope::new(vec![Binder(y.clone()), Binder(x.clone())], var(&x));
+
+    assert!(!vec1.term_eq(&vec2));
+
+    // Unbinding returns the binders in the order that they occur in the body
+    let set6 = Scope::new(
+        BindSet::new(vec![Binder(x.clone()), Binder(y.clone())]),
+        RcExpr::from(Expr::App(var(&y), vec![var(&x)])),
+    );
+    let (binders, body) = set6.unbind();
+    let binders = binders.into_binders();
+    assert_eq!(binders[0].0.pretty_name, Some(String::from("y")));
+    assert_term_eq!(
+        body,
+        RcExpr::from(Expr::App(var(&binders[0].0), vec![var(&binders[1].0)])),
+    );
+}
+
+#[test]
+fn test_term_eq_bind_set_renamed() {
+    use moniker::{BindSet, BoundTerm, FreeVar};
+
+    let a = FreeVar::fresh_named("a");
+    let b = FreeVar::fresh_named("b");
+    let z = FreeVar::fresh_named("z");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // Renaming a binder does not change where it is sorted
+    let set1 = Scope::new(
+        BindSet::new(vec![Binder(a.clone()), Binder(b.clone())]),
+        var(&a),
+    );
+    let set2 = Scope::new(
+        BindSet::new(vec![Binder(z.clone()), Binder(b.clone())]),
+        var(&z),
+    );
+
+    assert!(set1.term_eq(&set2));
+
+    // Even when the body refers to each of the binders
+    let set3 = Scope::new(
+        BindSet::new(vec![Binder(a.clone()), Binder(b.clone())]),
+        RcExpr::from(Expr::App(var(&b), vec![var(&a)])),
+    );
+    let set4 = Scope::new(
+        BindSet::new(vec![Binder(b.clone()), Binder(z.clone())]),
+        RcExpr::from(Expr::App(var(&b), vec![var(&z)])),
+    );
+
+    assert!(set3.term_eq(&set4));
+}
+
+#[test]
+fn test_term_eq_wildcard_binders() {
+    use moniker::{BinderIndex, BoundTerm, BoundVar, FreeVar, ScopeOffset, Wildcard};
+
+    #[derive(Debug, Clone, BoundPattern)]
+    enum Param {
+        Named(Binder<String>),
+        Wildcard(Wildcard),
+    }
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let a = FreeVar::fresh_named("a");
+    let b = FreeVar::fresh_named("b");
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+    let params = |fv1: &FreeVar<String>, fv2: &FreeVar<String>| {
+        vec![
+            Param::Named(Binder(fv1.clone())),
+            Param::Wildcard(Wildcard),
+            Param::Named(Binder(fv2.clone())),
+        ]
+    };
+
+    // \(x, _, y) => y
+    let lam1 = Scope::new(params(&x, &y), var(&y));
+    // \(a, _, b) => b
+    let lam2 = Scope::new(params(&a, &b), var(&b));
+    // \(a, _, b) => a
+    let lam3 = Scope::new(params(&a, &b), var(&a));
+
+    assert!(lam1.term_eq(&lam2));
+    assert!(!lam1.term_eq(&lam3));
+
+    // The wildcard is skipped when numbering the binders
+    let (_, raw_body) = lam1.clone().into_raw_parts();
+    match *raw_body.inner {
+        Expr::Var(Var::Bound(ref bound_var)) => assert_eq!(
+            *bound_var,
+            BoundVar::new(ScopeOffset(0), BinderIndex(1), None),
+        ),
+        ref expr => panic!("expected a bound variable, found: {:?}", expr),
+    }
+
+    let (params, body) = lam1.unbind();
+    match params[2] {
+        Param::Named(ref binder) => assert_term_eq!(body, var(&binder.0)),
+        Param::Wildcard(_) => panic!("expected a named parameter"),
+    }
+}
+
 fn main() {}
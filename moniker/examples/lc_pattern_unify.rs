@@ -0,0 +1,196 @@
+//! An example of using the `moniker` library to solve metavariables in the
+//! untyped lambda calculus, using higher-order pattern unification
+
+#[macro_use]
+extern crate moniker;
+
+use moniker::{Binder, MetaVar, Scope, Var};
+use std::rc::Rc;
+
+#[cfg(test)]
+use moniker::{FreeVar, PatternUnifier, PatternUnifyError};
+
+/// Expressions
+///
+/// ```text
+/// e ::= x          variables
+///     | ?α         metavariables
+///     | \x => e    anonymous functions
+///     | e₁ e₂      function application
+/// ````
+#[derive(Debug, Clone, BoundTerm, Instantiate, Zip)]
+#[instantiate(replacement = "RcExpr")]
+#[zip(replacement = "RcExpr")]
+pub enum Expr {
+    /// Variables
+    Var(Var<String>),
+    /// Metavariables
+    #[instantiate(meta)]
+    Meta(MetaVar<String>),
+    /// Lambda expressions
+    Lam(Scope<Binder<String>, RcExpr>),
+    /// Function application
+    App(RcExpr, RcExpr),
+}
+
+/// Reference counted expressions
+#[derive(Debug, Clone, BoundTerm, Instantiate, Zip)]
+#[moniker(transparent)]
+pub struct RcExpr {
+    pub inner: Rc<Expr>,
+}
+
+impl From<Expr> for RcExpr {
+    fn from(src: Expr) -> RcExpr {
+        RcExpr {
+            inner: Rc::new(src),
+        }
+    }
+}
+
+/// Returns the metavariable and its arguments if the expression is a
+/// metavariable applied to variables
+pub fn as_pattern(expr: &RcExpr) -> Option<(MetaVar<String>, Vec<Var<String>>)> {
+    let mut args = Vec::new();
+    let mut expr = expr;
+    loop {
+        match *expr.inner {
+            Expr::Meta(ref meta) => {
+                args.reverse();
+                return Some((meta.clone(), args));
+            },
+            Expr::App(ref fun, ref arg) => match *arg.inner {
+                Expr::Var(ref var) => {
+                    args.push(var.clone());
+                    expr = fun;
+                },
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+fn var(free_var: &FreeVar<String>) -> RcExpr {
+    RcExpr::from(Expr::Var(Var::Free(free_var.clone())))
+}
+
+#[cfg(test)]
+fn meta(meta: &MetaVar<String>) -> RcExpr {
+    RcExpr::from(Expr::Meta(meta.clone()))
+}
+
+#[cfg(test)]
+fn lam(free_var: &FreeVar<String>, body: RcExpr) -> RcExpr {
+    RcExpr::from(Expr::Lam(Scope::new(Binder(free_var.clone()), body)))
+}
+
+#[cfg(test)]
+fn app(fun: RcExpr, arg: RcExpr) -> RcExpr {
+    RcExpr::from(Expr::App(fun, arg))
+}
+
+#[test]
+fn test_unify_pattern() {
+    let f = FreeVar::fresh_named("f");
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let meta_f = MetaVar::fresh_named("F");
+
+    // \x y => ?F x y
+    let lhs = lam(&x, lam(&y, app(app(meta(&meta_f), var(&x)), var(&y))));
+    // \x y => f y x
+    let rhs = lam(&x, lam(&y, app(app(var(&f), var(&y)), var(&x))));
+
+    let mut unifier = PatternUnifier::new(as_pattern);
+    unifier.unify(&lhs, &rhs).unwrap();
+    assert_term_eq!(unifier.instantiate(&lhs), rhs);
+
+    // ?F := \a b => f b a
+    let a = FreeVar::fresh_named("a");
+    let b = FreeVar::fresh_named("b");
+    assert_term_eq!(
+        unifier.solution(&meta_f).unwrap(),
+        Scope::new(
+            vec![Binder(a.clone()), Binder(b.clone())],
+            app(app(var(&f), var(&b)), var(&a)),
+        ),
+    );
+}
+
+#[test]
+fn test_unify_pattern_free_args() {
+    let f = FreeVar::fresh_named("f");
+    let x = FreeVar::fresh_named("x");
+    let meta_f = MetaVar::fresh_named("F");
+
+    // ?F x ~ f x x, where x was introduced by unbinding a scope
+    let lhs = app(meta(&meta_f), var(&x));
+    let rhs = app(app(var(&f), var(&x)), var(&x));
+
+    let mut unifier = PatternUnifier::new(as_pattern);
+    unifier.unify(&lhs, &rhs).unwrap();
+    assert_term_eq!(unifier.instantiate(&lhs), rhs);
+
+    // The solution can be applied to other arguments
+    let y = FreeVar::fresh_named("y");
+    assert_term_eq!(
+        unifier.instantiate(&lam(&y, app(meta(&meta_f), var(&y)))),
+        lam(&y, app(app(var(&f), var(&y)), var(&y))),
+    );
+}
+
+#[test]
+fn test_unify_pattern_chained() {
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let meta_f = MetaVar::fresh_named("F");
+    let meta_g = MetaVar::fresh_named("G");
+
+    let mut unifier = PatternUnifier::new(as_pattern);
+    // ?F ~ \x => ?G x
+    unifier
+        .unify(&meta(&meta_f), &lam(&x, app(meta(&meta_g), var(&x))))
+        .unwrap();
+    // \y => ?G y ~ \y => y
+    unifier
+        .unify(&lam(&y, app(meta(&meta_g), var(&y))), &lam(&y, var(&y)))
+        .unwrap();
+
+    // ?F := \x => x
+    assert_term_eq!(
+        unifier.solution(&meta_f).unwrap(),
+        Scope::new(vec![], lam(&x, var(&x))),
+    );
+}
+
+#[test]
+fn test_unify_pattern_errors() {
+    let f = FreeVar::fresh_named("f");
+    let x = FreeVar::fresh_named("x");
+    let meta_f = MetaVar::fresh_named("F");
+
+    // \x => ?F ~ \x => x
+    let mut unifier = PatternUnifier::new(as_pattern);
+    let result = unifier.unify(&lam(&x, meta(&meta_f)), &lam(&x, var(&x)));
+    assert_eq!(result, Err(PatternUnifyError::Escape(meta_f.clone())));
+
+    // ?F ~ f ?F
+    let mut unifier = PatternUnifier::new(as_pattern);
+    let result = unifier.unify(&meta(&meta_f), &app(var(&f), meta(&meta_f)));
+    assert_eq!(result, Err(PatternUnifyError::Occurs(meta_f.clone())));
+
+    // \x => ?F x x ~ \x => x
+    let mut unifier = PatternUnifier::new(as_pattern);
+    let lhs = lam(&x, app(app(meta(&meta_f), var(&x)), var(&x)));
+    let result = unifier.unify(&lhs, &lam(&x, var(&x)));
+    assert_eq!(result, Err(PatternUnifyError::NotPattern(meta_f.clone())));
+
+    // \x => x ~ f
+    let mut unifier = PatternUnifier::new(as_pattern);
+    let result = unifier.unify(&lam(&x, var(&x)), &var(&f));
+    assert_eq!(result, Err(PatternUnifyError::Mismatch));
+}
+
+fn main() {}
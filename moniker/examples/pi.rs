@@ -0,0 +1,234 @@
+//! An example of using the `moniker` library to implement the pi calculus,
+//! where processes are compared up to the commutativity and associativity of
+//! parallel composition, and channels are named using interned symbols
+
+#[macro_use]
+extern crate moniker;
+
+use moniker::{Binder, Scope, Symbol, Var};
+use std::rc::Rc;
+
+#[cfg(test)]
+use moniker::FreeVar;
+
+/// Processes
+///
+/// ```text
+/// P ::= 0           the inactive process
+///     | x!y. P      send y along the channel x, then continue as P
+///     | x?y. P      receive y along the channel x, then continue as P
+///     | P | Q       parallel composition
+///     | new x. P    restrict the channel x to P
+/// ````
+#[derive(Debug, Clone, AlphaHash, AlphaOrd, BoundTerm)]
+#[moniker(ident = "Symbol")]
+pub enum Proc {
+    /// The inactive process
+    Nil,
+    /// Send a channel along another channel
+    Send(Var<Symbol>, Var<Symbol>, RcProc),
+    /// Receive a channel along another channel
+    Recv(Var<Symbol>, Scope<Binder<Symbol>, RcProc>),
+    /// Parallel composition
+    #[moniker(commutative, associative = "RcProc::proc")]
+    Par(RcProc, RcProc),
+    /// Channel restriction
+    New(Scope<Binder<Symbol>, RcProc>),
+}
+
+/// Reference counted processes
+#[derive(Debug, Clone, AlphaHash, AlphaOrd, BoundTerm)]
+#[moniker(ident = "Symbol", transparent)]
+pub struct RcProc {
+    pub inner: Rc<Proc>,
+}
+
+impl RcProc {
+    /// The process that this refers to
+    pub fn proc(&self) -> &Proc {
+        &self.inner
+    }
+}
+
+impl From<Proc> for RcProc {
+    fn from(src: Proc) -> RcProc {
+        RcProc {
+            inner: Rc::new(src),
+        }
+    }
+}
+
+#[cfg(test)]
+fn nil() -> RcProc {
+    RcProc::from(Proc::Nil)
+}
+
+#[cfg(test)]
+fn send(chan: &FreeVar<Symbol>, msg: &FreeVar<Symbol>, proc: RcProc) -> RcProc {
+    RcProc::from(Proc::Send(
+        Var::Free(chan.clone()),
+        Var::Free(msg.clone()),
+        proc,
+    ))
+}
+
+#[cfg(test)]
+fn recv(chan: &FreeVar<Symbol>, msg: &FreeVar<Symbol>, proc: RcProc) -> RcProc {
+    RcProc::from(Proc::Recv(
+        Var::Free(chan.clone()),
+        Scope::new(Binder(msg.clone()), proc),
+    ))
+}
+
+#[cfg(test)]
+fn par(lhs: RcProc, rhs: RcProc) -> RcProc {
+    RcProc::from(Proc::Par(lhs, rhs))
+}
+
+#[cfg(test)]
+fn new(chan: &FreeVar<Symbol>, proc: RcProc) -> RcProc {
+    RcProc::from(Proc::New(Scope::new(Binder(chan.clone()), proc)))
+}
+
+#[test]
+fn test_term_eq_par() {
+    use moniker::BoundTerm;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+    let p = send(&x, &y, nil());
+    let q = recv(&x, &z, nil());
+    let r = send(&y, &z, nil());
+
+    // P | Q == Q | P
+    assert_term_eq!(par(p.clone(), q.clone()), par(q.clone(), p.clone()));
+    // P | (Q | R) == (P | Q) | R
+    assert_term_eq!(
+        par(p.clone(), par(q.clone(), r.clone())),
+        par(par(p.clone(), q.clone()), r.clone()),
+    );
+    // P | (Q | R) == (R | P) | Q
+    assert_term_eq!(
+        par(p.clone(), par(q.clone(), r.clone())),
+        par(par(r.clone(), p.clone()), q.clone()),
+    );
+    // P | (Q | R) != P | (Q | P)
+    assert!(!par(p.clone(), par(q.clone(), r.clone()))
+        .term_eq(&par(p.clone(), par(q.clone(), p.clone()))));
+    // P | P != P
+    assert!(!par(p.clone(), p.clone()).term_eq(&p));
+}
+
+#[test]
+fn test_term_eq_par_under_binders() {
+    let x = FreeVar::fresh_named("x");
+    let w = FreeVar::fresh_named("w");
+    let y = FreeVar::fresh_named("y");
+    let z = FreeVar::fresh_named("z");
+
+    // new x. (x!y. 0 | x?z. z!y. 0) == new w. (w?z. z!y. 0 | w!y. 0)
+    assert_term_eq!(
+        new(
+            &x,
+            par(send(&x, &y, nil()), recv(&x, &z, send(&z, &y, nil())))
+        ),
+        new(
+            &w,
+            par(recv(&w, &z, send(&z, &y, nil())), send(&w, &y, nil()))
+        ),
+    );
+}
+
+#[test]
+fn test_term_eq_check_par() {
+    use moniker::{BoundTerm, PathSegment};
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+
+    // new x. (x!y. 0 | 0) != new x. (x!x. 0 | 0)
+    let lhs = new(&x, par(send(&x, &y, nil()), nil()));
+    let rhs = new(&x, par(send(&x, &x, nil()), nil()));
+
+    let mismatch = lhs.term_eq_check(&rhs).unwrap_err();
+    assert_eq!(
+        mismatch.path,
+        vec![
+            PathSegment::Field("New.0"),
+            PathSegment::Body(vec![Binder(x.clone())]),
+            PathSegment::Field("Par"),
+        ],
+    );
+}
+
+#[test]
+fn test_alpha_hash_par() {
+    use moniker::AlphaHash;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+
+    let hash = |proc: &RcProc| {
+        let mut state = DefaultHasher::new();
+        proc.alpha_hash(&mut state);
+        state.finish()
+    };
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let p = send(&x, &y, nil());
+    let q = send(&y, &x, nil());
+    let r = recv(&x, &y, nil());
+
+    assert_eq!(
+        hash(&par(p.clone(), par(q.clone(), r.clone()))),
+        hash(&par(par(r.clone(), p.clone()), q.clone())),
+    );
+}
+
+#[test]
+fn test_alpha_cmp_par() {
+    use moniker::AlphaOrd;
+    use std::cmp::Ordering;
+
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+    let p = send(&x, &y, nil());
+    let q = send(&y, &x, nil());
+    let r = recv(&x, &y, nil());
+
+    let lhs = par(p.clone(), par(q.clone(), r.clone()));
+    assert_eq!(
+        lhs.alpha_cmp(&par(par(r.clone(), p.clone()), q.clone())),
+        Ordering::Equal
+    );
+    assert_ne!(lhs.alpha_cmp(&par(p.clone(), q.clone())), Ordering::Equal);
+}
+
+#[test]
+fn test_symbol_intern() {
+    let x1 = Symbol::intern("x");
+    let x2 = Symbol::from(String::from("x"));
+    let y = Symbol::intern("y");
+
+    assert_eq!(x1, x2);
+    assert_ne!(x1, y);
+    assert!(x1 < y);
+    assert_eq!(x2.as_str(), "x");
+    assert_eq!(FreeVar::fresh_named("x").pretty_name, Some(x1));
+}
+
+#[test]
+fn test_bound_var_size() {
+    use moniker::BoundVar;
+    use std::mem;
+
+    // The null pointer of the symbol is taken by its `Option`, so optional
+    // variables use the niche of the packed indices instead
+    assert_eq!(
+        mem::size_of::<Option<BoundVar<Symbol>>>(),
+        mem::size_of::<BoundVar<Symbol>>()
+    );
+}
+
+fn main() {}
@@ -5,11 +5,12 @@
 //! to get some level of type inference.
 
 extern crate im;
+#[allow(unused_imports)]
 #[macro_use]
 extern crate moniker;
 
 use im::HashMap;
-use moniker::{Binder, BoundTerm, Embed, FreeVar, Scope, Var};
+use moniker::{Binder, BoundTerm, Embed, FreeVar, Scope, Subst, Var};
 use std::rc::Rc;
 
 /// Types
@@ -20,7 +21,8 @@ use std::rc::Rc;
 ///     | String                string types
 ///     | t -> t                function types
 /// ```
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
+#[subst(replacement = "RcExpr")]
 pub enum Type {
     /// Integers
     Int,
@@ -33,7 +35,9 @@ pub enum Type {
 }
 
 /// Reference counted types
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
+#[moniker(transparent)]
+#[subst(replacement = "RcExpr")]
 pub struct RcType {
     pub inner: Rc<Type>,
 }
@@ -47,7 +51,8 @@ impl From<Type> for RcType {
 }
 
 /// Literal values
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
+#[subst(replacement = "RcExpr")]
 pub enum Literal {
     /// Integer literals
     Int(i32),
@@ -66,13 +71,15 @@ pub enum Literal {
 ///     | \x : t => e   anonymous functions (with type annotation)
 ///     | e₁ e₂         function application
 /// ```
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
+#[subst(replacement = "RcExpr")]
 pub enum Expr {
     /// Annotated expressions
     Ann(RcExpr, RcType),
     /// Literals
     Literal(Literal),
     /// Variables
+    #[subst(var)]
     Var(Var<String>),
     /// Lambda expressions, with an optional type annotation for the parameter
     Lam(Scope<(Binder<String>, Embed<Option<RcType>>), RcExpr>),
@@ -81,8 +88,9 @@ pub enum Expr {
 }
 
 /// Reference counted expressions
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
 pub struct RcExpr {
+    #[subst(var)]
     pub inner: Rc<Expr>,
 }
 
@@ -94,27 +102,6 @@ impl From<Expr> for RcExpr {
     }
 }
 
-impl RcExpr {
-    // FIXME: auto-derive this somehow!
-    fn subst<N: PartialEq<Var<String>>>(&self, name: &N, replacement: &RcExpr) -> RcExpr {
-        match *self.inner {
-            Expr::Ann(ref expr, ref ty) => {
-                RcExpr::from(Expr::Ann(expr.subst(name, replacement), ty.clone()))
-            },
-            Expr::Var(ref var) if name == var => replacement.clone(),
-            Expr::Var(_) | Expr::Literal(_) => self.clone(),
-            Expr::Lam(ref scope) => RcExpr::from(Expr::Lam(Scope {
-                unsafe_pattern: scope.unsafe_pattern.clone(),
-                unsafe_body: scope.unsafe_body.subst(name, replacement),
-            })),
-            Expr::App(ref fun, ref arg) => RcExpr::from(Expr::App(
-                fun.subst(name, replacement),
-                arg.subst(name, replacement),
-            )),
-        }
-    }
-}
-
 /// Evaluate an expression into its normal form
 pub fn eval(expr: &RcExpr) -> RcExpr {
     match *expr.inner {
@@ -135,25 +122,21 @@ type Context = HashMap<FreeVar<String>, RcType>;
 
 /// Check that a (potentially ambiguous) expression conforms to a given type
 pub fn check(context: &Context, expr: &RcExpr, expected_ty: &RcType) -> Result<(), String> {
-    match (&*expr.inner, &*expected_ty.inner) {
-        (&Expr::Lam(ref scope), &Type::Arrow(ref param_ty, ref ret_ty)) => {
-            if let ((Binder(free_var), Embed(None)), body) = scope.clone().unbind() {
-                check(&context.update(free_var, param_ty.clone()), &body, ret_ty)?;
-                return Ok(());
-            }
-        },
-        (_, _) => {},
+    if let (Expr::Lam(scope), Type::Arrow(param_ty, ret_ty)) = (&*expr.inner, &*expected_ty.inner) {
+        if let ((Binder(free_var), Embed(None)), body) = scope.clone().unbind() {
+            check(&context.update(free_var, param_ty.clone()), &body, ret_ty)?;
+            return Ok(());
+        }
     }
 
-    let inferred_ty = infer(&context, expr)?;
+    let inferred_ty = infer(context, expr)?;
 
-    if RcType::term_eq(&inferred_ty, expected_ty) {
-        Ok(())
-    } else {
-        Err(format!(
-            "type mismatch - found `{:?}` but expected `{:?}`",
-            inferred_ty, expected_ty
-        ))
+    match RcType::term_eq_check(&inferred_ty, expected_ty) {
+        Ok(()) => Ok(()),
+        Err(mismatch) => Err(format!(
+            "type mismatch - found `{:?}` but expected `{:?}` ({})",
+            inferred_ty, expected_ty, mismatch,
+        )),
     }
 }
 
@@ -222,6 +205,61 @@ fn test_infer() {
     );
 }
 
+#[test]
+fn test_check_mismatch() {
+    let x = FreeVar::fresh_named("x");
+
+    // expr = (\x : Int -> x)
+    let expr = RcExpr::from(Expr::Lam(Scope::new(
+        (Binder(x.clone()), Embed(Some(RcType::from(Type::Int)))),
+        RcExpr::from(Expr::Var(Var::Free(x.clone()))),
+    )));
+    let expected_ty = RcType::from(Type::Arrow(
+        RcType::from(Type::Int),
+        RcType::from(Type::Float),
+    ));
+
+    let message = check(&Context::new(), &expr, &expected_ty).unwrap_err();
+    assert!(message.ends_with("(at `Arrow.1`: `Int` differs from `Float`)"));
+}
+
+#[test]
+fn test_map_pattern() {
+    let x = FreeVar::fresh_named("x");
+
+    // \x -> x
+    let scope = Scope::new(
+        (Binder(x.clone()), Embed(None::<RcType>)),
+        RcExpr::from(Expr::Var(Var::Free(x.clone()))),
+    );
+
+    // \x : Int -> x
+    let scope = scope.map_pattern(|(binder, _)| (binder, Embed(Some(RcType::from(Type::Int)))));
+
+    assert_term_eq!(
+        scope,
+        Scope::new(
+            (Binder(x.clone()), Embed(Some(RcType::from(Type::Int)))),
+            RcExpr::from(Expr::Var(Var::Free(x.clone()))),
+        ),
+    );
+}
+
+#[test]
+#[should_panic(expected = "cannot change the binders of a pattern when mapping over it")]
+fn test_map_pattern_binders() {
+    let x = FreeVar::fresh_named("x");
+    let y = FreeVar::fresh_named("y");
+
+    // \x -> x
+    let scope = Scope::new(
+        (Binder(x.clone()), Embed(None::<RcType>)),
+        RcExpr::from(Expr::Var(Var::Free(x.clone()))),
+    );
+
+    scope.map_pattern(|(_, ann)| (Binder(y.clone()), ann));
+}
+
 // TODO: Use property testing for this!
 // http://janmidtgaard.dk/papers/Midtgaard-al%3AICFP17-full.pdf
 
@@ -14,7 +14,7 @@ extern crate im;
 extern crate moniker;
 
 use im::HashMap;
-use moniker::{Binder, BoundTerm, Embed, FreeVar, Scope, Var};
+use moniker::{Binder, BoundTerm, Embed, FreeVar, Record, Scope, Subst, Var};
 use std::rc::Rc;
 
 /// Types
@@ -27,7 +27,8 @@ use std::rc::Rc;
 ///     | {l₁:t₁, ..., lₙ:tₙ}   record types
 ///     | <l₁:t₁, ..., lₙ:tₙ>   variant types
 /// ```
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, AlphaHash, AlphaOrd, BoundTerm, Subst)]
+#[subst(replacement = "RcExpr")]
 pub enum Type {
     /// Integers
     Int,
@@ -38,13 +39,14 @@ pub enum Type {
     /// Function types
     Arrow(RcType, RcType),
     /// Record types
-    Record(Vec<(String, RcType)>),
+    Record(#[moniker(unordered)] Vec<(String, RcType)>),
     /// Variant types
-    Variant(Vec<(String, RcType)>),
+    Variant(#[moniker(unordered)] Vec<(String, RcType)>),
 }
 
 /// Reference counted types
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, AlphaHash, AlphaOrd, BoundTerm, Subst)]
+#[subst(replacement = "RcExpr")]
 pub struct RcType {
     pub inner: Rc<Type>,
 }
@@ -58,7 +60,8 @@ impl From<Type> for RcType {
 }
 
 /// Literal values
-#[derive(Debug, Clone, PartialEq, BoundTerm, BoundPattern)]
+#[derive(Debug, Clone, PartialEq, Alpha, Subst)]
+#[subst(replacement = "RcExpr")]
 pub enum Literal {
     /// Integer literals
     Int(i32),
@@ -77,7 +80,8 @@ pub enum Literal {
 ///     | {l₁=p₁, ..., lₙ=pₙ}   record patterns
 ///     | <l=p>                 tag patterns
 /// ```
-#[derive(Debug, Clone, BoundPattern)]
+#[derive(Debug, Clone, BoundPattern, Subst)]
+#[subst(replacement = "RcExpr")]
 pub enum Pattern {
     /// Wildcard patterns
     Wildcard,
@@ -88,13 +92,14 @@ pub enum Pattern {
     /// Patterns that bind variables
     Binder(Binder<String>),
     /// Record patterns
-    Record(Vec<(String, RcPattern)>),
+    Record(Record<String, RcPattern>),
     /// Tag pattern
     Tag(String, RcPattern),
 }
 
 /// Reference counted patterns
-#[derive(Debug, Clone, BoundPattern)]
+#[derive(Debug, Clone, BoundPattern, Subst)]
+#[subst(replacement = "RcExpr")]
 pub struct RcPattern {
     pub inner: Rc<Pattern>,
 }
@@ -120,13 +125,15 @@ impl From<Pattern> for RcPattern {
 ///     | <l=e>                             tag expressions
 ///     | case e of p₁=>e₁, ..., pₙ=>eₙ     case expressions
 /// ```
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
+#[subst(replacement = "RcExpr")]
 pub enum Expr {
     /// Annotated expressions
     Ann(RcExpr, RcType),
     /// Literals
     Literal(Literal),
     /// Variables
+    #[subst(var)]
     Var(Var<String>),
     /// Lambda expressions
     Lam(Scope<RcPattern, RcExpr>),
@@ -143,8 +150,9 @@ pub enum Expr {
 }
 
 /// Reference counted expressions
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
 pub struct RcExpr {
+    #[subst(var)]
     pub inner: Rc<Expr>,
 }
 
@@ -156,53 +164,6 @@ impl From<Expr> for RcExpr {
     }
 }
 
-impl RcExpr {
-    // FIXME: auto-derive this somehow!
-    fn substs<N: PartialEq<Var<String>>>(&self, mappings: &[(N, RcExpr)]) -> RcExpr {
-        match *self.inner {
-            Expr::Ann(ref expr, ref ty) => {
-                RcExpr::from(Expr::Ann(expr.substs(mappings), ty.clone()))
-            },
-            Expr::Var(ref var) => match mappings.iter().find(|&(name, _)| name == var) {
-                Some((_, ref replacement)) => replacement.clone(),
-                None => self.clone(),
-            },
-            Expr::Literal(_) => self.clone(),
-            Expr::Lam(ref scope) => RcExpr::from(Expr::Lam(Scope {
-                unsafe_pattern: scope.unsafe_pattern.clone(),
-                unsafe_body: scope.unsafe_body.substs(mappings),
-            })),
-            Expr::App(ref fun, ref arg) => {
-                RcExpr::from(Expr::App(fun.substs(mappings), arg.substs(mappings)))
-            },
-            Expr::Record(ref fields) => {
-                let fields = fields
-                    .iter()
-                    .map(|&(ref label, ref elem)| (label.clone(), elem.substs(mappings)))
-                    .collect();
-
-                RcExpr::from(Expr::Record(fields))
-            },
-            Expr::Proj(ref expr, ref label) => {
-                RcExpr::from(Expr::Proj(expr.substs(mappings), label.clone()))
-            },
-            Expr::Tag(ref label, ref expr) => {
-                RcExpr::from(Expr::Tag(label.clone(), expr.substs(mappings)))
-            },
-            Expr::Case(ref expr, ref clauses) => RcExpr::from(Expr::Case(
-                expr.substs(mappings),
-                clauses
-                    .iter()
-                    .map(|scope| Scope {
-                        unsafe_pattern: scope.unsafe_pattern.clone(), // subst?
-                        unsafe_body: scope.unsafe_body.substs(mappings),
-                    })
-                    .collect(),
-            )),
-        }
-    }
-}
-
 /// Evaluate an expression into its normal form
 pub fn eval(expr: &RcExpr) -> RcExpr {
     match *expr.inner {
@@ -221,7 +182,7 @@ pub fn eval(expr: &RcExpr) -> RcExpr {
         Expr::Record(ref fields) => {
             let fields = fields
                 .iter()
-                .map(|&(ref label, ref elem)| (label.clone(), eval(elem)))
+                .map(|(label, elem)| (label.clone(), eval(elem)))
                 .collect();
 
             RcExpr::from(Expr::Record(fields))
@@ -230,7 +191,7 @@ pub fn eval(expr: &RcExpr) -> RcExpr {
             let expr = eval(expr);
 
             if let Expr::Record(ref fields) = *expr.inner {
-                if let Some(&(_, ref e)) = fields.iter().find(|&(ref l, _)| l == label) {
+                if let Some((_, e)) = fields.iter().find(|(l, _)| l == label) {
                     return e.clone();
                 }
             }
@@ -257,28 +218,21 @@ pub fn eval(expr: &RcExpr) -> RcExpr {
 /// We assume that the given expression has been evaluated first!
 pub fn match_expr(pattern: &RcPattern, expr: &RcExpr) -> Option<Vec<(FreeVar<String>, RcExpr)>> {
     match (&*pattern.inner, &*expr.inner) {
-        (&Pattern::Ann(ref pattern, _), _) => match_expr(pattern, expr),
-        (&Pattern::Literal(ref pattern_lit), &Expr::Literal(ref expr_lit))
-            if pattern_lit == expr_lit =>
-        {
+        (Pattern::Ann(pattern, _), _) => match_expr(pattern, expr),
+        (Pattern::Literal(pattern_lit), Expr::Literal(expr_lit)) if pattern_lit == expr_lit => {
             Some(vec![])
         },
         (&Pattern::Binder(Binder(ref free_var)), _) => Some(vec![(free_var.clone(), expr.clone())]),
-        (&Pattern::Record(ref pattern_fields), &Expr::Record(ref expr_fields))
+        (Pattern::Record(pattern_fields), Expr::Record(expr_fields))
             if pattern_fields.len() == expr_fields.len() =>
         {
-            // FIXME: allow out-of-order fields in records
             let mut mappings = Vec::new();
-            for (pattern_field, expr_field) in <_>::zip(pattern_fields.iter(), expr_fields.iter()) {
-                if pattern_field.0 != expr_field.0 {
-                    return None;
-                } else {
-                    mappings.extend(match_expr(&pattern_field.1, &expr_field.1)?);
-                }
+            for (label, expr) in expr_fields {
+                mappings.extend(match_expr(pattern_fields.get(label)?, expr)?);
             }
             Some(mappings)
-        }
-        (&Pattern::Tag(ref pattern_label, ref pattern), &Expr::Tag(ref expr_label, ref expr))
+        },
+        (Pattern::Tag(pattern_label, pattern), Expr::Tag(expr_label, expr))
             if pattern_label == expr_label =>
         {
             match_expr(pattern, expr)
@@ -293,21 +247,21 @@ type Context = HashMap<FreeVar<String>, RcType>;
 /// Check that a (potentially ambiguous) expression conforms to a given ype
 pub fn check_expr(context: &Context, expr: &RcExpr, expected_ty: &RcType) -> Result<(), String> {
     match (&*expr.inner, &*expected_ty.inner) {
-        (&Expr::Lam(ref scope), &Type::Arrow(ref param_ty, ref ret_ty)) => {
+        (Expr::Lam(scope), Type::Arrow(param_ty, ret_ty)) => {
             let (pattern, body) = scope.clone().unbind();
             let bindings = check_pattern(context, &pattern, param_ty)?;
             return check_expr(&(context + &bindings), &body, ret_ty);
         },
-        (&Expr::Tag(ref label, ref expr), &Type::Variant(ref variants)) => {
+        (Expr::Tag(label, expr), Type::Variant(variants)) => {
             return match variants.iter().find(|&(l, _)| l == label) {
                 None => Err(format!(
                     "variant type did not contain the label `{}`",
                     label
                 )),
-                Some(&(_, ref ty)) => check_expr(context, expr, ty),
+                Some((_, ty)) => check_expr(context, expr, ty),
             };
         },
-        (&Expr::Case(ref expr, ref clauses), _) => {
+        (Expr::Case(expr, clauses), _) => {
             let expr_ty = infer_expr(context, expr)?;
             for clause in clauses {
                 let (pattern, body) = clause.clone().unbind();
@@ -319,7 +273,7 @@ pub fn check_expr(context: &Context, expr: &RcExpr, expected_ty: &RcType) -> Res
         (_, _) => {},
     }
 
-    let inferred_ty = infer_expr(&context, expr)?;
+    let inferred_ty = infer_expr(context, expr)?;
 
     // FIXME: allow out-of-order fields in records
     if RcType::term_eq(&inferred_ty, expected_ty) {
@@ -372,14 +326,14 @@ pub fn infer_expr(context: &Context, expr: &RcExpr) -> Result<RcType, String> {
         Expr::Record(ref fields) => {
             let fields = fields
                 .iter()
-                .map(|&(ref label, ref expr)| Ok((label.clone(), infer_expr(context, expr)?)))
+                .map(|(label, expr)| Ok((label.clone(), infer_expr(context, expr)?)))
                 .collect::<Result<_, String>>()?;
 
             Ok(RcType::from(Type::Record(fields)))
         },
         Expr::Proj(ref expr, ref label) => match *infer_expr(context, expr)?.inner {
             Type::Record(ref fields) => match fields.iter().find(|&(l, _)| l == label) {
-                Some(&(_, ref ty)) => Ok(ty.clone()),
+                Some((_, ty)) => Ok(ty.clone()),
                 None => Err(format!("field `{}` not found in type", label)),
             },
             _ => Err("record expected".to_string()),
@@ -403,21 +357,21 @@ pub fn check_pattern(
 ) -> Result<Context, String> {
     match (&*pattern.inner, &*expected_ty.inner) {
         (&Pattern::Binder(Binder(ref free_var)), _) => {
-            return Ok(Context::singleton(free_var.clone(), expected_ty.clone()));
+            return Ok(Context::unit(free_var.clone(), expected_ty.clone()));
         },
-        (&Pattern::Tag(ref label, ref pattern), &Type::Variant(ref variants)) => {
+        (Pattern::Tag(label, pattern), Type::Variant(variants)) => {
             return match variants.iter().find(|&(l, _)| l == label) {
                 None => Err(format!(
                     "variant type did not contain the label `{}`",
                     label
                 )),
-                Some(&(_, ref ty)) => check_pattern(context, pattern, ty),
+                Some((_, ty)) => check_pattern(context, pattern, ty),
             };
         },
         (_, _) => {},
     }
 
-    let (inferred_ty, telescope) = infer_pattern(&context, pattern)?;
+    let (inferred_ty, telescope) = infer_pattern(context, pattern)?;
 
     // FIXME: allow out-of-order fields in records
     if RcType::term_eq(&inferred_ty, expected_ty) {
@@ -450,7 +404,7 @@ pub fn infer_pattern(context: &Context, expr: &RcPattern) -> Result<(RcType, Con
 
             let fields = fields
                 .iter()
-                .map(|&(ref label, ref pattern)| {
+                .map(|(label, pattern)| {
                     let (pattern_ty, pattern_telescope) = infer_pattern(context, pattern)?;
                     telescope.extend(pattern_telescope);
                     Ok((label.clone(), pattern_ty))
@@ -523,7 +477,7 @@ fn test_infer_expr_record1() {
 
     // expr = \{ x = a : Int, y = b : String } -> b
     let expr = RcExpr::from(Expr::Lam(Scope::new(
-        RcPattern::from(Pattern::Record(vec![
+        RcPattern::from(Pattern::Record(Record::new(vec![
             (
                 String::from("x"),
                 RcPattern::from(Pattern::Ann(
@@ -538,7 +492,7 @@ fn test_infer_expr_record1() {
                     Embed(RcType::from(Type::String)),
                 )),
             ),
-        ])),
+        ]))),
         RcExpr::from(Expr::Var(Var::Free(b.clone()))),
     )));
 
@@ -564,7 +518,7 @@ fn test_infer_expr_record2() {
 
     // expr = \{ x = a : Int, y = b : String, z = c : Float } -> { x = a, y = b, z = c }
     let expr = RcExpr::from(Expr::Lam(Scope::new(
-        RcPattern::from(Pattern::Record(vec![
+        RcPattern::from(Pattern::Record(Record::new(vec![
             (
                 String::from("x"),
                 RcPattern::from(Pattern::Ann(
@@ -586,7 +540,7 @@ fn test_infer_expr_record2() {
                     Embed(RcType::from(Type::Float)),
                 )),
             ),
-        ])),
+        ]))),
         RcExpr::from(Expr::Record(vec![
             (
                 String::from("x"),
@@ -620,6 +574,216 @@ fn test_infer_expr_record2() {
     );
 }
 
+#[test]
+fn test_term_eq_alternative_patterns() {
+    use moniker::FreeVar;
+
+    let a = FreeVar::fresh_named("a");
+    let b = FreeVar::fresh_named("b");
+
+    // \<foo = a> -> a
+    let tag_lam = |binder: &FreeVar<String>| {
+        Scope::new(
+            RcPattern::from(Pattern::Tag(
+                String::from("foo"),
+                RcPattern::from(Pattern::Binder(Binder(binder.clone()))),
+            )),
+            RcExpr::from(Expr::Var(Var::Free(binder.clone()))),
+        )
+    };
+
+    // \{ foo = a } -> a
+    let record_lam = Scope::new(
+        RcPattern::from(Pattern::Record(Record::new(vec![(
+            String::from("foo"),
+            RcPattern::from(Pattern::Binder(Binder(a.clone()))),
+        )]))),
+        RcExpr::from(Expr::Var(Var::Free(a.clone()))),
+    );
+
+    assert_term_eq!(tag_lam(&a), tag_lam(&b));
+    assert!(!tag_lam(&a).term_eq(&record_lam));
+}
+
+#[test]
+fn test_term_eq_record_types_unordered() {
+    use moniker::BoundTerm;
+
+    let field = |label: &str, ty| (String::from(label), RcType::from(ty));
+
+    // {foo : Int, bar : String} == {bar : String, foo : Int}
+    let ty1 = RcType::from(Type::Record(vec![
+        field("foo", Type::Int),
+        field("bar", Type::String),
+    ]));
+    let ty2 = RcType::from(Type::Record(vec![
+        field("bar", Type::String),
+        field("foo", Type::Int),
+    ]));
+    // {foo : Int, bar : Int}
+    let ty3 = RcType::from(Type::Record(vec![
+        field("foo", Type::Int),
+        field("bar", Type::Int),
+    ]));
+
+    assert_term_eq!(ty1, ty2);
+    assert!(!ty1.term_eq(&ty3));
+    assert!(!ty1.term_eq(&RcType::from(Type::Record(vec![field("foo", Type::Int)]))));
+}
+
+#[test]
+fn test_alpha_hash_record_types_unordered() {
+    use moniker::AlphaHash;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+
+    let field = |label: &str, ty| (String::from(label), RcType::from(ty));
+    let hash = |ty: &RcType| {
+        let mut state = DefaultHasher::new();
+        ty.alpha_hash(&mut state);
+        state.finish()
+    };
+
+    // {foo : Int, bar : String} and {bar : String, foo : Int}
+    let ty1 = RcType::from(Type::Record(vec![
+        field("foo", Type::Int),
+        field("bar", Type::String),
+    ]));
+    let ty2 = RcType::from(Type::Record(vec![
+        field("bar", Type::String),
+        field("foo", Type::Int),
+    ]));
+    // <foo : Int, bar : String>
+    let ty3 = RcType::from(Type::Variant(vec![
+        field("foo", Type::Int),
+        field("bar", Type::String),
+    ]));
+
+    assert_eq!(hash(&ty1), hash(&ty2));
+    assert_ne!(hash(&ty1), hash(&ty3));
+}
+
+#[test]
+fn test_alpha_cmp_record_types_unordered() {
+    use moniker::AlphaOrd;
+    use std::cmp::Ordering;
+
+    let field = |label: &str, ty| (String::from(label), RcType::from(ty));
+
+    // {foo : Int, bar : String} and {bar : String, foo : Int}
+    let ty1 = RcType::from(Type::Record(vec![
+        field("foo", Type::Int),
+        field("bar", Type::String),
+    ]));
+    let ty2 = RcType::from(Type::Record(vec![
+        field("bar", Type::String),
+        field("foo", Type::Int),
+    ]));
+    // {bar : Int, foo : Int}
+    let ty3 = RcType::from(Type::Record(vec![
+        field("bar", Type::Int),
+        field("foo", Type::Int),
+    ]));
+
+    assert_eq!(ty1.alpha_cmp(&ty2), Ordering::Equal);
+    assert_eq!(ty1.alpha_cmp(&ty3), ty2.alpha_cmp(&ty3));
+    assert_eq!(ty1.alpha_cmp(&ty3), ty3.alpha_cmp(&ty1).reverse());
+    assert_ne!(ty1.alpha_cmp(&ty3), Ordering::Equal);
+}
+
+#[test]
+fn test_term_eq_record_patterns_unordered() {
+    use moniker::FreeVar;
+
+    let a = FreeVar::fresh_named("a");
+    let b = FreeVar::fresh_named("b");
+    let c = FreeVar::fresh_named("c");
+    let d = FreeVar::fresh_named("d");
+
+    let field = |label: &str, fv: &FreeVar<String>| {
+        (
+            String::from(label),
+            RcPattern::from(Pattern::Binder(Binder(fv.clone()))),
+        )
+    };
+    let var = |fv: &FreeVar<String>| RcExpr::from(Expr::Var(Var::Free(fv.clone())));
+
+    // \{ foo = a, bar = b } -> a
+    let lam1 = Scope::new(
+        RcPattern::from(Pattern::Record(Record::new(vec![
+            field("foo", &a),
+            field("bar", &b),
+        ]))),
+        var(&a),
+    );
+    // \{ bar = d, foo = c } -> c
+    let lam2 = Scope::new(
+        RcPattern::from(Pattern::Record(Record::new(vec![
+            field("bar", &d),
+            field("foo", &c),
+        ]))),
+        var(&c),
+    );
+    // \{ bar = d, foo = c } -> d
+    let lam3 = Scope::new(
+        RcPattern::from(Pattern::Record(Record::new(vec![
+            field("bar", &d),
+            field("foo", &c),
+        ]))),
+        var(&d),
+    );
+
+    assert_term_eq!(lam1, lam2);
+    assert!(!lam1.term_eq(&lam3));
+
+    // Record expressions can be matched regardless of the order of their fields
+    let expr = RcExpr::from(Expr::Record(vec![
+        (
+            String::from("foo"),
+            RcExpr::from(Expr::Literal(Literal::Int(1))),
+        ),
+        (
+            String::from("bar"),
+            RcExpr::from(Expr::Literal(Literal::Int(2))),
+        ),
+    ]));
+    let (pattern, body) = lam3.unbind();
+    let mappings = match_expr(&pattern, &expr).unwrap();
+    assert_term_eq!(
+        body.substs(&mappings),
+        RcExpr::from(Expr::Literal(Literal::Int(2))),
+    );
+}
+
+#[test]
+fn test_unbind2_alternative_patterns() {
+    use moniker::{FreeVar, UnbindError};
+
+    let a = FreeVar::fresh_named("a");
+
+    // \<foo = a> -> a
+    let tag_lam = Scope::new(
+        RcPattern::from(Pattern::Tag(
+            String::from("foo"),
+            RcPattern::from(Pattern::Binder(Binder(a.clone()))),
+        )),
+        RcExpr::from(Expr::Var(Var::Free(a.clone()))),
+    );
+    // \_ -> 1
+    let wildcard_lam = Scope::new(
+        RcPattern::from(Pattern::Wildcard),
+        RcExpr::from(Expr::Literal(Literal::Int(1))),
+    );
+
+    assert_eq!(
+        tag_lam.unbind2(wildcard_lam).unwrap_err(),
+        UnbindError::BinderCountMismatch {
+            first: 1,
+            second: 0,
+        },
+    );
+}
+
 // TODO: Use property testing for this!
 // http://janmidtgaard.dk/papers/Midtgaard-al%3AICFP17-full.pdf
 
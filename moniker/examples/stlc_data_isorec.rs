@@ -15,7 +15,7 @@ extern crate im;
 extern crate moniker;
 
 use im::HashMap;
-use moniker::{Binder, BoundTerm, Embed, FreeVar, Rec, Scope, Var};
+use moniker::{Binder, BoundTerm, Embed, FreeVar, Rec, Scope, Subst, Var};
 use std::rc::Rc;
 
 /// Types
@@ -29,7 +29,8 @@ use std::rc::Rc;
 ///     | <l₁:t₁, ..., lₙ:tₙ>   variant types
 ///     | rec x => t            recursive type
 /// ```
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
+#[subst(replacement = "RcType", replacement = "RcExpr")]
 pub enum Type {
     /// Integers
     Int,
@@ -38,6 +39,7 @@ pub enum Type {
     /// Strings
     String,
     /// Type variables
+    #[subst(var)]
     Var(Var<String>), // TODO: Separate identifier namespaces? See issue #8
     /// Function types
     Arrow(RcType, RcType),
@@ -46,12 +48,17 @@ pub enum Type {
     /// Variant types
     Variant(Vec<(String, RcType)>),
     /// Recursive types
-    Rec(Scope<Rec<(Binder<String>, Embed<RcType>)>, ()>),
+    Rec(Scope<Rec<TypeBinding>, ()>),
 }
 
+/// The binding of a recursive type, from a type variable to its body
+pub type TypeBinding = (Binder<String>, Embed<RcType>);
+
 /// Reference counted types
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
+#[subst(replacement = "RcType", replacement = "RcExpr")]
 pub struct RcType {
+    #[subst(var)]
     pub inner: Rc<Type>,
 }
 
@@ -63,47 +70,9 @@ impl From<Type> for RcType {
     }
 }
 
-impl RcType {
-    // FIXME: auto-derive this somehow!
-    fn subst<N: PartialEq<Var<String>>>(&self, name: &N, replacement: &RcType) -> RcType {
-        match *self.inner {
-            Type::Var(ref var) if name == var => replacement.clone(),
-            Type::Var(_) | Type::Int | Type::Float | Type::String => self.clone(),
-            Type::Arrow(ref param, ref body) => RcType::from(Type::Arrow(
-                param.subst(name, replacement),
-                body.subst(name, replacement),
-            )),
-            Type::Record(ref fields) => {
-                let fields = fields
-                    .iter()
-                    .map(|&(ref label, ref elem)| (label.clone(), elem.subst(name, replacement)))
-                    .collect();
-
-                RcType::from(Type::Record(fields))
-            },
-            Type::Variant(ref variants) => {
-                let variants = variants
-                    .iter()
-                    .map(|&(ref label, ref elem)| (label.clone(), elem.subst(name, replacement)))
-                    .collect();
-
-                RcType::from(Type::Variant(variants))
-            },
-            Type::Rec(ref scope) => {
-                let (ref n, Embed(ref ann)) = scope.unsafe_pattern.unsafe_pattern;
-                RcType::from(Type::Rec(Scope {
-                    unsafe_pattern: Rec {
-                        unsafe_pattern: (n.clone(), Embed(ann.subst(name, replacement))),
-                    },
-                    unsafe_body: (),
-                }))
-            },
-        }
-    }
-}
-
 /// Literal values
-#[derive(Debug, Clone, PartialEq, BoundTerm, BoundPattern)]
+#[derive(Debug, Clone, PartialEq, Alpha, Subst)]
+#[subst(replacement = "RcExpr")]
 pub enum Literal {
     /// Integer literals
     Int(i32),
@@ -122,7 +91,8 @@ pub enum Literal {
 ///     | {l₁=p₁, ..., lₙ=pₙ}   record patterns
 ///     | <l=p>                 tag patterns
 /// ```
-#[derive(Debug, Clone, BoundPattern)]
+#[derive(Debug, Clone, BoundPattern, Subst)]
+#[subst(replacement = "RcExpr")]
 pub enum Pattern {
     /// Wildcard patterns
     Wildcard,
@@ -139,7 +109,8 @@ pub enum Pattern {
 }
 
 /// Reference counted patterns
-#[derive(Debug, Clone, BoundPattern)]
+#[derive(Debug, Clone, BoundPattern, Subst)]
+#[subst(replacement = "RcExpr")]
 pub struct RcPattern {
     pub inner: Rc<Pattern>,
 }
@@ -167,13 +138,15 @@ impl From<Pattern> for RcPattern {
 ///     | fold t => e                       fold expressions
 ///     | unfold t => e                     unfold expressions
 /// ```
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
+#[subst(replacement = "RcExpr")]
 pub enum Expr {
     /// Annotated expressions
     Ann(RcExpr, RcType),
     /// Literals
     Literal(Literal),
     /// Variables
+    #[subst(var)]
     Var(Var<String>), // TODO: Separate identifier namespaces? See issue #8
     /// Lambda expressions, with an optional type annotation for the parameter
     Lam(Scope<RcPattern, RcExpr>),
@@ -194,8 +167,9 @@ pub enum Expr {
 }
 
 /// Reference counted expressions
-#[derive(Debug, Clone, BoundTerm)]
+#[derive(Debug, Clone, BoundTerm, Subst)]
 pub struct RcExpr {
+    #[subst(var)]
     pub inner: Rc<Expr>,
 }
 
@@ -207,59 +181,6 @@ impl From<Expr> for RcExpr {
     }
 }
 
-impl RcExpr {
-    // FIXME: auto-derive this somehow!
-    fn substs<N: PartialEq<Var<String>>>(&self, mappings: &[(N, RcExpr)]) -> RcExpr {
-        match *self.inner {
-            Expr::Ann(ref expr, ref ty) => {
-                RcExpr::from(Expr::Ann(expr.substs(mappings), ty.clone()))
-            },
-            Expr::Var(ref var) => match mappings.iter().find(|&(name, _)| name == var) {
-                Some((_, ref replacement)) => replacement.clone(),
-                None => self.clone(),
-            },
-            Expr::Literal(_) => self.clone(),
-            Expr::Lam(ref scope) => RcExpr::from(Expr::Lam(Scope {
-                unsafe_pattern: scope.unsafe_pattern.clone(),
-                unsafe_body: scope.unsafe_body.substs(mappings),
-            })),
-            Expr::App(ref fun, ref arg) => {
-                RcExpr::from(Expr::App(fun.substs(mappings), arg.substs(mappings)))
-            },
-            Expr::Record(ref fields) => {
-                let fields = fields
-                    .iter()
-                    .map(|&(ref label, ref elem)| (label.clone(), elem.substs(mappings)))
-                    .collect();
-
-                RcExpr::from(Expr::Record(fields))
-            },
-            Expr::Proj(ref expr, ref label) => {
-                RcExpr::from(Expr::Proj(expr.substs(mappings), label.clone()))
-            },
-            Expr::Tag(ref label, ref expr) => {
-                RcExpr::from(Expr::Tag(label.clone(), expr.substs(mappings)))
-            },
-            Expr::Case(ref expr, ref clauses) => RcExpr::from(Expr::Case(
-                expr.substs(mappings),
-                clauses
-                    .iter()
-                    .map(|scope| Scope {
-                        unsafe_pattern: scope.unsafe_pattern.clone(), // subst?
-                        unsafe_body: scope.unsafe_body.substs(mappings),
-                    })
-                    .collect(),
-            )),
-            Expr::Fold(ref ty, ref expr) => {
-                RcExpr::from(Expr::Fold(ty.clone(), expr.substs(mappings)))
-            },
-            Expr::Unfold(ref ty, ref expr) => {
-                RcExpr::from(Expr::Unfold(ty.clone(), expr.substs(mappings)))
-            },
-        }
-    }
-}
-
 /// A context containing a series of type annotations
 type Context = HashMap<FreeVar<String>, RcType>;
 
@@ -281,7 +202,7 @@ pub fn eval(expr: &RcExpr) -> RcExpr {
         Expr::Record(ref fields) => {
             let fields = fields
                 .iter()
-                .map(|&(ref label, ref elem)| (label.clone(), eval(elem)))
+                .map(|(label, elem)| (label.clone(), eval(elem)))
                 .collect();
 
             RcExpr::from(Expr::Record(fields))
@@ -290,7 +211,7 @@ pub fn eval(expr: &RcExpr) -> RcExpr {
             let expr = eval(expr);
 
             if let Expr::Record(ref fields) = *expr.inner {
-                if let Some(&(_, ref e)) = fields.iter().find(|&(ref l, _)| l == label) {
+                if let Some((_, e)) = fields.iter().find(|(l, _)| l == label) {
                     return e.clone();
                 }
             }
@@ -325,14 +246,12 @@ pub fn eval(expr: &RcExpr) -> RcExpr {
 /// We assume that the given expression has been evaluated first!
 pub fn match_expr(pattern: &RcPattern, expr: &RcExpr) -> Option<Vec<(FreeVar<String>, RcExpr)>> {
     match (&*pattern.inner, &*expr.inner) {
-        (&Pattern::Ann(ref pattern, _), _) => match_expr(pattern, expr),
-        (&Pattern::Literal(ref pattern_lit), &Expr::Literal(ref expr_lit))
-            if pattern_lit == expr_lit =>
-        {
+        (Pattern::Ann(pattern, _), _) => match_expr(pattern, expr),
+        (Pattern::Literal(pattern_lit), Expr::Literal(expr_lit)) if pattern_lit == expr_lit => {
             Some(vec![])
         },
         (&Pattern::Binder(Binder(ref free_var)), _) => Some(vec![(free_var.clone(), expr.clone())]),
-        (&Pattern::Record(ref pattern_fields), &Expr::Record(ref expr_fields))
+        (Pattern::Record(pattern_fields), Expr::Record(expr_fields))
             if pattern_fields.len() == expr_fields.len() =>
         {
             // FIXME: allow out-of-order fields in records
@@ -345,8 +264,8 @@ pub fn match_expr(pattern: &RcPattern, expr: &RcExpr) -> Option<Vec<(FreeVar<Str
                 }
             }
             Some(mappings)
-        }
-        (&Pattern::Tag(ref pattern_label, ref pattern), &Expr::Tag(ref expr_label, ref expr))
+        },
+        (Pattern::Tag(pattern_label, pattern), Expr::Tag(expr_label, expr))
             if pattern_label == expr_label =>
         {
             match_expr(pattern, expr)
@@ -359,21 +278,21 @@ pub fn match_expr(pattern: &RcPattern, expr: &RcExpr) -> Option<Vec<(FreeVar<Str
 /// expected type
 pub fn check_expr(context: &Context, expr: &RcExpr, expected_ty: &RcType) -> Result<(), String> {
     match (&*expr.inner, &*expected_ty.inner) {
-        (&Expr::Lam(ref scope), &Type::Arrow(ref param_ty, ref ret_ty)) => {
+        (Expr::Lam(scope), Type::Arrow(param_ty, ret_ty)) => {
             let (pattern, body) = scope.clone().unbind();
             let bindings = check_pattern(context, &pattern, param_ty)?;
             return check_expr(&(context + &bindings), &body, ret_ty);
         },
-        (&Expr::Tag(ref label, ref expr), &Type::Variant(ref variants)) => {
+        (Expr::Tag(label, expr), Type::Variant(variants)) => {
             return match variants.iter().find(|&(l, _)| l == label) {
                 None => Err(format!(
                     "variant type did not contain the label `{}`",
                     label
                 )),
-                Some(&(_, ref ty)) => check_expr(context, expr, ty),
+                Some((_, ty)) => check_expr(context, expr, ty),
             };
         },
-        (&Expr::Case(ref expr, ref clauses), _) => {
+        (Expr::Case(expr, clauses), _) => {
             let expr_ty = infer_expr(context, expr)?;
             for clause in clauses {
                 let (pattern, body) = clause.clone().unbind();
@@ -385,7 +304,7 @@ pub fn check_expr(context: &Context, expr: &RcExpr, expected_ty: &RcType) -> Res
         (_, _) => {},
     }
 
-    let inferred_ty = infer_expr(&context, expr)?;
+    let inferred_ty = infer_expr(context, expr)?;
 
     if RcType::term_eq(&inferred_ty, expected_ty) {
         Ok(())
@@ -437,12 +356,12 @@ pub fn infer_expr(context: &Context, expr: &RcExpr) -> Result<RcType, String> {
         Expr::Record(ref fields) => Ok(RcType::from(Type::Record(
             fields
                 .iter()
-                .map(|&(ref label, ref expr)| Ok((label.clone(), infer_expr(context, expr)?)))
+                .map(|(label, expr)| Ok((label.clone(), infer_expr(context, expr)?)))
                 .collect::<Result<_, String>>()?,
         ))),
         Expr::Proj(ref expr, ref label) => match *infer_expr(context, expr)?.inner {
             Type::Record(ref fields) => match fields.iter().find(|&(l, _)| l == label) {
-                Some(&(_, ref ty)) => Ok(ty.clone()),
+                Some((_, ty)) => Ok(ty.clone()),
                 None => Err(format!("field `{}` not found in type", label)),
             },
             _ => Err("record expected".to_string()),
@@ -482,21 +401,21 @@ pub fn check_pattern(
 ) -> Result<Context, String> {
     match (&*pattern.inner, &*expected_ty.inner) {
         (&Pattern::Binder(Binder(ref free_var)), _) => {
-            return Ok(Context::singleton(free_var.clone(), expected_ty.clone()));
+            return Ok(Context::unit(free_var.clone(), expected_ty.clone()));
         },
-        (&Pattern::Tag(ref label, ref pattern), &Type::Variant(ref variants)) => {
+        (Pattern::Tag(label, pattern), Type::Variant(variants)) => {
             return match variants.iter().find(|&(l, _)| l == label) {
                 None => Err(format!(
                     "variant type did not contain the label `{}`",
                     label
                 )),
-                Some(&(_, ref ty)) => check_pattern(context, pattern, ty),
+                Some((_, ty)) => check_pattern(context, pattern, ty),
             };
         },
         (_, _) => {},
     }
 
-    let (inferred_ty, telescope) = infer_pattern(&context, pattern)?;
+    let (inferred_ty, telescope) = infer_pattern(context, pattern)?;
 
     // FIXME: allow out-of-order fields in records
     if RcType::term_eq(&inferred_ty, expected_ty) {
@@ -529,7 +448,7 @@ pub fn infer_pattern(context: &Context, expr: &RcPattern) -> Result<(RcType, Con
 
             let fields = fields
                 .iter()
-                .map(|&(ref label, ref pattern)| {
+                .map(|(label, pattern)| {
                     let (pattern_ty, pattern_telescope) = infer_pattern(context, pattern)?;
                     telescope.extend(pattern_telescope);
                     Ok((label.clone(), pattern_ty))
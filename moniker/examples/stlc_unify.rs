@@ -0,0 +1,282 @@
+//! An example of using the `moniker` library to infer the types of the simply
+//! typed lambda calculus, using metavariables to stand in for the types of
+//! unannotated parameters until they are solved by unification
+
+extern crate im;
+#[macro_use]
+extern crate moniker;
+
+use im::HashMap;
+use moniker::{Binder, FreeVar, Instantiate, MetaVar, Scope, Var};
+use std::rc::Rc;
+
+/// Types
+///
+/// ```text
+/// t ::= Int           integer types
+///     | t -> t        function types
+///     | ?α            metavariables
+/// ```
+#[derive(Debug, Clone, BoundTerm, Instantiate)]
+#[instantiate(replacement = "RcType")]
+pub enum Type {
+    /// Integers
+    Int,
+    /// Function types
+    Arrow(RcType, RcType),
+    /// Metavariables
+    #[instantiate(meta)]
+    Meta(MetaVar<String>),
+}
+
+/// Reference counted types
+#[derive(Debug, Clone, BoundTerm, Instantiate)]
+#[moniker(transparent)]
+pub struct RcType {
+    pub inner: Rc<Type>,
+}
+
+impl From<Type> for RcType {
+    fn from(src: Type) -> RcType {
+        RcType {
+            inner: Rc::new(src),
+        }
+    }
+}
+
+/// Expressions
+///
+/// ```text
+/// e ::= i             integer literals
+///     | x             variables
+///     | \x => e       anonymous functions
+///     | e₁ e₂         function application
+/// ```
+#[derive(Debug, Clone, BoundTerm)]
+pub enum Expr {
+    /// Integer literals
+    Int(i32),
+    /// Variables
+    Var(Var<String>),
+    /// Lambda expressions
+    Lam(Scope<Binder<String>, RcExpr>),
+    /// Function application
+    App(RcExpr, RcExpr),
+}
+
+/// Reference counted expressions
+#[derive(Debug, Clone, BoundTerm)]
+pub struct RcExpr {
+    pub inner: Rc<Expr>,
+}
+
+impl From<Expr> for RcExpr {
+    fn from(src: Expr) -> RcExpr {
+        RcExpr {
+            inner: Rc::new(src),
+        }
+    }
+}
+
+/// A context containing a series of type annotations
+type Context = HashMap<FreeVar<String>, RcType>;
+
+/// The solutions to the metavariables that have been found so far
+#[derive(Debug, Clone, Default)]
+pub struct Solutions {
+    solutions: std::collections::HashMap<MetaVar<String>, RcType>,
+}
+
+impl Solutions {
+    pub fn new() -> Solutions {
+        Solutions::default()
+    }
+
+    /// Replace each of the solved metavariables in a type with its solution
+    pub fn zonk(&self, ty: &RcType) -> RcType {
+        let ty = ty.instantiate(&self.solutions);
+        // Solutions may refer to other metavariables that have since been
+        // solved, so we need to keep going until there are none left
+        if ty
+            .metas()
+            .iter()
+            .any(|meta| self.solutions.contains_key(meta))
+        {
+            self.zonk(&ty)
+        } else {
+            ty
+        }
+    }
+
+    /// Solve a metavariable, checking that the solution does not refer to it
+    fn solve(&mut self, meta: &MetaVar<String>, ty: &RcType) -> Result<(), String> {
+        let ty = self.zonk(ty);
+        if ty.metas().contains(meta) {
+            Err(format!(
+                "cannot construct the infinite type `{} = {:?}`",
+                meta, ty
+            ))
+        } else {
+            self.solutions.insert(meta.clone(), ty);
+            Ok(())
+        }
+    }
+
+    /// Unify two types, solving any metavariables that are needed to make them
+    /// equal
+    pub fn unify(&mut self, lhs: &RcType, rhs: &RcType) -> Result<(), String> {
+        let lhs = self.zonk(lhs);
+        let rhs = self.zonk(rhs);
+
+        match (&*lhs.inner, &*rhs.inner) {
+            (Type::Int, Type::Int) => Ok(()),
+            (Type::Arrow(lhs_param, lhs_ret), Type::Arrow(rhs_param, rhs_ret)) => {
+                self.unify(lhs_param, rhs_param)?;
+                self.unify(lhs_ret, rhs_ret)
+            },
+            (Type::Meta(lhs_meta), Type::Meta(rhs_meta)) if lhs_meta == rhs_meta => Ok(()),
+            (Type::Meta(meta), _) => self.solve(meta, &rhs),
+            (_, Type::Meta(meta)) => self.solve(meta, &lhs),
+            (_, _) => Err(format!(
+                "type mismatch - found `{:?}` but expected `{:?}`",
+                lhs, rhs,
+            )),
+        }
+    }
+
+    /// Synthesize the type of an expression, which may contain unsolved
+    /// metavariables
+    pub fn infer(&mut self, context: &Context, expr: &RcExpr) -> Result<RcType, String> {
+        match *expr.inner {
+            Expr::Int(_) => Ok(RcType::from(Type::Int)),
+            Expr::Var(Var::Free(ref free_var)) => match context.get(free_var) {
+                Some(ty) => Ok((*ty).clone()),
+                None => Err(format!("`{}` not found in `{:?}`", free_var, context)),
+            },
+            Expr::Var(Var::Bound(ref bound_var)) => {
+                panic!("encountered a bound variable: {}", bound_var)
+            },
+            Expr::Lam(ref scope) => {
+                let (Binder(free_var), body) = scope.clone().unbind();
+                let param_meta = MetaVar::fresh(free_var.pretty_name.clone());
+                let param_ty = RcType::from(Type::Meta(param_meta));
+                let body_ty = self.infer(&context.update(free_var, param_ty.clone()), &body)?;
+                Ok(RcType::from(Type::Arrow(param_ty, body_ty)))
+            },
+            Expr::App(ref fun, ref arg) => {
+                let fun_ty = self.infer(context, fun)?;
+                let arg_ty = self.infer(context, arg)?;
+                let ret_ty = RcType::from(Type::Meta(MetaVar::fresh_unnamed()));
+                self.unify(&fun_ty, &RcType::from(Type::Arrow(arg_ty, ret_ty.clone())))?;
+                Ok(ret_ty)
+            },
+        }
+    }
+}
+
+/// Infer the type of an expression, replacing the metavariables that were
+/// solved along the way
+pub fn infer(context: &Context, expr: &RcExpr) -> Result<RcType, String> {
+    let mut solutions = Solutions::new();
+    let ty = solutions.infer(context, expr)?;
+    Ok(solutions.zonk(&ty))
+}
+
+#[cfg(test)]
+fn var(free_var: &FreeVar<String>) -> RcExpr {
+    RcExpr::from(Expr::Var(Var::Free(free_var.clone())))
+}
+
+#[test]
+fn test_infer_id() {
+    let x = FreeVar::fresh_named("x");
+
+    // expr = \x => x
+    let expr = RcExpr::from(Expr::Lam(Scope::new(Binder(x.clone()), var(&x))));
+    let ty = infer(&Context::new(), &expr).unwrap();
+
+    // The parameter type is left unsolved
+    let metas = ty.metas();
+    assert_eq!(metas.len(), 1);
+    let meta = RcType::from(Type::Meta(metas.into_iter().next().unwrap()));
+    assert_term_eq!(ty, RcType::from(Type::Arrow(meta.clone(), meta)));
+}
+
+#[test]
+fn test_infer_app() {
+    let x = FreeVar::fresh_named("x");
+
+    // expr = (\x => x) 1
+    let expr = RcExpr::from(Expr::App(
+        RcExpr::from(Expr::Lam(Scope::new(Binder(x.clone()), var(&x)))),
+        RcExpr::from(Expr::Int(1)),
+    ));
+
+    assert_term_eq!(
+        infer(&Context::new(), &expr).unwrap(),
+        RcType::from(Type::Int)
+    );
+}
+
+#[test]
+fn test_infer_compose() {
+    let f = FreeVar::fresh_named("f");
+    let x = FreeVar::fresh_named("x");
+
+    // expr = \f => \x => f (f x)
+    let expr = RcExpr::from(Expr::Lam(Scope::new(
+        Binder(f.clone()),
+        RcExpr::from(Expr::Lam(Scope::new(
+            Binder(x.clone()),
+            RcExpr::from(Expr::App(
+                var(&f),
+                RcExpr::from(Expr::App(var(&f), var(&x))),
+            )),
+        ))),
+    )));
+    let ty = infer(&Context::new(), &expr).unwrap();
+
+    // ty = (?a -> ?a) -> ?a -> ?a
+    let metas = ty.metas();
+    assert_eq!(metas.len(), 1);
+    let meta = RcType::from(Type::Meta(metas.into_iter().next().unwrap()));
+    let arrow = |param_ty, ret_ty| RcType::from(Type::Arrow(param_ty, ret_ty));
+    assert_term_eq!(
+        ty,
+        arrow(arrow(meta.clone(), meta.clone()), arrow(meta.clone(), meta)),
+    );
+}
+
+#[test]
+fn test_infer_occurs_check() {
+    let x = FreeVar::fresh_named("x");
+
+    // expr = \x => x x
+    let expr = RcExpr::from(Expr::Lam(Scope::new(
+        Binder(x.clone()),
+        RcExpr::from(Expr::App(var(&x), var(&x))),
+    )));
+
+    assert!(infer(&Context::new(), &expr).is_err());
+}
+
+#[test]
+fn test_instantiate() {
+    let a = MetaVar::fresh_named("a");
+    let b = MetaVar::fresh_named("b");
+    let meta = |meta: &MetaVar<String>| RcType::from(Type::Meta(meta.clone()));
+
+    // ?a -> ?b
+    let ty = RcType::from(Type::Arrow(meta(&a), meta(&b)));
+    assert_eq!(ty.metas(), [a.clone(), b.clone()].iter().cloned().collect());
+
+    // Unsolved metavariables are left untouched
+    let mut solutions = std::collections::HashMap::new();
+    solutions.insert(a.clone(), RcType::from(Type::Int));
+    assert_term_eq!(
+        ty.instantiate(&solutions),
+        RcType::from(Type::Arrow(RcType::from(Type::Int), meta(&b))),
+    );
+}
+
+fn main() {}
@@ -0,0 +1,351 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use ann_binder::AnnBinder;
+use bind_set::BindSet;
+use binder::Binder;
+use closed::Closed;
+use embed::Embed;
+use free_var::FreeVar;
+use hash_cons::Interned;
+use hashed::Hashed;
+use ignore::Ignore;
+use meta_var::MetaVar;
+use nest::Nest;
+use rebind::Rebind;
+use rec::Rec;
+use record::Record;
+use scope::Scope;
+use small_vec::SmallVec;
+use sorted::OtherSort;
+use spanned::Spanned;
+use var::Var;
+use wildcard::Wildcard;
+
+/// Hashing that is consistent with alpha equivalence
+///
+/// Terms that are equal according to `BoundTerm::term_eq`, and patterns that
+/// are equal according to `BoundPattern::pattern_eq`, produce the same hash.
+/// Bound variables are hashed using their de Bruijn indices and the names of
+/// binders are skipped, so renaming the bound variables of a term has no
+/// effect on its hash. Free variables and metavariables are hashed using
+/// their unique ids.
+///
+/// This can be used to implement `Hash` for a newtype that implements `Eq`
+/// using `BoundTerm::term_eq`, allowing terms to be used as the keys of hash
+/// maps, or to memoize functions on terms.
+pub trait AlphaHash<N> {
+    /// Feed the term or pattern into the given hasher, up to alpha equivalence
+    fn alpha_hash<H: Hasher>(&self, state: &mut H);
+}
+
+/// Hash the elements of a collection in a way that does not depend on their
+/// order, consistent with `unordered_term_eq`
+pub fn unordered_alpha_hash<'a, N, T, H>(elems: impl IntoIterator<Item = &'a T>, state: &mut H)
+where
+    T: AlphaHash<N> + 'a,
+    H: Hasher,
+{
+    // Summing the hashes of the elements makes the result independent of the
+    // order that they are visited in
+    let (len, sum) = elems
+        .into_iter()
+        .fold((0, 0), |(len, sum): (usize, u64), elem| {
+            let mut elem_state = DefaultHasher::new();
+            elem.alpha_hash(&mut elem_state);
+            (len + 1, sum.wrapping_add(elem_state.finish()))
+        });
+    state.write_usize(len);
+    state.write_u64(sum);
+}
+
+impl<N> AlphaHash<N> for Var<N> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            Var::Free(ref free_var) => {
+                state.write_u8(0);
+                free_var.unique_id.hash(state);
+            },
+            Var::Bound(ref bound_var) => {
+                state.write_u8(1);
+                bound_var.scope().hash(state);
+                bound_var.binder().hash(state);
+            },
+        }
+    }
+}
+
+impl<N> AlphaHash<N> for FreeVar<N> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        self.unique_id.hash(state);
+    }
+}
+
+impl<N> AlphaHash<N> for MetaVar<N> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        self.unique_id.hash(state);
+    }
+}
+
+/// The names of binders are not taken into account
+impl<N> AlphaHash<N> for Binder<N> {
+    fn alpha_hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl<N> AlphaHash<N> for BindSet<N> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        <[Binder<N>]>::alpha_hash(&self.unsafe_binders, state);
+    }
+}
+
+impl<N, T> AlphaHash<N> for Ignore<T> {
+    fn alpha_hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl<N> AlphaHash<N> for Wildcard {
+    fn alpha_hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl<N, T: AlphaHash<N>> AlphaHash<N> for Embed<T> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        self.0.alpha_hash(state);
+    }
+}
+
+impl<N, P: AlphaHash<N>> AlphaHash<N> for Nest<P> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        <[P]>::alpha_hash(&self.unsafe_patterns, state);
+    }
+}
+
+impl<N, P1: AlphaHash<N>, P2: AlphaHash<N>> AlphaHash<N> for Rebind<P1, P2> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        self.unsafe_left.alpha_hash(state);
+        self.unsafe_right.alpha_hash(state);
+    }
+}
+
+impl<N, P: AlphaHash<N>> AlphaHash<N> for Rec<P> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        self.unsafe_pattern.alpha_hash(state);
+    }
+}
+
+impl<N, L: Hash, P: AlphaHash<N>> AlphaHash<N> for Record<L, P> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.unsafe_fields.len());
+        for (label, pattern) in &self.unsafe_fields {
+            label.hash(state);
+            pattern.alpha_hash(state);
+        }
+    }
+}
+
+impl<N, P: AlphaHash<N>, T: AlphaHash<N>> AlphaHash<N> for Scope<P, T> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        self.unsafe_pattern.alpha_hash(state);
+        self.unsafe_body.alpha_hash(state);
+    }
+}
+
+impl<N, T: AlphaHash<N>> AlphaHash<N> for Closed<T> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        self.unsafe_term.alpha_hash(state);
+    }
+}
+
+impl<N, T: AlphaHash<N>> AlphaHash<N> for Interned<T> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        T::alpha_hash(self, state);
+    }
+}
+
+/// The embedded term is hashed using its own sort of variables
+impl<N, M, T: AlphaHash<M>> AlphaHash<N> for OtherSort<M, T> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        AlphaHash::<M>::alpha_hash(&self.term, state);
+    }
+}
+
+/// The cached hash is used in place of the hash of the term
+impl<N, T> AlphaHash<N> for Hashed<T> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash_value());
+    }
+}
+
+/// Annotations are not taken into account, because they are ignored when
+/// comparing the binders for alpha equality
+impl<N, A> AlphaHash<N> for AnnBinder<N, A> {
+    fn alpha_hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+/// Spans are not taken into account
+impl<N, S, T: AlphaHash<N>> AlphaHash<N> for Spanned<S, T> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.alpha_hash(state);
+    }
+}
+
+// Implementations for common types
+
+macro_rules! impl_alpha_hash_hash {
+    ([$($lifetime:tt),*] $T:ty) => {
+        impl<$($lifetime,)* N> AlphaHash<N> for $T {
+            fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+                self.hash(state);
+            }
+        }
+    };
+    ($T:ty) => {
+        impl_alpha_hash_hash!([] $T);
+    };
+}
+
+impl_alpha_hash_hash!(());
+impl_alpha_hash_hash!(String);
+impl_alpha_hash_hash!(str);
+impl_alpha_hash_hash!(['a] &'a str);
+impl_alpha_hash_hash!(char);
+impl_alpha_hash_hash!(bool);
+impl_alpha_hash_hash!(u8);
+impl_alpha_hash_hash!(u16);
+impl_alpha_hash_hash!(u32);
+impl_alpha_hash_hash!(u64);
+impl_alpha_hash_hash!(usize);
+impl_alpha_hash_hash!(i8);
+impl_alpha_hash_hash!(i16);
+impl_alpha_hash_hash!(i32);
+impl_alpha_hash_hash!(i64);
+impl_alpha_hash_hash!(isize);
+
+macro_rules! impl_alpha_hash_float {
+    ($T:ident) => {
+        /// Positive and negative zero are hashed the same, because they are
+        /// equal according to `PartialEq`
+        impl<N> AlphaHash<N> for $T {
+            fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+                let value = if *self == 0.0 { 0.0 } else { *self };
+                value.to_bits().hash(state);
+            }
+        }
+    };
+}
+
+impl_alpha_hash_float!(f32);
+impl_alpha_hash_float!(f64);
+
+impl<N, T: AlphaHash<N>> AlphaHash<N> for Option<T> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            None => state.write_u8(0),
+            Some(ref inner) => {
+                state.write_u8(1);
+                inner.alpha_hash(state);
+            },
+        }
+    }
+}
+
+impl<N, T: AlphaHash<N> + ?Sized> AlphaHash<N> for Box<T> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        T::alpha_hash(self, state);
+    }
+}
+
+impl<N, T: AlphaHash<N> + ?Sized> AlphaHash<N> for Rc<T> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        T::alpha_hash(self, state);
+    }
+}
+
+impl<N, T: AlphaHash<N> + ?Sized> AlphaHash<N> for Arc<T> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        T::alpha_hash(self, state);
+    }
+}
+
+impl<N, T1, T2> AlphaHash<N> for (T1, T2)
+where
+    T1: AlphaHash<N>,
+    T2: AlphaHash<N>,
+{
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        self.0.alpha_hash(state);
+        self.1.alpha_hash(state);
+    }
+}
+
+impl<N, T1, T2, T3> AlphaHash<N> for (T1, T2, T3)
+where
+    T1: AlphaHash<N>,
+    T2: AlphaHash<N>,
+    T3: AlphaHash<N>,
+{
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        self.0.alpha_hash(state);
+        self.1.alpha_hash(state);
+        self.2.alpha_hash(state);
+    }
+}
+
+impl<N, T1, T2, T3, T4> AlphaHash<N> for (T1, T2, T3, T4)
+where
+    T1: AlphaHash<N>,
+    T2: AlphaHash<N>,
+    T3: AlphaHash<N>,
+    T4: AlphaHash<N>,
+{
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        self.0.alpha_hash(state);
+        self.1.alpha_hash(state);
+        self.2.alpha_hash(state);
+        self.3.alpha_hash(state);
+    }
+}
+
+impl<N, T1, T2, T3, T4, T5> AlphaHash<N> for (T1, T2, T3, T4, T5)
+where
+    T1: AlphaHash<N>,
+    T2: AlphaHash<N>,
+    T3: AlphaHash<N>,
+    T4: AlphaHash<N>,
+    T5: AlphaHash<N>,
+{
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        self.0.alpha_hash(state);
+        self.1.alpha_hash(state);
+        self.2.alpha_hash(state);
+        self.3.alpha_hash(state);
+        self.4.alpha_hash(state);
+    }
+}
+
+impl<N, T: AlphaHash<N>> AlphaHash<N> for [T] {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        for elem in self {
+            elem.alpha_hash(state);
+        }
+    }
+}
+
+impl<N, T: AlphaHash<N>> AlphaHash<N> for Vec<T> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        <[T]>::alpha_hash(self, state);
+    }
+}
+
+impl<N, T: AlphaHash<N>> AlphaHash<N> for SmallVec<T> {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        <[T]>::alpha_hash(self, state);
+    }
+}
+
+impl<N, T: AlphaHash<N>, const LEN: usize> AlphaHash<N> for [T; LEN] {
+    fn alpha_hash<H: Hasher>(&self, state: &mut H) {
+        <[T]>::alpha_hash(self, state);
+    }
+}
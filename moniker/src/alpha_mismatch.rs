@@ -0,0 +1,115 @@
+use std::error::Error;
+use std::fmt;
+
+use binder::Binder;
+use var::Var;
+
+/// The place where two terms were found to differ by `BoundTerm::term_eq_check`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlphaMismatch<N> {
+    /// The path from the roots of the terms to the subterms that differ
+    pub path: Vec<PathSegment<N>>,
+    /// The subterm found in the left hand term
+    pub lhs: Mismatched<N>,
+    /// The subterm found in the right hand term
+    pub rhs: Mismatched<N>,
+}
+
+impl<N> AlphaMismatch<N> {
+    /// Create a mismatch between subterms at the root of the compared terms
+    pub fn new(lhs: Mismatched<N>, rhs: Mismatched<N>) -> AlphaMismatch<N> {
+        AlphaMismatch {
+            path: Vec::new(),
+            lhs,
+            rhs,
+        }
+    }
+
+    /// Place the mismatch inside an enclosing term, at the given `segment`
+    pub fn within(mut self, segment: PathSegment<N>) -> AlphaMismatch<N> {
+        self.path.insert(0, segment);
+        self
+    }
+}
+
+impl<N: fmt::Display> fmt::Display for AlphaMismatch<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "at `")?;
+            for (i, segment) in self.path.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " > ")?;
+                }
+                write!(f, "{}", segment)?;
+            }
+            write!(f, "`: ")?;
+        }
+        match (&self.lhs, &self.rhs) {
+            (Mismatched::Value, Mismatched::Value) => write!(f, "the values differ"),
+            (lhs, rhs) => write!(f, "{} differs from {}", lhs, rhs),
+        }
+    }
+}
+
+impl<N: fmt::Debug + fmt::Display> Error for AlphaMismatch<N> {}
+
+/// A step along the path to a subterm
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment<N> {
+    /// A field of a struct or an enum variant, like `App.0` or `Let.body`
+    Field(&'static str),
+    /// An element of a collection
+    Index(usize),
+    /// The pattern of a scope
+    Pattern,
+    /// The body of a scope, along with the binders of the scope's pattern
+    Body(Vec<Binder<N>>),
+}
+
+impl<N: fmt::Display> fmt::Display for PathSegment<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PathSegment::Field(label) => write!(f, "{}", label),
+            PathSegment::Index(index) => write!(f, "[{}]", index),
+            PathSegment::Pattern => write!(f, "pattern"),
+            PathSegment::Body(ref binders) => {
+                write!(f, "body")?;
+                for (i, binder) in binders.iter().enumerate() {
+                    write!(f, "{}", if i == 0 { " of " } else { ", " })?;
+                    match binder.0.pretty_name {
+                        Some(ref pretty_name) => write!(f, "{}", pretty_name)?,
+                        None => write!(f, "{}", binder)?,
+                    }
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+/// A description of one of the subterms that differ
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mismatched<N> {
+    /// A variant of an enum
+    Variant(&'static str),
+    /// A variable
+    Var(Var<N>),
+    /// A collection with the given number of elements
+    Len(usize),
+    /// A pattern that binds the given number of variables
+    Binders(usize),
+    /// Some other value, like a constant
+    Value,
+}
+
+impl<N: fmt::Display> fmt::Display for Mismatched<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Mismatched::Variant(name) => write!(f, "`{}`", name),
+            Mismatched::Var(ref var) => write!(f, "`{}`", var),
+            Mismatched::Len(len) => write!(f, "{} elements", len),
+            Mismatched::Binders(len) => write!(f, "a pattern binding {} variables", len),
+            Mismatched::Value => write!(f, "a value"),
+        }
+    }
+}
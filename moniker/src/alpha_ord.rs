@@ -0,0 +1,368 @@
+use std::cmp::Ordering;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use ann_binder::AnnBinder;
+use bind_set::BindSet;
+use binder::Binder;
+use closed::Closed;
+use embed::Embed;
+use free_var::FreeVar;
+use hash_cons::Interned;
+use hashed::Hashed;
+use ignore::Ignore;
+use meta_var::MetaVar;
+use nest::Nest;
+use rebind::Rebind;
+use rec::Rec;
+use record::Record;
+use scope::Scope;
+use small_vec::SmallVec;
+use spanned::Spanned;
+use var::Var;
+use wildcard::Wildcard;
+
+/// A total ordering that is consistent with alpha equivalence
+///
+/// Two terms are ordered as `Ordering::Equal` exactly when they are equal
+/// according to `BoundTerm::term_eq`, and likewise for patterns and
+/// `BoundPattern::pattern_eq`. Bound variables are ordered by their de Bruijn
+/// indices and the names of binders are skipped. Free variables and
+/// metavariables are ordered by their name hints first, falling back to their
+/// unique ids only when the name hints are the same, so the ordering of terms
+/// with distinctly named free variables does not depend on the order that the
+/// variables were generated in.
+///
+/// This can be used to implement `Ord` for a newtype that implements `Eq`
+/// using `BoundTerm::term_eq`, allowing terms to be stored in `BTreeMap`s and
+/// `BTreeSet`s, or to be sorted deterministically.
+pub trait AlphaOrd<N> {
+    /// Compare two terms or patterns, up to alpha equivalence
+    fn alpha_cmp(&self, other: &Self) -> Ordering;
+}
+
+/// Compare the elements of two collections without regard to their order,
+/// consistent with `unordered_term_eq`
+pub fn unordered_alpha_cmp<'a, N, T>(
+    lhs: impl IntoIterator<Item = &'a T>,
+    rhs: impl IntoIterator<Item = &'a T>,
+) -> Ordering
+where
+    T: AlphaOrd<N> + 'a,
+{
+    let mut lhs = lhs.into_iter().collect::<Vec<_>>();
+    let mut rhs = rhs.into_iter().collect::<Vec<_>>();
+    lhs.sort_by(|lhs, rhs| T::alpha_cmp(lhs, rhs));
+    rhs.sort_by(|lhs, rhs| T::alpha_cmp(lhs, rhs));
+    <[&T]>::alpha_cmp(&lhs, &rhs)
+}
+
+impl<N: Ord> AlphaOrd<N> for Var<N> {
+    fn alpha_cmp(&self, other: &Var<N>) -> Ordering {
+        match (self, other) {
+            (Var::Free(lhs), Var::Free(rhs)) => FreeVar::alpha_cmp(lhs, rhs),
+            (Var::Bound(lhs), Var::Bound(rhs)) => {
+                (lhs.scope(), lhs.binder()).cmp(&(rhs.scope(), rhs.binder()))
+            },
+            (Var::Free(_), Var::Bound(_)) => Ordering::Less,
+            (Var::Bound(_), Var::Free(_)) => Ordering::Greater,
+        }
+    }
+}
+
+impl<N: Ord> AlphaOrd<N> for FreeVar<N> {
+    fn alpha_cmp(&self, other: &FreeVar<N>) -> Ordering {
+        Ord::cmp(
+            &(&self.pretty_name, self.unique_id),
+            &(&other.pretty_name, other.unique_id),
+        )
+    }
+}
+
+impl<N: Ord> AlphaOrd<N> for MetaVar<N> {
+    fn alpha_cmp(&self, other: &MetaVar<N>) -> Ordering {
+        Ord::cmp(
+            &(&self.pretty_name, self.unique_id),
+            &(&other.pretty_name, other.unique_id),
+        )
+    }
+}
+
+/// The names of binders are not taken into account
+impl<N> AlphaOrd<N> for Binder<N> {
+    fn alpha_cmp(&self, _: &Binder<N>) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+impl<N> AlphaOrd<N> for BindSet<N> {
+    fn alpha_cmp(&self, other: &BindSet<N>) -> Ordering {
+        <[Binder<N>]>::alpha_cmp(&self.unsafe_binders, &other.unsafe_binders)
+    }
+}
+
+impl<N, T> AlphaOrd<N> for Ignore<T> {
+    fn alpha_cmp(&self, _: &Ignore<T>) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+impl<N> AlphaOrd<N> for Wildcard {
+    fn alpha_cmp(&self, _: &Wildcard) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+impl<N, T: AlphaOrd<N>> AlphaOrd<N> for Embed<T> {
+    fn alpha_cmp(&self, other: &Embed<T>) -> Ordering {
+        T::alpha_cmp(&self.0, &other.0)
+    }
+}
+
+impl<N, P: AlphaOrd<N>> AlphaOrd<N> for Nest<P> {
+    fn alpha_cmp(&self, other: &Nest<P>) -> Ordering {
+        <[P]>::alpha_cmp(&self.unsafe_patterns, &other.unsafe_patterns)
+    }
+}
+
+impl<N, P1: AlphaOrd<N>, P2: AlphaOrd<N>> AlphaOrd<N> for Rebind<P1, P2> {
+    fn alpha_cmp(&self, other: &Rebind<P1, P2>) -> Ordering {
+        P1::alpha_cmp(&self.unsafe_left, &other.unsafe_left)
+            .then_with(|| P2::alpha_cmp(&self.unsafe_right, &other.unsafe_right))
+    }
+}
+
+impl<N, P: AlphaOrd<N>> AlphaOrd<N> for Rec<P> {
+    fn alpha_cmp(&self, other: &Rec<P>) -> Ordering {
+        P::alpha_cmp(&self.unsafe_pattern, &other.unsafe_pattern)
+    }
+}
+
+impl<N, L: Ord, P: AlphaOrd<N>> AlphaOrd<N> for Record<L, P> {
+    fn alpha_cmp(&self, other: &Record<L, P>) -> Ordering {
+        let lhs_fields = self.unsafe_fields.iter();
+        let rhs_fields = other.unsafe_fields.iter();
+        <_>::zip(lhs_fields, rhs_fields)
+            .map(|(lhs, rhs)| lhs.0.cmp(&rhs.0).then_with(|| P::alpha_cmp(&lhs.1, &rhs.1)))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or_else(|| self.unsafe_fields.len().cmp(&other.unsafe_fields.len()))
+    }
+}
+
+impl<N, P: AlphaOrd<N>, T: AlphaOrd<N>> AlphaOrd<N> for Scope<P, T> {
+    fn alpha_cmp(&self, other: &Scope<P, T>) -> Ordering {
+        P::alpha_cmp(&self.unsafe_pattern, &other.unsafe_pattern)
+            .then_with(|| T::alpha_cmp(&self.unsafe_body, &other.unsafe_body))
+    }
+}
+
+impl<N, T: AlphaOrd<N>> AlphaOrd<N> for Closed<T> {
+    fn alpha_cmp(&self, other: &Closed<T>) -> Ordering {
+        T::alpha_cmp(&self.unsafe_term, &other.unsafe_term)
+    }
+}
+
+impl<N, T: AlphaOrd<N>> AlphaOrd<N> for Interned<T> {
+    fn alpha_cmp(&self, other: &Interned<T>) -> Ordering {
+        if Interned::ptr_eq(self, other) {
+            return Ordering::Equal;
+        }
+        T::alpha_cmp(self, other)
+    }
+}
+
+impl<N, T: AlphaOrd<N>> AlphaOrd<N> for Hashed<T> {
+    fn alpha_cmp(&self, other: &Hashed<T>) -> Ordering {
+        T::alpha_cmp(self, other)
+    }
+}
+
+/// Annotations are not taken into account, because they are ignored when
+/// comparing the binders for alpha equality
+impl<N, A> AlphaOrd<N> for AnnBinder<N, A> {
+    fn alpha_cmp(&self, _: &AnnBinder<N, A>) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+/// Spans are not taken into account
+impl<N, S, T: AlphaOrd<N>> AlphaOrd<N> for Spanned<S, T> {
+    fn alpha_cmp(&self, other: &Spanned<S, T>) -> Ordering {
+        T::alpha_cmp(&self.inner, &other.inner)
+    }
+}
+
+// Implementations for common types
+
+macro_rules! impl_alpha_ord_ord {
+    ([$($lifetime:tt),*] $T:ty) => {
+        impl<$($lifetime,)* N> AlphaOrd<N> for $T {
+            fn alpha_cmp(&self, other: &$T) -> Ordering {
+                Ord::cmp(self, other)
+            }
+        }
+    };
+    ($T:ty) => {
+        impl_alpha_ord_ord!([] $T);
+    };
+}
+
+impl_alpha_ord_ord!(());
+impl_alpha_ord_ord!(String);
+impl_alpha_ord_ord!(str);
+impl_alpha_ord_ord!(char);
+impl_alpha_ord_ord!(bool);
+impl_alpha_ord_ord!(u8);
+impl_alpha_ord_ord!(u16);
+impl_alpha_ord_ord!(u32);
+impl_alpha_ord_ord!(u64);
+impl_alpha_ord_ord!(usize);
+impl_alpha_ord_ord!(i8);
+impl_alpha_ord_ord!(i16);
+impl_alpha_ord_ord!(i32);
+impl_alpha_ord_ord!(i64);
+impl_alpha_ord_ord!(isize);
+
+macro_rules! impl_alpha_ord_float {
+    ($T:ident) => {
+        /// Positive and negative zero are ordered as equal, because they are
+        /// equal according to `PartialEq`. Other values, including `NaN`s,
+        /// are ordered using `total_cmp`.
+        impl<N> AlphaOrd<N> for $T {
+            fn alpha_cmp(&self, other: &$T) -> Ordering {
+                if self == other {
+                    Ordering::Equal
+                } else {
+                    $T::total_cmp(self, other)
+                }
+            }
+        }
+    };
+}
+
+impl_alpha_ord_float!(f32);
+impl_alpha_ord_float!(f64);
+
+impl<N, T: AlphaOrd<N>> AlphaOrd<N> for Option<T> {
+    fn alpha_cmp(&self, other: &Option<T>) -> Ordering {
+        match (self, other) {
+            (None, None) => Ordering::Equal,
+            (Some(lhs), Some(rhs)) => T::alpha_cmp(lhs, rhs),
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+        }
+    }
+}
+
+impl<'a, N, T: AlphaOrd<N> + ?Sized> AlphaOrd<N> for &'a T {
+    fn alpha_cmp(&self, other: &&'a T) -> Ordering {
+        T::alpha_cmp(self, other)
+    }
+}
+
+impl<N, T: AlphaOrd<N> + ?Sized> AlphaOrd<N> for Box<T> {
+    fn alpha_cmp(&self, other: &Box<T>) -> Ordering {
+        T::alpha_cmp(self, other)
+    }
+}
+
+impl<N, T: AlphaOrd<N> + ?Sized> AlphaOrd<N> for Rc<T> {
+    fn alpha_cmp(&self, other: &Rc<T>) -> Ordering {
+        if Rc::ptr_eq(self, other) {
+            return Ordering::Equal;
+        }
+        T::alpha_cmp(self, other)
+    }
+}
+
+impl<N, T: AlphaOrd<N> + ?Sized> AlphaOrd<N> for Arc<T> {
+    fn alpha_cmp(&self, other: &Arc<T>) -> Ordering {
+        if Arc::ptr_eq(self, other) {
+            return Ordering::Equal;
+        }
+        T::alpha_cmp(self, other)
+    }
+}
+
+impl<N, T1, T2> AlphaOrd<N> for (T1, T2)
+where
+    T1: AlphaOrd<N>,
+    T2: AlphaOrd<N>,
+{
+    fn alpha_cmp(&self, other: &(T1, T2)) -> Ordering {
+        T1::alpha_cmp(&self.0, &other.0).then_with(|| T2::alpha_cmp(&self.1, &other.1))
+    }
+}
+
+impl<N, T1, T2, T3> AlphaOrd<N> for (T1, T2, T3)
+where
+    T1: AlphaOrd<N>,
+    T2: AlphaOrd<N>,
+    T3: AlphaOrd<N>,
+{
+    fn alpha_cmp(&self, other: &(T1, T2, T3)) -> Ordering {
+        T1::alpha_cmp(&self.0, &other.0)
+            .then_with(|| T2::alpha_cmp(&self.1, &other.1))
+            .then_with(|| T3::alpha_cmp(&self.2, &other.2))
+    }
+}
+
+impl<N, T1, T2, T3, T4> AlphaOrd<N> for (T1, T2, T3, T4)
+where
+    T1: AlphaOrd<N>,
+    T2: AlphaOrd<N>,
+    T3: AlphaOrd<N>,
+    T4: AlphaOrd<N>,
+{
+    fn alpha_cmp(&self, other: &(T1, T2, T3, T4)) -> Ordering {
+        T1::alpha_cmp(&self.0, &other.0)
+            .then_with(|| T2::alpha_cmp(&self.1, &other.1))
+            .then_with(|| T3::alpha_cmp(&self.2, &other.2))
+            .then_with(|| T4::alpha_cmp(&self.3, &other.3))
+    }
+}
+
+impl<N, T1, T2, T3, T4, T5> AlphaOrd<N> for (T1, T2, T3, T4, T5)
+where
+    T1: AlphaOrd<N>,
+    T2: AlphaOrd<N>,
+    T3: AlphaOrd<N>,
+    T4: AlphaOrd<N>,
+    T5: AlphaOrd<N>,
+{
+    fn alpha_cmp(&self, other: &(T1, T2, T3, T4, T5)) -> Ordering {
+        T1::alpha_cmp(&self.0, &other.0)
+            .then_with(|| T2::alpha_cmp(&self.1, &other.1))
+            .then_with(|| T3::alpha_cmp(&self.2, &other.2))
+            .then_with(|| T4::alpha_cmp(&self.3, &other.3))
+            .then_with(|| T5::alpha_cmp(&self.4, &other.4))
+    }
+}
+
+/// Slices are ordered lexicographically
+impl<N, T: AlphaOrd<N>> AlphaOrd<N> for [T] {
+    fn alpha_cmp(&self, other: &[T]) -> Ordering {
+        <_>::zip(self.iter(), other)
+            .map(|(lhs, rhs)| T::alpha_cmp(lhs, rhs))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or_else(|| self.len().cmp(&other.len()))
+    }
+}
+
+impl<N, T: AlphaOrd<N>> AlphaOrd<N> for Vec<T> {
+    fn alpha_cmp(&self, other: &Vec<T>) -> Ordering {
+        <[T]>::alpha_cmp(self, other)
+    }
+}
+
+impl<N, T: AlphaOrd<N>> AlphaOrd<N> for SmallVec<T> {
+    fn alpha_cmp(&self, other: &SmallVec<T>) -> Ordering {
+        <[T]>::alpha_cmp(self, other)
+    }
+}
+
+impl<N, T: AlphaOrd<N>, const LEN: usize> AlphaOrd<N> for [T; LEN] {
+    fn alpha_cmp(&self, other: &[T; LEN]) -> Ordering {
+        <[T]>::alpha_cmp(self, other)
+    }
+}
@@ -0,0 +1,99 @@
+use std::hash::Hash;
+
+use alpha_mismatch::PathSegment;
+use binder::Binder;
+use bound::{BinderStack, BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use free_var::FreeVar;
+use subst::Subst;
+use var::Var;
+
+/// A binder that carries an annotation, like a type, a multiplicity, or a
+/// source span
+///
+/// The annotation travels along with the binder when it is bound, unbound, and
+/// freshened, but is ignored when comparing patterns for alpha equality. This
+/// avoids having to pair each binder with an `Embed<T>` when the annotation
+/// does not need to refer to the variables bound earlier in the pattern.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnnBinder<N, A> {
+    /// The binder
+    pub binder: Binder<N>,
+    /// The annotation on the binder
+    pub annotation: A,
+}
+
+impl<N, A> AnnBinder<N, A> {
+    /// Create a binder for the free variable, with an annotation
+    pub fn new(free_var: FreeVar<N>, annotation: A) -> AnnBinder<N, A> {
+        AnnBinder {
+            binder: Binder(free_var),
+            annotation,
+        }
+    }
+}
+
+impl<N, A> BoundPattern<N> for AnnBinder<N, A>
+where
+    N: Clone + Eq + Hash,
+{
+    fn pattern_eq(&self, _: &AnnBinder<N, A>) -> bool {
+        true
+    }
+
+    fn close_pattern(&mut self, _: ScopeState, _: &impl OnFreeFn<N>) {}
+
+    fn open_pattern(&mut self, _: ScopeState, _: &impl OnBoundFn<N>) {}
+
+    fn visit_vars(&self, _: &mut impl FnMut(&Var<N>)) {}
+
+    fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
+
+    fn visit_vars_with_binders(
+        &self,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+    }
+
+    fn visit_scopes(
+        &self,
+        _: &mut Vec<PathSegment<N>>,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+    }
+
+    fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
+
+    fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
+        on_binder(&self.binder)
+    }
+
+    fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
+        on_binder(&mut self.binder)
+    }
+
+    fn order_binders(&mut self, _: &impl BoundTerm<N>) {}
+}
+
+impl<N: Clone, T, A: Clone> Subst<N, T> for AnnBinder<N, A> {
+    fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> AnnBinder<N, A> {
+        self.clone()
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> AnnBinder<N, A> {
+        self.clone()
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, _: &V, _: &T) {}
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, _: &[(V, T)]) {}
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Option<AnnBinder<N, A>> {
+        None
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Option<AnnBinder<N, A>> {
+        None
+    }
+}
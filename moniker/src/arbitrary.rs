@@ -0,0 +1,456 @@
+use proptest::arbitrary::{any, Arbitrary};
+use proptest::collection;
+use proptest::sample;
+use proptest::strategy::{BoxedStrategy, Just, LazyJust, Strategy, Union};
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use ann_binder::AnnBinder;
+use bind_set::BindSet;
+use binder::Binder;
+use bound::{BoundPattern, BoundTerm};
+use closed::Closed;
+use embed::Embed;
+use free_var::FreeVar;
+use ignore::Ignore;
+use meta_var::MetaVar;
+use nest::Nest;
+use rebind::Rebind;
+use rec::Rec;
+use record::Record;
+use scope::Scope;
+use spanned::Spanned;
+use var::Var;
+use wildcard::Wildcard;
+
+/// The variables that are available when generating arbitrary terms
+#[derive(Debug, Clone)]
+pub struct ArbitraryEnv {
+    /// Free variables that may appear anywhere in the generated terms
+    free_vars: Vec<FreeVar<String>>,
+    /// The binders of the enclosing scopes
+    binders: Vec<FreeVar<String>>,
+    /// The name hints to choose from when generating binders
+    names: Vec<String>,
+    /// The number of nested types that can still be generated
+    depth: u32,
+}
+
+impl ArbitraryEnv {
+    /// An environment for generating closed terms, where each variable refers
+    /// to an enclosing binder
+    pub fn closed() -> ArbitraryEnv {
+        ArbitraryEnv {
+            free_vars: Vec::new(),
+            binders: Vec::new(),
+            names: vec![String::from("x"), String::from("y"), String::from("z")],
+            depth: 5,
+        }
+    }
+
+    /// An environment for generating open terms, where variables may also
+    /// refer to the given free variables
+    pub fn open(free_vars: Vec<FreeVar<String>>) -> ArbitraryEnv {
+        ArbitraryEnv {
+            free_vars,
+            ..ArbitraryEnv::closed()
+        }
+    }
+
+    /// Limit the number of nested types that will be generated
+    ///
+    /// Each derived type that is generated uses up one level of depth.
+    pub fn with_depth(self, depth: u32) -> ArbitraryEnv {
+        ArbitraryEnv { depth, ..self }
+    }
+
+    /// Choose name hints for binders from the given names
+    pub fn with_names(self, names: Vec<String>) -> ArbitraryEnv {
+        ArbitraryEnv { names, ..self }
+    }
+
+    /// The environment for generating the fields of a type, or `None` if the
+    /// maximum depth has been reached
+    pub fn deeper(&self) -> Option<ArbitraryEnv> {
+        match self.depth {
+            0 => None,
+            depth => Some(ArbitraryEnv {
+                depth: depth - 1,
+                ..self.clone()
+            }),
+        }
+    }
+
+    /// The environment for generating terms in the scope of some binders
+    pub fn bind(&self, binders: Vec<Binder<String>>) -> ArbitraryEnv {
+        let mut env = self.clone();
+        env.binders
+            .extend(binders.into_iter().map(|binder| binder.0));
+        env
+    }
+
+    /// The variables that may be referred to in this environment
+    pub fn vars(&self) -> Vec<FreeVar<String>> {
+        let mut vars = self.free_vars.clone();
+        vars.extend(self.binders.iter().cloned());
+        vars
+    }
+}
+
+impl Default for ArbitraryEnv {
+    fn default() -> ArbitraryEnv {
+        ArbitraryEnv::closed()
+    }
+}
+
+/// Generate arbitrary terms that are well scoped
+///
+/// Variables in the generated terms only ever refer to the binders of
+/// enclosing scopes, or to the free variables of the environment. This can be
+/// derived for your own types using `#[derive(ArbitraryTerm)]`, which will also
+/// implement `proptest::arbitrary::Arbitrary`, using an `ArbitraryEnv` as its
+/// parameters.
+pub trait ArbitraryTerm: Sized + fmt::Debug + 'static {
+    /// Returns a strategy for generating terms in the environment, or `None`
+    /// if no terms could be generated, for example if there are no variables
+    /// in scope, or if the maximum depth has been reached
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Self>>;
+}
+
+impl ArbitraryTerm for Var<String> {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Var<String>>> {
+        let vars = env.vars();
+        if vars.is_empty() {
+            None
+        } else {
+            Some(sample::select(vars).prop_map(Var::Free).boxed())
+        }
+    }
+}
+
+impl ArbitraryTerm for FreeVar<String> {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<FreeVar<String>>> {
+        let vars = env.vars();
+        if vars.is_empty() {
+            None
+        } else {
+            Some(sample::select(vars).boxed())
+        }
+    }
+}
+
+/// Metavariables are never generated, because they only appear in terms that
+/// are in the middle of being elaborated
+impl ArbitraryTerm for MetaVar<String> {
+    fn arbitrary_term(_: &ArbitraryEnv) -> Option<BoxedStrategy<MetaVar<String>>> {
+        None
+    }
+}
+
+impl ArbitraryTerm for Binder<String> {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Binder<String>>> {
+        let names = env.names.clone();
+        let binder = sample::select(names).prop_map(|name| Binder(FreeVar::fresh_named(name)));
+        Some(binder.boxed())
+    }
+}
+
+impl<A: Arbitrary + 'static> ArbitraryTerm for AnnBinder<String, A> {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<AnnBinder<String, A>>> {
+        let binder = Binder::arbitrary_term(env)?;
+        let ann_binder =
+            (binder, any::<A>()).prop_map(|(binder, annotation)| AnnBinder { binder, annotation });
+        Some(ann_binder.boxed())
+    }
+}
+
+impl ArbitraryTerm for BindSet<String> {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<BindSet<String>>> {
+        let binders = Vec::<Binder<String>>::arbitrary_term(env)?;
+        Some(binders.prop_map(BindSet::new).boxed())
+    }
+}
+
+impl<T: Arbitrary + 'static> ArbitraryTerm for Ignore<T> {
+    fn arbitrary_term(_: &ArbitraryEnv) -> Option<BoxedStrategy<Ignore<T>>> {
+        Some(any::<T>().prop_map(Ignore).boxed())
+    }
+}
+
+impl ArbitraryTerm for Wildcard {
+    fn arbitrary_term(_: &ArbitraryEnv) -> Option<BoxedStrategy<Wildcard>> {
+        Some(Just(Wildcard).boxed())
+    }
+}
+
+/// Closed terms are generated without any of the variables in the environment
+impl<T: ArbitraryTerm> ArbitraryTerm for Closed<T> {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Closed<T>>> {
+        let env = ArbitraryEnv {
+            free_vars: Vec::new(),
+            binders: Vec::new(),
+            ..env.clone()
+        };
+        let term = T::arbitrary_term(&env)?;
+        Some(term.prop_map(|unsafe_term| Closed { unsafe_term }).boxed())
+    }
+}
+
+impl<T: ArbitraryTerm> ArbitraryTerm for Embed<T> {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Embed<T>>> {
+        T::arbitrary_term(env).map(|inner| inner.prop_map(Embed).boxed())
+    }
+}
+
+impl<P> ArbitraryTerm for Nest<P>
+where
+    P: ArbitraryTerm + BoundPattern<String> + Clone,
+{
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Nest<P>>> {
+        /// Generate `len` patterns, each in the scope of the previous ones
+        fn patterns<P>(env: ArbitraryEnv, len: usize) -> BoxedStrategy<Vec<P>>
+        where
+            P: ArbitraryTerm + BoundPattern<String> + Clone,
+        {
+            match (len, P::arbitrary_term(&env)) {
+                (0, _) | (_, None) => LazyJust::new(Vec::new).boxed(),
+                (_, Some(pattern)) => pattern
+                    .prop_flat_map(move |pattern| {
+                        let rest = patterns(env.bind(pattern.binders()), len - 1);
+                        rest.prop_map(move |mut rest| {
+                            rest.insert(0, pattern.clone());
+                            rest
+                        })
+                    })
+                    .boxed(),
+            }
+        }
+
+        let env = env.clone();
+        let nest = (0..4usize)
+            .prop_flat_map(move |len| patterns(env.clone(), len))
+            .prop_map(Nest::new::<String>);
+        Some(nest.boxed())
+    }
+}
+
+impl<P1, P2> ArbitraryTerm for Rebind<P1, P2>
+where
+    P1: ArbitraryTerm + BoundPattern<String> + Clone,
+    P2: ArbitraryTerm + BoundPattern<String>,
+{
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Rebind<P1, P2>>> {
+        let left = P1::arbitrary_term(env)?;
+        // Adding binders to the environment only ever makes more patterns
+        // possible, so the right pattern can always be generated if it can be
+        // generated without them
+        let _ = P2::arbitrary_term(env)?;
+
+        let env = env.clone();
+        let rebind = left.prop_flat_map(move |left| {
+            let right = P2::arbitrary_term(&env.bind(left.binders()))
+                .expect("the right pattern could not be generated");
+            right.prop_map(move |right| Rebind::new(left.clone(), right))
+        });
+        Some(rebind.boxed())
+    }
+}
+
+impl<P> ArbitraryTerm for Rec<P>
+where
+    P: ArbitraryTerm + BoundPattern<String> + Clone,
+{
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Rec<P>>> {
+        P::arbitrary_term(env).map(|pattern| pattern.prop_map(Rec::new::<String>).boxed())
+    }
+}
+
+impl<L, P> ArbitraryTerm for Record<L, P>
+where
+    L: Arbitrary + Ord + 'static,
+    P: ArbitraryTerm,
+{
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Record<L, P>>> {
+        match P::arbitrary_term(env) {
+            None => Some(LazyJust::new(|| Record::new(Vec::new())).boxed()),
+            // Generate the fields as a map so that the labels are unique
+            Some(field) => Some(
+                collection::btree_map(any::<L>(), field, 0..4)
+                    .prop_map(Record::new)
+                    .boxed(),
+            ),
+        }
+    }
+}
+
+impl<P, T> ArbitraryTerm for Scope<P, T>
+where
+    P: ArbitraryTerm + BoundPattern<String> + Clone,
+    T: ArbitraryTerm + BoundTerm<String>,
+{
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Scope<P, T>>> {
+        let mut pattern = P::arbitrary_term(env)?;
+
+        // Adding binders to the environment only ever makes more terms
+        // possible, so if the body can't be generated with an extra binder in
+        // scope, it can't be generated at all
+        let placeholder = Binder(FreeVar::fresh_unnamed());
+        let _ = T::arbitrary_term(&env.bind(vec![placeholder]))?;
+        if T::arbitrary_term(env).is_none() {
+            pattern = pattern
+                .prop_filter("the pattern must bind a variable", |pattern| {
+                    !pattern.binders().is_empty()
+                })
+                .boxed();
+        }
+
+        let env = env.clone();
+        let scope = pattern.prop_flat_map(move |pattern| {
+            let body = T::arbitrary_term(&env.bind(pattern.binders()))
+                .expect("the body of the scope could not be generated");
+            body.prop_map(move |body| Scope::new(pattern.clone(), body))
+        });
+        Some(scope.boxed())
+    }
+}
+
+impl<S: Arbitrary + 'static, T: ArbitraryTerm> ArbitraryTerm for Spanned<S, T> {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Spanned<S, T>>> {
+        let inner = T::arbitrary_term(env)?;
+        let spanned = (any::<S>(), inner).prop_map(|(span, inner)| Spanned::new(span, inner));
+        Some(spanned.boxed())
+    }
+}
+
+// Implementations for common types
+
+macro_rules! impl_arbitrary_term_any {
+    ($T:ty) => {
+        impl ArbitraryTerm for $T {
+            fn arbitrary_term(_: &ArbitraryEnv) -> Option<BoxedStrategy<$T>> {
+                Some(any::<$T>().boxed())
+            }
+        }
+    };
+}
+
+impl_arbitrary_term_any!(());
+impl_arbitrary_term_any!(String);
+impl_arbitrary_term_any!(char);
+impl_arbitrary_term_any!(bool);
+impl_arbitrary_term_any!(u8);
+impl_arbitrary_term_any!(u16);
+impl_arbitrary_term_any!(u32);
+impl_arbitrary_term_any!(u64);
+impl_arbitrary_term_any!(usize);
+impl_arbitrary_term_any!(i8);
+impl_arbitrary_term_any!(i16);
+impl_arbitrary_term_any!(i32);
+impl_arbitrary_term_any!(i64);
+impl_arbitrary_term_any!(isize);
+impl_arbitrary_term_any!(f32);
+impl_arbitrary_term_any!(f64);
+
+impl<T: ArbitraryTerm> ArbitraryTerm for Option<T> {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Option<T>>> {
+        let none = LazyJust::new(|| None).boxed();
+        match T::arbitrary_term(env) {
+            None => Some(none),
+            Some(inner) => Some(Union::new(vec![none, inner.prop_map(Some).boxed()]).boxed()),
+        }
+    }
+}
+
+impl<T: ArbitraryTerm> ArbitraryTerm for Box<T> {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Box<T>>> {
+        T::arbitrary_term(env).map(|inner| inner.prop_map(Box::new).boxed())
+    }
+}
+
+impl<T: ArbitraryTerm> ArbitraryTerm for Rc<T> {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Rc<T>>> {
+        T::arbitrary_term(env).map(|inner| inner.prop_map(Rc::new).boxed())
+    }
+}
+
+impl<T: ArbitraryTerm + Send + Sync> ArbitraryTerm for Arc<T> {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Arc<T>>> {
+        T::arbitrary_term(env).map(|inner| inner.prop_map(Arc::new).boxed())
+    }
+}
+
+impl<T1: ArbitraryTerm, T2: ArbitraryTerm> ArbitraryTerm for (T1, T2) {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<(T1, T2)>> {
+        Some((T1::arbitrary_term(env)?, T2::arbitrary_term(env)?).boxed())
+    }
+}
+
+impl<T1, T2, T3> ArbitraryTerm for (T1, T2, T3)
+where
+    T1: ArbitraryTerm,
+    T2: ArbitraryTerm,
+    T3: ArbitraryTerm,
+{
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<(T1, T2, T3)>> {
+        Some(
+            (
+                T1::arbitrary_term(env)?,
+                T2::arbitrary_term(env)?,
+                T3::arbitrary_term(env)?,
+            )
+                .boxed(),
+        )
+    }
+}
+
+impl<T1, T2, T3, T4> ArbitraryTerm for (T1, T2, T3, T4)
+where
+    T1: ArbitraryTerm,
+    T2: ArbitraryTerm,
+    T3: ArbitraryTerm,
+    T4: ArbitraryTerm,
+{
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<(T1, T2, T3, T4)>> {
+        Some(
+            (
+                T1::arbitrary_term(env)?,
+                T2::arbitrary_term(env)?,
+                T3::arbitrary_term(env)?,
+                T4::arbitrary_term(env)?,
+            )
+                .boxed(),
+        )
+    }
+}
+
+impl<T1, T2, T3, T4, T5> ArbitraryTerm for (T1, T2, T3, T4, T5)
+where
+    T1: ArbitraryTerm,
+    T2: ArbitraryTerm,
+    T3: ArbitraryTerm,
+    T4: ArbitraryTerm,
+    T5: ArbitraryTerm,
+{
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<(T1, T2, T3, T4, T5)>> {
+        Some(
+            (
+                T1::arbitrary_term(env)?,
+                T2::arbitrary_term(env)?,
+                T3::arbitrary_term(env)?,
+                T4::arbitrary_term(env)?,
+                T5::arbitrary_term(env)?,
+            )
+                .boxed(),
+        )
+    }
+}
+
+impl<T: ArbitraryTerm> ArbitraryTerm for Vec<T> {
+    fn arbitrary_term(env: &ArbitraryEnv) -> Option<BoxedStrategy<Vec<T>>> {
+        match T::arbitrary_term(env) {
+            None => Some(LazyJust::new(Vec::new).boxed()),
+            Some(elem) => Some(collection::vec(elem, 0..4).boxed()),
+        }
+    }
+}
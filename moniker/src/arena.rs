@@ -0,0 +1,348 @@
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::ops::{ControlFlow, Deref};
+use std::ptr;
+use std::thread::LocalKey;
+
+use alpha_mismatch::{AlphaMismatch, PathSegment};
+use binder::Binder;
+use bound::{BinderStack, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use bound_var::BoundVar;
+use free_var::FreeVar;
+use subst::Subst;
+use var::Var;
+
+/// The number of nodes in the first chunk of an arena
+const INITIAL_CHUNK_LEN: usize = 64;
+
+/// The wrapped function that `ArenaRef::close_term` passes to its node
+type DynOnFree<'a, N> = dyn Fn(ScopeState, &FreeVar<N>) -> Option<BoundVar<N>> + 'a;
+
+/// The wrapped function that `ArenaRef::open_term` passes to its node
+type DynOnBound<'a, N> = dyn Fn(ScopeState, &BoundVar<N>) -> Option<FreeVar<N>> + 'a;
+
+thread_local! {
+    /// The address of the function for replacing variables that was passed
+    /// to the nodes of the traversal in progress on this thread, after being
+    /// wrapped by `ArenaRef` to call `mark_changed`
+    static TRACKED_FN: Cell<*const ()> = const { Cell::new(ptr::null()) };
+
+    /// Whether any variables have been replaced in the node that
+    /// `ArenaRef::modify` is traversing on this thread
+    static CHANGED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Restores a thread local to its previous value once a node has been
+/// traversed, even if the traversal panics
+struct Restore<T: Copy + 'static>(&'static LocalKey<Cell<T>>, T);
+
+impl<T: Copy + 'static> Drop for Restore<T> {
+    fn drop(&mut self) {
+        self.0.with(|cell| cell.set(self.1));
+    }
+}
+
+/// Record that a variable in the node that is being traversed was replaced
+fn mark_changed() {
+    CHANGED.with(|changed| changed.set(true));
+}
+
+/// Returns `true` if `f` was already wrapped to call `mark_changed` by an
+/// `ArenaRef` that encloses the current node
+fn is_tracked<F>(f: &F) -> bool {
+    TRACKED_FN.with(Cell::get) == f as *const F as *const ()
+}
+
+/// Pass a function that has been wrapped to call `mark_changed` to
+/// `traverse`, so that the `ArenaRef`s nested in the term don't wrap it
+/// again, however deep the term is
+fn track<F, R>(f: &F, traverse: impl FnOnce(&F) -> R) -> R {
+    let previous = TRACKED_FN.with(|tracked| tracked.replace(f as *const F as *const ()));
+    let _restore = Restore(&TRACKED_FN, previous);
+    traverse(f)
+}
+
+/// Pass a mutable function that has been wrapped to call `mark_changed` to
+/// `traverse`, in the same way as `track`
+fn track_mut<F, R>(f: &mut F, traverse: impl FnOnce(&mut F) -> R) -> R {
+    let previous = TRACKED_FN.with(|tracked| tracked.replace(f as *const F as *const ()));
+    let _restore = Restore(&TRACKED_FN, previous);
+    traverse(f)
+}
+
+/// An arena that terms can be allocated in
+///
+/// Nodes are allocated in chunks, and are only freed once the whole arena is
+/// dropped. This avoids the reference counting of `Rc` in syntax trees that
+/// are built up and thrown away in one go, like the terms of a single
+/// compilation unit. Nodes are referred to using `ArenaRef`, which also
+/// allocates the nodes that are rebuilt when terms are bound, unbound, or
+/// substituted into.
+pub struct Arena<T> {
+    /// The chunks that nodes are allocated in, which are never grown beyond
+    /// their initial capacity so that the nodes in them are never moved
+    chunks: RefCell<Vec<Vec<T>>>,
+}
+
+impl<T> Arena<T> {
+    /// Create an empty arena
+    pub fn new() -> Arena<T> {
+        Arena {
+            chunks: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Move a node into the arena, returning a reference to it
+    pub fn alloc(&self, node: T) -> &T {
+        let mut chunks = self.chunks.borrow_mut();
+        let chunk_len = match chunks.last() {
+            Some(chunk) if chunk.len() < chunk.capacity() => None,
+            Some(chunk) => Some(chunk.capacity() * 2),
+            None => Some(INITIAL_CHUNK_LEN),
+        };
+        if let Some(chunk_len) = chunk_len {
+            chunks.push(Vec::with_capacity(chunk_len));
+        }
+
+        let chunk = chunks.last_mut().unwrap();
+        chunk.push(node);
+        let node = chunk.last().unwrap() as *const T;
+
+        // SAFETY: The chunk was not full, so pushing onto it did not move the
+        // existing nodes, and the nodes are only dropped along with the arena
+        unsafe { &*node }
+    }
+
+    /// Move a node into the arena, returning an `ArenaRef` to it
+    pub fn alloc_ref(&self, node: T) -> ArenaRef<'_, T> {
+        ArenaRef {
+            arena: self,
+            node: self.alloc(node),
+        }
+    }
+
+    /// The number of nodes that have been allocated in the arena
+    pub fn len(&self) -> usize {
+        self.chunks.borrow().iter().map(Vec::len).sum()
+    }
+
+    /// Returns `true` if no nodes have been allocated in the arena
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Arena<T> {
+        Arena::new()
+    }
+}
+
+impl<T> fmt::Debug for Arena<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Arena").field("len", &self.len()).finish()
+    }
+}
+
+/// A reference to a node in an `Arena`
+///
+/// This can be used in place of `Rc` in syntax trees, like
+/// `Scope<Binder<String>, ArenaRef<'a, Expr<'a>>>`. Nodes in the arena are
+/// never mutated, so binding, unbinding, and substituting into a term
+/// allocates new nodes in the same arena for the parts of the term that
+/// changed, in the same way as `Rc::make_mut` clones shared nodes. The nodes
+/// that do not contain any of the replaced variables are shared with the
+/// original term.
+pub struct ArenaRef<'a, T: 'a> {
+    arena: &'a Arena<T>,
+    node: &'a T,
+}
+
+impl<'a, T> ArenaRef<'a, T> {
+    /// The arena that the node was allocated in
+    pub fn arena(this: &ArenaRef<'a, T>) -> &'a Arena<T> {
+        this.arena
+    }
+
+    /// The node that this refers to, borrowed for as long as the arena
+    pub fn get(this: &ArenaRef<'a, T>) -> &'a T {
+        this.node
+    }
+
+    /// Returns `true` if both references point to the same node
+    pub fn ptr_eq(this: &ArenaRef<'a, T>, other: &ArenaRef<'a, T>) -> bool {
+        ptr::eq(this.node, other.node)
+    }
+
+    /// Modify a copy of the node, replacing the node with the copy if `f`
+    /// called `mark_changed`
+    fn modify(&mut self, f: impl FnOnce(&mut T))
+    where
+        T: Clone,
+    {
+        let mut node = self.node.clone();
+        let restore = Restore(&CHANGED, CHANGED.with(|changed| changed.replace(false)));
+        f(&mut node);
+
+        if CHANGED.with(Cell::get) {
+            self.node = self.arena.alloc(node);
+            // The enclosing node contains this one, so it has changed too
+            drop(restore);
+            mark_changed();
+        }
+    }
+}
+
+impl<'a, T> Copy for ArenaRef<'a, T> {}
+
+impl<'a, T> Clone for ArenaRef<'a, T> {
+    fn clone(&self) -> ArenaRef<'a, T> {
+        *self
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for ArenaRef<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.node, f)
+    }
+}
+
+impl<'a, T> Deref for ArenaRef<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.node
+    }
+}
+
+impl<'a, N, T> BoundTerm<N> for ArenaRef<'a, T>
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N> + Clone,
+{
+    fn term_eq(&self, other: &ArenaRef<'a, T>) -> bool {
+        ArenaRef::ptr_eq(self, other) || T::term_eq(self.node, other.node)
+    }
+
+    fn term_eq_check(&self, other: &ArenaRef<'a, T>) -> Result<(), AlphaMismatch<N>> {
+        if ArenaRef::ptr_eq(self, other) {
+            return Ok(());
+        }
+        T::term_eq_check(self.node, other.node)
+    }
+
+    fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        self.modify(|node| {
+            if is_tracked(on_free) {
+                return node.close_term(state, on_free);
+            }
+            let on_free = |state, free_var: &FreeVar<N>| {
+                let bound_var = on_free.call(state, free_var);
+                if bound_var.is_some() {
+                    mark_changed();
+                }
+                bound_var
+            };
+            let on_free: &DynOnFree<N> = &on_free;
+            track(&on_free, |on_free| node.close_term(state, on_free));
+        });
+    }
+
+    fn open_term(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        self.modify(|node| {
+            if is_tracked(on_bound) {
+                return node.open_term(state, on_bound);
+            }
+            let on_bound = |state, bound_var: &BoundVar<N>| {
+                let free_var = on_bound.call(state, bound_var);
+                if free_var.is_some() {
+                    mark_changed();
+                }
+                free_var
+            };
+            let on_bound: &DynOnBound<N> = &on_bound;
+            track(&on_bound, |on_bound| node.open_term(state, on_bound));
+        });
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        T::visit_vars(self.node, on_var);
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        T::try_visit_vars(self.node, on_var)
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        self.modify(|node| {
+            if is_tracked(on_var) {
+                return node.visit_mut_vars(on_var);
+            }
+            let mut on_var = |var: &mut Var<N>| {
+                let original = var.clone();
+                on_var(var);
+                if *var != original || var.pretty_name() != original.pretty_name() {
+                    mark_changed();
+                }
+            };
+            let mut on_var: &mut dyn FnMut(&mut Var<N>) = &mut on_var;
+            track_mut(&mut on_var, |on_var| node.visit_mut_vars(on_var));
+        });
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        T::visit_vars_with_binders(self.node, binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        T::visit_scopes(self.node, path, binders, on_scope);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        T::visit_nodes(self.node, depth, on_node);
+    }
+}
+
+impl<'a, N, R, T> Subst<N, R> for ArenaRef<'a, T>
+where
+    T: Subst<N, R>,
+{
+    fn as_var(&self) -> Option<&Var<N>> {
+        self.node.as_var()
+    }
+
+    /// Nodes are only allocated for the parts of the term that changed
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &R) -> ArenaRef<'a, T> {
+        self.subst_changed(name, replacement).unwrap_or(*self)
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, R)]) -> ArenaRef<'a, T> {
+        self.substs_changed(mappings).unwrap_or(*self)
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(
+        &self,
+        name: &V,
+        replacement: &R,
+    ) -> Option<ArenaRef<'a, T>> {
+        let node = self.node.subst_changed(name, replacement)?;
+        Some(self.arena.alloc_ref(node))
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, R)]) -> Option<ArenaRef<'a, T>> {
+        let node = self.node.substs_changed(mappings)?;
+        Some(self.arena.alloc_ref(node))
+    }
+}
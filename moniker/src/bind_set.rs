@@ -0,0 +1,144 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::ops::ControlFlow;
+
+use alpha_mismatch::PathSegment;
+use binder::Binder;
+use bound::{BinderStack, BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use subst::Subst;
+use var::Var;
+
+/// A set of binders, where the order that the binders were given in does not
+/// matter for alpha equality
+///
+/// When a set is used to create a `Scope`, its binders are put in the order
+/// that they first occur in the body, so that each binder is given the same
+/// index however the set was written and whatever its binders are named.
+/// Binders that do not occur in the body are kept in the order that they were
+/// given in, after the others. Duplicate binders are removed when the set is
+/// created.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BindSet<N> {
+    /// The binders, in the order that they first occur in the body of the
+    /// enclosing scope
+    ///
+    /// You can access this directly, but only if you understand what you are
+    /// doing! Bound variables refer to the binders by their positions, so the
+    /// order must not be changed.
+    pub unsafe_binders: Vec<Binder<N>>,
+}
+
+impl<N> BindSet<N> {
+    /// Create a set from the given binders
+    pub fn new(binders: impl IntoIterator<Item = Binder<N>>) -> BindSet<N> {
+        let mut seen = HashSet::new();
+        let binders = binders
+            .into_iter()
+            .filter(|binder| seen.insert(binder.0.unique_id))
+            .collect();
+
+        BindSet {
+            unsafe_binders: binders,
+        }
+    }
+
+    /// The number of binders in the set
+    pub fn len(&self) -> usize {
+        self.unsafe_binders.len()
+    }
+
+    /// Returns `true` if the set binds no variables
+    pub fn is_empty(&self) -> bool {
+        self.unsafe_binders.is_empty()
+    }
+
+    /// Returns the binders, in the order that they first occur in the body of
+    /// the enclosing scope
+    pub fn into_binders(self) -> Vec<Binder<N>> {
+        self.unsafe_binders
+    }
+}
+
+impl<N> BoundPattern<N> for BindSet<N>
+where
+    N: Clone + Eq + Hash,
+{
+    fn pattern_eq(&self, other: &BindSet<N>) -> bool {
+        <[Binder<N>]>::pattern_eq(&self.unsafe_binders, &other.unsafe_binders)
+    }
+
+    fn close_pattern(&mut self, _: ScopeState, _: &impl OnFreeFn<N>) {}
+
+    fn open_pattern(&mut self, _: ScopeState, _: &impl OnBoundFn<N>) {}
+
+    fn visit_vars(&self, _: &mut impl FnMut(&Var<N>)) {}
+
+    fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
+
+    fn visit_vars_with_binders(
+        &self,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+    }
+
+    fn visit_scopes(
+        &self,
+        _: &mut Vec<PathSegment<N>>,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+    }
+
+    fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
+
+    fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
+        <[Binder<N>]>::visit_binders(&self.unsafe_binders, on_binder);
+    }
+
+    fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
+        <[Binder<N>]>::visit_mut_binders(&mut self.unsafe_binders, on_binder);
+    }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        let binders = &mut self.unsafe_binders;
+        // The binders before this index have been found in the body
+        let mut found = 0;
+        let _ = body.try_visit_vars(&mut |var| {
+            if let Var::Free(ref free_var) = *var {
+                let unfound = &mut binders[found..];
+                if let Some(index) = unfound.iter().position(|binder| binder.0 == *free_var) {
+                    unfound[..=index].rotate_right(1);
+                    found += 1;
+                }
+            }
+            if found == binders.len() {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+    }
+}
+
+impl<N: Clone, T> Subst<N, T> for BindSet<N> {
+    fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> BindSet<N> {
+        self.clone()
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> BindSet<N> {
+        self.clone()
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, _: &V, _: &T) {}
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, _: &[(V, T)]) {}
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Option<BindSet<N>> {
+        None
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Option<BindSet<N>> {
+        None
+    }
+}
@@ -1,10 +1,23 @@
 use std::fmt;
 
 use free_var::FreeVar;
+use name_supply::NameSupply;
+use subst::Subst;
+use var::Var;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Binder<N>(pub FreeVar<N>);
 
+impl<N> Binder<N> {
+    /// Create a binder for a fresh free variable, using an id from the given
+    /// supply
+    pub fn fresh_from(supply: &mut impl NameSupply, pretty_name: Option<N>) -> Binder<N> {
+        Binder(FreeVar::fresh_from(supply, pretty_name))
+    }
+}
+
+/// Displays the variable that is bound in the same way as `FreeVar`, so that
+/// the binder and the variables it binds are shown with the same name
 impl<N: fmt::Display> fmt::Display for Binder<N> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -28,3 +41,45 @@ where
         *self == other.0
     }
 }
+
+/// Compares the name hint of the binder, like the impl for `FreeVar<String>`
+impl PartialEq<str> for Binder<String> {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Binder<String> {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.0 == **other
+    }
+}
+
+/// Creates a binder for a fresh variable with the string as its name hint
+impl<'a> From<&'a str> for Binder<String> {
+    fn from(src: &'a str) -> Binder<String> {
+        Binder(FreeVar::from(src))
+    }
+}
+
+impl<N: Clone, T> Subst<N, T> for Binder<N> {
+    fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Binder<N> {
+        self.clone()
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Binder<N> {
+        self.clone()
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, _: &V, _: &T) {}
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, _: &[(V, T)]) {}
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Option<Binder<N>> {
+        None
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Option<Binder<N>> {
+        None
+    }
+}
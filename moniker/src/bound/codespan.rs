@@ -1,9 +1,14 @@
+use std::fmt;
+
 use codespan::{
     ByteIndex, ByteOffset, ColumnIndex, ColumnNumber, ColumnOffset, LineIndex, LineNumber,
     LineOffset, Span,
 };
 
 use super::*;
+use display::{DisplayEnv, DisplayTerm};
+use subst::Subst;
+use visit::Visit;
 
 macro_rules! impl_bound_term_ignore {
     ($T:ty) => {
@@ -19,6 +24,23 @@ macro_rules! impl_bound_term_ignore {
             fn visit_vars(&self, _: &mut impl FnMut(&Var<N>)) {}
 
             fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
+
+            fn visit_vars_with_binders(
+                &self,
+                _: &mut BinderStack<N>,
+                _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+            ) {
+            }
+
+            fn visit_scopes(
+                &self,
+                _: &mut Vec<PathSegment<N>>,
+                _: &mut BinderStack<N>,
+                _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+            ) {
+            }
+
+            fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
         }
     };
 }
@@ -44,6 +66,23 @@ impl<N: Clone + PartialEq, T> BoundTerm<N> for Span<T> {
     fn visit_vars(&self, _: &mut impl FnMut(&Var<N>)) {}
 
     fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
+
+    fn visit_vars_with_binders(
+        &self,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+    }
+
+    fn visit_scopes(
+        &self,
+        _: &mut Vec<PathSegment<N>>,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+    }
+
+    fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
 }
 
 macro_rules! impl_bound_pattern_ignore {
@@ -61,9 +100,28 @@ macro_rules! impl_bound_pattern_ignore {
 
             fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
 
+            fn visit_vars_with_binders(
+                &self,
+                _: &mut BinderStack<N>,
+                _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+            ) {
+            }
+
+            fn visit_scopes(
+                &self,
+                _: &mut Vec<PathSegment<N>>,
+                _: &mut BinderStack<N>,
+                _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+            ) {
+            }
+
+            fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
+
             fn visit_binders(&self, _: &mut impl FnMut(&Binder<N>)) {}
 
             fn visit_mut_binders(&mut self, _: &mut impl FnMut(&mut Binder<N>)) {}
+
+            fn order_binders(&mut self, _: &impl BoundTerm<N>) {}
         }
     };
 }
@@ -90,7 +148,105 @@ impl<N: Clone + PartialEq, T> BoundPattern<N> for Span<T> {
 
     fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
 
+    fn visit_vars_with_binders(
+        &self,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+    }
+
+    fn visit_scopes(
+        &self,
+        _: &mut Vec<PathSegment<N>>,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+    }
+
+    fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
+
     fn visit_binders(&self, _: &mut impl FnMut(&Binder<N>)) {}
 
     fn visit_mut_binders(&mut self, _: &mut impl FnMut(&mut Binder<N>)) {}
+
+    fn order_binders(&mut self, _: &impl BoundTerm<N>) {}
+}
+
+macro_rules! impl_subst_clone {
+    ($T:ty) => {
+        impl<N, T> Subst<N, T> for $T {
+            fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> $T {
+                self.clone()
+            }
+
+            fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> $T {
+                self.clone()
+            }
+        }
+    };
+}
+
+impl_subst_clone!(ByteIndex);
+impl_subst_clone!(ByteOffset);
+impl_subst_clone!(ColumnIndex);
+impl_subst_clone!(ColumnNumber);
+impl_subst_clone!(ColumnOffset);
+impl_subst_clone!(LineIndex);
+impl_subst_clone!(LineNumber);
+impl_subst_clone!(LineOffset);
+
+impl<N, T, U: Copy> Subst<N, T> for Span<U> {
+    fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Span<U> {
+        *self
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Span<U> {
+        *self
+    }
+}
+
+macro_rules! impl_visit_leaf {
+    ($T:ty) => {
+        impl<V: ?Sized> Visit<V> for $T {
+            fn visit(&self, _: &mut V) {}
+        }
+    };
+}
+
+impl_visit_leaf!(ByteIndex);
+impl_visit_leaf!(ByteOffset);
+impl_visit_leaf!(ColumnIndex);
+impl_visit_leaf!(ColumnNumber);
+impl_visit_leaf!(ColumnOffset);
+impl_visit_leaf!(LineIndex);
+impl_visit_leaf!(LineNumber);
+impl_visit_leaf!(LineOffset);
+
+impl<V: ?Sized, T> Visit<V> for Span<T> {
+    fn visit(&self, _: &mut V) {}
+}
+
+macro_rules! impl_display_term_debug {
+    ($T:ty) => {
+        impl<N> DisplayTerm<N> for $T {
+            fn fmt_term(&self, _: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{:?}", self)
+            }
+        }
+    };
+}
+
+impl_display_term_debug!(ByteIndex);
+impl_display_term_debug!(ByteOffset);
+impl_display_term_debug!(ColumnIndex);
+impl_display_term_debug!(ColumnNumber);
+impl_display_term_debug!(ColumnOffset);
+impl_display_term_debug!(LineIndex);
+impl_display_term_debug!(LineNumber);
+impl_display_term_debug!(LineOffset);
+
+impl<N, T: fmt::Debug> DisplayTerm<N> for Span<T> {
+    fn fmt_term(&self, _: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
 }
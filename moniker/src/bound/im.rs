@@ -1,6 +1,12 @@
+use std::fmt;
+use std::ops::ControlFlow;
+
 use im::Vector;
 
 use super::*;
+use display::{DisplayEnv, DisplayTerm};
+use subst::Subst;
+use visit::Visit;
 
 impl<N, T> BoundTerm<N> for Vector<T>
 where
@@ -30,11 +36,50 @@ where
         }
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        for elem in self.iter() {
+            elem.try_visit_vars(on_var)?;
+        }
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         for elem in self.iter_mut() {
             elem.visit_mut_vars(on_var);
         }
     }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        for elem in self.iter() {
+            elem.visit_vars_with_binders(binders, on_var);
+        }
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        for (index, elem) in self.iter().enumerate() {
+            path.push(PathSegment::Index(index));
+            elem.visit_scopes(path, binders, on_scope);
+            path.pop();
+        }
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        for elem in self.iter() {
+            elem.visit_nodes(depth, on_node);
+        }
+    }
 }
 
 impl<N, P> BoundPattern<N> for Vector<P>
@@ -65,12 +110,45 @@ where
         }
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        for elem in self.iter() {
+            elem.try_visit_vars(on_var)?;
+        }
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         for elem in self.iter_mut() {
             elem.visit_mut_vars(on_var);
         }
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        for elem in self.iter() {
+            elem.visit_vars_with_binders(binders, on_var);
+        }
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        for (index, elem) in self.iter().enumerate() {
+            path.push(PathSegment::Index(index));
+            elem.visit_scopes(path, binders, on_scope);
+            path.pop();
+        }
+    }
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         for elem in self.iter() {
             elem.visit_binders(on_binder);
@@ -82,4 +160,52 @@ where
             elem.visit_mut_binders(on_binder);
         }
     }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        for elem in self.iter_mut() {
+            elem.order_binders(body);
+        }
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        for elem in self.iter() {
+            elem.visit_nodes(depth, on_node);
+        }
+    }
+}
+
+impl<N, T, U> Subst<N, T> for Vector<U>
+where
+    U: Clone + Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Vector<U> {
+        self.iter()
+            .map(|elem| elem.subst(name, replacement))
+            .collect()
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Vector<U> {
+        self.iter().map(|elem| elem.substs(mappings)).collect()
+    }
+}
+
+impl<V: ?Sized, T: Clone + Visit<V>> Visit<V> for Vector<T> {
+    fn visit(&self, visitor: &mut V) {
+        for elem in self.iter() {
+            elem.visit(visitor);
+        }
+    }
+}
+
+impl<N, T: Clone + DisplayTerm<N>> DisplayTerm<N> for Vector<T> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, elem) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            elem.fmt_term(env, f)?;
+        }
+        write!(f, "]")
+    }
 }
@@ -1,11 +1,18 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
+use std::marker::PhantomData;
+use std::ops::ControlFlow;
 use std::rc::Rc;
 use std::sync::Arc;
 
+use alpha_mismatch::{AlphaMismatch, Mismatched, PathSegment};
 use binder::Binder;
 use bound_var::{BinderIndex, BoundVar, ScopeOffset};
 use free_var::FreeVar;
+use shadowing::{Shadowed, Shadowing};
+use small_vec::SmallVec;
+use subst::Subst;
+use unique_id::UniqueId;
 use var::Var;
 
 #[cfg(feature = "codespan")]
@@ -15,39 +22,145 @@ mod im;
 #[cfg(feature = "num-bigint")]
 mod num_bigint;
 
+/// The state that is threaded through a term while opening or closing it
+///
+/// This tracks the number of scopes that have been entered since the
+/// operation started, so that bound variables can be matched up with the
+/// scope that binds them. Implementations of `BoundTerm` and `BoundPattern`
+/// should pass the state along unchanged to their fields, and call
+/// `ScopeState::incr` when entering the body of a new scope.
 #[derive(Debug, Copy, Clone)]
 pub struct ScopeState {
     depth: u32,
 }
 
 impl ScopeState {
+    /// The state at the start of an operation, before any scopes have been
+    /// entered
     pub fn new() -> ScopeState {
         ScopeState { depth: 0 }
     }
 
+    /// The number of scopes that have been entered
     pub fn depth(&self) -> ScopeOffset {
         ScopeOffset(self.depth)
     }
 
+    /// The state after entering another scope
     pub fn incr(mut self) -> ScopeState {
         self.depth += 1;
         self
     }
 }
 
+impl Default for ScopeState {
+    fn default() -> ScopeState {
+        ScopeState::new()
+    }
+}
+
+/// The binders that are in scope at a point in a term
+///
+/// This is threaded through `BoundTerm::visit_vars_with_binders`, with the
+/// binders of each scope being pushed when its body is entered, and popped when
+/// it is exited. Bound variables can then be looked up to find the binder that
+/// they refer to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinderStack<N> {
+    scopes: Vec<Vec<Binder<N>>>,
+}
+
+impl<N> BinderStack<N> {
+    /// A stack with no binders in scope
+    pub fn new() -> BinderStack<N> {
+        BinderStack { scopes: Vec::new() }
+    }
+
+    /// The number of scopes that have been entered
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Enter a scope that introduces `binders`
+    pub fn push(&mut self, binders: Vec<Binder<N>>) {
+        self.scopes.push(binders);
+    }
+
+    /// Exit the innermost scope, returning its binders
+    pub fn pop(&mut self) -> Option<Vec<Binder<N>>> {
+        self.scopes.pop()
+    }
+
+    /// The binders of each of the scopes, starting from the innermost one
+    pub fn scopes(&self) -> impl Iterator<Item = &[Binder<N>]> {
+        self.scopes.iter().rev().map(Vec::as_slice)
+    }
+
+    /// All of the binders that are in scope, starting from the innermost
+    /// scope
+    pub fn binders(&self) -> impl Iterator<Item = &Binder<N>> {
+        self.scopes().flat_map(|binders| binders.iter())
+    }
+
+    /// Find the binder that a bound variable refers to, returning `None` if it
+    /// refers to a scope that has not been entered
+    pub fn lookup(&self, bound_var: &BoundVar<N>) -> Option<&Binder<N>> {
+        let ScopeOffset(scope) = bound_var.scope();
+        let index = self.scopes.len().checked_sub(scope as usize + 1)?;
+        self.scopes[index].get(bound_var.binder().to_usize())
+    }
+
+    /// Enter a scope below the `depth` innermost scopes
+    ///
+    /// This is used by `Nest`, where the binders of the earlier patterns are
+    /// referred to by later patterns in order of increasing scope offsets.
+    pub(crate) fn insert(&mut self, depth: usize, binders: Vec<Binder<N>>) {
+        let index = self.scopes.len() - depth;
+        self.scopes.insert(index, binders);
+    }
+
+    /// Exit scopes until only `depth` scopes remain
+    pub(crate) fn truncate(&mut self, depth: usize) {
+        self.scopes.truncate(depth);
+    }
+}
+
+impl<N> Default for BinderStack<N> {
+    fn default() -> BinderStack<N> {
+        BinderStack::new()
+    }
+}
+
+/// Decides which free variables should be bound when closing a term
+///
+/// This is implemented for the binders of patterns, like `Vec<Binder<N>>`,
+/// and for closures, allowing terms to be closed in custom ways.
 pub trait OnFreeFn<N> {
+    /// Returns the bound variable that should replace a free variable, or
+    /// `None` if the free variable should be left as it is
     fn call(&self, state: ScopeState, free_var: &FreeVar<N>) -> Option<BoundVar<N>>;
 }
 
+impl<N, F> OnFreeFn<N> for F
+where
+    F: Fn(ScopeState, &FreeVar<N>) -> Option<BoundVar<N>>,
+{
+    fn call(&self, state: ScopeState, free_var: &FreeVar<N>) -> Option<BoundVar<N>> {
+        self(state, free_var)
+    }
+}
+
 impl<N: Clone + PartialEq> OnFreeFn<N> for Vec<Binder<N>> {
     fn call(&self, state: ScopeState, free_var: &FreeVar<N>) -> Option<BoundVar<N>> {
         self.iter()
             .enumerate()
-            .find(|&(_, ref binder)| *binder == free_var)
-            .map(|(i, _)| BoundVar {
-                scope: state.depth(),
-                binder: BinderIndex(i as u32),
-                pretty_name: free_var.pretty_name.clone(),
+            .find(|&(_, binder)| binder == free_var)
+            .map(|(i, _)| {
+                BoundVar::new(
+                    state.depth(),
+                    BinderIndex(i as u32),
+                    free_var.pretty_name.clone(),
+                )
             })
     }
 }
@@ -59,24 +172,81 @@ impl<N: Clone + PartialEq> OnFreeFn<N> for Vec<Vec<Binder<N>>> {
                 let result = OnFreeFn::call(binders, state, free_var);
                 state = state.incr();
                 result
-            }).next()
+            })
+            .next()
+    }
+}
+
+/// The binders of a pattern, indexed by the ids of their free variables
+///
+/// Closing a term using a `Vec<Binder<N>>` scans the binders for each free
+/// variable in the term, which is quadratic for patterns that bind hundreds of
+/// variables, like the parameters of generated code. A table looks up each
+/// free variable in constant time instead. `Scope::new` builds one for large
+/// patterns automatically.
+#[derive(Debug, Clone)]
+pub struct BinderTable<N> {
+    indices: HashMap<UniqueId, BinderIndex>,
+    _name: PhantomData<N>,
+}
+
+impl<N> BinderTable<N> {
+    /// Index the binders of a pattern
+    ///
+    /// If a free variable is bound more than once, its occurrences are bound
+    /// to the first of the binders, as when using `Vec<Binder<N>>`.
+    pub fn new(binders: &[Binder<N>]) -> BinderTable<N> {
+        let mut indices = HashMap::with_capacity(binders.len());
+        for (i, binder) in binders.iter().enumerate() {
+            indices
+                .entry(binder.0.unique_id)
+                .or_insert(BinderIndex(i as u32));
+        }
+
+        BinderTable {
+            indices,
+            _name: PhantomData,
+        }
+    }
+}
+
+impl<N: Clone> OnFreeFn<N> for BinderTable<N> {
+    fn call(&self, state: ScopeState, free_var: &FreeVar<N>) -> Option<BoundVar<N>> {
+        self.indices
+            .get(&free_var.unique_id)
+            .map(|&binder| BoundVar::new(state.depth(), binder, free_var.pretty_name.clone()))
     }
 }
 
+/// Decides which bound variables should be freed when opening a term
+///
+/// This is implemented for the binders of patterns, like `Vec<Binder<N>>`,
+/// and for closures, allowing terms to be opened in custom ways.
 pub trait OnBoundFn<N> {
+    /// Returns the free variable that should replace a bound variable, or
+    /// `None` if the bound variable should be left as it is
     fn call(&self, state: ScopeState, bound_var: &BoundVar<N>) -> Option<FreeVar<N>>;
 }
 
+impl<N, F> OnBoundFn<N> for F
+where
+    F: Fn(ScopeState, &BoundVar<N>) -> Option<FreeVar<N>>,
+{
+    fn call(&self, state: ScopeState, bound_var: &BoundVar<N>) -> Option<FreeVar<N>> {
+        self(state, bound_var)
+    }
+}
+
 impl<N: Clone + PartialEq> OnBoundFn<N> for Vec<Binder<N>> {
     fn call(&self, state: ScopeState, bound_var: &BoundVar<N>) -> Option<FreeVar<N>> {
-        if bound_var.scope == state.depth() {
-            match self.get(bound_var.binder.to_usize()) {
-                Some(&Binder(ref free_var)) => Some(free_var.clone()),
+        if bound_var.scope() == state.depth() {
+            match self.get(bound_var.binder().to_usize()) {
+                Some(Binder(free_var)) => Some(free_var.clone()),
                 None => {
                     // FIXME: better error?
                     panic!(
                         "too few variables in pattern: expected at least {}",
-                        bound_var.binder,
+                        bound_var.binder(),
                     );
                 },
             }
@@ -93,19 +263,53 @@ impl<N: Clone + PartialEq> OnBoundFn<N> for Vec<Vec<Binder<N>>> {
                 let result = OnBoundFn::call(binders, state, bound_var);
                 state = state.incr();
                 result
-            }).next()
+            })
+            .next()
     }
 }
 
 /// Terms that may contain variables that can be bound by patterns
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a term",
+    label = "the trait `BoundTerm<{N}>` is not implemented for `{Self}`",
+    note = "data that does not contain variables can be wrapped in `Ignore<T>`, \
+            or the field can be skipped with `#[moniker(ignore)]`",
+    note = "binders can only be used in patterns, which are bound in terms using `Scope<P, T>`"
+)]
 pub trait BoundTerm<N: Clone + PartialEq> {
     /// Alpha equivalence for terms
     fn term_eq(&self, other: &Self) -> bool;
 
+    /// Alpha equivalence for terms, reporting where the terms differ
+    ///
+    /// Returns the path to the first pair of subterms that are not alpha
+    /// equivalent, along with a description of each of them. This is used by
+    /// `assert_term_eq!` to explain why two large terms are not equal.
+    fn term_eq_check(&self, other: &Self) -> Result<(), AlphaMismatch<N>> {
+        if self.term_eq(other) {
+            Ok(())
+        } else {
+            Err(AlphaMismatch::new(Mismatched::Value, Mismatched::Value))
+        }
+    }
+
     /// Close the term using the supplied binders
+    ///
+    /// Each free variable in the term is replaced with the bound variable
+    /// returned by `on_free`. This is the operation that `Scope::new` uses to
+    /// bind the body of a scope, and should usually be started with
+    /// `ScopeState::new`. The new bound variables refer to binders outside of
+    /// the term, so it must be placed in a matching scope afterwards. Prefer
+    /// the methods on `Scope` where possible.
     fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>);
 
     /// Open the term using the supplied binders
+    ///
+    /// Each bound variable in the term is replaced with the free variable
+    /// returned by `on_bound`. This is the operation that `Scope::unbind` uses
+    /// to unbind the body of a scope, and should usually be started with
+    /// `ScopeState::new`. The free variables should be fresh, otherwise they
+    /// might clash with the other free variables in the term.
     fn open_term(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>);
 
     /// Visit each variable in the term, calling the `on_var` callback on each
@@ -116,7 +320,84 @@ pub trait BoundTerm<N: Clone + PartialEq> {
     /// of them in turn
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>));
 
+    /// Visit each variable in the term, calling the `on_var` callback on each
+    /// of them in turn until it returns `ControlFlow::Break`
+    ///
+    /// This is useful for checks that can be answered without traversing the
+    /// whole term, like `BoundTerm::is_closed`. The default implementation
+    /// uses `visit_vars`, which stops calling `on_var` once it breaks, but
+    /// still traverses the rest of the term. Types that contain other terms
+    /// should forward this to them, so that the traversal stops early.
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        let mut result = ControlFlow::Continue(());
+        self.visit_vars(&mut |var| {
+            if result.is_continue() {
+                result = on_var(var);
+            }
+        });
+        result
+    }
+
+    /// Visit each variable in the term, calling the `on_var` callback with the
+    /// binders that are in scope where it occurs
+    ///
+    /// Scopes push the binders of their patterns onto `binders` while their
+    /// bodies are visited. Types that contain other terms should forward this
+    /// to them.
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    );
+
+    /// Visit each scope in the term, calling the `on_scope` callback with the
+    /// path to the scope, the binders of the enclosing scopes, and the binders
+    /// of the scope's own pattern
+    ///
+    /// The path is made up of the same segments that `term_eq_check` uses to
+    /// report mismatches. Types that contain other terms should forward this
+    /// to them.
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    );
+
+    /// Visit each node of the term, calling the `on_node` callback with the
+    /// depth that it occurs at, starting from `depth`
+    ///
+    /// Derived implementations treat each value of the type as a node, and
+    /// visit its fields one level deeper. Transparent types visit their field
+    /// at the same depth. Variables and constants are considered to be part of
+    /// the nodes that contain them, so they visit nothing.
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize));
+
+    /// Fold over each variable in the term, along with the binders that are
+    /// in scope where it occurs
+    ///
+    /// This is useful for passes that need to know which binders are visible,
+    /// like scope checkers and pretty printers, without having to track them
+    /// by hand.
+    fn fold_with_binders<A>(
+        &self,
+        init: A,
+        mut f: impl FnMut(A, &BinderStack<N>, &Var<N>) -> A,
+    ) -> A {
+        let mut acc = Some(init);
+        self.visit_vars_with_binders(&mut BinderStack::new(), &mut |binders, var| {
+            let prev = acc.take().expect("accumulator was not restored");
+            acc = Some(f(prev, binders, var));
+        });
+        acc.expect("accumulator was not restored")
+    }
+
     /// Returns the set of free variables in this term
+    ///
+    /// Variables that have been bound by a scope in the term are not included.
     fn free_vars(&self) -> HashSet<FreeVar<N>>
     where
         N: Eq + Hash + Clone,
@@ -130,6 +411,239 @@ pub trait BoundTerm<N: Clone + PartialEq> {
         });
         free_vars
     }
+
+    /// Returns the free variables in this term, in the order that they first
+    /// occur, without duplicates
+    ///
+    /// This is useful when a deterministic ordering is needed, for example
+    /// when generating the parameters of a lambda-lifted function.
+    fn free_vars_ordered(&self) -> Vec<FreeVar<N>>
+    where
+        N: Eq + Hash + Clone,
+    {
+        let mut seen = HashSet::new();
+        let mut free_vars = Vec::new();
+        self.visit_vars(&mut |var| match *var {
+            Var::Bound(_) => {},
+            Var::Free(ref free_var) => {
+                if seen.insert(free_var.clone()) {
+                    free_vars.push(free_var.clone());
+                }
+            },
+        });
+        free_vars
+    }
+
+    /// Rename each free occurrence of `old` in the term to `new`
+    ///
+    /// Variables bound by the scopes in the term are unaffected, so this can
+    /// never result in `new` being captured. To rename a variable that was
+    /// bound by a scope, unbind the scope, then rename the variable in both
+    /// the pattern and the body before rebinding them.
+    fn rename_free_var(&mut self, old: &FreeVar<N>, new: &FreeVar<N>) {
+        self.visit_mut_vars(&mut |var| match *var {
+            Var::Free(ref mut free_var) if free_var == old => *free_var = new.clone(),
+            Var::Free(_) | Var::Bound(_) => {},
+        });
+    }
+
+    /// Replace each free occurrence of `free_var` in the term with
+    /// `replacement`
+    ///
+    /// This is a shorthand for `Subst::subst` for terms that are their own
+    /// replacement type. The substitution is capture-avoiding, so the free
+    /// variables of `replacement` will never be bound by the scopes in the
+    /// term.
+    fn replace_var(&self, free_var: &FreeVar<N>, replacement: &Self) -> Self
+    where
+        Self: Subst<N, Self>,
+    {
+        self.subst(free_var, replacement)
+    }
+
+    /// Returns the number of times that `free_var` occurs in the term
+    ///
+    /// This can be used to check if substituting for the variable would
+    /// duplicate or discard work, for example when deciding whether to inline
+    /// a definition.
+    fn count_occurrences(&self, free_var: &FreeVar<N>) -> usize {
+        let mut count = 0;
+        self.visit_vars(&mut |var| match *var {
+            Var::Free(ref fv) if fv == free_var => count += 1,
+            Var::Free(_) | Var::Bound(_) => {},
+        });
+        count
+    }
+
+    /// Returns the number of times that each free variable occurs in the term
+    fn occurrence_counts(&self) -> HashMap<FreeVar<N>, usize>
+    where
+        N: Eq + Hash + Clone,
+    {
+        let mut counts = HashMap::new();
+        self.visit_vars(&mut |var| match *var {
+            Var::Bound(_) => {},
+            Var::Free(ref free_var) => {
+                *counts.entry(free_var.clone()).or_insert(0) += 1;
+            },
+        });
+        counts
+    }
+
+    /// Returns `true` if the term has no free variables
+    ///
+    /// This is cheaper than checking if `free_vars` is empty, because no set
+    /// of variables needs to be built up along the way, and the traversal
+    /// stops at the first free variable.
+    fn is_closed(&self) -> bool {
+        let free_var = self.try_visit_vars(&mut |var| match *var {
+            Var::Free(_) => ControlFlow::Break(()),
+            Var::Bound(_) => ControlFlow::Continue(()),
+        });
+        free_var.is_continue()
+    }
+
+    /// Returns the binders in the term that shadow an earlier binding with the
+    /// same pretty name
+    ///
+    /// A binder shadows the binders of the enclosing scopes, the binders that
+    /// come before it in the same pattern, and any of the given `free_vars`,
+    /// which could be the names that are defined at the top level. Binders
+    /// without pretty names never shadow anything. This is useful for
+    /// reporting warnings in languages where shadowing might be unintended.
+    fn shadowing(&self, free_vars: &[FreeVar<N>]) -> Vec<Shadowing<N>> {
+        let mut shadowing = Vec::new();
+        let mut on_scope = |path: &[_], outer: &BinderStack<N>, binders: &[Binder<N>]| {
+            for (i, binder) in binders.iter().enumerate() {
+                let pretty_name = match binder.0.pretty_name {
+                    None => continue,
+                    Some(ref pretty_name) => pretty_name,
+                };
+                let same_name =
+                    |other: &FreeVar<N>| other.pretty_name.as_ref() == Some(pretty_name);
+
+                // Look for the closest binding with the same name, starting
+                // with the earlier binders in the same pattern
+                let shadowed = binders[..i]
+                    .iter()
+                    .rev()
+                    .chain(outer.binders())
+                    .find(|other| same_name(&other.0))
+                    .map(|other| Shadowed::Binder(other.clone()))
+                    .or_else(|| {
+                        let free_var = free_vars.iter().find(|other| same_name(other))?;
+                        Some(Shadowed::FreeVar(free_var.clone()))
+                    });
+
+                if let Some(shadowed) = shadowed {
+                    shadowing.push(Shadowing {
+                        path: path.to_vec(),
+                        binder: binder.clone(),
+                        shadowed,
+                    });
+                }
+            }
+        };
+        self.visit_scopes(&mut Vec::new(), &mut BinderStack::new(), &mut on_scope);
+        shadowing
+    }
+
+    /// Returns the number of nodes in the term, as visited by `visit_nodes`
+    ///
+    /// This is useful for size based heuristics, like deciding whether to
+    /// inline a definition, or preferring smaller terms when shrinking test
+    /// cases.
+    fn term_size(&self) -> usize {
+        let mut size = 0;
+        self.visit_nodes(0, &mut |_| size += 1);
+        size
+    }
+
+    /// Returns the number of nodes along the deepest path through the term,
+    /// as visited by `visit_nodes`
+    fn term_depth(&self) -> usize {
+        let mut max_depth = 0;
+        self.visit_nodes(0, &mut |depth| max_depth = usize::max(max_depth, depth + 1));
+        max_depth
+    }
+
+    /// Shift the bound variables that refer to binders outside of the term by
+    /// `amount` scopes
+    ///
+    /// This is needed when moving a term that is not locally closed under
+    /// `amount` new binders, for example when implementing evaluators with
+    /// explicit substitutions. Variables that are bound within the term are
+    /// left as they are.
+    fn shift(&mut self, amount: u32)
+    where
+        N: Eq + Hash + Clone,
+    {
+        use std::cell::RefCell;
+
+        // Replace the escaping variables with placeholders, remembering which
+        // binders they referred to relative to the term
+        let placeholders = RefCell::new(HashMap::new());
+        self.open_term(
+            ScopeState::new(),
+            &|state: ScopeState, bound_var: &BoundVar<N>| {
+                let ScopeOffset(depth) = state.depth();
+                if bound_var.scope().0 < depth {
+                    return None;
+                }
+                let placeholder = FreeVar::fresh(bound_var.pretty_name.clone());
+                let escaping = BoundVar::new(
+                    ScopeOffset(bound_var.scope().0 - depth).shift(amount),
+                    bound_var.binder(),
+                    bound_var.pretty_name.clone(),
+                );
+                placeholders
+                    .borrow_mut()
+                    .insert(placeholder.clone(), escaping);
+                Some(placeholder)
+            },
+        );
+
+        // Replace the placeholders with the shifted variables
+        let placeholders = placeholders.into_inner();
+        self.close_term(
+            ScopeState::new(),
+            &|state: ScopeState, free_var: &FreeVar<N>| {
+                placeholders
+                    .get(free_var)
+                    .map(|escaping| escaping.clone().shift(state.depth().0))
+            },
+        );
+    }
+}
+
+/// Compare two collections of terms for alpha equality, ignoring the order
+/// of their elements
+///
+/// This is used by derived impls for fields marked with
+/// `#[moniker(unordered)]`.
+pub fn unordered_term_eq<'a, N, T>(
+    lhs: impl IntoIterator<Item = &'a T>,
+    rhs: impl IntoIterator<Item = &'a T>,
+) -> bool
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N> + 'a,
+{
+    // Alpha equality is an equivalence relation, so we can match up the
+    // elements greedily
+    let mut unmatched = rhs.into_iter().collect::<Vec<_>>();
+    for lhs_elem in lhs {
+        match unmatched
+            .iter()
+            .position(|rhs_elem| T::term_eq(lhs_elem, rhs_elem))
+        {
+            Some(index) => {
+                unmatched.swap_remove(index);
+            },
+            None => return false,
+        }
+    }
+    unmatched.is_empty()
 }
 
 impl<N: Clone + PartialEq> BoundTerm<N> for FreeVar<N> {
@@ -137,6 +651,16 @@ impl<N: Clone + PartialEq> BoundTerm<N> for FreeVar<N> {
         self == other
     }
 
+    fn term_eq_check(&self, other: &FreeVar<N>) -> Result<(), AlphaMismatch<N>> {
+        if self == other {
+            Ok(())
+        } else {
+            let lhs = Mismatched::Var(Var::Free(self.clone()));
+            let rhs = Mismatched::Var(Var::Free(other.clone()));
+            Err(AlphaMismatch::new(lhs, rhs))
+        }
+    }
+
     fn close_term(&mut self, _: ScopeState, _: &impl OnFreeFn<N>) {}
 
     fn open_term(&mut self, _: ScopeState, _: &impl OnBoundFn<N>) {}
@@ -144,6 +668,23 @@ impl<N: Clone + PartialEq> BoundTerm<N> for FreeVar<N> {
     fn visit_vars(&self, _: &mut impl FnMut(&Var<N>)) {}
 
     fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
+
+    fn visit_vars_with_binders(
+        &self,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+    }
+
+    fn visit_scopes(
+        &self,
+        _: &mut Vec<PathSegment<N>>,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+    }
+
+    fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
 }
 
 impl<N: Clone + PartialEq> BoundTerm<N> for Var<N> {
@@ -151,6 +692,16 @@ impl<N: Clone + PartialEq> BoundTerm<N> for Var<N> {
         self == other
     }
 
+    fn term_eq_check(&self, other: &Var<N>) -> Result<(), AlphaMismatch<N>> {
+        if self == other {
+            Ok(())
+        } else {
+            let lhs = Mismatched::Var(self.clone());
+            let rhs = Mismatched::Var(other.clone());
+            Err(AlphaMismatch::new(lhs, rhs))
+        }
+    }
+
     fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
         // NOTE: Working around NLL
         *self = match *self {
@@ -177,16 +728,41 @@ impl<N: Clone + PartialEq> BoundTerm<N> for Var<N> {
         on_var(self);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        on_var(self)
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         on_var(self);
     }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        on_var(binders, self);
+    }
+
+    fn visit_scopes(
+        &self,
+        _: &mut Vec<PathSegment<N>>,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+    }
+
+    fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
 }
 
 // Implementations for common types
 
 macro_rules! impl_bound_term_partial_eq {
-    ($T:ty) => {
-        impl<N: Clone + PartialEq> BoundTerm<N> for $T {
+    ([$($lt:lifetime),*] $T:ty) => {
+        impl<$($lt,)* N: Clone + PartialEq> BoundTerm<N> for $T {
             fn term_eq(&self, other: &$T) -> bool {
                 self == other
             }
@@ -198,13 +774,35 @@ macro_rules! impl_bound_term_partial_eq {
             fn visit_vars(&self, _: &mut impl FnMut(&Var<N>)) {}
 
             fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
+
+            fn visit_vars_with_binders(
+                &self,
+                _: &mut BinderStack<N>,
+                _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+            ) {
+            }
+
+            fn visit_scopes(
+                &self,
+                _: &mut Vec<PathSegment<N>>,
+                _: &mut BinderStack<N>,
+                _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+            ) {
+            }
+
+            fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
         }
     };
+    ($T:ty) => {
+        impl_bound_term_partial_eq!([] $T);
+    };
 }
 
 impl_bound_term_partial_eq!(());
 impl_bound_term_partial_eq!(String);
 impl_bound_term_partial_eq!(str);
+impl_bound_term_partial_eq!(['a] &'a str);
+impl_bound_term_partial_eq!(Arc<str>);
 impl_bound_term_partial_eq!(char);
 impl_bound_term_partial_eq!(bool);
 impl_bound_term_partial_eq!(u8);
@@ -227,12 +825,27 @@ where
 {
     fn term_eq(&self, other: &Option<T>) -> bool {
         match (self, other) {
-            (&Some(ref lhs), &Some(ref rhs)) => T::term_eq(lhs, rhs),
-            (&None, &None) => true,
+            (Some(lhs), Some(rhs)) => T::term_eq(lhs, rhs),
+            (None, None) => true,
             (_, _) => false,
         }
     }
 
+    fn term_eq_check(&self, other: &Option<T>) -> Result<(), AlphaMismatch<N>> {
+        match (self, other) {
+            (Some(lhs), Some(rhs)) => T::term_eq_check(lhs, rhs),
+            (None, None) => Ok(()),
+            (Some(_), None) => Err(AlphaMismatch::new(
+                Mismatched::Variant("Some"),
+                Mismatched::Variant("None"),
+            )),
+            (None, Some(_)) => Err(AlphaMismatch::new(
+                Mismatched::Variant("None"),
+                Mismatched::Variant("Some"),
+            )),
+        }
+    }
+
     fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
         if let Some(ref mut inner) = *self {
             inner.close_term(state, on_free);
@@ -251,11 +864,48 @@ where
         }
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        if let Some(ref inner) = *self {
+            inner.try_visit_vars(on_var)?;
+        }
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         if let Some(ref mut inner) = *self {
             inner.visit_mut_vars(on_var);
         }
     }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        if let Some(ref inner) = *self {
+            inner.visit_vars_with_binders(binders, on_var);
+        }
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        if let Some(ref inner) = *self {
+            inner.visit_scopes(path, binders, on_scope);
+        }
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        if let Some(ref inner) = *self {
+            inner.visit_nodes(depth, on_node);
+        }
+    }
 }
 
 impl<N, T> BoundTerm<N> for Box<T>
@@ -267,6 +917,10 @@ where
         T::term_eq(self, other)
     }
 
+    fn term_eq_check(&self, other: &Box<T>) -> Result<(), AlphaMismatch<N>> {
+        T::term_eq_check(self, other)
+    }
+
     fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
         T::close_term(self, state, on_free);
     }
@@ -279,9 +933,37 @@ where
         T::visit_vars(self, on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        T::try_visit_vars(self, on_var)
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         T::visit_mut_vars(self, on_var);
     }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        T::visit_vars_with_binders(self, binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        T::visit_scopes(self, path, binders, on_scope);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        T::visit_nodes(self, depth, on_node);
+    }
 }
 
 impl<N, T> BoundTerm<N> for Rc<T>
@@ -290,7 +972,16 @@ where
     T: BoundTerm<N> + Clone,
 {
     fn term_eq(&self, other: &Rc<T>) -> bool {
-        T::term_eq(self, other)
+        // Shared subterms are equal to themselves, so there is no need to
+        // traverse them
+        Rc::ptr_eq(self, other) || T::term_eq(self, other)
+    }
+
+    fn term_eq_check(&self, other: &Rc<T>) -> Result<(), AlphaMismatch<N>> {
+        if Rc::ptr_eq(self, other) {
+            return Ok(());
+        }
+        T::term_eq_check(self, other)
     }
 
     fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
@@ -305,9 +996,37 @@ where
         T::visit_vars(self, on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        T::try_visit_vars(self, on_var)
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         T::visit_mut_vars(Rc::make_mut(self), on_var);
     }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        T::visit_vars_with_binders(self, binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        T::visit_scopes(self, path, binders, on_scope);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        T::visit_nodes(self, depth, on_node);
+    }
 }
 
 impl<N, T> BoundTerm<N> for Arc<T>
@@ -316,7 +1035,16 @@ where
     T: BoundTerm<N> + Clone,
 {
     fn term_eq(&self, other: &Arc<T>) -> bool {
-        T::term_eq(self, other)
+        // Shared subterms are equal to themselves, so there is no need to
+        // traverse them
+        Arc::ptr_eq(self, other) || T::term_eq(self, other)
+    }
+
+    fn term_eq_check(&self, other: &Arc<T>) -> Result<(), AlphaMismatch<N>> {
+        if Arc::ptr_eq(self, other) {
+            return Ok(());
+        }
+        T::term_eq_check(self, other)
     }
 
     fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
@@ -331,9 +1059,37 @@ where
         T::visit_vars(self, on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        T::try_visit_vars(self, on_var)
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         T::visit_mut_vars(Arc::make_mut(self), on_var);
     }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        T::visit_vars_with_binders(self, binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        T::visit_scopes(self, path, binders, on_scope);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        T::visit_nodes(self, depth, on_node);
+    }
 }
 
 impl<N, T1, T2> BoundTerm<N> for (T1, T2)
@@ -346,6 +1102,14 @@ where
         T1::term_eq(&self.0, &other.0) && T2::term_eq(&self.1, &other.1)
     }
 
+    fn term_eq_check(&self, other: &(T1, T2)) -> Result<(), AlphaMismatch<N>> {
+        T1::term_eq_check(&self.0, &other.0)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(0)))?;
+        T2::term_eq_check(&self.1, &other.1)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(1)))?;
+        Ok(())
+    }
+
     fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
         self.0.close_term(state, on_free);
         self.1.close_term(state, on_free);
@@ -361,10 +1125,47 @@ where
         self.1.visit_vars(on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.0.try_visit_vars(on_var)?;
+        self.1.try_visit_vars(on_var)?;
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         self.0.visit_mut_vars(on_var);
         self.1.visit_mut_vars(on_var);
     }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.0.visit_vars_with_binders(binders, on_var);
+        self.1.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        path.push(PathSegment::Index(0));
+        self.0.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(1));
+        self.1.visit_scopes(path, binders, on_scope);
+        path.pop();
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.0.visit_nodes(depth, on_node);
+        self.1.visit_nodes(depth, on_node);
+    }
 }
 
 impl<N, T1, T2, T3> BoundTerm<N> for (T1, T2, T3)
@@ -380,6 +1181,16 @@ where
             && T3::term_eq(&self.2, &other.2)
     }
 
+    fn term_eq_check(&self, other: &(T1, T2, T3)) -> Result<(), AlphaMismatch<N>> {
+        T1::term_eq_check(&self.0, &other.0)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(0)))?;
+        T2::term_eq_check(&self.1, &other.1)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(1)))?;
+        T3::term_eq_check(&self.2, &other.2)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(2)))?;
+        Ok(())
+    }
+
     fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
         self.0.close_term(state, on_free);
         self.1.close_term(state, on_free);
@@ -398,11 +1209,54 @@ where
         self.2.visit_vars(on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.0.try_visit_vars(on_var)?;
+        self.1.try_visit_vars(on_var)?;
+        self.2.try_visit_vars(on_var)?;
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         self.0.visit_mut_vars(on_var);
         self.1.visit_mut_vars(on_var);
         self.2.visit_mut_vars(on_var);
     }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.0.visit_vars_with_binders(binders, on_var);
+        self.1.visit_vars_with_binders(binders, on_var);
+        self.2.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        path.push(PathSegment::Index(0));
+        self.0.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(1));
+        self.1.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(2));
+        self.2.visit_scopes(path, binders, on_scope);
+        path.pop();
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.0.visit_nodes(depth, on_node);
+        self.1.visit_nodes(depth, on_node);
+        self.2.visit_nodes(depth, on_node);
+    }
 }
 
 impl<N, T1, T2, T3, T4> BoundTerm<N> for (T1, T2, T3, T4)
@@ -420,6 +1274,18 @@ where
             && T4::term_eq(&self.3, &other.3)
     }
 
+    fn term_eq_check(&self, other: &(T1, T2, T3, T4)) -> Result<(), AlphaMismatch<N>> {
+        T1::term_eq_check(&self.0, &other.0)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(0)))?;
+        T2::term_eq_check(&self.1, &other.1)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(1)))?;
+        T3::term_eq_check(&self.2, &other.2)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(2)))?;
+        T4::term_eq_check(&self.3, &other.3)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(3)))?;
+        Ok(())
+    }
+
     fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
         self.0.close_term(state, on_free);
         self.1.close_term(state, on_free);
@@ -441,12 +1307,61 @@ where
         self.3.visit_vars(on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.0.try_visit_vars(on_var)?;
+        self.1.try_visit_vars(on_var)?;
+        self.2.try_visit_vars(on_var)?;
+        self.3.try_visit_vars(on_var)?;
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         self.0.visit_mut_vars(on_var);
         self.1.visit_mut_vars(on_var);
         self.2.visit_mut_vars(on_var);
         self.3.visit_mut_vars(on_var);
     }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.0.visit_vars_with_binders(binders, on_var);
+        self.1.visit_vars_with_binders(binders, on_var);
+        self.2.visit_vars_with_binders(binders, on_var);
+        self.3.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        path.push(PathSegment::Index(0));
+        self.0.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(1));
+        self.1.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(2));
+        self.2.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(3));
+        self.3.visit_scopes(path, binders, on_scope);
+        path.pop();
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.0.visit_nodes(depth, on_node);
+        self.1.visit_nodes(depth, on_node);
+        self.2.visit_nodes(depth, on_node);
+        self.3.visit_nodes(depth, on_node);
+    }
 }
 
 impl<N, T1, T2, T3, T4, T5> BoundTerm<N> for (T1, T2, T3, T4, T5)
@@ -466,6 +1381,20 @@ where
             && T5::term_eq(&self.4, &other.4)
     }
 
+    fn term_eq_check(&self, other: &(T1, T2, T3, T4, T5)) -> Result<(), AlphaMismatch<N>> {
+        T1::term_eq_check(&self.0, &other.0)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(0)))?;
+        T2::term_eq_check(&self.1, &other.1)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(1)))?;
+        T3::term_eq_check(&self.2, &other.2)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(2)))?;
+        T4::term_eq_check(&self.3, &other.3)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(3)))?;
+        T5::term_eq_check(&self.4, &other.4)
+            .map_err(|mismatch| mismatch.within(PathSegment::Index(4)))?;
+        Ok(())
+    }
+
     fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
         self.0.close_term(state, on_free);
         self.1.close_term(state, on_free);
@@ -490,6 +1419,18 @@ where
         self.4.visit_vars(on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.0.try_visit_vars(on_var)?;
+        self.1.try_visit_vars(on_var)?;
+        self.2.try_visit_vars(on_var)?;
+        self.3.try_visit_vars(on_var)?;
+        self.4.try_visit_vars(on_var)?;
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         self.0.visit_mut_vars(on_var);
         self.1.visit_mut_vars(on_var);
@@ -497,6 +1438,49 @@ where
         self.3.visit_mut_vars(on_var);
         self.4.visit_mut_vars(on_var);
     }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.0.visit_vars_with_binders(binders, on_var);
+        self.1.visit_vars_with_binders(binders, on_var);
+        self.2.visit_vars_with_binders(binders, on_var);
+        self.3.visit_vars_with_binders(binders, on_var);
+        self.4.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        path.push(PathSegment::Index(0));
+        self.0.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(1));
+        self.1.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(2));
+        self.2.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(3));
+        self.3.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(4));
+        self.4.visit_scopes(path, binders, on_scope);
+        path.pop();
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.0.visit_nodes(depth, on_node);
+        self.1.visit_nodes(depth, on_node);
+        self.2.visit_nodes(depth, on_node);
+        self.3.visit_nodes(depth, on_node);
+        self.4.visit_nodes(depth, on_node);
+    }
 }
 
 impl<N, T> BoundTerm<N> for [T]
@@ -509,6 +1493,19 @@ where
             && <_>::zip(self.iter(), other.iter()).all(|(lhs, rhs)| T::term_eq(lhs, rhs))
     }
 
+    fn term_eq_check(&self, other: &[T]) -> Result<(), AlphaMismatch<N>> {
+        if self.len() != other.len() {
+            let lhs = Mismatched::Len(self.len());
+            let rhs = Mismatched::Len(other.len());
+            return Err(AlphaMismatch::new(lhs, rhs));
+        }
+        for (index, (lhs, rhs)) in <_>::zip(self.iter(), other.iter()).enumerate() {
+            T::term_eq_check(lhs, rhs)
+                .map_err(|mismatch| mismatch.within(PathSegment::Index(index)))?;
+        }
+        Ok(())
+    }
+
     fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
         for elem in self {
             elem.close_term(state, on_free);
@@ -521,28 +1518,187 @@ where
         }
     }
 
-    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
-        for elem in self {
-            elem.visit_vars(on_var);
-        }
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        for elem in self {
+            elem.visit_vars(on_var);
+        }
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        for elem in self {
+            elem.try_visit_vars(on_var)?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        for elem in self {
+            elem.visit_mut_vars(on_var);
+        }
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        for elem in self {
+            elem.visit_vars_with_binders(binders, on_var);
+        }
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        for (index, elem) in self.iter().enumerate() {
+            path.push(PathSegment::Index(index));
+            elem.visit_scopes(path, binders, on_scope);
+            path.pop();
+        }
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        for elem in self {
+            elem.visit_nodes(depth, on_node);
+        }
+    }
+}
+
+impl<N, T> BoundTerm<N> for Vec<T>
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N>,
+{
+    fn term_eq(&self, other: &Vec<T>) -> bool {
+        <[T]>::term_eq(self, other)
+    }
+
+    fn term_eq_check(&self, other: &Vec<T>) -> Result<(), AlphaMismatch<N>> {
+        <[T]>::term_eq_check(self, other)
+    }
+
+    fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        <[T]>::close_term(self, state, on_free)
+    }
+
+    fn open_term(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        <[T]>::open_term(self, state, on_bound)
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        <[T]>::visit_vars(self, on_var);
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        <[T]>::try_visit_vars(self, on_var)
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        <[T]>::visit_mut_vars(self, on_var);
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        <[T]>::visit_vars_with_binders(self, binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        <[T]>::visit_scopes(self, path, binders, on_scope);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        <[T]>::visit_nodes(self, depth, on_node);
+    }
+}
+
+impl<N, T> BoundTerm<N> for SmallVec<T>
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N>,
+{
+    fn term_eq(&self, other: &SmallVec<T>) -> bool {
+        <[T]>::term_eq(self, other)
+    }
+
+    fn term_eq_check(&self, other: &SmallVec<T>) -> Result<(), AlphaMismatch<N>> {
+        <[T]>::term_eq_check(self, other)
+    }
+
+    fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        <[T]>::close_term(self, state, on_free)
+    }
+
+    fn open_term(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        <[T]>::open_term(self, state, on_bound)
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        <[T]>::visit_vars(self, on_var);
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        <[T]>::try_visit_vars(self, on_var)
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        <[T]>::visit_mut_vars(self, on_var);
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        <[T]>::visit_vars_with_binders(self, binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        <[T]>::visit_scopes(self, path, binders, on_scope);
     }
 
-    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
-        for elem in self {
-            elem.visit_mut_vars(on_var);
-        }
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        <[T]>::visit_nodes(self, depth, on_node);
     }
 }
 
-impl<N, T> BoundTerm<N> for Vec<T>
+impl<N, T, const LEN: usize> BoundTerm<N> for [T; LEN]
 where
     N: Clone + PartialEq,
     T: BoundTerm<N>,
 {
-    fn term_eq(&self, other: &Vec<T>) -> bool {
+    fn term_eq(&self, other: &[T; LEN]) -> bool {
         <[T]>::term_eq(self, other)
     }
 
+    fn term_eq_check(&self, other: &[T; LEN]) -> Result<(), AlphaMismatch<N>> {
+        <[T]>::term_eq_check(self, other)
+    }
+
     fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
         <[T]>::close_term(self, state, on_free)
     }
@@ -555,12 +1711,47 @@ where
         <[T]>::visit_vars(self, on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        <[T]>::try_visit_vars(self, on_var)
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         <[T]>::visit_mut_vars(self, on_var);
     }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        <[T]>::visit_vars_with_binders(self, binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        <[T]>::visit_scopes(self, path, binders, on_scope);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        <[T]>::visit_nodes(self, depth, on_node);
+    }
 }
 
 /// Patterns that bind variables in terms
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a pattern",
+    label = "the trait `BoundPattern<{N}>` is not implemented for `{Self}`",
+    note = "terms can be embedded in patterns by wrapping them in `Embed<T>`",
+    note = "data that does not contain variables or binders can be wrapped in `Ignore<T>`, \
+            or the field can be skipped with `#[moniker(ignore)]`"
+)]
 pub trait BoundPattern<N: Clone + PartialEq> {
     /// Alpha equivalence for patterns
     fn pattern_eq(&self, other: &Self) -> bool;
@@ -579,7 +1770,55 @@ pub trait BoundPattern<N: Clone + PartialEq> {
     /// of them in turn
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>));
 
+    /// Visit each variable in the pattern, calling the `on_var` callback on
+    /// each of them in turn until it returns `ControlFlow::Break`
+    ///
+    /// See `BoundTerm::try_visit_vars` for more details.
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        let mut result = ControlFlow::Continue(());
+        self.visit_vars(&mut |var| {
+            if result.is_continue() {
+                result = on_var(var);
+            }
+        });
+        result
+    }
+
+    /// Visit each variable in the pattern, calling the `on_var` callback with
+    /// the binders that are in scope where it occurs
+    ///
+    /// See `BoundTerm::visit_vars_with_binders` for more details. Types that
+    /// contain other terms or patterns should forward this to them.
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    );
+
+    /// Visit each scope in the terms embedded in the pattern, calling the
+    /// `on_scope` callback with the path to the scope, the binders of the
+    /// enclosing scopes, and the binders of the scope's own pattern
+    ///
+    /// See `BoundTerm::visit_scopes` for more details.
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    );
+
+    /// Visit each node of the terms embedded in the pattern, calling the
+    /// `on_node` callback with the depth that it occurs at
+    ///
+    /// See `BoundTerm::visit_nodes` for more details.
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize));
+
     /// Returns the set of free variables in this term
+    ///
+    /// Variables that have been bound by a scope in the term are not included.
     fn free_vars(&self) -> HashSet<FreeVar<N>>
     where
         N: Eq + Hash + Clone,
@@ -594,6 +1833,28 @@ pub trait BoundPattern<N: Clone + PartialEq> {
         free_vars
     }
 
+    /// Returns the free variables in this term, in the order that they first
+    /// occur, without duplicates
+    ///
+    /// This is useful when a deterministic ordering is needed, for example
+    /// when generating the parameters of a lambda-lifted function.
+    fn free_vars_ordered(&self) -> Vec<FreeVar<N>>
+    where
+        N: Eq + Hash + Clone,
+    {
+        let mut seen = HashSet::new();
+        let mut free_vars = Vec::new();
+        self.visit_vars(&mut |var| match *var {
+            Var::Bound(_) => {},
+            Var::Free(ref free_var) => {
+                if seen.insert(free_var.clone()) {
+                    free_vars.push(free_var.clone());
+                }
+            },
+        });
+        free_vars
+    }
+
     /// Visit each of the binders in the term, calling the `on_binder` callback
     /// on each of them in turn
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>));
@@ -602,6 +1863,15 @@ pub trait BoundPattern<N: Clone + PartialEq> {
     /// on each of them in turn
     fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>));
 
+    /// Put the binders of any unordered sets in the pattern, like `BindSet`,
+    /// in the order that they first occur free in `body`
+    ///
+    /// `Scope::new` calls this before it closes the body, so that the bound
+    /// variables in the body refer to the binders of a set in an order that
+    /// depends neither on their names nor on the order that they were given
+    /// in. Types that contain other patterns should forward this to them.
+    fn order_binders(&mut self, body: &impl BoundTerm<N>);
+
     /// Returns the binders in this pattern
     fn binders(&self) -> Vec<Binder<N>>
     where
@@ -613,6 +1883,23 @@ pub trait BoundPattern<N: Clone + PartialEq> {
         });
         binders
     }
+
+    /// Rename each free occurrence of `old` in the pattern to `new`,
+    /// including the binders that introduce it
+    ///
+    /// The pretty names of the renamed binders are taken from `new`, so they
+    /// will be displayed using the new name once the pattern is rebound.
+    fn rename_free_var(&mut self, old: &FreeVar<N>, new: &FreeVar<N>) {
+        self.visit_mut_vars(&mut |var| match *var {
+            Var::Free(ref mut free_var) if free_var == old => *free_var = new.clone(),
+            Var::Free(_) | Var::Bound(_) => {},
+        });
+        self.visit_mut_binders(&mut |binder| {
+            if binder.0 == *old {
+                binder.0 = new.clone();
+            }
+        });
+    }
 }
 
 impl<N> BoundPattern<N> for Binder<N>
@@ -631,6 +1918,23 @@ where
 
     fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
 
+    fn visit_vars_with_binders(
+        &self,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+    }
+
+    fn visit_scopes(
+        &self,
+        _: &mut Vec<PathSegment<N>>,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+    }
+
+    fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         on_binder(self)
     }
@@ -638,13 +1942,15 @@ where
     fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
         on_binder(self)
     }
+
+    fn order_binders(&mut self, _: &impl BoundTerm<N>) {}
 }
 
 // Implementations for common types
 
 macro_rules! impl_bound_pattern_partial_eq {
-    ($T:ty) => {
-        impl<N: Clone + PartialEq> BoundPattern<N> for $T {
+    ([$($lt:lifetime),*] $T:ty) => {
+        impl<$($lt,)* N: Clone + PartialEq> BoundPattern<N> for $T {
             fn pattern_eq(&self, other: &$T) -> bool {
                 self == other
             }
@@ -657,16 +1963,41 @@ macro_rules! impl_bound_pattern_partial_eq {
 
             fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
 
+            fn visit_vars_with_binders(
+                &self,
+                _: &mut BinderStack<N>,
+                _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+            ) {
+            }
+
+            fn visit_scopes(
+                &self,
+                _: &mut Vec<PathSegment<N>>,
+                _: &mut BinderStack<N>,
+                _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+            ) {
+            }
+
+            fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
+
             fn visit_binders(&self, _: &mut impl FnMut(&Binder<N>)) {}
 
             fn visit_mut_binders(&mut self, _: &mut impl FnMut(&mut Binder<N>)) {}
+
+
+            fn order_binders(&mut self, _: &impl BoundTerm<N>) {}
         }
     };
+    ($T:ty) => {
+        impl_bound_pattern_partial_eq!([] $T);
+    };
 }
 
 impl_bound_pattern_partial_eq!(());
 impl_bound_pattern_partial_eq!(String);
 impl_bound_pattern_partial_eq!(str);
+impl_bound_pattern_partial_eq!(['a] &'a str);
+impl_bound_pattern_partial_eq!(Arc<str>);
 impl_bound_pattern_partial_eq!(char);
 impl_bound_pattern_partial_eq!(bool);
 impl_bound_pattern_partial_eq!(u8);
@@ -689,8 +2020,8 @@ where
 {
     fn pattern_eq(&self, other: &Option<P>) -> bool {
         match (self, other) {
-            (&Some(ref lhs), &Some(ref rhs)) => P::pattern_eq(lhs, rhs),
-            (&None, &None) => true,
+            (Some(lhs), Some(rhs)) => P::pattern_eq(lhs, rhs),
+            (None, None) => true,
             (_, _) => false,
         }
     }
@@ -713,12 +2044,43 @@ where
         }
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        if let Some(ref inner) = *self {
+            inner.try_visit_vars(on_var)?;
+        }
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         if let Some(ref mut inner) = *self {
             inner.visit_mut_vars(on_var);
         }
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        if let Some(ref inner) = *self {
+            inner.visit_vars_with_binders(binders, on_var);
+        }
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        if let Some(ref inner) = *self {
+            inner.visit_scopes(path, binders, on_scope);
+        }
+    }
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         if let Some(ref inner) = *self {
             inner.visit_binders(on_binder);
@@ -730,6 +2092,18 @@ where
             inner.visit_mut_binders(on_binder);
         }
     }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        if let Some(ref mut inner) = *self {
+            inner.order_binders(body);
+        }
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        if let Some(ref inner) = *self {
+            inner.visit_nodes(depth, on_node);
+        }
+    }
 }
 
 impl<N, P1, P2> BoundPattern<N> for (P1, P2)
@@ -757,11 +2131,43 @@ where
         self.1.visit_vars(on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.0.try_visit_vars(on_var)?;
+        self.1.try_visit_vars(on_var)?;
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         self.0.visit_mut_vars(on_var);
         self.1.visit_mut_vars(on_var);
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.0.visit_vars_with_binders(binders, on_var);
+        self.1.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        path.push(PathSegment::Index(0));
+        self.0.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(1));
+        self.1.visit_scopes(path, binders, on_scope);
+        path.pop();
+    }
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         self.0.visit_binders(on_binder);
         self.1.visit_binders(on_binder);
@@ -771,6 +2177,16 @@ where
         self.0.visit_mut_binders(on_binder);
         self.1.visit_mut_binders(on_binder);
     }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        self.0.order_binders(body);
+        self.1.order_binders(body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.0.visit_nodes(depth, on_node);
+        self.1.visit_nodes(depth, on_node);
+    }
 }
 
 impl<N, P1, P2, P3> BoundPattern<N> for (P1, P2, P3)
@@ -804,12 +2220,49 @@ where
         self.2.visit_vars(on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.0.try_visit_vars(on_var)?;
+        self.1.try_visit_vars(on_var)?;
+        self.2.try_visit_vars(on_var)?;
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         self.0.visit_mut_vars(on_var);
         self.1.visit_mut_vars(on_var);
         self.2.visit_mut_vars(on_var);
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.0.visit_vars_with_binders(binders, on_var);
+        self.1.visit_vars_with_binders(binders, on_var);
+        self.2.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        path.push(PathSegment::Index(0));
+        self.0.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(1));
+        self.1.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(2));
+        self.2.visit_scopes(path, binders, on_scope);
+        path.pop();
+    }
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         self.0.visit_binders(on_binder);
         self.1.visit_binders(on_binder);
@@ -821,6 +2274,18 @@ where
         self.1.visit_mut_binders(on_binder);
         self.2.visit_mut_binders(on_binder);
     }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        self.0.order_binders(body);
+        self.1.order_binders(body);
+        self.2.order_binders(body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.0.visit_nodes(depth, on_node);
+        self.1.visit_nodes(depth, on_node);
+        self.2.visit_nodes(depth, on_node);
+    }
 }
 
 impl<N, P1, P2, P3, P4> BoundPattern<N> for (P1, P2, P3, P4)
@@ -859,6 +2324,17 @@ where
         self.3.visit_vars(on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.0.try_visit_vars(on_var)?;
+        self.1.try_visit_vars(on_var)?;
+        self.2.try_visit_vars(on_var)?;
+        self.3.try_visit_vars(on_var)?;
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         self.0.visit_mut_vars(on_var);
         self.1.visit_mut_vars(on_var);
@@ -866,6 +2342,37 @@ where
         self.3.visit_mut_vars(on_var);
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.0.visit_vars_with_binders(binders, on_var);
+        self.1.visit_vars_with_binders(binders, on_var);
+        self.2.visit_vars_with_binders(binders, on_var);
+        self.3.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        path.push(PathSegment::Index(0));
+        self.0.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(1));
+        self.1.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(2));
+        self.2.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(3));
+        self.3.visit_scopes(path, binders, on_scope);
+        path.pop();
+    }
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         self.0.visit_binders(on_binder);
         self.1.visit_binders(on_binder);
@@ -879,6 +2386,20 @@ where
         self.2.visit_mut_binders(on_binder);
         self.3.visit_mut_binders(on_binder);
     }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        self.0.order_binders(body);
+        self.1.order_binders(body);
+        self.2.order_binders(body);
+        self.3.order_binders(body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.0.visit_nodes(depth, on_node);
+        self.1.visit_nodes(depth, on_node);
+        self.2.visit_nodes(depth, on_node);
+        self.3.visit_nodes(depth, on_node);
+    }
 }
 
 impl<N, P1, P2, P3, P4, P5> BoundPattern<N> for (P1, P2, P3, P4, P5)
@@ -922,6 +2443,18 @@ where
         self.4.visit_vars(on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.0.try_visit_vars(on_var)?;
+        self.1.try_visit_vars(on_var)?;
+        self.2.try_visit_vars(on_var)?;
+        self.3.try_visit_vars(on_var)?;
+        self.4.try_visit_vars(on_var)?;
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         self.0.visit_mut_vars(on_var);
         self.1.visit_mut_vars(on_var);
@@ -930,6 +2463,41 @@ where
         self.4.visit_mut_vars(on_var);
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.0.visit_vars_with_binders(binders, on_var);
+        self.1.visit_vars_with_binders(binders, on_var);
+        self.2.visit_vars_with_binders(binders, on_var);
+        self.3.visit_vars_with_binders(binders, on_var);
+        self.4.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        path.push(PathSegment::Index(0));
+        self.0.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(1));
+        self.1.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(2));
+        self.2.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(3));
+        self.3.visit_scopes(path, binders, on_scope);
+        path.pop();
+        path.push(PathSegment::Index(4));
+        self.4.visit_scopes(path, binders, on_scope);
+        path.pop();
+    }
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         self.0.visit_binders(on_binder);
         self.1.visit_binders(on_binder);
@@ -945,6 +2513,22 @@ where
         self.3.visit_mut_binders(on_binder);
         self.4.visit_mut_binders(on_binder);
     }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        self.0.order_binders(body);
+        self.1.order_binders(body);
+        self.2.order_binders(body);
+        self.3.order_binders(body);
+        self.4.order_binders(body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.0.visit_nodes(depth, on_node);
+        self.1.visit_nodes(depth, on_node);
+        self.2.visit_nodes(depth, on_node);
+        self.3.visit_nodes(depth, on_node);
+        self.4.visit_nodes(depth, on_node);
+    }
 }
 
 impl<N, P> BoundPattern<N> for Box<P>
@@ -968,10 +2552,34 @@ where
         P::visit_vars(self, on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        P::try_visit_vars(self, on_var)
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         P::visit_mut_vars(self, on_var);
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        P::visit_vars_with_binders(self, binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        P::visit_scopes(self, path, binders, on_scope);
+    }
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         P::visit_binders(self, on_binder);
     }
@@ -979,6 +2587,14 @@ where
     fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
         P::visit_mut_binders(self, on_binder);
     }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        P::order_binders(self, body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        P::visit_nodes(self, depth, on_node);
+    }
 }
 
 impl<N, P> BoundPattern<N> for Rc<P>
@@ -987,7 +2603,7 @@ where
     P: BoundPattern<N> + Clone,
 {
     fn pattern_eq(&self, other: &Rc<P>) -> bool {
-        P::pattern_eq(self, other)
+        Rc::ptr_eq(self, other) || P::pattern_eq(self, other)
     }
 
     fn close_pattern(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
@@ -1002,10 +2618,34 @@ where
         P::visit_vars(self, on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        P::try_visit_vars(self, on_var)
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         P::visit_mut_vars(Rc::make_mut(self), on_var);
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        P::visit_vars_with_binders(self, binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        P::visit_scopes(self, path, binders, on_scope);
+    }
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         P::visit_binders(self, on_binder);
     }
@@ -1013,6 +2653,14 @@ where
     fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
         P::visit_mut_binders(Rc::make_mut(self), on_binder);
     }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        P::order_binders(Rc::make_mut(self), body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        P::visit_nodes(self, depth, on_node);
+    }
 }
 
 impl<N, P> BoundPattern<N> for Arc<P>
@@ -1021,7 +2669,7 @@ where
     P: BoundPattern<N> + Clone,
 {
     fn pattern_eq(&self, other: &Arc<P>) -> bool {
-        P::pattern_eq(self, other)
+        Arc::ptr_eq(self, other) || P::pattern_eq(self, other)
     }
 
     fn close_pattern(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
@@ -1036,10 +2684,34 @@ where
         P::visit_vars(self, on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        P::try_visit_vars(self, on_var)
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         P::visit_mut_vars(Arc::make_mut(self), on_var);
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        P::visit_vars_with_binders(self, binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        P::visit_scopes(self, path, binders, on_scope);
+    }
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         P::visit_binders(self, on_binder);
     }
@@ -1047,6 +2719,14 @@ where
     fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
         P::visit_mut_binders(Arc::make_mut(self), on_binder);
     }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        P::order_binders(Arc::make_mut(self), body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        P::visit_nodes(self, depth, on_node);
+    }
 }
 
 impl<N, P> BoundPattern<N> for [P]
@@ -1078,12 +2758,45 @@ where
         }
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        for elem in self {
+            elem.try_visit_vars(on_var)?;
+        }
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         for elem in self {
             elem.visit_mut_vars(on_var);
         }
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        for elem in self {
+            elem.visit_vars_with_binders(binders, on_var);
+        }
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        for (index, elem) in self.iter().enumerate() {
+            path.push(PathSegment::Index(index));
+            elem.visit_scopes(path, binders, on_scope);
+            path.pop();
+        }
+    }
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         for elem in self {
             elem.visit_binders(on_binder);
@@ -1095,6 +2808,18 @@ where
             elem.visit_mut_binders(on_binder);
         }
     }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        for elem in self {
+            elem.order_binders(body);
+        }
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        for elem in self {
+            elem.visit_nodes(depth, on_node);
+        }
+    }
 }
 
 impl<N, P> BoundPattern<N> for Vec<P>
@@ -1118,10 +2843,166 @@ where
         <[P]>::visit_vars(self, on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        <[P]>::try_visit_vars(self, on_var)
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        <[P]>::visit_mut_vars(self, on_var);
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        <[P]>::visit_vars_with_binders(self, binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        <[P]>::visit_scopes(self, path, binders, on_scope);
+    }
+
+    fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
+        <[P]>::visit_binders(self, on_binder);
+    }
+
+    fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
+        <[P]>::visit_mut_binders(self, on_binder);
+    }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        <[P]>::order_binders(self, body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        <[P]>::visit_nodes(self, depth, on_node);
+    }
+}
+
+impl<N, P> BoundPattern<N> for SmallVec<P>
+where
+    N: Clone + PartialEq,
+    P: BoundPattern<N>,
+{
+    fn pattern_eq(&self, other: &SmallVec<P>) -> bool {
+        <[P]>::pattern_eq(self, other)
+    }
+
+    fn close_pattern(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        <[P]>::close_pattern(self, state, on_free);
+    }
+
+    fn open_pattern(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        <[P]>::open_pattern(self, state, on_bound);
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        <[P]>::visit_vars(self, on_var);
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        <[P]>::try_visit_vars(self, on_var)
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        <[P]>::visit_mut_vars(self, on_var);
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        <[P]>::visit_vars_with_binders(self, binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        <[P]>::visit_scopes(self, path, binders, on_scope);
+    }
+
+    fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
+        <[P]>::visit_binders(self, on_binder);
+    }
+
+    fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
+        <[P]>::visit_mut_binders(self, on_binder);
+    }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        <[P]>::order_binders(self, body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        <[P]>::visit_nodes(self, depth, on_node);
+    }
+}
+
+impl<N, P, const LEN: usize> BoundPattern<N> for [P; LEN]
+where
+    N: Clone + PartialEq,
+    P: BoundPattern<N>,
+{
+    fn pattern_eq(&self, other: &[P; LEN]) -> bool {
+        <[P]>::pattern_eq(self, other)
+    }
+
+    fn close_pattern(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        <[P]>::close_pattern(self, state, on_free);
+    }
+
+    fn open_pattern(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        <[P]>::open_pattern(self, state, on_bound);
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        <[P]>::visit_vars(self, on_var);
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        <[P]>::try_visit_vars(self, on_var)
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         <[P]>::visit_mut_vars(self, on_var);
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        <[P]>::visit_vars_with_binders(self, binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        <[P]>::visit_scopes(self, path, binders, on_scope);
+    }
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         <[P]>::visit_binders(self, on_binder);
     }
@@ -1129,4 +3010,12 @@ where
     fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
         <[P]>::visit_mut_binders(self, on_binder);
     }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        <[P]>::order_binders(self, body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        <[P]>::visit_nodes(self, depth, on_node);
+    }
 }
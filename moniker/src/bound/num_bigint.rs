@@ -1,6 +1,11 @@
+use std::fmt;
+
 use num_bigint::{BigInt, BigUint};
 
 use super::*;
+use display::{DisplayEnv, DisplayTerm};
+use subst::Subst;
+use visit::Visit;
 
 macro_rules! impl_bound_term_partial_eq {
     ($T:ty) => {
@@ -16,6 +21,23 @@ macro_rules! impl_bound_term_partial_eq {
             fn visit_vars(&self, _: &mut impl FnMut(&Var<N>)) {}
 
             fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
+
+            fn visit_vars_with_binders(
+                &self,
+                _: &mut BinderStack<N>,
+                _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+            ) {
+            }
+
+            fn visit_scopes(
+                &self,
+                _: &mut Vec<PathSegment<N>>,
+                _: &mut BinderStack<N>,
+                _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+            ) {
+            }
+
+            fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
         }
     };
 }
@@ -38,12 +60,68 @@ macro_rules! impl_bound_pattern_partial_eq {
 
             fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
 
+            fn visit_vars_with_binders(
+                &self,
+                _: &mut BinderStack<N>,
+                _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+            ) {
+            }
+
+            fn visit_scopes(
+                &self,
+                _: &mut Vec<PathSegment<N>>,
+                _: &mut BinderStack<N>,
+                _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+            ) {
+            }
+
+            fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
+
             fn visit_binders(&self, _: &mut impl FnMut(&Binder<N>)) {}
 
             fn visit_mut_binders(&mut self, _: &mut impl FnMut(&mut Binder<N>)) {}
+
+            fn order_binders(&mut self, _: &impl BoundTerm<N>) {}
         }
     };
 }
 
 impl_bound_pattern_partial_eq!(BigInt);
 impl_bound_pattern_partial_eq!(BigUint);
+
+macro_rules! impl_subst_clone {
+    ($T:ty) => {
+        impl<N, T> Subst<N, T> for $T {
+            fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> $T {
+                self.clone()
+            }
+
+            fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> $T {
+                self.clone()
+            }
+        }
+    };
+}
+
+impl_subst_clone!(BigInt);
+impl_subst_clone!(BigUint);
+
+impl<V: ?Sized> Visit<V> for BigInt {
+    fn visit(&self, _: &mut V) {}
+}
+
+impl<V: ?Sized> Visit<V> for BigUint {
+    fn visit(&self, _: &mut V) {}
+}
+
+impl<N> DisplayTerm<N> for BigInt {
+    fn fmt_term(&self, _: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl<N> DisplayTerm<N> for BigUint {
+    fn fmt_term(&self, _: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
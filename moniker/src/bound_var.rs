@@ -1,5 +1,6 @@
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::num::NonZeroU64;
 
 /// The [Debruijn index] of the binder that introduced the variable
 ///
@@ -26,6 +27,11 @@ impl ScopeOffset {
             ScopeOffset(i) => Some(ScopeOffset(i - 1)),
         }
     }
+
+    /// Move the current Debruijn index into `amount` inner binders
+    pub fn shift(self, amount: u32) -> ScopeOffset {
+        ScopeOffset(self.0 + amount)
+    }
 }
 
 impl fmt::Display for ScopeOffset {
@@ -49,16 +55,75 @@ impl fmt::Display for BinderIndex {
     }
 }
 
-#[derive(Debug, Clone)]
+/// A variable that refers to a binder of an enclosing scope
+///
+/// The scope offset and binder index are packed into a single non-zero
+/// integer, so that variables are as small as possible, and so that
+/// `Option<BoundVar<N>>` takes up no more space than `BoundVar<N>`, even when
+/// the pretty name has no spare bit patterns of its own.
+#[derive(Clone)]
 pub struct BoundVar<N> {
-    pub scope: ScopeOffset,
-    pub binder: BinderIndex,
+    /// The scope offset in the upper bits and the binder index in the lower
+    /// bits, with all of the bits inverted to keep the value non-zero
+    index: NonZeroU64,
+    /// The name hint of the binder that the variable refers to
+    ///
+    /// This is copied over from the free variable when a term is closed, so
+    /// that closed terms can still be printed and debugged without unbinding
+    /// them. It is ignored when comparing and hashing variables.
     pub pretty_name: Option<N>,
 }
 
+impl<N> BoundVar<N> {
+    /// Create a variable that refers to the binder at `binder` in the scope at
+    /// `scope`
+    ///
+    /// # Panics
+    ///
+    /// Panics if both the scope offset and the binder index are `u32::MAX`,
+    /// which is the one combination that cannot be represented.
+    pub fn new(scope: ScopeOffset, binder: BinderIndex, pretty_name: Option<N>) -> BoundVar<N> {
+        let packed = u64::from(scope.0) << 32 | u64::from(binder.0);
+        let index = NonZeroU64::new(!packed).expect(
+            "cannot represent a bound variable with both a scope offset and a binder index of \
+             `u32::MAX`",
+        );
+
+        BoundVar { index, pretty_name }
+    }
+
+    /// The scope that the variable refers to, counting outwards from the scope
+    /// that the variable appears in
+    pub fn scope(&self) -> ScopeOffset {
+        ScopeOffset((!self.index.get() >> 32) as u32)
+    }
+
+    /// The position of the binder that the variable refers to, in the pattern
+    /// of its scope
+    pub fn binder(&self) -> BinderIndex {
+        BinderIndex(!self.index.get() as u32)
+    }
+
+    /// Make the variable refer to a binder that is `amount` scopes further
+    /// out, for when the variable is moved under that many new binders
+    pub fn shift(self, amount: u32) -> BoundVar<N> {
+        BoundVar::new(self.scope().shift(amount), self.binder(), self.pretty_name)
+    }
+}
+
+impl<N: fmt::Debug> fmt::Debug for BoundVar<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BoundVar")
+            .field("scope", &self.scope())
+            .field("binder", &self.binder())
+            .field("pretty_name", &self.pretty_name)
+            .finish()
+    }
+}
+
 impl<N> PartialEq for BoundVar<N> {
     fn eq(&self, other: &BoundVar<N>) -> bool {
-        self.scope == other.scope && self.binder == other.binder
+        self.index == other.index
     }
 }
 
@@ -69,16 +134,18 @@ where
     N: Hash,
 {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.scope.hash(state);
-        self.binder.hash(state);
+        self.scope().hash(state);
+        self.binder().hash(state);
     }
 }
 
 impl<N: fmt::Display> fmt::Display for BoundVar<N> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.pretty_name {
-            None => write!(f, "@{}.{}", self.scope, self.binder),
-            Some(ref pretty_name) => write!(f, "{}@{}.{}", pretty_name, self.scope, self.binder),
+            None => write!(f, "@{}.{}", self.scope(), self.binder()),
+            Some(ref pretty_name) => {
+                write!(f, "{}@{}.{}", pretty_name, self.scope(), self.binder())
+            },
         }
     }
 }
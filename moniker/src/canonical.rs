@@ -0,0 +1,261 @@
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Error, Serialize, Serializer};
+
+use ann_binder::AnnBinder;
+use bind_set::BindSet;
+use binder::Binder;
+use bound_var::{BinderIndex, BoundVar, ScopeOffset};
+use closed::Closed;
+use embed::Embed;
+use free_var::FreeVar;
+use ignore::Ignore;
+use meta_var::MetaVar;
+use nest::Nest;
+use rebind::Rebind;
+use rec::Rec;
+use record::Record;
+use scope::Scope;
+use spanned::Spanned;
+use var::Var;
+use wildcard::Wildcard;
+
+impl<N> Serialize for Var<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Var::Free(ref free_var) => Err(S::Error::custom(format_args!(
+                "the free variable with id `{}` can not be serialized, \
+                 because free variables have no canonical form",
+                free_var.unique_id,
+            ))),
+            Var::Bound(ref bound_var) => {
+                (bound_var.scope().0, bound_var.binder().0).serialize(serializer)
+            },
+        }
+    }
+}
+
+/// Metavariables are not known until they have been solved, so they have
+/// no canonical form
+impl<N> Serialize for MetaVar<N> {
+    fn serialize<S: Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+        Err(S::Error::custom(format_args!(
+            "the metavariable with id `{}` can not be serialized, \
+             because metavariables have no canonical form",
+            self.unique_id,
+        )))
+    }
+}
+
+impl<'de, N> Deserialize<'de> for MetaVar<N> {
+    fn deserialize<D: Deserializer<'de>>(_: D) -> Result<MetaVar<N>, D::Error> {
+        Err(<D::Error as serde::de::Error>::custom(
+            "metavariables can not be deserialized, because they have no canonical form",
+        ))
+    }
+}
+
+impl<'de, N> Deserialize<'de> for Var<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Var<N>, D::Error> {
+        let (scope, binder) = <(u32, u32)>::deserialize(deserializer)?;
+        if scope == u32::MAX && binder == u32::MAX {
+            return Err(<D::Error as serde::de::Error>::custom(
+                "cannot deserialize a bound variable with both a scope offset and a binder \
+                 index of `u32::MAX`",
+            ));
+        }
+        Ok(Var::Bound(BoundVar::new(
+            ScopeOffset(scope),
+            BinderIndex(binder),
+            None,
+        )))
+    }
+}
+
+impl<N> Serialize for Binder<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_unit()
+    }
+}
+
+impl<'de, N> Deserialize<'de> for Binder<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Binder<N>, D::Error> {
+        <()>::deserialize(deserializer)?;
+        Ok(Binder(FreeVar::fresh_unnamed()))
+    }
+}
+
+/// Annotations are ignored when comparing patterns, so they are left out of
+/// the canonical form, and are replaced with their default value when
+/// deserializing
+impl<N, A> Serialize for AnnBinder<N, A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.binder.serialize(serializer)
+    }
+}
+
+impl<'de, N, A: Default> Deserialize<'de> for AnnBinder<N, A> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<AnnBinder<N, A>, D::Error> {
+        let binder = Binder::deserialize(deserializer)?;
+        Ok(AnnBinder {
+            binder,
+            annotation: A::default(),
+        })
+    }
+}
+
+impl<N> Serialize for BindSet<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.unsafe_binders.serialize(serializer)
+    }
+}
+
+impl<'de, N> Deserialize<'de> for BindSet<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<BindSet<N>, D::Error> {
+        // The binders were serialized in canonical order
+        let unsafe_binders = Vec::deserialize(deserializer)?;
+        Ok(BindSet { unsafe_binders })
+    }
+}
+
+impl<T: Serialize> Serialize for Closed<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.unsafe_term.serialize(serializer)
+    }
+}
+
+/// Deserialized terms only ever contain bound variables, because free
+/// variables have no canonical form
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Closed<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Closed<T>, D::Error> {
+        let unsafe_term = T::deserialize(deserializer)?;
+        Ok(Closed { unsafe_term })
+    }
+}
+
+impl<T: Serialize> Serialize for Embed<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Embed<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Embed<T>, D::Error> {
+        T::deserialize(deserializer).map(Embed)
+    }
+}
+
+/// Ignored data is skipped when comparing terms, so it is left out of the
+/// canonical form, and is replaced with its default value when deserializing
+impl<T> Serialize for Ignore<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_unit()
+    }
+}
+
+impl<'de, T: Default> Deserialize<'de> for Ignore<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Ignore<T>, D::Error> {
+        <()>::deserialize(deserializer)?;
+        Ok(Ignore(T::default()))
+    }
+}
+
+impl Serialize for Wildcard {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_unit()
+    }
+}
+
+impl<'de> Deserialize<'de> for Wildcard {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Wildcard, D::Error> {
+        <()>::deserialize(deserializer)?;
+        Ok(Wildcard)
+    }
+}
+
+impl<P: Serialize> Serialize for Nest<P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.unsafe_patterns.serialize(serializer)
+    }
+}
+
+impl<'de, P: Deserialize<'de>> Deserialize<'de> for Nest<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Nest<P>, D::Error> {
+        let unsafe_patterns = Vec::deserialize(deserializer)?;
+        Ok(Nest { unsafe_patterns })
+    }
+}
+
+impl<P1: Serialize, P2: Serialize> Serialize for Rebind<P1, P2> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&self.unsafe_left, &self.unsafe_right).serialize(serializer)
+    }
+}
+
+impl<'de, P1: Deserialize<'de>, P2: Deserialize<'de>> Deserialize<'de> for Rebind<P1, P2> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Rebind<P1, P2>, D::Error> {
+        let (unsafe_left, unsafe_right) = <(P1, P2)>::deserialize(deserializer)?;
+        Ok(Rebind {
+            unsafe_left,
+            unsafe_right,
+        })
+    }
+}
+
+impl<P: Serialize> Serialize for Rec<P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.unsafe_pattern.serialize(serializer)
+    }
+}
+
+impl<'de, P: Deserialize<'de>> Deserialize<'de> for Rec<P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Rec<P>, D::Error> {
+        let unsafe_pattern = P::deserialize(deserializer)?;
+        Ok(Rec { unsafe_pattern })
+    }
+}
+
+impl<L: Serialize, P: Serialize> Serialize for Record<L, P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.unsafe_fields.serialize(serializer)
+    }
+}
+
+impl<'de, L: Deserialize<'de>, P: Deserialize<'de>> Deserialize<'de> for Record<L, P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Record<L, P>, D::Error> {
+        // The fields were serialized in canonical order
+        let unsafe_fields = Vec::deserialize(deserializer)?;
+        Ok(Record { unsafe_fields })
+    }
+}
+
+impl<P: Serialize, T: Serialize> Serialize for Scope<P, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&self.unsafe_pattern, &self.unsafe_body).serialize(serializer)
+    }
+}
+
+impl<'de, P: Deserialize<'de>, T: Deserialize<'de>> Deserialize<'de> for Scope<P, T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Scope<P, T>, D::Error> {
+        let (unsafe_pattern, unsafe_body) = <(P, T)>::deserialize(deserializer)?;
+        Ok(Scope {
+            unsafe_pattern,
+            unsafe_body,
+        })
+    }
+}
+
+/// Spans are skipped when comparing terms, so they are left out of the
+/// canonical form, and are replaced with their default value when
+/// deserializing
+impl<Span, T: Serialize> Serialize for Spanned<Span, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.inner.serialize(serializer)
+    }
+}
+
+impl<'de, Span: Default, T: Deserialize<'de>> Deserialize<'de> for Spanned<Span, T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Spanned<Span, T>, D::Error> {
+        let inner = T::deserialize(deserializer)?;
+        Ok(Spanned::new(Span::default(), inner))
+    }
+}
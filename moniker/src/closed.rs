@@ -0,0 +1,162 @@
+use std::hash::Hash;
+use std::ops::{ControlFlow, Deref};
+
+use alpha_mismatch::{AlphaMismatch, PathSegment};
+use binder::Binder;
+use bound::{BinderStack, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use scope::Scope;
+use subst::Subst;
+use var::Var;
+
+/// A term that contains no free variables
+///
+/// This allows functions like evaluators and serializers to require closed
+/// terms in their signatures, rather than checking for free variables at
+/// runtime. Closed terms can be created by checking for free variables with
+/// `Closed::new`, or by binding each of the free variables of a term with
+/// `Closed::abstract_over`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Closed<T> {
+    /// The closed term
+    ///
+    /// You can access this directly, but only if you understand what you are
+    /// doing! Adding free variables to the term will break the guarantees of
+    /// this type. Prefer calling `Closed::new` instead.
+    pub unsafe_term: T,
+}
+
+impl<T> Closed<T> {
+    /// Check that a term contains no free variables, returning it back if it
+    /// does
+    pub fn new<N>(term: T) -> Result<Closed<T>, T>
+    where
+        N: Clone + Eq + Hash,
+        T: BoundTerm<N>,
+    {
+        if term.is_closed() {
+            Ok(Closed { unsafe_term: term })
+        } else {
+            Err(term)
+        }
+    }
+
+    /// Returns the closed term
+    pub fn into_inner(self) -> T {
+        self.unsafe_term
+    }
+}
+
+impl<N, T> Closed<Scope<Vec<Binder<N>>, T>> {
+    /// Close a term by binding each of its free variables
+    ///
+    /// The binders are ordered by the ids of the free variables, which is the
+    /// order that the free variables were created in.
+    pub fn abstract_over(term: T) -> Closed<Scope<Vec<Binder<N>>, T>>
+    where
+        N: Clone + Eq + Hash,
+        T: BoundTerm<N>,
+    {
+        let mut free_vars = term.free_vars().into_iter().collect::<Vec<_>>();
+        free_vars.sort_by_key(|free_var| free_var.unique_id);
+
+        Closed {
+            unsafe_term: Scope::abstract_over(free_vars, term),
+        }
+    }
+}
+
+impl<T> AsRef<T> for Closed<T> {
+    fn as_ref(&self) -> &T {
+        &self.unsafe_term
+    }
+}
+
+impl<T> Deref for Closed<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.unsafe_term
+    }
+}
+
+impl<N, T> BoundTerm<N> for Closed<T>
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N>,
+{
+    fn term_eq(&self, other: &Closed<T>) -> bool {
+        T::term_eq(&self.unsafe_term, &other.unsafe_term)
+    }
+
+    fn term_eq_check(&self, other: &Closed<T>) -> Result<(), AlphaMismatch<N>> {
+        T::term_eq_check(&self.unsafe_term, &other.unsafe_term)
+    }
+
+    fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        self.unsafe_term.close_term(state, on_free);
+    }
+
+    fn open_term(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        self.unsafe_term.open_term(state, on_bound);
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        self.unsafe_term.visit_vars(on_var);
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.unsafe_term.try_visit_vars(on_var)
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        self.unsafe_term.visit_mut_vars(on_var);
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.unsafe_term.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        self.unsafe_term.visit_scopes(path, binders, on_scope);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.unsafe_term.visit_nodes(depth, on_node);
+    }
+}
+
+/// Closed terms have no free variables to substitute, so they are left
+/// untouched
+impl<N, R, T: Clone> Subst<N, R> for Closed<T> {
+    fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &R) -> Closed<T> {
+        self.clone()
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, R)]) -> Closed<T> {
+        self.clone()
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, _: &V, _: &R) {}
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, _: &[(V, R)]) {}
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, _: &V, _: &R) -> Option<Closed<T>> {
+        None
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, _: &[(V, R)]) -> Option<Closed<T>> {
+        None
+    }
+}
@@ -0,0 +1,584 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use ann_binder::AnnBinder;
+use bind_set::BindSet;
+use binder::Binder;
+use bound::{BoundPattern, BoundTerm};
+use closed::Closed;
+use embed::Embed;
+use free_var::FreeVar;
+use hash_cons::Interned;
+use hashed::Hashed;
+use ignore::Ignore;
+use meta_var::MetaVar;
+use nest::Nest;
+use rebind::Rebind;
+use rec::Rec;
+use record::Record;
+use scope::Scope;
+use small_vec::SmallVec;
+use sorted::OtherSort;
+use spanned::Spanned;
+use unique_id::UniqueId;
+use var::Var;
+use wildcard::Wildcard;
+
+/// The names that have been chosen for the variables of a term
+///
+/// Binders are named using their name hints, with a numeric suffix if that
+/// would capture some other variable, like `x1`. Free variables that were not
+/// introduced by a scope are named in the same way when they are first
+/// displayed, with a suffix only if a different free variable has already
+/// been given the same name. Free variables without name hints are numbered
+/// in the order that they are displayed, like `$0` and `$1`. None of these
+/// names depend on the ids of the variables, so displaying the same term
+/// always results in the same string.
+#[derive(Debug, Clone)]
+pub struct DisplayEnv<N> {
+    names: HashMap<FreeVar<N>, String>,
+    /// The names that have been chosen for the free variables that were not
+    /// introduced by a scope
+    free_names: HashSet<String>,
+}
+
+impl<N: Eq + Hash> DisplayEnv<N> {
+    /// Create an environment with no binders in scope
+    pub fn new() -> DisplayEnv<N> {
+        DisplayEnv {
+            names: HashMap::new(),
+            free_names: HashSet::new(),
+        }
+    }
+
+    /// The name to display for a free variable
+    ///
+    /// This is the name chosen for the binder of the variable if it was
+    /// introduced by a scope, and otherwise the name that the variable was
+    /// given when it was first displayed.
+    pub fn name(&mut self, free_var: &FreeVar<N>) -> String
+    where
+        N: Clone + fmt::Display,
+    {
+        if let Some(name) = self.names.get(free_var) {
+            return name.clone();
+        }
+
+        let name = free_name(free_var.pretty_name.as_ref(), &self.free_names);
+        self.free_names.insert(name.clone());
+        self.names.insert(free_var.clone(), name.clone());
+        name
+    }
+
+    /// Choose names for the `binders`, using their name hints where this would
+    /// not capture any of the `nearby` variables
+    ///
+    /// Names that would capture another variable are disambiguated with a
+    /// numeric suffix.
+    pub fn bind(&mut self, binders: &[Binder<N>], nearby: &HashSet<FreeVar<N>>)
+    where
+        N: Clone + fmt::Display,
+    {
+        // Name the variables that have not been displayed yet in the order
+        // that they were created, so that the names do not depend on the
+        // order of the set
+        let mut nearby = nearby
+            .iter()
+            .filter(|free_var| !binders.iter().any(|binder| binder == *free_var))
+            .collect::<Vec<_>>();
+        nearby.sort_by_key(|free_var| free_var.unique_id);
+        let taken = nearby.into_iter().map(|free_var| self.name(free_var));
+        let mut taken = taken.collect::<HashSet<_>>();
+
+        for binder in binders {
+            let name = match binder.0.pretty_name {
+                Some(ref pretty_name) => fresh_name(&pretty_name.to_string(), &taken),
+                None => fresh_name("_", &taken),
+            };
+            taken.insert(name.clone());
+            self.names.insert(binder.0.clone(), name);
+        }
+    }
+}
+
+/// Choose a name based on `hint` that is not one of the `used` names
+///
+/// This returns the hint itself if it is not used, and otherwise the hint
+/// with the smallest numeric suffix that is not used, like `x1`, `x2`, and so
+/// on. This is how `DisplayEnv` names binders, and can also be used by pretty
+/// printers that do not go through `DisplayTerm`.
+pub fn fresh_name(hint: &str, used: &HashSet<String>) -> String {
+    if !used.contains(hint) {
+        return hint.to_owned();
+    }
+
+    (1..)
+        .map(|suffix| format!("{}{}", hint, suffix))
+        .find(|name| !used.contains(name))
+        .unwrap()
+}
+
+/// Choose a name for a free variable that was not introduced by a scope,
+/// avoiding the names that have already been `used`
+fn free_name<N: fmt::Display>(pretty_name: Option<&N>, used: &HashSet<String>) -> String {
+    match pretty_name {
+        Some(pretty_name) => fresh_name(&pretty_name.to_string(), used),
+        None => (0..)
+            .map(|i| format!("${}", i))
+            .find(|name| !used.contains(name))
+            .unwrap(),
+    }
+}
+
+/// The names that the `Display` impls of variables have chosen for the free
+/// variables that have been displayed on the current thread
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DisplayedNames {
+    /// The name chosen for each variable, by its id and name hint
+    names: HashMap<(UniqueId, Option<String>), String>,
+    used: HashSet<String>,
+}
+
+thread_local! {
+    static DISPLAYED_NAMES: RefCell<DisplayedNames> = RefCell::new(DisplayedNames::default());
+}
+
+/// Replace the names that have been chosen by the `Display` impls of
+/// variables on the current thread, returning the previous names
+pub(crate) fn replace_displayed_names(names: DisplayedNames) -> DisplayedNames {
+    DISPLAYED_NAMES.with(|displayed| displayed.replace(names))
+}
+
+/// The name that the `Display` impls of variables show for `free_var`
+///
+/// This is chosen in the same way as `DisplayEnv::name`, with the names being
+/// remembered for the lifetime of the current thread, or until the call to
+/// `with_isolated_ids` that they were chosen in returns.
+pub(crate) fn displayed_name<N: fmt::Display>(free_var: &FreeVar<N>) -> String {
+    let pretty_name = free_var.pretty_name.as_ref();
+    let key = (free_var.unique_id, pretty_name.map(|name| name.to_string()));
+
+    DISPLAYED_NAMES.with(|displayed| {
+        let displayed = &mut *displayed.borrow_mut();
+        if let Some(name) = displayed.names.get(&key) {
+            return name.clone();
+        }
+
+        let name = free_name(pretty_name, &displayed.used);
+        displayed.used.insert(name.clone());
+        displayed.names.insert(key, name.clone());
+        name
+    })
+}
+
+impl<N: Eq + Hash> Default for DisplayEnv<N> {
+    fn default() -> DisplayEnv<N> {
+        DisplayEnv::new()
+    }
+}
+
+/// Display terms using the names of their binders in place of bound variables
+///
+/// Scopes are displayed as `<pattern> body`. Binders keep their name hints,
+/// and are only renamed when this is needed to avoid capturing some other
+/// variable of the same name. This can be derived for your own types using
+/// `#[derive(DisplayTerm)]`, which displays them like their constructors.
+pub trait DisplayTerm<N> {
+    /// Display the term, looking up the names of variables in `env`
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result;
+
+    /// Returns a value that implements `fmt::Display` for the term
+    fn display_term(&self) -> TermDisplay<'_, N, Self>
+    where
+        Self: Sized,
+    {
+        TermDisplay {
+            term: self,
+            name: PhantomData,
+        }
+    }
+}
+
+/// Displays a term using `DisplayTerm`
+///
+/// This is returned by `DisplayTerm::display_term`.
+pub struct TermDisplay<'a, N, T: 'a> {
+    term: &'a T,
+    name: PhantomData<N>,
+}
+
+impl<'a, N, T> fmt::Display for TermDisplay<'a, N, T>
+where
+    N: Eq + Hash,
+    T: DisplayTerm<N>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.term.fmt_term(&mut DisplayEnv::new(), f)
+    }
+}
+
+/// Write a comma separated sequence of terms
+fn fmt_terms<'a, N: 'a, T: DisplayTerm<N> + 'a>(
+    terms: impl IntoIterator<Item = &'a T>,
+    env: &mut DisplayEnv<N>,
+    f: &mut fmt::Formatter,
+) -> fmt::Result {
+    for (i, term) in terms.into_iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        term.fmt_term(env, f)?;
+    }
+    Ok(())
+}
+
+impl<N: Clone + Eq + Hash + fmt::Display> DisplayTerm<N> for Var<N> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Var::Free(ref free_var) => write!(f, "{}", env.name(free_var)),
+            // Only encountered when displaying the body of a scope directly
+            Var::Bound(ref bound_var) => write!(f, "{}", bound_var),
+        }
+    }
+}
+
+impl<N: Clone + Eq + Hash + fmt::Display> DisplayTerm<N> for FreeVar<N> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", env.name(self))
+    }
+}
+
+impl<N: fmt::Display> DisplayTerm<N> for MetaVar<N> {
+    fn fmt_term(&self, _: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl<N: Clone + Eq + Hash + fmt::Display> DisplayTerm<N> for Binder<N> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", env.name(&self.0))
+    }
+}
+
+/// Annotations are ignored when comparing patterns, so only the binder is
+/// displayed
+impl<N: Clone + Eq + Hash + fmt::Display, A> DisplayTerm<N> for AnnBinder<N, A> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        self.binder.fmt_term(env, f)
+    }
+}
+
+impl<N: Clone + Eq + Hash + fmt::Display> DisplayTerm<N> for BindSet<N> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{")?;
+        fmt_terms(&self.unsafe_binders, env, f)?;
+        write!(f, "}}")
+    }
+}
+
+impl<N, T> DisplayTerm<N> for Ignore<T> {
+    fn fmt_term(&self, _: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "_")
+    }
+}
+
+impl<N> DisplayTerm<N> for Wildcard {
+    fn fmt_term(&self, _: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "_")
+    }
+}
+
+impl<N, T: DisplayTerm<N>> DisplayTerm<N> for Closed<T> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        self.unsafe_term.fmt_term(env, f)
+    }
+}
+
+impl<N, T: DisplayTerm<N>> DisplayTerm<N> for Interned<T> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        T::fmt_term(self, env, f)
+    }
+}
+
+/// The embedded term is displayed using a separate environment, because its
+/// variables are never bound by the scopes of the enclosing term
+impl<N, M, T> DisplayTerm<N> for OtherSort<M, T>
+where
+    M: Eq + Hash,
+    T: DisplayTerm<M>,
+{
+    fn fmt_term(&self, _: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        self.term.fmt_term(&mut DisplayEnv::new(), f)
+    }
+}
+
+impl<N, T: DisplayTerm<N>> DisplayTerm<N> for Hashed<T> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        T::fmt_term(self, env, f)
+    }
+}
+
+impl<N, T: DisplayTerm<N>> DisplayTerm<N> for Embed<T> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_term(env, f)
+    }
+}
+
+impl<N, P> DisplayTerm<N> for Nest<P>
+where
+    N: Clone + PartialEq,
+    P: BoundPattern<N> + DisplayTerm<N> + Clone,
+{
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        // The binders have already been named by the enclosing scope, so we
+        // only need to open the references to the earlier patterns
+        let patterns = self.clone().unnest();
+        write!(f, "[")?;
+        fmt_terms(&patterns, env, f)?;
+        write!(f, "]")
+    }
+}
+
+impl<N, P1, P2> DisplayTerm<N> for Rebind<P1, P2>
+where
+    N: Clone + PartialEq,
+    P1: BoundPattern<N> + DisplayTerm<N> + Clone,
+    P2: BoundPattern<N> + DisplayTerm<N> + Clone,
+{
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        let (left, right) = self.clone().unrebind();
+        write!(f, "[")?;
+        left.fmt_term(env, f)?;
+        write!(f, ", ")?;
+        right.fmt_term(env, f)?;
+        write!(f, "]")
+    }
+}
+
+impl<N, P> DisplayTerm<N> for Rec<P>
+where
+    N: Clone + PartialEq,
+    P: BoundPattern<N> + DisplayTerm<N> + Clone,
+{
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        self.clone().unrec().fmt_term(env, f)
+    }
+}
+
+impl<N, L: fmt::Display, P: DisplayTerm<N>> DisplayTerm<N> for Record<L, P> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{")?;
+        for (i, field) in self.unsafe_fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} = ", field.0)?;
+            field.1.fmt_term(env, f)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl<N, P, T> DisplayTerm<N> for Scope<P, T>
+where
+    N: Clone + Eq + Hash + fmt::Display,
+    P: BoundPattern<N> + DisplayTerm<N> + Clone,
+    T: BoundTerm<N> + DisplayTerm<N> + Clone,
+{
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        let (pattern, body) = self.clone().unbind();
+
+        // Name the free variables in the order that they appear
+        let binders = pattern.binders();
+        let mut on_var = |var: &Var<N>| {
+            if let Var::Free(ref free_var) = *var {
+                if !binders.iter().any(|binder| binder == free_var) {
+                    env.name(free_var);
+                }
+            }
+        };
+        pattern.visit_vars(&mut on_var);
+        body.visit_vars(&mut on_var);
+
+        let mut nearby = body.free_vars();
+        nearby.extend(pattern.free_vars());
+        env.bind(&binders, &nearby);
+
+        write!(f, "<")?;
+        pattern.fmt_term(env, f)?;
+        write!(f, "> ")?;
+        body.fmt_term(env, f)
+    }
+}
+
+impl<N, S, T: DisplayTerm<N>> DisplayTerm<N> for Spanned<S, T> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.fmt_term(env, f)
+    }
+}
+
+// Implementations for common types
+
+macro_rules! impl_display_term_debug {
+    ([$($lt:lifetime),*] $T:ty) => {
+        impl<$($lt,)* N> DisplayTerm<N> for $T {
+            fn fmt_term(&self, _: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{:?}", self)
+            }
+        }
+    };
+    ($T:ty) => {
+        impl_display_term_debug!([] $T);
+    };
+}
+
+impl_display_term_debug!(());
+impl_display_term_debug!(String);
+impl_display_term_debug!(str);
+impl_display_term_debug!(['a] &'a str);
+impl_display_term_debug!(char);
+impl_display_term_debug!(bool);
+impl_display_term_debug!(u8);
+impl_display_term_debug!(u16);
+impl_display_term_debug!(u32);
+impl_display_term_debug!(u64);
+impl_display_term_debug!(usize);
+impl_display_term_debug!(i8);
+impl_display_term_debug!(i16);
+impl_display_term_debug!(i32);
+impl_display_term_debug!(i64);
+impl_display_term_debug!(isize);
+impl_display_term_debug!(f32);
+impl_display_term_debug!(f64);
+
+impl<N, T: DisplayTerm<N>> DisplayTerm<N> for Option<T> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            None => write!(f, "None"),
+            Some(ref inner) => {
+                write!(f, "Some(")?;
+                inner.fmt_term(env, f)?;
+                write!(f, ")")
+            },
+        }
+    }
+}
+
+impl<N, T: DisplayTerm<N> + ?Sized> DisplayTerm<N> for Box<T> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        T::fmt_term(self, env, f)
+    }
+}
+
+impl<N, T: DisplayTerm<N> + ?Sized> DisplayTerm<N> for Rc<T> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        T::fmt_term(self, env, f)
+    }
+}
+
+impl<N, T: DisplayTerm<N> + ?Sized> DisplayTerm<N> for Arc<T> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        T::fmt_term(self, env, f)
+    }
+}
+
+impl<N, T1: DisplayTerm<N>, T2: DisplayTerm<N>> DisplayTerm<N> for (T1, T2) {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        self.0.fmt_term(env, f)?;
+        write!(f, ", ")?;
+        self.1.fmt_term(env, f)?;
+        write!(f, ")")
+    }
+}
+
+impl<N, T1, T2, T3> DisplayTerm<N> for (T1, T2, T3)
+where
+    T1: DisplayTerm<N>,
+    T2: DisplayTerm<N>,
+    T3: DisplayTerm<N>,
+{
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        self.0.fmt_term(env, f)?;
+        write!(f, ", ")?;
+        self.1.fmt_term(env, f)?;
+        write!(f, ", ")?;
+        self.2.fmt_term(env, f)?;
+        write!(f, ")")
+    }
+}
+
+impl<N, T1, T2, T3, T4> DisplayTerm<N> for (T1, T2, T3, T4)
+where
+    T1: DisplayTerm<N>,
+    T2: DisplayTerm<N>,
+    T3: DisplayTerm<N>,
+    T4: DisplayTerm<N>,
+{
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        self.0.fmt_term(env, f)?;
+        write!(f, ", ")?;
+        self.1.fmt_term(env, f)?;
+        write!(f, ", ")?;
+        self.2.fmt_term(env, f)?;
+        write!(f, ", ")?;
+        self.3.fmt_term(env, f)?;
+        write!(f, ")")
+    }
+}
+
+impl<N, T1, T2, T3, T4, T5> DisplayTerm<N> for (T1, T2, T3, T4, T5)
+where
+    T1: DisplayTerm<N>,
+    T2: DisplayTerm<N>,
+    T3: DisplayTerm<N>,
+    T4: DisplayTerm<N>,
+    T5: DisplayTerm<N>,
+{
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        self.0.fmt_term(env, f)?;
+        write!(f, ", ")?;
+        self.1.fmt_term(env, f)?;
+        write!(f, ", ")?;
+        self.2.fmt_term(env, f)?;
+        write!(f, ", ")?;
+        self.3.fmt_term(env, f)?;
+        write!(f, ", ")?;
+        self.4.fmt_term(env, f)?;
+        write!(f, ")")
+    }
+}
+
+impl<N, T: DisplayTerm<N>> DisplayTerm<N> for [T] {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[")?;
+        fmt_terms(self, env, f)?;
+        write!(f, "]")
+    }
+}
+
+impl<N, T: DisplayTerm<N>, const LEN: usize> DisplayTerm<N> for [T; LEN] {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        <[T]>::fmt_term(self, env, f)
+    }
+}
+
+impl<N, T: DisplayTerm<N>> DisplayTerm<N> for Vec<T> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        <[T]>::fmt_term(self, env, f)
+    }
+}
+
+impl<N, T: DisplayTerm<N>> DisplayTerm<N> for SmallVec<T> {
+    fn fmt_term(&self, env: &mut DisplayEnv<N>, f: &mut fmt::Formatter) -> fmt::Result {
+        <[T]>::fmt_term(self, env, f)
+    }
+}
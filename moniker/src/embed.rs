@@ -1,11 +1,54 @@
+use std::ops::{ControlFlow, Deref, DerefMut};
+
+use alpha_mismatch::PathSegment;
 use binder::Binder;
-use bound::{BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use bound::{BinderStack, BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use subst::Subst;
 use var::Var;
 
 /// Embed a term in a pattern
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Embed<T>(pub T);
 
+impl<T> Embed<T> {
+    /// Returns the embedded term
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Embed<T> {
+    fn from(src: T) -> Embed<T> {
+        Embed(src)
+    }
+}
+
+impl<T> AsRef<T> for Embed<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> AsMut<T> for Embed<T> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> Deref for Embed<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Embed<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
 impl<N, T> BoundPattern<N> for Embed<T>
 where
     N: Clone + PartialEq,
@@ -27,11 +70,70 @@ where
         self.0.visit_vars(on_var)
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.0.try_visit_vars(on_var)
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         self.0.visit_mut_vars(on_var)
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.0.visit_vars_with_binders(binders, on_var)
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        self.0.visit_scopes(path, binders, on_scope)
+    }
+
     fn visit_binders(&self, _: &mut impl FnMut(&Binder<N>)) {}
 
     fn visit_mut_binders(&mut self, _: &mut impl FnMut(&mut Binder<N>)) {}
+
+    fn order_binders(&mut self, _: &impl BoundTerm<N>) {}
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.0.visit_nodes(depth, on_node)
+    }
+}
+
+impl<N, T, U> Subst<N, T> for Embed<U>
+where
+    U: Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Embed<U> {
+        Embed(self.0.subst(name, replacement))
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Embed<U> {
+        Embed(self.0.substs(mappings))
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        self.0.subst_mut(name, replacement);
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        self.0.substs_mut(mappings);
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Option<Embed<U>> {
+        self.0.subst_changed(name, replacement).map(Embed)
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<Embed<U>> {
+        self.0.substs_changed(mappings).map(Embed)
+    }
 }
@@ -1,6 +1,9 @@
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
+use display::displayed_name;
+use name_supply::NameSupply;
+use symbol::Interner;
 use unique_id::UniqueId;
 
 /// A free variable
@@ -21,7 +24,21 @@ impl<N> FreeVar<N> {
         }
     }
 
+    /// Create a fresh free variable using an id from the given supply, with
+    /// an optional name hint for pretty printing
+    pub fn fresh_from(supply: &mut impl NameSupply, pretty_name: Option<N>) -> FreeVar<N> {
+        FreeVar {
+            unique_id: supply.next_id(),
+            pretty_name,
+        }
+    }
+
     /// Create a fresh free variable, with no name hint
+    ///
+    /// This is useful for generated temporaries, where coming up with a name
+    /// would be wasted work. A name can be attached later on using
+    /// `FreeVar::with_pretty_name`, if the variable ends up being shown to
+    /// the user.
     pub fn fresh_unnamed() -> FreeVar<N> {
         FreeVar::fresh(None)
     }
@@ -30,6 +47,37 @@ impl<N> FreeVar<N> {
     pub fn fresh_named(pretty_name: impl Into<N>) -> FreeVar<N> {
         FreeVar::fresh(Some(pretty_name.into()))
     }
+
+    /// Create a fresh free variable, with a name hint that is interned using
+    /// the given interner
+    pub fn fresh_interned<I>(interner: &mut I, pretty_name: &str) -> FreeVar<N>
+    where
+        I: Interner<Symbol = N>,
+    {
+        FreeVar::fresh(Some(interner.intern(pretty_name)))
+    }
+
+    /// The name hint of the variable, looked up using the interner that it
+    /// was interned with
+    pub fn resolve_name<'a, I>(&self, interner: &'a I) -> Option<&'a str>
+    where
+        I: Interner<Symbol = N>,
+        N: Copy,
+    {
+        self.pretty_name
+            .map(|pretty_name| interner.resolve(pretty_name))
+    }
+
+    /// Attach a name hint for pretty printing, keeping the same id
+    ///
+    /// The returned variable is still equal to the original one, because
+    /// name hints are ignored when comparing variables.
+    pub fn with_pretty_name(self, pretty_name: impl Into<N>) -> FreeVar<N> {
+        FreeVar {
+            unique_id: self.unique_id,
+            pretty_name: Some(pretty_name.into()),
+        }
+    }
 }
 
 impl<N> PartialEq for FreeVar<N>
@@ -43,6 +91,34 @@ where
 
 impl<N> Eq for FreeVar<N> where N: Eq {}
 
+/// Compares the name hint of the variable, for convenience in tests
+///
+/// Note that this ignores the id of the variable, so different variables with
+/// the same name hint are all equal to the same string.
+impl PartialEq<str> for FreeVar<String> {
+    fn eq(&self, other: &str) -> bool {
+        self.pretty_name
+            .as_ref()
+            .is_some_and(|pretty_name| pretty_name == other)
+    }
+}
+
+impl<'a> PartialEq<&'a str> for FreeVar<String> {
+    fn eq(&self, other: &&'a str) -> bool {
+        *self == **other
+    }
+}
+
+/// Creates a fresh variable with the string as its name hint
+///
+/// Each conversion generates a new id, so converting the same string twice
+/// results in two different variables.
+impl<'a> From<&'a str> for FreeVar<String> {
+    fn from(src: &'a str) -> FreeVar<String> {
+        FreeVar::fresh_named(src)
+    }
+}
+
 impl<N> Hash for FreeVar<N>
 where
     N: Hash,
@@ -52,11 +128,17 @@ where
     }
 }
 
+/// Displays the name hint of the variable, like `x`
+///
+/// The first variable with a given name hint to be displayed on a thread is
+/// shown using just the name hint, and later variables with the same hint
+/// are given a numeric suffix, like `x1`. Variables without name hints are
+/// numbered in the order that they are displayed, like `$0`. None of these
+/// names depend on the ids of the variables, and each variable is shown with
+/// the same name every time it is displayed on the same thread. The ids can
+/// be shown using the `Debug` impl.
 impl<N: fmt::Display> fmt::Display for FreeVar<N> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self.pretty_name {
-            None => write!(f, "${}", self.unique_id),
-            Some(ref pretty_name) => write!(f, "{}${}", pretty_name, self.unique_id),
-        }
+        f.write_str(&displayed_name(self))
     }
 }
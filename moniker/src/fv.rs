@@ -0,0 +1,293 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::ops::{ControlFlow, Deref};
+use std::sync::Arc;
+
+use alpha_mismatch::{AlphaMismatch, PathSegment};
+use binder::Binder;
+use bound::{BinderStack, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use bound_var::{BoundVar, ScopeOffset};
+use free_var::FreeVar;
+use subst::Subst;
+use var::Var;
+
+/// A term that caches the set of its free variables
+///
+/// Wrapping the shared nodes of a syntax tree in `Fv<N, T>` lets operations
+/// skip over the subterms that they would leave unchanged. Substitutions
+/// return the subterms that do not mention the substituted variables without
+/// traversing them, and closing or opening a term stops at the subterms that
+/// have no variables to bind or unbind. The cache is kept up to date by the
+/// operations of `BoundTerm` and `Subst`, so it cannot be modified directly.
+#[derive(Debug, Clone)]
+pub struct Fv<N, T> {
+    /// The wrapped term
+    term: T,
+    /// The free variables of the term
+    free_vars: Arc<HashSet<FreeVar<N>>>,
+    /// Whether the term has no bound variables that refer to scopes outside of
+    /// it
+    locally_closed: bool,
+}
+
+impl<N, T> Fv<N, T> {
+    /// Wrap a term, computing its free variables
+    pub fn new(term: T) -> Fv<N, T>
+    where
+        N: Clone + Eq + Hash,
+        T: BoundTerm<N>,
+    {
+        let free_vars = term.free_vars();
+        let locally_closed = is_locally_closed(&term);
+
+        Fv {
+            term,
+            free_vars: Arc::new(free_vars),
+            locally_closed,
+        }
+    }
+
+    /// Returns `true` if `free_var` is one of the free variables of the term
+    ///
+    /// This is a constant time lookup in the cached set of free variables.
+    pub fn contains_free_var(&self, free_var: &FreeVar<N>) -> bool
+    where
+        N: Eq + Hash,
+    {
+        self.free_vars.contains(free_var)
+    }
+
+    /// Returns the wrapped term
+    pub fn into_inner(self) -> T {
+        self.term
+    }
+
+    /// Returns `true` if any of the free variables of the term match the
+    /// variable that is being substituted for
+    fn mentions<V: PartialEq<Var<N>>>(&self, name: &V) -> bool
+    where
+        N: Clone,
+    {
+        self.free_vars
+            .iter()
+            .any(|free_var| *name == Var::Free(free_var.clone()))
+    }
+}
+
+/// Returns `true` if the term has no bound variables that refer to scopes
+/// outside of it
+fn is_locally_closed<N, T>(term: &T) -> bool
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N>,
+{
+    let mut locally_closed = true;
+    term.visit_vars_with_binders(&mut BinderStack::new(), &mut |binders, var| {
+        if let Var::Bound(ref bound_var) = *var {
+            if binders.lookup(bound_var).is_none() {
+                locally_closed = false;
+            }
+        }
+    });
+    locally_closed
+}
+
+impl<N, T> AsRef<T> for Fv<N, T> {
+    fn as_ref(&self) -> &T {
+        &self.term
+    }
+}
+
+impl<N, T> Deref for Fv<N, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.term
+    }
+}
+
+impl<N, T> From<T> for Fv<N, T>
+where
+    N: Clone + Eq + Hash,
+    T: BoundTerm<N>,
+{
+    fn from(src: T) -> Fv<N, T> {
+        Fv::new(src)
+    }
+}
+
+impl<N, T> BoundTerm<N> for Fv<N, T>
+where
+    N: Clone + Eq + Hash,
+    T: BoundTerm<N>,
+{
+    fn term_eq(&self, other: &Fv<N, T>) -> bool {
+        T::term_eq(&self.term, &other.term)
+    }
+
+    fn term_eq_check(&self, other: &Fv<N, T>) -> Result<(), AlphaMismatch<N>> {
+        T::term_eq_check(&self.term, &other.term)
+    }
+
+    fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        // Only the free variables of the term can be bound
+        let closed = self
+            .free_vars
+            .iter()
+            .filter(|free_var| on_free.call(state, free_var).is_some())
+            .cloned()
+            .collect::<Vec<_>>();
+        if closed.is_empty() {
+            return;
+        }
+
+        self.term.close_term(state, on_free);
+
+        let free_vars = Arc::make_mut(&mut self.free_vars);
+        for free_var in &closed {
+            free_vars.remove(free_var);
+        }
+        // The new bound variables refer to a scope outside of the term
+        self.locally_closed = false;
+    }
+
+    fn open_term(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        // Only the bound variables that refer outside of the term can be freed
+        if self.locally_closed {
+            return;
+        }
+
+        let opened = RefCell::new(Vec::new());
+        let locally_closed = Cell::new(true);
+        let ScopeOffset(start) = state.depth();
+        self.term
+            .open_term(state, &|state: ScopeState, bound_var: &BoundVar<N>| {
+                let result = on_bound.call(state, bound_var);
+                let ScopeOffset(depth) = state.depth();
+                match result {
+                    Some(ref free_var) => opened.borrow_mut().push(free_var.clone()),
+                    None if bound_var.scope().0 >= depth - start => locally_closed.set(false),
+                    None => {},
+                }
+                result
+            });
+
+        Arc::make_mut(&mut self.free_vars).extend(opened.into_inner());
+        self.locally_closed = locally_closed.get();
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        self.term.visit_vars(on_var);
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.term.try_visit_vars(on_var)
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        // The variables could be changed in any way, so the cache is rebuilt
+        self.term.visit_mut_vars(on_var);
+        self.free_vars = Arc::new(self.term.free_vars());
+        self.locally_closed = is_locally_closed(&self.term);
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.term.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        self.term.visit_scopes(path, binders, on_scope);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.term.visit_nodes(depth, on_node);
+    }
+
+    fn free_vars(&self) -> HashSet<FreeVar<N>> {
+        (*self.free_vars).clone()
+    }
+
+    fn is_closed(&self) -> bool {
+        self.free_vars.is_empty()
+    }
+}
+
+/// Subterms that do not mention the substituted variables are returned
+/// without traversing them
+impl<N, R, T> Subst<N, R> for Fv<N, T>
+where
+    N: Clone + Eq + Hash,
+    R: BoundTerm<N>,
+    T: BoundTerm<N> + Subst<N, R> + Clone,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &R) -> Fv<N, T> {
+        if !self.mentions(name) {
+            return self.clone();
+        }
+
+        let mut free_vars = (*self.free_vars).clone();
+        free_vars.retain(|free_var| *name != Var::Free(free_var.clone()));
+        free_vars.extend(replacement.free_vars());
+
+        Fv {
+            term: self.term.subst(name, replacement),
+            free_vars: Arc::new(free_vars),
+            locally_closed: self.locally_closed && is_locally_closed(replacement),
+        }
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, R)]) -> Fv<N, T> {
+        let mentioned = mappings
+            .iter()
+            .filter(|(name, _)| self.mentions(name))
+            .collect::<Vec<_>>();
+        if mentioned.is_empty() {
+            return self.clone();
+        }
+
+        let mut free_vars = (*self.free_vars).clone();
+        let mut locally_closed = self.locally_closed;
+        for (name, replacement) in &mentioned {
+            free_vars.retain(|free_var| *name != Var::Free(free_var.clone()));
+            locally_closed = locally_closed && is_locally_closed(replacement);
+        }
+        for (_, replacement) in &mentioned {
+            free_vars.extend(replacement.free_vars());
+        }
+
+        Fv {
+            term: self.term.substs(mappings),
+            free_vars: Arc::new(free_vars),
+            locally_closed,
+        }
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &R) -> Option<Fv<N, T>> {
+        if self.mentions(name) {
+            Some(self.subst(name, replacement))
+        } else {
+            None
+        }
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, R)]) -> Option<Fv<N, T>> {
+        if mappings.iter().any(|(name, _)| self.mentions(name)) {
+            Some(self.substs(mappings))
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{ControlFlow, Deref};
+use std::rc::{Rc, Weak};
+
+use alpha_hash::AlphaHash;
+use alpha_mismatch::{AlphaMismatch, PathSegment};
+use binder::Binder;
+use bound::{BinderStack, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use subst::Subst;
+use var::Var;
+
+/// A store of hash-consed terms
+///
+/// Interning a term returns a shared handle to it. Terms that are alpha
+/// equivalent to a term that is already in the store are deduplicated, so the
+/// handles to them point to the same allocation, and can be compared for
+/// equality in constant time. This is useful for type checkers that normalize
+/// the same terms over and over again, where deep comparisons of the results
+/// would otherwise dominate.
+///
+/// The store only holds weak references to the terms, so interned terms are
+/// freed once the last handle to them is dropped.
+pub struct HashCons<N, T> {
+    /// The interned terms, grouped by their alpha equivalent hashes
+    terms: HashMap<u64, Vec<Weak<T>>>,
+    _name: PhantomData<N>,
+}
+
+impl<N, T> HashCons<N, T> {
+    /// Create an empty store
+    pub fn new() -> HashCons<N, T> {
+        HashCons {
+            terms: HashMap::new(),
+            _name: PhantomData,
+        }
+    }
+
+    /// Intern a term, returning a handle to the alpha equivalent term in the
+    /// store if there is one
+    pub fn intern(&mut self, term: T) -> Interned<T>
+    where
+        N: Clone + PartialEq,
+        T: AlphaHash<N> + BoundTerm<N>,
+    {
+        let mut state = DefaultHasher::new();
+        term.alpha_hash(&mut state);
+
+        let bucket = self.terms.entry(state.finish()).or_default();
+        // Forget about the terms that have been dropped since the last time
+        // that this bucket was used
+        bucket.retain(|weak| weak.upgrade().is_some());
+
+        for weak in bucket.iter() {
+            if let Some(interned) = weak.upgrade() {
+                if T::term_eq(&interned, &term) {
+                    return Interned(interned);
+                }
+            }
+        }
+
+        let interned = Rc::new(term);
+        bucket.push(Rc::downgrade(&interned));
+        Interned(interned)
+    }
+
+    /// The number of distinct terms that are still in use
+    pub fn len(&self) -> usize {
+        self.terms
+            .values()
+            .flat_map(|bucket| bucket.iter())
+            .filter(|weak| weak.upgrade().is_some())
+            .count()
+    }
+
+    /// Returns `true` if none of the terms in the store are still in use
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<N, T> Default for HashCons<N, T> {
+    fn default() -> HashCons<N, T> {
+        HashCons::new()
+    }
+}
+
+impl<N, T> fmt::Debug for HashCons<N, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HashCons")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// A handle to a term in a `HashCons` store
+///
+/// Handles are compared and hashed using the address of the term that they
+/// point to. As long as two handles come from the same store, they are equal
+/// exactly when the terms are alpha equivalent. Operations that change the
+/// term, like substitution or closing over free variables, return handles
+/// that are no longer in the store, so the results should be interned again
+/// if they are to be compared cheaply.
+#[derive(Debug)]
+pub struct Interned<T>(Rc<T>);
+
+impl<T> Interned<T> {
+    /// Returns `true` if both handles point to the same term
+    pub fn ptr_eq(this: &Interned<T>, other: &Interned<T>) -> bool {
+        Rc::ptr_eq(&this.0, &other.0)
+    }
+}
+
+impl<T> Clone for Interned<T> {
+    fn clone(&self) -> Interned<T> {
+        Interned(self.0.clone())
+    }
+}
+
+impl<T> PartialEq for Interned<T> {
+    fn eq(&self, other: &Interned<T>) -> bool {
+        Interned::ptr_eq(self, other)
+    }
+}
+
+impl<T> Eq for Interned<T> {}
+
+impl<T> Hash for Interned<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (&*self.0 as *const T).hash(state);
+    }
+}
+
+impl<T> AsRef<T> for Interned<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Deref for Interned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<N, T> BoundTerm<N> for Interned<T>
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N> + Clone,
+{
+    fn term_eq(&self, other: &Interned<T>) -> bool {
+        // Handles that were interned in different stores, or that were
+        // modified after being interned, might still be alpha equivalent, so
+        // this falls back to a deep comparison if the pointers differ
+        self.0.term_eq(&other.0)
+    }
+
+    fn term_eq_check(&self, other: &Interned<T>) -> Result<(), AlphaMismatch<N>> {
+        self.0.term_eq_check(&other.0)
+    }
+
+    fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        self.0.close_term(state, on_free);
+    }
+
+    fn open_term(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        self.0.open_term(state, on_bound);
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        self.0.visit_vars(on_var);
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.0.try_visit_vars(on_var)
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        self.0.visit_mut_vars(on_var);
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.0.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        self.0.visit_scopes(path, binders, on_scope);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.0.visit_nodes(depth, on_node);
+    }
+}
+
+impl<N, R, T> Subst<N, R> for Interned<T>
+where
+    T: Subst<N, R>,
+{
+    fn as_var(&self) -> Option<&Var<N>> {
+        self.0.as_var()
+    }
+
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &R) -> Interned<T> {
+        Interned(self.0.subst(name, replacement))
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, R)]) -> Interned<T> {
+        Interned(self.0.substs(mappings))
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(
+        &self,
+        name: &V,
+        replacement: &R,
+    ) -> Option<Interned<T>> {
+        self.0.subst_changed(name, replacement).map(Interned)
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, R)]) -> Option<Interned<T>> {
+        self.0.substs_changed(mappings).map(Interned)
+    }
+}
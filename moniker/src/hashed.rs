@@ -0,0 +1,173 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::ops::{ControlFlow, Deref};
+
+use alpha_hash::AlphaHash;
+use alpha_mismatch::{AlphaMismatch, PathSegment};
+use binder::Binder;
+use bound::{BinderStack, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use subst::Subst;
+use var::Var;
+
+/// A term that caches its alpha equivalent hash
+///
+/// Wrapping the shared nodes of a syntax tree in `Hashed<T>` means that
+/// hashing a term only hashes the nodes above the nearest `Hashed` subterms,
+/// using the cached hashes of the subterms in their place. Alpha equivalence
+/// checks also compare the cached hashes first, returning early if they
+/// differ. The cache is kept up to date by the operations of `BoundTerm` and
+/// `Subst`, so it cannot be modified directly.
+#[derive(Debug, Clone)]
+pub struct Hashed<T> {
+    /// The wrapped term
+    term: T,
+    /// The alpha equivalent hash of the term
+    hash: u64,
+}
+
+impl<T> Hashed<T> {
+    /// Wrap a term, computing its hash
+    pub fn new<N>(term: T) -> Hashed<T>
+    where
+        T: AlphaHash<N>,
+    {
+        let hash = alpha_hash_of(&term);
+        Hashed { term, hash }
+    }
+
+    /// The cached hash of the term, as produced by `AlphaHash::alpha_hash`
+    /// with a `DefaultHasher`
+    pub fn hash_value(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns the wrapped term
+    pub fn into_inner(self) -> T {
+        self.term
+    }
+
+    /// Recompute the hash after the term has been modified in place
+    fn rehash<N>(&mut self)
+    where
+        T: AlphaHash<N>,
+    {
+        self.hash = alpha_hash_of(&self.term);
+    }
+}
+
+fn alpha_hash_of<N, T: AlphaHash<N>>(term: &T) -> u64 {
+    let mut state = DefaultHasher::new();
+    term.alpha_hash(&mut state);
+    state.finish()
+}
+
+impl<T> AsRef<T> for Hashed<T> {
+    fn as_ref(&self) -> &T {
+        &self.term
+    }
+}
+
+impl<T> Deref for Hashed<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.term
+    }
+}
+
+impl<N, T> BoundTerm<N> for Hashed<T>
+where
+    N: Clone + PartialEq,
+    T: AlphaHash<N> + BoundTerm<N>,
+{
+    fn term_eq(&self, other: &Hashed<T>) -> bool {
+        // Alpha equivalent terms always have the same hash
+        self.hash == other.hash && T::term_eq(&self.term, &other.term)
+    }
+
+    fn term_eq_check(&self, other: &Hashed<T>) -> Result<(), AlphaMismatch<N>> {
+        T::term_eq_check(&self.term, &other.term)
+    }
+
+    fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        self.term.close_term(state, on_free);
+        self.rehash();
+    }
+
+    fn open_term(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        self.term.open_term(state, on_bound);
+        self.rehash();
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        self.term.visit_vars(on_var);
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.term.try_visit_vars(on_var)
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        self.term.visit_mut_vars(on_var);
+        self.rehash();
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.term.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        self.term.visit_scopes(path, binders, on_scope);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.term.visit_nodes(depth, on_node);
+    }
+}
+
+impl<N, R, T> Subst<N, R> for Hashed<T>
+where
+    T: AlphaHash<N> + Subst<N, R>,
+{
+    fn as_var(&self) -> Option<&Var<N>> {
+        self.term.as_var()
+    }
+
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &R) -> Hashed<T> {
+        Hashed::new(self.term.subst(name, replacement))
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, R)]) -> Hashed<T> {
+        Hashed::new(self.term.substs(mappings))
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &R) {
+        self.term.subst_mut(name, replacement);
+        self.rehash();
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, R)]) {
+        self.term.substs_mut(mappings);
+        self.rehash();
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &R) -> Option<Hashed<T>> {
+        self.term.subst_changed(name, replacement).map(Hashed::new)
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, R)]) -> Option<Hashed<T>> {
+        self.term.substs_changed(mappings).map(Hashed::new)
+    }
+}
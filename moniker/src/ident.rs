@@ -0,0 +1,88 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use free_var::FreeVar;
+
+/// Identifiers that can be used to name variables
+///
+/// Variables are generic over the type of their names, so names can be
+/// interned symbols like `Symbol`, integer ids, or `&'static str`s, rather
+/// than always being allocated as `String`s. Names are only used for pretty
+/// printing, so the only requirement is that they can be cloned, compared and
+/// hashed.
+///
+/// This is implemented for every type that meets these requirements.
+pub trait Ident: Clone + Eq + Hash {}
+
+impl<N: Clone + Eq + Hash> Ident for N {}
+
+/// A name with some extra data attached to it
+///
+/// Using `Named<N, D>` as the name of variables, like
+/// `FreeVar<Named<String, Span>>`, lets tools store things like the
+/// definition site or documentation of a variable alongside it, instead of in
+/// a separate table. The data is carried along with the name hint when
+/// scopes are bound and unbound and when terms are substituted into. It is
+/// ignored when comparing and hashing names, and when displaying them.
+#[derive(Debug, Clone)]
+pub struct Named<N, D> {
+    /// The name to display
+    pub name: N,
+    /// The data attached to the name
+    pub data: D,
+}
+
+impl<N, D> Named<N, D> {
+    /// Attach some data to a name
+    pub fn new(name: N, data: D) -> Named<N, D> {
+        Named { name, data }
+    }
+}
+
+impl<N, D> FreeVar<Named<N, D>> {
+    /// Create a fresh free variable, with a name hint and some data attached
+    /// to it
+    pub fn fresh_with_data(pretty_name: impl Into<N>, data: D) -> FreeVar<Named<N, D>> {
+        FreeVar::fresh(Some(Named::new(pretty_name.into(), data)))
+    }
+
+    /// The data attached to the name hint of the variable
+    pub fn data(&self) -> Option<&D> {
+        self.pretty_name
+            .as_ref()
+            .map(|pretty_name| &pretty_name.data)
+    }
+}
+
+impl<N: PartialEq, D> PartialEq for Named<N, D> {
+    fn eq(&self, other: &Named<N, D>) -> bool {
+        self.name == other.name
+    }
+}
+
+impl<N: Eq, D> Eq for Named<N, D> {}
+
+impl<N: PartialOrd, D> PartialOrd for Named<N, D> {
+    fn partial_cmp(&self, other: &Named<N, D>) -> Option<Ordering> {
+        self.name.partial_cmp(&other.name)
+    }
+}
+
+impl<N: Ord, D> Ord for Named<N, D> {
+    fn cmp(&self, other: &Named<N, D>) -> Ordering {
+        self.name.cmp(&other.name)
+    }
+}
+
+impl<N: Hash, D> Hash for Named<N, D> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
+
+impl<N: fmt::Display, D> fmt::Display for Named<N, D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.name, f)
+    }
+}
@@ -1,5 +1,7 @@
+use alpha_mismatch::PathSegment;
 use binder::Binder;
-use bound::{BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use bound::{BinderStack, BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use subst::Subst;
 use var::Var;
 
 /// Data that does not participate in name binding
@@ -24,6 +26,23 @@ where
     fn visit_vars(&self, _: &mut impl FnMut(&Var<N>)) {}
 
     fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
+
+    fn visit_vars_with_binders(
+        &self,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+    }
+
+    fn visit_scopes(
+        &self,
+        _: &mut Vec<PathSegment<N>>,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+    }
+
+    fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
 }
 
 impl<N, T> BoundPattern<N> for Ignore<T>
@@ -42,7 +61,48 @@ where
 
     fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
 
+    fn visit_vars_with_binders(
+        &self,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+    }
+
+    fn visit_scopes(
+        &self,
+        _: &mut Vec<PathSegment<N>>,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+    }
+
+    fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
+
     fn visit_binders(&self, _: &mut impl FnMut(&Binder<N>)) {}
 
     fn visit_mut_binders(&mut self, _: &mut impl FnMut(&mut Binder<N>)) {}
+
+    fn order_binders(&mut self, _: &impl BoundTerm<N>) {}
+}
+
+impl<N, T, U: Clone> Subst<N, T> for Ignore<U> {
+    fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Ignore<U> {
+        self.clone()
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Ignore<U> {
+        self.clone()
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, _: &V, _: &T) {}
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, _: &[(V, T)]) {}
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Option<Ignore<U>> {
+        None
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Option<Ignore<U>> {
+        None
+    }
 }
@@ -0,0 +1,414 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use ann_binder::AnnBinder;
+use bind_set::BindSet;
+use binder::Binder;
+use embed::Embed;
+use free_var::FreeVar;
+use ignore::Ignore;
+use meta_var::MetaVar;
+use nest::Nest;
+use rebind::Rebind;
+use rec::Rec;
+use record::Record;
+use scope::Scope;
+use small_vec::SmallVec;
+use spanned::Spanned;
+use var::Var;
+use wildcard::Wildcard;
+
+/// Instantiation of metavariables with their solutions
+///
+/// `T` is the type of the solutions, in the same way as the replacement terms
+/// of `Subst`. This can be derived by marking the metavariable variant of a
+/// term with `#[instantiate(meta)]`.
+///
+/// Metavariables are never bound, so solutions can be instantiated under
+/// scopes without needing to be shifted. The solutions are not themselves
+/// instantiated, so a solution that mentions other solved metavariables will
+/// need to be instantiated separately.
+pub trait Instantiate<N, T>: Sized {
+    /// Returns the metavariable if this term is one that can be replaced
+    /// during instantiation
+    fn as_meta(&self) -> Option<&MetaVar<N>> {
+        None
+    }
+
+    /// Visit each metavariable in the term, calling the `on_meta` callback on
+    /// each of them in turn
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>));
+
+    /// Replace each metavariable in the term that has a solution in
+    /// `solutions`, leaving the unsolved metavariables untouched
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> Self;
+
+    /// Returns the set of metavariables in this term
+    fn metas(&self) -> HashSet<MetaVar<N>>
+    where
+        N: Eq + Hash + Clone,
+    {
+        let mut metas = HashSet::new();
+        self.visit_metas(&mut |meta| {
+            metas.insert(meta.clone());
+        });
+        metas
+    }
+}
+
+impl<N: Clone, T> Instantiate<N, T> for MetaVar<N> {
+    fn as_meta(&self) -> Option<&MetaVar<N>> {
+        Some(self)
+    }
+
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        on_meta(self);
+    }
+
+    fn instantiate(&self, _: &HashMap<MetaVar<N>, T>) -> MetaVar<N> {
+        self.clone()
+    }
+}
+
+macro_rules! impl_instantiate_clone {
+    ([$($param:tt)*] $T:ty) => {
+        impl<$($param)*, T> Instantiate<N, T> for $T
+        where
+            $T: Clone,
+        {
+            fn visit_metas(&self, _: &mut impl FnMut(&MetaVar<N>)) {}
+
+            fn instantiate(&self, _: &HashMap<MetaVar<N>, T>) -> $T {
+                self.clone()
+            }
+        }
+    };
+    ($T:ty) => {
+        impl_instantiate_clone!([N] $T);
+    };
+}
+
+impl_instantiate_clone!(Var<N>);
+impl_instantiate_clone!(FreeVar<N>);
+impl_instantiate_clone!(Binder<N>);
+impl_instantiate_clone!(BindSet<N>);
+impl_instantiate_clone!([N, U] Ignore<U>);
+impl_instantiate_clone!(Wildcard);
+
+impl<N, T, U: Instantiate<N, T>> Instantiate<N, T> for Embed<U> {
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        self.0.visit_metas(on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> Embed<U> {
+        Embed(self.0.instantiate(solutions))
+    }
+}
+
+impl<N, T, P: Instantiate<N, T>> Instantiate<N, T> for Nest<P> {
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        self.unsafe_patterns.visit_metas(on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> Nest<P> {
+        Nest {
+            unsafe_patterns: self.unsafe_patterns.instantiate(solutions),
+        }
+    }
+}
+
+impl<N, T, P1, P2> Instantiate<N, T> for Rebind<P1, P2>
+where
+    P1: Instantiate<N, T>,
+    P2: Instantiate<N, T>,
+{
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        self.unsafe_left.visit_metas(on_meta);
+        self.unsafe_right.visit_metas(on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> Rebind<P1, P2> {
+        Rebind {
+            unsafe_left: self.unsafe_left.instantiate(solutions),
+            unsafe_right: self.unsafe_right.instantiate(solutions),
+        }
+    }
+}
+
+impl<N, T, P: Instantiate<N, T>> Instantiate<N, T> for Rec<P> {
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        self.unsafe_pattern.visit_metas(on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> Rec<P> {
+        Rec {
+            unsafe_pattern: self.unsafe_pattern.instantiate(solutions),
+        }
+    }
+}
+
+impl<N, T, L: Clone, P: Instantiate<N, T>> Instantiate<N, T> for Record<L, P> {
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        for field in &self.unsafe_fields {
+            field.1.visit_metas(on_meta);
+        }
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> Record<L, P> {
+        Record {
+            unsafe_fields: self
+                .unsafe_fields
+                .iter()
+                .map(|field| (field.0.clone(), field.1.instantiate(solutions)))
+                .collect(),
+        }
+    }
+}
+
+impl<N, T, P, U> Instantiate<N, T> for Scope<P, U>
+where
+    P: Instantiate<N, T>,
+    U: Instantiate<N, T>,
+{
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        self.unsafe_pattern.visit_metas(on_meta);
+        self.unsafe_body.visit_metas(on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> Scope<P, U> {
+        Scope {
+            unsafe_pattern: self.unsafe_pattern.instantiate(solutions),
+            unsafe_body: self.unsafe_body.instantiate(solutions),
+        }
+    }
+}
+
+impl<N: Clone, T, A: Instantiate<N, T>> Instantiate<N, T> for AnnBinder<N, A> {
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        self.annotation.visit_metas(on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> AnnBinder<N, A> {
+        AnnBinder {
+            binder: self.binder.clone(),
+            annotation: self.annotation.instantiate(solutions),
+        }
+    }
+}
+
+impl<N, T, S: Clone, U: Instantiate<N, T>> Instantiate<N, T> for Spanned<S, U> {
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        self.inner.visit_metas(on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> Spanned<S, U> {
+        Spanned {
+            span: self.span.clone(),
+            inner: self.inner.instantiate(solutions),
+        }
+    }
+}
+
+// Implementations for common types
+
+impl_instantiate_clone!(());
+impl_instantiate_clone!(String);
+impl_instantiate_clone!(['a, N] &'a str);
+impl_instantiate_clone!([N] Arc<str>);
+impl_instantiate_clone!(char);
+impl_instantiate_clone!(bool);
+impl_instantiate_clone!(u8);
+impl_instantiate_clone!(u16);
+impl_instantiate_clone!(u32);
+impl_instantiate_clone!(u64);
+impl_instantiate_clone!(usize);
+impl_instantiate_clone!(i8);
+impl_instantiate_clone!(i16);
+impl_instantiate_clone!(i32);
+impl_instantiate_clone!(i64);
+impl_instantiate_clone!(isize);
+impl_instantiate_clone!(f32);
+impl_instantiate_clone!(f64);
+
+impl<N, T, U: Instantiate<N, T>> Instantiate<N, T> for Option<U> {
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        if let Some(ref inner) = *self {
+            inner.visit_metas(on_meta);
+        }
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> Option<U> {
+        self.as_ref().map(|inner| inner.instantiate(solutions))
+    }
+}
+
+impl<N, T, U: Instantiate<N, T>> Instantiate<N, T> for Box<U> {
+    fn as_meta(&self) -> Option<&MetaVar<N>> {
+        U::as_meta(self)
+    }
+
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        U::visit_metas(self, on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> Box<U> {
+        Box::new(U::instantiate(self, solutions))
+    }
+}
+
+impl<N, T, U: Instantiate<N, T>> Instantiate<N, T> for Rc<U> {
+    fn as_meta(&self) -> Option<&MetaVar<N>> {
+        U::as_meta(self)
+    }
+
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        U::visit_metas(self, on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> Rc<U> {
+        Rc::new(U::instantiate(self, solutions))
+    }
+}
+
+impl<N, T, U: Instantiate<N, T>> Instantiate<N, T> for Arc<U> {
+    fn as_meta(&self) -> Option<&MetaVar<N>> {
+        U::as_meta(self)
+    }
+
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        U::visit_metas(self, on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> Arc<U> {
+        Arc::new(U::instantiate(self, solutions))
+    }
+}
+
+impl<N, T, U1, U2> Instantiate<N, T> for (U1, U2)
+where
+    U1: Instantiate<N, T>,
+    U2: Instantiate<N, T>,
+{
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        self.0.visit_metas(on_meta);
+        self.1.visit_metas(on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> (U1, U2) {
+        (self.0.instantiate(solutions), self.1.instantiate(solutions))
+    }
+}
+
+impl<N, T, U1, U2, U3> Instantiate<N, T> for (U1, U2, U3)
+where
+    U1: Instantiate<N, T>,
+    U2: Instantiate<N, T>,
+    U3: Instantiate<N, T>,
+{
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        self.0.visit_metas(on_meta);
+        self.1.visit_metas(on_meta);
+        self.2.visit_metas(on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> (U1, U2, U3) {
+        (
+            self.0.instantiate(solutions),
+            self.1.instantiate(solutions),
+            self.2.instantiate(solutions),
+        )
+    }
+}
+
+impl<N, T, U1, U2, U3, U4> Instantiate<N, T> for (U1, U2, U3, U4)
+where
+    U1: Instantiate<N, T>,
+    U2: Instantiate<N, T>,
+    U3: Instantiate<N, T>,
+    U4: Instantiate<N, T>,
+{
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        self.0.visit_metas(on_meta);
+        self.1.visit_metas(on_meta);
+        self.2.visit_metas(on_meta);
+        self.3.visit_metas(on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> (U1, U2, U3, U4) {
+        (
+            self.0.instantiate(solutions),
+            self.1.instantiate(solutions),
+            self.2.instantiate(solutions),
+            self.3.instantiate(solutions),
+        )
+    }
+}
+
+impl<N, T, U1, U2, U3, U4, U5> Instantiate<N, T> for (U1, U2, U3, U4, U5)
+where
+    U1: Instantiate<N, T>,
+    U2: Instantiate<N, T>,
+    U3: Instantiate<N, T>,
+    U4: Instantiate<N, T>,
+    U5: Instantiate<N, T>,
+{
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        self.0.visit_metas(on_meta);
+        self.1.visit_metas(on_meta);
+        self.2.visit_metas(on_meta);
+        self.3.visit_metas(on_meta);
+        self.4.visit_metas(on_meta);
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> (U1, U2, U3, U4, U5) {
+        (
+            self.0.instantiate(solutions),
+            self.1.instantiate(solutions),
+            self.2.instantiate(solutions),
+            self.3.instantiate(solutions),
+            self.4.instantiate(solutions),
+        )
+    }
+}
+
+impl<N, T, U: Instantiate<N, T>> Instantiate<N, T> for Vec<U> {
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        for elem in self {
+            elem.visit_metas(on_meta);
+        }
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> Vec<U> {
+        self.iter()
+            .map(|elem| elem.instantiate(solutions))
+            .collect()
+    }
+}
+
+impl<N, T, U: Instantiate<N, T>> Instantiate<N, T> for SmallVec<U> {
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        for elem in self {
+            elem.visit_metas(on_meta);
+        }
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> SmallVec<U> {
+        self.iter()
+            .map(|elem| elem.instantiate(solutions))
+            .collect()
+    }
+}
+
+impl<N, T, U: Instantiate<N, T>, const LEN: usize> Instantiate<N, T> for [U; LEN] {
+    fn visit_metas(&self, on_meta: &mut impl FnMut(&MetaVar<N>)) {
+        for elem in self {
+            elem.visit_metas(on_meta);
+        }
+    }
+
+    fn instantiate(&self, solutions: &HashMap<MetaVar<N>, T>) -> [U; LEN] {
+        self.each_ref().map(|elem| elem.instantiate(solutions))
+    }
+}
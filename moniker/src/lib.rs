@@ -44,36 +44,1123 @@
 //! - [`Var<N>`]: A variable that is either free or bound
 //! - [`Scope<P: BoundPattern<N>, T: BoundTerm<N>>`]: bind the term `T` using the pattern `P`
 //! - [`Ignore<T>`]: Ignores `T` when comparing for alpha equality
+//! - [`Spanned<S, T>`]: Annotates `T` with a span `S`, which is ignored for alpha equality
+//! - [`Closed<T: BoundTerm<N>>`]: A term `T` that contains no free variables
+//! - [`Fv<N, T: BoundTerm<N>>`]: A term `T` that caches its free variables, letting
+//!   substitutions skip the subterms that do not mention the substituted variable
+//! - [`Hashed<T: AlphaHash<N>>`]: A term `T` that caches its alpha equivalent hash
 //!
-//! Implementations for tuples, strings, numbers, slices, vectors, and mart pointers
-//! are also provided for convenience.
+//! Implementations for tuples, strings, numbers, slices, arrays, vectors, and
+//! smart pointers are also provided for convenience.
+//!
+//! Once `BoundTerm` has been implemented or derived for a type, the free
+//! variables of a term can be collected using [`BoundTerm::free_vars`].
+//! Variables that are bound by an enclosing scope are never included.
+//! When working with whole programs, the alpha equivalence and free variables
+//! of slices of terms can be computed in parallel with the `rayon` feature
+//! enabled, using [`par_term_eq`] and [`par_free_vars`].
+//! The number of nodes in a term and the depth that they are nested to can be
+//! measured using [`BoundTerm::term_size`] and [`BoundTerm::term_depth`].
+//! Binders that shadow the binders of enclosing scopes can be found using
+//! [`BoundTerm::shadowing`], for example to warn about them in a surface
+//! language.
+//!
+//! A single pattern can be bound over several bodies using `Scope<P, Vec<T>>`.
+//! The bodies can be unbound one at a time using [`Scope::unbind_body`], or
+//! transformed together using [`Scope::map_bodies`], which unbinds each of
+//! them using the same fresh names.
+//! Directly nested scopes, like `Scope<P1, Scope<P2, T>>`, can be created and
+//! unbound in a single traversal of the body using [`Scope::new_nested`] and
+//! [`Scope::unbind_nested`].
+//! Patterns that usually only bind one or two variables can be stored in a
+//! [`SmallVec`], which keeps up to two elements inline instead of allocating.
+//!
+//! Terms can be compared for alpha equivalence using [`BoundTerm::term_eq`].
+//! When the reason for a difference is needed, for example when reporting a
+//! failed conversion check, [`BoundTerm::term_eq_check`] returns an
+//! [`AlphaMismatch`] describing the path to the first subterms that differ.
+//! Subterms that are shared behind the same `Rc` or `Arc` are equal without
+//! being traversed, so comparing terms that share most of their structure is
+//! cheap.
+//! Terms that are compared often can be interned in a [`HashCons`] store,
+//! which deduplicates alpha equivalent terms so that the [`Interned`] handles
+//! to them can be compared by pointer.
 //!
 //! [`BoundTerm`]: trait.BoundTerm.html
+//! [`BoundTerm::term_eq`]: trait.BoundTerm.html#tymethod.term_eq
+//! [`BoundTerm::term_eq_check`]: trait.BoundTerm.html#method.term_eq_check
+//! [`AlphaMismatch`]: struct.AlphaMismatch.html
+//! [`HashCons`]: struct.HashCons.html
+//! [`Interned`]: struct.Interned.html
+//! [`BoundTerm::free_vars`]: trait.BoundTerm.html#method.free_vars
+//! [`par_term_eq`]: fn.par_term_eq.html
+//! [`par_free_vars`]: fn.par_free_vars.html
+//! [`BoundTerm::term_size`]: trait.BoundTerm.html#method.term_size
+//! [`BoundTerm::term_depth`]: trait.BoundTerm.html#method.term_depth
+//! [`BoundTerm::shadowing`]: trait.BoundTerm.html#method.shadowing
+//! [`Scope::unbind_body`]: struct.Scope.html#method.unbind_body
+//! [`Scope::map_bodies`]: struct.Scope.html#method.map_bodies
+//! [`Scope::new_nested`]: struct.Scope.html#method.new_nested
+//! [`Scope::unbind_nested`]: struct.Scope.html#method.unbind_nested
+//! [`SmallVec`]: struct.SmallVec.html
 //! [`Var<N>`]: enum.Var.html
 //! [`Scope<P: BoundPattern<N>, T: BoundTerm<N>>`]: struct.Scope.html
+//! [`Closed<T: BoundTerm<N>>`]: struct.Closed.html
+//! [`Fv<N, T: BoundTerm<N>>`]: struct.Fv.html
+//! [`Hashed<T: AlphaHash<N>>`]: struct.Hashed.html
 //!
 //! ## Patterns
 //!
 //! Patterns are data types that implement the [`BoundPattern`] trait.
 //!
 //! - [`Binder<N>`]: Captures a free variables within a term, but is ignored for alpha equality
+//! - [`AnnBinder<N, A>`]: A binder annotated with `A`, which is ignored for alpha equality
 //! - [`Ignore<T>`]: Ignores `T` when comparing for alpha equality
+//! - [`Spanned<S, T>`]: Annotates `T` with a span `S`, which is ignored for alpha equality
 //! - [`Embed<T: BoundTerm<N>>`]: Embed a term `T` in a pattern
 //! - [`Nest<P: BoundPattern<N>>`]: Multiple nested binding patterns
 //! - [`Rec<P: BoundPattern<N>>`]: Recursively bind a pattern in itself
+//! - [`Rebind<P1: BoundPattern<N>, P2: BoundPattern<N>>`]: Bind the pattern `P1` in the pattern `P2`
+//! - [`BindSet<N>`]: A set of binders, where their order does not matter
+//! - [`Record<L, P: BoundPattern<N>>`]: Patterns labelled by `L`, where their order does not matter
+//! - [`Wildcard`]: A pattern that binds no variables
 //!
-//! Implementations for tuples, strings, numbers, slices, vectors, and mart pointers
-//! are also provided for convenience.
+//! Implementations for tuples, strings, numbers, slices, arrays, vectors, and
+//! smart pointers are also provided for convenience. Optional patterns, like
+//! `Option<Binder<N>>`, bind a variable when they are `Some`, and bind nothing
+//! when they are `None`.
 //!
 //! [`BoundPattern`]: trait.BoundPattern.html
 //! [`Binder<N>`]: enum.Binder.html
+//! [`AnnBinder<N, A>`]: struct.AnnBinder.html
 //! [`Ignore<T>`]: struct.Ignore.html
+//! [`Spanned<S, T>`]: struct.Spanned.html
 //! [`Embed<T: BoundTerm<N>>`]: struct.Embed.html
 //! [`Nest<P: BoundPattern<N>>`]: struct.Nest.html
 //! [`Rec<P: BoundPattern<N>>`]: struct.Rec.html
+//! [`Rebind<P1: BoundPattern<N>, P2: BoundPattern<N>>`]: struct.Rebind.html
+//! [`BindSet<N>`]: struct.BindSet.html
+//! [`Record<L, P: BoundPattern<N>>`]: struct.Record.html
+//! [`Wildcard`]: struct.Wildcard.html
+//!
+//! ## Substitution
+//!
+//! Capture-avoiding substitution is provided by the [`Subst`] trait. This can
+//! be derived by marking the variable variant of a term with `#[subst(var)]`:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//! use moniker::{Binder, FreeVar, Scope, Subst, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm, Subst)]
+//! pub enum Expr {
+//!     #[subst(var)]
+//!     Var(Var<String>),
+//!     Lam(Scope<Binder<String>, Rc<Expr>>),
+//!     App(Rc<Expr>, Rc<Expr>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let x = FreeVar::fresh_named("x");
+//! let y = FreeVar::fresh_named("y");
+//! let var = |fv: &FreeVar<String>| Rc::new(Expr::Var(Var::Free(fv.clone())));
+//!
+//! // (x y)[x := y]
+//! let expr = Expr::App(var(&x), var(&y)).subst(&x, &Expr::Var(Var::Free(y.clone())));
+//!
+//! assert_term_eq!(expr, Expr::App(var(&y), var(&y)));
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! Many variables can be replaced at once using `Subst::substs`, which is
+//! useful when eliminating multi-binders or nested let bindings.
+//! Subterms behind an `Rc` or `Arc` that do not mention the substituted
+//! variables are shared with the original term rather than being rebuilt, so
+//! substitutions that leave a term unchanged return it without allocating.
+//! Terms that are uniquely owned can be substituted into in place using
+//! `Subst::subst_mut` and `Subst::substs_mut`, which only rebuild the nodes
+//! behind an `Rc` or `Arc` if they are shared.
+//!
+//! When the replacements are built up over time, like the solutions found
+//! during unification, they can be collected in a [`Substitution`]. These can
+//! be composed and restricted to a set of variables, and are applied to a term
+//! in a single traversal.
+//!
+//! When beta reducing, `Scope::instantiate` and `Scope::instantiates` replace
+//! the variables bound by a scope with arguments directly, without generating
+//! fresh names for the binders first. For evaluators that perform many beta
+//! reductions in a row, `Scope::instantiate_suspended` returns a [`Suspended`]
+//! term instead, which delays the substitutions until the term is forced.
+//!
+//! The replacement terms default to the type that the trait is being derived
+//! for. If terms are wrapped in a newtype, like `RcExpr { inner: Rc<Expr> }`,
+//! mark the wrapped field with `#[subst(var)]` too (or mark the newtype with
+//! `#[moniker(transparent)]`), and substitute using the wrapper type by adding
+//! `#[subst(replacement = "RcExpr")]` to the other types in the syntax tree.
+//! The derive checks that the replacement type implements [`SubstVar`], which
+//! means that it replaces itself when it is a variable that is being
+//! substituted for, so substituting never silently leaves the variables alone.
+//!
+//! [`Subst`]: trait.Subst.html
+//! [`SubstVar`]: trait.SubstVar.html
+//! [`Substitution`]: struct.Substitution.html
+//! [`Suspended`]: struct.Suspended.html
+//!
+//! ## Metavariables
+//!
+//! A [`MetaVar<N>`] stands in for a term that is not yet known, like the
+//! unknown types that arise when implementing unification-based type
+//! inference. Metavariables are unaffected by binding, and are replaced with
+//! their solutions using the [`Instantiate`] trait. This can be derived by
+//! marking the metavariable variant of a term with `#[instantiate(meta)]`:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::collections::HashMap;
+//! use std::rc::Rc;
+//! use moniker::{Instantiate, MetaVar};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm, Instantiate)]
+//! pub enum Type {
+//!     Int,
+//!     Arrow(Rc<Type>, Rc<Type>),
+//!     #[instantiate(meta)]
+//!     Meta(MetaVar<String>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let a = MetaVar::fresh_named("a");
+//! let ty = Type::Arrow(Rc::new(Type::Meta(a.clone())), Rc::new(Type::Int));
+//! assert_eq!(ty.metas(), [a.clone()].iter().cloned().collect());
+//!
+//! let mut solutions = HashMap::new();
+//! solutions.insert(a, Type::Int);
+//!
+//! assert_term_eq!(
+//!     ty.instantiate(&solutions),
+//!     Type::Arrow(Rc::new(Type::Int), Rc::new(Type::Int)),
+//! );
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! The solutions default to the type that the trait is being derived for, and
+//! can be changed with `#[instantiate(replacement = "...")]`, in the same way
+//! as for `Subst`.
+//!
+//! Metavariables that are applied to distinct variables, like `?F x y`, can be
+//! solved using higher-order pattern unification with a [`PatternUnifier`].
+//! This solves each metavariable with a scope that binds its arguments, and
+//! only needs to be told how to recognise the patterns in a term.
+//!
+//! [`MetaVar<N>`]: struct.MetaVar.html
+//! [`Instantiate`]: trait.Instantiate.html
+//! [`PatternUnifier`]: struct.PatternUnifier.html
+//!
+//! ## Opening and closing terms
+//!
+//! The operations used to bind and unbind scopes are available as the
+//! [`BoundTerm::close_term`] and [`BoundTerm::open_term`] methods, for
+//! implementing custom binding structures or evaluators. These take a
+//! [`ScopeState`], which tracks the number of scopes that have been entered,
+//! along with the binders to use, or a closure:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//! use moniker::{Binder, BoundTerm, BoundVar, FreeVar, Scope, ScopeState, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm)]
+//! pub enum Expr {
+//!     Var(Var<String>),
+//!     Lam(Scope<Binder<String>, Rc<Expr>>),
+//!     App(Rc<Expr>, Rc<Expr>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let x = FreeVar::fresh_named("x");
+//! let y = FreeVar::fresh_named("y");
+//! let var = |fv: &FreeVar<String>| Rc::new(Expr::Var(Var::Free(fv.clone())));
+//!
+//! // Close `x`, then open the result using `y`
+//! let mut expr = Expr::App(var(&x), var(&x));
+//! expr.close_term(ScopeState::new(), &vec![Binder(x.clone())]);
+//! expr.open_term(ScopeState::new(), &|_, _: &BoundVar<String>| Some(y.clone()));
+//!
+//! assert_term_eq!(expr, Expr::App(var(&y), var(&y)));
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! [`BoundTerm::close_term`]: trait.BoundTerm.html#tymethod.close_term
+//! [`BoundTerm::open_term`]: trait.BoundTerm.html#tymethod.open_term
+//! [`ScopeState`]: struct.ScopeState.html
+//!
+//! ## Generic types and lifetimes
+//!
+//! When deriving for types with type parameters, each parameter is required to
+//! implement the derived trait. These bounds can be replaced when more are
+//! needed, for example when the parameter appears behind an `Rc`:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//! use moniker::{Binder, BoundTerm, Scope, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm)]
+//! #[moniker(bound = "T: BoundTerm<String> + Clone")]
+//! pub enum Expr<T> {
+//!     Var(Var<String>),
+//!     Literal(T),
+//!     Lam(Scope<Binder<String>, Rc<Expr<T>>>),
+//!     App(Rc<Expr<T>>, Rc<Expr<T>>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let one = Expr::App(Rc::new(Expr::Literal(1)), Rc::new(Expr::Literal(2)));
+//! let two = Expr::App(Rc::new(Expr::Literal(1)), Rc::new(Expr::Literal(2)));
+//!
+//! assert_term_eq!(one, two);
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! The bounds of derived `Subst` impls can be replaced in the same way using
+//! `#[subst(bound = "...")]`.
+//!
+//! Lifetime parameters are also supported, allowing syntax trees to borrow
+//! from the source code rather than allocating:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use moniker::{Binder, Scope, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm)]
+//! pub enum Expr<'a> {
+//!     Var(Var<String>),
+//!     Ident(&'a str),
+//!     Lam(Scope<Binder<String>, Box<Expr<'a>>>),
+//!     App(Box<Expr<'a>>, Box<Expr<'a>>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let src = String::from("x y");
+//! let one = Expr::App(Box::new(Expr::Ident(&src[0..1])), Box::new(Expr::Ident(&src[2..3])));
+//! let two = Expr::App(Box::new(Expr::Ident("x")), Box::new(Expr::Ident("y")));
+//!
+//! assert_term_eq!(one, two);
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! Note that the variables of a term can only be bound and unbound when they
+//! are owned by the term, so shared references to other terms, like
+//! `&'a [Expr<'a>]`, are not supported. Syntax trees that are allocated in an
+//! [`Arena`] can refer to their subterms using [`ArenaRef`] instead, which
+//! allocates the nodes that are rebuilt when binding, unbinding, or
+//! substituting in the same arena.
+//!
+//! [`Arena`]: struct.Arena.html
+//! [`ArenaRef`]: struct.ArenaRef.html
+//!
+//! ## Smart pointers
+//!
+//! Terms and patterns can be stored behind a `Box`, `Rc`, or `Arc`. Compilers
+//! that share terms between threads can use `Arc` in place of `Rc`, and
+//! helpers can be written for any of these pointers using the [`Pointer`]
+//! trait:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::sync::Arc;
+//! use std::thread;
+//! use moniker::{Binder, BoundTerm, FreeVar, Pointer, Scope, Var};
+//!
+//! type Ptr<T> = Arc<T>;
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm)]
+//! pub enum Expr {
+//!     Var(Var<String>),
+//!     Lam(Scope<Binder<String>, Ptr<Expr>>),
+//!     App(Ptr<Expr>, Ptr<Expr>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! fn var<P: Pointer<Expr>>(free_var: &FreeVar<String>) -> P {
+//!     P::new(Expr::Var(Var::Free(free_var.clone())))
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let x = FreeVar::fresh_named("x");
+//! let id: Ptr<Expr> = Ptr::new(Expr::Lam(Scope::new(Binder(x.clone()), var(&x))));
+//!
+//! let handle = thread::spawn({
+//!     let id = id.clone();
+//!     move || match *id {
+//!         Expr::Lam(ref scope) => scope.clone().unbind().1,
+//!         _ => panic!("expected a lambda"),
+//!     }
+//! });
+//! let body = handle.join().unwrap();
+//!
+//! assert_eq!(body.free_vars().len(), 1);
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! The traversals over terms recurse into each subterm, so very deep terms,
+//! like those produced by code generators, could overflow the stack. The
+//! derived impls call [`maybe_grow_stack`] at each node, which, with the
+//! `stacker` feature enabled, allocates a new stack segment on the current
+//! thread once the stack is running low. Other recursive code, like dropping a
+//! deep term, can be run inside [`with_stack_size`], which runs a function on
+//! a thread with a larger stack.
+//!
+//! [`Pointer`]: trait.Pointer.html
+//! [`maybe_grow_stack`]: fn.maybe_grow_stack.html
+//! [`with_stack_size`]: fn.with_stack_size.html
+//!
+//! ## Visitors
+//!
+//! Deriving [`Visit`] for a type generates a visitor trait with a hook for
+//! each of its variants, which are called while walking over a term. For
+//! example, deriving `Visit` for `Expr` generates an `ExprVisitor` trait with
+//! `visit_var`, `visit_lam`, and `visit_app` methods. Wrapper types can reuse
+//! an existing visitor trait with `#[visit(visitor = "ExprVisitor")]`.
+//!
+//! Visitors must also implement [`ScopeVisitor`], which is notified whenever
+//! the body of a scope is entered or exited.
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//! use moniker::{Binder, FreeVar, Scope, ScopeVisitor, Var, Visit};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm, Visit)]
+//! pub enum Expr {
+//!     Var(Var<String>),
+//!     Lam(Scope<Binder<String>, Rc<Expr>>),
+//!     App(Rc<Expr>, Rc<Expr>),
+//! }
+//!
+//! /// Count the number of lambdas in an expression
+//! struct CountLams(usize);
+//!
+//! impl ScopeVisitor for CountLams {}
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! impl ExprVisitor for CountLams {
+//!     fn visit_lam(&mut self, _: &Scope<Binder<String>, Rc<Expr>>) {
+//!         self.0 += 1;
+//!     }
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let x = FreeVar::fresh_named("x");
+//! let id = Rc::new(Expr::Lam(Scope::new(Binder(x.clone()), Rc::new(Expr::Var(Var::Free(x))))));
+//! let expr = Expr::App(id.clone(), id);
+//!
+//! let mut count = CountLams(0);
+//! expr.visit(&mut count);
+//! assert_eq!(count.0, 2);
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! Passes that only care about variables don't need a visitor. The
+//! [`BoundTerm::visit_vars`] and [`BoundTerm::visit_mut_vars`] methods call a
+//! closure on each variable in a term, which is enough for collecting,
+//! retagging, or validating variables:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//! use moniker::{Binder, BoundTerm, FreeVar, Scope, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm)]
+//! pub enum Expr {
+//!     Var(Var<String>),
+//!     Lam(Scope<Binder<String>, Rc<Expr>>),
+//!     App(Rc<Expr>, Rc<Expr>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let x = FreeVar::fresh_named("x");
+//! let y = FreeVar::fresh_named("y");
+//! let var = |fv: &FreeVar<String>| Rc::new(Expr::Var(Var::Free(fv.clone())));
+//!
+//! // \x => x y
+//! let mut expr = Expr::Lam(Scope::new(Binder(x.clone()), Rc::new(Expr::App(var(&x), var(&y)))));
+//!
+//! let mut bound_count = 0;
+//! expr.visit_vars(&mut |var| {
+//!     if let Var::Bound(_) = *var {
+//!         bound_count += 1;
+//!     }
+//! });
+//! assert_eq!(bound_count, 1);
+//!
+//! // Forget the pretty names of the free variables
+//! expr.visit_mut_vars(&mut |var| {
+//!     if let Var::Free(ref mut free_var) = *var {
+//!         free_var.pretty_name = None;
+//!     }
+//! });
+//! assert!(expr.free_vars().iter().all(|free_var| free_var.pretty_name.is_none()));
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! When a pass needs to know which binders are visible at each variable, use
+//! [`BoundTerm::fold_with_binders`] instead. This threads a [`BinderStack`]
+//! through the term, which can be used to look up the binders that bound
+//! variables refer to.
+//!
+//! [`Visit`]: trait.Visit.html
+//! [`ScopeVisitor`]: trait.ScopeVisitor.html
+//! [`BoundTerm::fold_with_binders`]: trait.BoundTerm.html#method.fold_with_binders
+//! [`BinderStack`]: struct.BinderStack.html
+//! [`BoundTerm::visit_vars`]: trait.BoundTerm.html#tymethod.visit_vars
+//! [`BoundTerm::visit_mut_vars`]: trait.BoundTerm.html#tymethod.visit_mut_vars
+//!
+//! ## Mapping variables
+//!
+//! Passes that rename or intern variables can be written using [`MapVars`],
+//! which returns a copy of a term with each variable and binder replaced by
+//! the results of the given callbacks:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//! use moniker::{Binder, FreeVar, MapVars, Scope, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm, MapVars)]
+//! pub enum Expr {
+//!     Var(Var<String>),
+//!     Lam(Scope<Binder<String>, Rc<Expr>>),
+//!     App(Rc<Expr>, Rc<Expr>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let x = FreeVar::fresh_named("x");
+//! let y = FreeVar::fresh_named("y");
+//! let var = |fv: &FreeVar<String>| Rc::new(Expr::Var(Var::Free(fv.clone())));
+//!
+//! // (x x)[x := y]
+//! let expr = Expr::App(var(&x), var(&x)).map_vars(
+//!     &mut |var| if *var == x { Var::Free(y.clone()) } else { var.clone() },
+//!     &mut |binder| binder.clone(),
+//! );
+//!
+//! assert_term_eq!(expr, Expr::App(var(&y), var(&y)));
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! `MapVars::alpha_normalize` uses this to rename the binders of a term in a
+//! canonical way, so that alpha equivalent terms can be compared and hashed
+//! with `PartialEq` and `Hash`.
+//!
+//! [`MapVars`]: trait.MapVars.html
+//!
+//! ## Matching terms
+//!
+//! Two terms can be traversed in lock-step using the [`Zip`] trait, which
+//! hands each pair of subterms to a [`Zipper`]. This is used by
+//! `Zip::alpha_match` to match a template term containing holes against a
+//! subject term, which is handy for implementing rewrite rules:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//! use moniker::{Binder, FreeVar, Scope, Var, Zip};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm, Subst, Zip)]
+//! pub enum Expr {
+//!     #[subst(var)]
+//!     Var(Var<String>),
+//!     Lam(Scope<Binder<String>, Rc<Expr>>),
+//!     App(Rc<Expr>, Rc<Expr>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let a = FreeVar::fresh_named("a");
+//! let x = FreeVar::fresh_named("x");
+//! let var = |fv: &FreeVar<String>| Rc::new(Expr::Var(Var::Free(fv.clone())));
+//! let id = Rc::new(Expr::Lam(Scope::new(Binder(x.clone()), var(&x))));
+//!
+//! // (a a) matches ((\x => x) (\x => x)) with a := \x => x
+//! let template = Expr::App(var(&a), var(&a));
+//! let substitution = template.alpha_match(&Expr::App(id.clone(), id.clone()), &[a.clone()]);
+//!
+//! assert_term_eq!(substitution.unwrap().get(&a).unwrap().clone(), *id);
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! `Zip::anti_unify` goes the other way, finding the most specific term that
+//! generalizes two terms, along with the substitutions that recover each of
+//! the original terms from it. `Zip::unify` solves the free variables that are
+//! treated as metavariables in both terms, with an occurs check, returning the
+//! solutions as a `Substitution`.
+//!
+//! The zipped subterms default to the type that the trait is being derived
+//! for, and can be changed with `#[zip(replacement = "...")]`, in the same way
+//! as for `Subst`.
+//!
+//! [`Zip`]: trait.Zip.html
+//! [`Zipper`]: trait.Zipper.html
+//!
+//! ## Hashing and ordering terms
+//!
+//! [`AlphaHash`] hashes terms in a way that is consistent with alpha
+//! equivalence, so that terms with differently named binders hash the same.
+//! This can be used to implement `Hash` for terms that are compared with
+//! `BoundTerm::term_eq`, without normalizing the names of their binders first:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::collections::hash_map::DefaultHasher;
+//! use std::hash::Hasher;
+//! use std::rc::Rc;
+//! use moniker::{AlphaHash, Binder, FreeVar, Scope, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm, AlphaHash)]
+//! pub enum Expr {
+//!     Var(Var<String>),
+//!     Lam(Scope<Binder<String>, Rc<Expr>>),
+//!     App(Rc<Expr>, Rc<Expr>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let hash = |expr: &Expr| {
+//!     let mut state = DefaultHasher::new();
+//!     expr.alpha_hash(&mut state);
+//!     state.finish()
+//! };
+//! let id = |fv: &FreeVar<String>| {
+//!     Expr::Lam(Scope::new(Binder(fv.clone()), Rc::new(Expr::Var(Var::Free(fv.clone())))))
+//! };
+//!
+//! // \x => x and \y => y have the same hash
+//! let x = FreeVar::fresh_named("x");
+//! let y = FreeVar::fresh_named("y");
+//! assert_eq!(hash(&id(&x)), hash(&id(&y)));
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! Fields marked with `#[moniker(constant)]` are hashed using `Hash`, and
+//! fields marked with `#[moniker(unordered)]` are hashed without regard to the
+//! order of their elements.
+//!
+//! In the same way, [`AlphaOrd`] provides a total ordering that is consistent
+//! with alpha equivalence, for storing terms in `BTreeMap`s and `BTreeSet`s.
+//! Free variables are ordered by their name hints before their generated ids,
+//! so sorting terms with distinctly named free variables gives the same
+//! results from run to run.
+//!
+//! [`AlphaHash`]: trait.AlphaHash.html
+//! [`AlphaOrd`]: trait.AlphaOrd.html
+//!
+//! ## Displaying terms
+//!
+//! The `Display` impls for variables show their name hints, only adding a
+//! suffix to tell apart different variables with the same hint, but show
+//! bound variables using their positions, like `x@0.0`. Deriving
+//! [`DisplayTerm`] instead displays whole terms like their constructors,
+//! using the name hints of binders in place of the variables they bind. Names
+//! are only disambiguated when they would capture another variable, or when
+//! two different free variables have the same name hint, and variables
+//! without name hints are numbered in the order that they appear, so the
+//! output is the same each time a term is displayed:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//! use moniker::{Binder, DisplayTerm, FreeVar, Scope, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm, DisplayTerm)]
+//! pub enum Expr {
+//!     Var(Var<String>),
+//!     Lam(Scope<Binder<String>, Rc<Expr>>),
+//!     App(Rc<Expr>, Rc<Expr>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let x = FreeVar::fresh_named("x");
+//! let var = |fv: &FreeVar<String>| Rc::new(Expr::Var(Var::Free(fv.clone())));
+//! let expr = Expr::App(Rc::new(Expr::Lam(Scope::new(Binder(x.clone()), var(&x)))), var(&x));
+//!
+//! assert_eq!(expr.display_term().to_string(), "App(Lam(<x> Var(x)), Var(x))");
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! [`DisplayTerm`]: trait.DisplayTerm.html
+//!
+//! ## Generating arbitrary terms
+//!
+//! With the `proptest` feature enabled, [`ArbitraryTerm`] can be derived to
+//! generate terms for property tests. The generated terms are well scoped:
+//! each variable refers to an enclosing binder, or to one of the free
+//! variables of the [`ArbitraryEnv`]. The derive also implements proptest's
+//! `Arbitrary`, so `any::<Expr>()` will generate closed expressions:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//! # #[cfg(feature = "proptest")]
+//! extern crate proptest;
+//!
+//! use std::rc::Rc;
+//! use moniker::{Binder, BoundTerm, Scope, Var};
+//! # #[cfg(feature = "proptest")]
+//! use proptest::strategy::{Strategy, ValueTree};
+//! # #[cfg(feature = "proptest")]
+//! use proptest::test_runner::TestRunner;
+//!
+//! # #[cfg(all(feature = "moniker-derive", feature = "proptest"))]
+//! #[derive(Debug, Clone, BoundTerm, ArbitraryTerm)]
+//! pub enum Expr {
+//!     Var(Var<String>),
+//!     Lam(Scope<Binder<String>, Rc<Expr>>),
+//!     App(Rc<Expr>, Rc<Expr>),
+//! }
+//!
+//! # #[cfg(all(feature = "moniker-derive", feature = "proptest"))]
+//! # fn main() {
+//! let mut runner = TestRunner::default();
+//! let expr = proptest::arbitrary::any::<Expr>().new_tree(&mut runner).unwrap().current();
+//!
+//! assert!(expr.free_vars().is_empty());
+//! # }
+//! # #[cfg(not(all(feature = "moniker-derive", feature = "proptest")))]
+//! # fn main() {}
+//! ```
+//!
+//! Fields marked with `#[moniker(ignore)]` are filled in using `Default`, and
+//! fields marked with `#[moniker(constant)]` are generated using their
+//! `Arbitrary` impls. Each derived type uses up one level of the environment's
+//! depth, which can be adjusted with [`ArbitraryEnv::with_depth`].
+//!
+//! [`ArbitraryTerm`]: trait.ArbitraryTerm.html
+//! [`ArbitraryEnv`]: struct.ArbitraryEnv.html
+//! [`ArbitraryEnv::with_depth`]: struct.ArbitraryEnv.html#method.with_depth
+//!
+//! ## Serialization
+//!
+//! With the `serde` feature enabled, the variable and binding types implement
+//! `Serialize` and `Deserialize`, so the impls for your own types can be
+//! derived using `serde_derive`. Terms are serialized in a canonical form,
+//! where alpha equivalent terms serialize identically:
+//!
+//! - Bound variables are serialized as their Debruijn indices
+//! - Binders are serialized without their generated ids or name hints, and
+//!   are given fresh ids when they are deserialized
+//! - Ignored data is skipped, and is replaced with its default value when
+//!   deserializing. Fields marked with `#[moniker(ignore)]` should be marked
+//!   with `#[serde(skip)]` too.
+//!
+//! Free variables have no canonical form, so serializing one results in an
+//! error. Open terms can be serialized by binding their free variables in a
+//! scope first.
+//!
+//! ## Deriving both terms and patterns
+//!
+//! Some types, like literals, are used both as terms and as patterns. Rather
+//! than deriving `BoundTerm` and `BoundPattern` separately, `Alpha` can be
+//! derived to implement both at once:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, PartialEq, Alpha)]
+//! pub enum Literal {
+//!     Int(i32),
+//!     String(String),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! use moniker::{BoundPattern, BoundTerm};
+//!
+//! assert!(Literal::Int(1).term_eq(&Literal::Int(1)));
+//! assert!(Literal::Int(1).pattern_eq(&Literal::Int(1)));
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! One of the impls can be left out using `#[moniker(skip_term)]` or
+//! `#[moniker(skip_pattern)]`, allowing it to be implemented by hand.
+//!
+//! ## Derive attributes
+//!
+//! The behaviour of the derived impls can be adjusted using `#[moniker(...)]`
+//! attributes:
+//!
+//! - `#[moniker(ignore)]`: Skip a field when comparing for alpha equality and
+//!   when visiting variables, as if it were wrapped in an [`Ignore<T>`]. This
+//!   is handy for source locations and other metadata:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//! use moniker::{Binder, Scope, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm)]
+//! pub enum Expr {
+//!     Var(#[moniker(ignore)] usize, Var<String>),
+//!     Lam(#[moniker(ignore)] usize, Scope<Binder<String>, Rc<Expr>>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let x = moniker::FreeVar::fresh_named("x");
+//! let var = |pos| Rc::new(Expr::Var(pos, Var::Free(x.clone())));
+//!
+//! assert_term_eq!(var(0), var(12));
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! - `#[moniker(constant)]`: Treat a field as a constant that contains no
+//!   variables or binders. It will be compared for alpha equality using
+//!   `PartialEq`, and skipped when visiting variables. This avoids needing to
+//!   implement `BoundTerm` for literals, or for types from other crates:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//! use std::time::Duration;
+//! use moniker::{Binder, Scope, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm)]
+//! pub enum Expr {
+//!     Var(Var<String>),
+//!     Duration(#[moniker(constant)] Duration),
+//!     Lam(Scope<Binder<String>, Rc<Expr>>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! use moniker::BoundTerm;
+//!
+//! let secs = |secs| Expr::Duration(Duration::from_secs(secs));
+//!
+//! assert_term_eq!(secs(1), secs(1));
+//! assert!(!secs(1).term_eq(&secs(2)));
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! - `#[moniker(unordered)]`: Compare the elements of a collection for alpha
+//!   equality regardless of their order, like the fields of a record type.
+//!   This can't be used in patterns, because the order of the binders in a
+//!   pattern is significant:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm)]
+//! pub enum Type {
+//!     Int,
+//!     Record(#[moniker(unordered)] Vec<(String, Rc<Type>)>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let field = |label: &str| (String::from(label), Rc::new(Type::Int));
+//!
+//! assert_term_eq!(
+//!     Type::Record(vec![field("x"), field("y")]),
+//!     Type::Record(vec![field("y"), field("x")]),
+//! );
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! - `#[moniker(commutative)]` and `#[moniker(associative = "...")]`: Compare
+//!   the fields of an enum variant modulo commutativity or associativity, like
+//!   the parallel composition of processes. The fields of commutative variants
+//!   are compared as a multiset. Nested uses of associative variants are
+//!   flattened first, using the given function to get at the enum that each
+//!   field refers to. Derived `AlphaHash` and `AlphaOrd` impls respect these
+//!   laws too:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//! use moniker::{FreeVar, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm)]
+//! pub enum Proc {
+//!     Send(Var<String>),
+//!     #[moniker(commutative, associative = "Rc::as_ref")]
+//!     Par(Rc<Proc>, Rc<Proc>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let send = |name| Rc::new(Proc::Send(Var::Free(FreeVar::fresh_named(name))));
+//! let par = |lhs, rhs| Rc::new(Proc::Par(lhs, rhs));
+//! let (a, b, c) = (send("a"), send("b"), send("c"));
+//!
+//! // a | (b | c) == (c | a) | b
+//! assert_term_eq!(
+//!     par(a.clone(), par(b.clone(), c.clone())),
+//!     par(par(c.clone(), a.clone()), b.clone()),
+//! );
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! - `#[moniker(transparent)]`: Delegate to the only field of a newtype, so
+//!   that it behaves exactly like the type that it wraps. Derived `Subst`
+//!   impls treat the field as a variable, derived `DisplayTerm` impls only
+//!   display the field, and derived `Visit` impls walk the field without
+//!   generating a visitor trait:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//! use moniker::{Binder, DisplayTerm, FreeVar, Scope, Subst, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm, DisplayTerm, Subst)]
+//! #[subst(replacement = "RcExpr")]
+//! pub enum Expr {
+//!     #[subst(var)]
+//!     Var(Var<String>),
+//!     Lam(Scope<Binder<String>, RcExpr>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm, DisplayTerm, Subst)]
+//! #[moniker(transparent)]
+//! pub struct RcExpr {
+//!     pub inner: Rc<Expr>,
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let x = FreeVar::fresh_named("x");
+//! let y = FreeVar::fresh_named("y");
+//! let var = |fv: &FreeVar<String>| RcExpr { inner: Rc::new(Expr::Var(Var::Free(fv.clone()))) };
+//!
+//! assert_term_eq!(var(&x).subst(&x, &var(&y)), var(&y));
+//! assert_eq!(var(&x).display_term().to_string(), "Var(x)");
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! - `#[moniker(remote = "...")]`: Derive for a type from another crate, by
+//!   describing its definition on a local mirror type. Because of the orphan
+//!   rule, this generates functions on the mirror type rather than trait
+//!   impls, so fields of the remote type must be marked with
+//!   `#[moniker(with = "...")]` to use them. Only `BoundTerm` and
+//!   `BoundPattern` can be derived for remote types:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use moniker::{Binder, FreeVar, Scope, Var};
+//!
+//! /// A syntax tree from another crate
+//! mod other {
+//!     use moniker::Var;
+//!
+//!     #[derive(Debug, Clone)]
+//!     pub enum Expr {
+//!         Var(Var<String>),
+//!         App(Box<Expr>, Box<Expr>),
+//!     }
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(BoundTerm)]
+//! #[moniker(remote = "other::Expr")]
+//! enum ExprDef {
+//!     Var(Var<String>),
+//!     App(
+//!         #[moniker(with = "ExprDef")] Box<other::Expr>,
+//!         #[moniker(with = "ExprDef")] Box<other::Expr>,
+//!     ),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm)]
+//! #[moniker(transparent)]
+//! pub struct Expr(#[moniker(with = "ExprDef")] other::Expr);
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let x = FreeVar::fresh_named("x");
+//! let y = FreeVar::fresh_named("y");
+//! let app = |fv: &FreeVar<String>| {
+//!     let var = || Box::new(other::Expr::Var(Var::Free(fv.clone())));
+//!     Expr(other::Expr::App(var(), var()))
+//! };
+//!
+//! assert_term_eq!(Scope::new(Binder(x.clone()), app(&x)), Scope::new(Binder(y.clone()), app(&y)));
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! - `#[moniker(ident = "...")]`: Set the type of the names used in the
+//!   variables and binders of the type, instead of `String`. This allows
+//!   interned symbols, integer ids, `&'static str`s, or `Arc<str>`s to be
+//!   used, so long as they implement the [`Ident`] trait. Variables named by
+//!   `Arc<str>` are cheap to clone, and can be sent between threads. The
+//!   provided [`Symbol`] type interns its strings, which makes cloning
+//!   variables cheap, and other interners can be used by implementing the
+//!   [`Interner`] trait for them. The interners of the `lasso` and
+//!   `string-interner` crates implement it when the features of the same
+//!   names are enabled. The same attribute must be given to each of the types
+//!   in the syntax tree:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker;
+//!
+//! use std::rc::Rc;
+//! use moniker::{Binder, FreeVar, Scope, Subst, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm, Subst)]
+//! #[moniker(ident = "&'static str")]
+//! pub enum Expr {
+//!     #[subst(var)]
+//!     Var(Var<&'static str>),
+//!     Lam(Scope<Binder<&'static str>, Rc<Expr>>),
+//!     App(Rc<Expr>, Rc<Expr>),
+//! }
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! # fn main() {
+//! let x = FreeVar::fresh_named("x");
+//! let y = FreeVar::fresh_named("y");
+//! let var = |fv: &FreeVar<&'static str>| Rc::new(Expr::Var(Var::Free(fv.clone())));
+//!
+//! let expr = Expr::App(var(&x), var(&y)).subst(&x, &Expr::Var(Var::Free(y.clone())));
+//!
+//! assert_term_eq!(expr, Expr::App(var(&y), var(&y)));
+//! # }
+//! # #[cfg(not(feature = "moniker-derive"))]
+//! # fn main() {}
+//! ```
+//!
+//! [`Ident`]: trait.Ident.html
+//! [`Interner`]: trait.Interner.html
+//! [`Symbol`]: struct.Symbol.html
+//!
+//! - `#[moniker(crate = "...")]`: Set the path to the `moniker` crate that is
+//!   used in the generated impls. This is needed when `moniker` is re-exported
+//!   from another crate, rather than being a direct dependency:
+//!
+//! ```rust
+//! #[macro_use]
+//! extern crate moniker as my_facade;
+//!
+//! use my_facade::{Binder, Scope, Var};
+//!
+//! # #[cfg(feature = "moniker-derive")]
+//! #[derive(Debug, Clone, BoundTerm)]
+//! #[moniker(crate = "my_facade")]
+//! pub enum Expr {
+//!     Var(Var<String>),
+//!     Lam(Scope<Binder<String>, Box<Expr>>),
+//! }
+//! # fn main() {}
+//! ```
 
-#[macro_use]
-extern crate lazy_static;
 #[cfg(feature = "moniker-derive")]
 #[allow(unused_imports)]
 #[macro_use]
@@ -84,8 +1171,23 @@ extern crate moniker_derive;
 extern crate codespan;
 #[cfg(feature = "im")]
 extern crate im;
+#[cfg(feature = "lasso")]
+extern crate lasso;
 #[cfg(feature = "num-bigint")]
 extern crate num_bigint;
+#[cfg(feature = "proptest")]
+#[doc(hidden)]
+pub extern crate proptest;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "string-interner")]
+extern crate string_interner;
+
+// Optional dependencies
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "stacker")]
+extern crate stacker;
 
 #[cfg(feature = "moniker-derive")]
 #[doc(hidden)]
@@ -95,26 +1197,105 @@ pub use moniker_derive::*;
 #[doc(hidden)]
 pub mod macros;
 
+mod alpha_hash;
+mod alpha_mismatch;
+mod alpha_ord;
+mod ann_binder;
+#[cfg(feature = "proptest")]
+mod arbitrary;
+mod arena;
+mod bind_set;
 mod binder;
 mod bound;
 mod bound_var;
+#[cfg(feature = "serde")]
+mod canonical;
+mod closed;
+mod display;
 mod embed;
 mod free_var;
+mod fv;
+mod hash_cons;
+mod hashed;
+mod ident;
 mod ignore;
+mod instantiate;
+mod map_vars;
+mod meta_var;
+mod name_supply;
 mod nest;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod pattern_unifier;
+mod pointer;
+mod rebind;
 mod rec;
+mod record;
 mod scope;
+mod shadowing;
+mod small_vec;
+mod sorted;
+mod spanned;
+mod stack;
+mod subst;
+mod substitution;
+mod suspended;
+mod symbol;
 mod unique_id;
 mod var;
+mod visit;
+mod wildcard;
+mod zip;
 
+pub use self::alpha_hash::{unordered_alpha_hash, AlphaHash};
+pub use self::alpha_mismatch::{AlphaMismatch, Mismatched, PathSegment};
+pub use self::alpha_ord::{unordered_alpha_cmp, AlphaOrd};
+pub use self::ann_binder::AnnBinder;
+#[cfg(feature = "proptest")]
+pub use self::arbitrary::{ArbitraryEnv, ArbitraryTerm};
+pub use self::arena::{Arena, ArenaRef};
+pub use self::bind_set::BindSet;
 pub use self::binder::Binder;
-pub use self::bound::{BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+pub use self::bound::{
+    unordered_term_eq, BinderStack, BinderTable, BoundPattern, BoundTerm, OnBoundFn, OnFreeFn,
+    ScopeState,
+};
 pub use self::bound_var::{BinderIndex, BoundVar, ScopeOffset};
+pub use self::closed::Closed;
+pub use self::display::{fresh_name, DisplayEnv, DisplayTerm, TermDisplay};
 pub use self::embed::Embed;
 pub use self::free_var::FreeVar;
+pub use self::fv::Fv;
+pub use self::hash_cons::{HashCons, Interned};
+pub use self::hashed::Hashed;
+pub use self::ident::{Ident, Named};
 pub use self::ignore::Ignore;
+pub use self::instantiate::Instantiate;
+pub use self::map_vars::MapVars;
+pub use self::meta_var::MetaVar;
+#[cfg(feature = "u64-ids")]
+pub use self::name_supply::StableNameSupply;
+pub use self::name_supply::{GlobalNameSupply, NameSupply, SeededNameSupply};
 pub use self::nest::Nest;
+#[cfg(feature = "rayon")]
+pub use self::parallel::{par_free_vars, par_term_eq};
+pub use self::pattern_unifier::{PatternUnifier, PatternUnifyError};
+pub use self::pointer::Pointer;
+pub use self::rebind::Rebind;
 pub use self::rec::Rec;
-pub use self::scope::Scope;
-pub use self::unique_id::UniqueId;
+pub use self::record::Record;
+pub use self::scope::{DuplicateBinderError, LazyUnbound, Scope, UnbindError, UnboundRef, Usage};
+pub use self::shadowing::{Shadowed, Shadowing};
+pub use self::small_vec::SmallVec;
+pub use self::sorted::{OtherSort, Sorted};
+pub use self::spanned::Spanned;
+pub use self::stack::{maybe_grow_stack, with_stack_size};
+pub use self::subst::{Subst, SubstVar};
+pub use self::substitution::Substitution;
+pub use self::suspended::Suspended;
+pub use self::symbol::{GlobalInterner, Interner, Symbol};
+pub use self::unique_id::{with_isolated_ids, IdOverflow, IdStrategy, RawId, UniqueId};
 pub use self::var::Var;
+pub use self::visit::{ScopeVisitor, Visit};
+pub use self::wildcard::Wildcard;
+pub use self::zip::{UnifyError, Zip, Zipper};
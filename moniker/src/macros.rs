@@ -1,10 +1,11 @@
 //! Macros for use with the Moniker library
 
 /// Assert that two expressions are alpha equivalent to each other (using
-/// `BoundTerm::term_eq`).
+/// `BoundTerm::term_eq_check`).
 ///
 /// On panic, this macro will print the values of the expressions with their
-/// debug representations.
+/// debug representations, along with the path to the first subterms that
+/// differ.
 ///
 /// Like `assert!`, this macro has a second form, where a custom
 /// panic message can be provided.
@@ -13,10 +14,11 @@ macro_rules! assert_term_eq {
     ($left:expr, $right:expr) => ({
         match (&$left, &$right) {
             (left_val, right_val) => {
-                if !::moniker::BoundTerm::term_eq(left_val, right_val) {
+                if let Err(mismatch) = ::moniker::BoundTerm::term_eq_check(left_val, right_val) {
                     panic!(r#"assertion failed: `<_>::term_eq(&left, &right)`
   left: `{:?}`,
- right: `{:?}`"#, left_val, right_val)
+ right: `{:?}`,
+  diff: {}"#, left_val, right_val, mismatch)
                 }
             }
         }
@@ -27,10 +29,11 @@ macro_rules! assert_term_eq {
     ($left:expr, $right:expr, $($arg:tt)+) => ({
         match (&($left), &($right)) {
             (left_val, right_val) => {
-                if !::moniker::BoundTerm::term_eq(left_val, right_val) {
+                if let Err(mismatch) = ::moniker::BoundTerm::term_eq_check(left_val, right_val) {
                     panic!(r#"assertion failed: `<_>::term_eq(&left, &right)`
   left: `{:?}`,
- right: `{:?}`: {}"#, left_val, right_val,
+ right: `{:?}`,
+  diff: {}: {}"#, left_val, right_val, mismatch,
                            format_args!($($arg)+))
                 }
             }
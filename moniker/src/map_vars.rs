@@ -0,0 +1,461 @@
+use std::rc::Rc;
+use std::sync::Arc;
+
+use ann_binder::AnnBinder;
+use bind_set::BindSet;
+use binder::Binder;
+use embed::Embed;
+use free_var::FreeVar;
+use ignore::Ignore;
+use meta_var::MetaVar;
+use nest::Nest;
+use rebind::Rebind;
+use rec::Rec;
+use record::Record;
+use scope::Scope;
+use small_vec::SmallVec;
+use spanned::Spanned;
+use unique_id::{RawId, UniqueId};
+use var::Var;
+use wildcard::Wildcard;
+
+/// Map each variable and binder in a term, leaving the rest of its structure
+/// untouched
+///
+/// This is useful for passes like renaming or interning the names of
+/// variables, which would otherwise need their own traversals. This can be
+/// derived for your own types using `#[derive(MapVars)]`.
+///
+/// Note that bound variables are passed to `on_var` as well as free ones, and
+/// that bound variables refer to their binders by position, so changing the
+/// free variable of a binder will not change the variables that it binds.
+pub trait MapVars<N>: Sized {
+    /// Returns a copy of the term, with each variable replaced by the result of
+    /// `on_var`, and each binder replaced by the result of `on_binder`
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Self;
+
+    /// Returns a canonical representative of the alpha-equivalence class of
+    /// the term
+    ///
+    /// Each binder is replaced with a new free variable whose id is decided by
+    /// the binder's position in the term, leaving the free variables alone.
+    /// This means that alpha equivalent terms will be equal according to
+    /// `PartialEq` and have the same hash, so they can be used as keys in hash
+    /// maps and caches. The pretty names of the binders are kept, because they
+    /// are ignored when comparing and hashing variables.
+    ///
+    /// The ids are counted down from `RawId::MAX`, so that they are unlikely to
+    /// clash with the ones generated by `UniqueId::new`.
+    fn alpha_normalize(&self) -> Self
+    where
+        N: Clone,
+    {
+        let mut next_id = RawId::MAX;
+        self.map_vars(&mut |var| var.clone(), &mut |binder| {
+            let unique_id = UniqueId::from_raw(next_id);
+            next_id = next_id.wrapping_sub(1);
+            Binder(FreeVar {
+                unique_id,
+                pretty_name: binder.0.pretty_name.clone(),
+            })
+        })
+    }
+
+    /// Returns a copy of the term with each binder replaced by a fresh free
+    /// variable, so that no two binders in the term are the same
+    ///
+    /// Many compiler passes, like closure conversion, assume this convention.
+    /// Note that `Scope::unbind` freshens binders again as it unbinds them, so
+    /// use `Scope::unbind_with` to keep the binders that were chosen here.
+    fn uniquify(&self) -> Self
+    where
+        N: Clone,
+    {
+        self.uniquify_with_renaming().0
+    }
+
+    /// Returns a copy of the term with each binder replaced by a fresh free
+    /// variable, along with each of the original binders and their
+    /// replacements, in the order that they were found
+    #[allow(clippy::type_complexity)]
+    fn uniquify_with_renaming(&self) -> (Self, Vec<(Binder<N>, Binder<N>)>)
+    where
+        N: Clone,
+    {
+        let mut renaming = Vec::new();
+        let term = self.map_vars(&mut |var| var.clone(), &mut |binder| {
+            let fresh = Binder(FreeVar::fresh(binder.0.pretty_name.clone()));
+            renaming.push((binder.clone(), fresh.clone()));
+            fresh
+        });
+        (term, renaming)
+    }
+}
+
+impl<N> MapVars<N> for Var<N> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        _: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Var<N> {
+        on_var(self)
+    }
+}
+
+impl<N: Clone> MapVars<N> for FreeVar<N> {
+    fn map_vars(
+        &self,
+        _: &mut impl FnMut(&Var<N>) -> Var<N>,
+        _: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> FreeVar<N> {
+        self.clone()
+    }
+}
+
+impl<N: Clone> MapVars<N> for MetaVar<N> {
+    fn map_vars(
+        &self,
+        _: &mut impl FnMut(&Var<N>) -> Var<N>,
+        _: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> MetaVar<N> {
+        self.clone()
+    }
+}
+
+impl<N> MapVars<N> for Binder<N> {
+    fn map_vars(
+        &self,
+        _: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Binder<N> {
+        on_binder(self)
+    }
+}
+
+impl<N, A: Clone> MapVars<N> for AnnBinder<N, A> {
+    fn map_vars(
+        &self,
+        _: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> AnnBinder<N, A> {
+        AnnBinder {
+            binder: on_binder(&self.binder),
+            annotation: self.annotation.clone(),
+        }
+    }
+}
+
+impl<N> MapVars<N> for BindSet<N> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> BindSet<N> {
+        // The binders keep their positions, because the variables that they
+        // bind refer to them by index
+        BindSet {
+            unsafe_binders: self.unsafe_binders.map_vars(on_var, on_binder),
+        }
+    }
+}
+
+impl<N, T: Clone> MapVars<N> for Ignore<T> {
+    fn map_vars(
+        &self,
+        _: &mut impl FnMut(&Var<N>) -> Var<N>,
+        _: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Ignore<T> {
+        self.clone()
+    }
+}
+
+impl<N> MapVars<N> for Wildcard {
+    fn map_vars(
+        &self,
+        _: &mut impl FnMut(&Var<N>) -> Var<N>,
+        _: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Wildcard {
+        Wildcard
+    }
+}
+
+impl<N, T: MapVars<N>> MapVars<N> for Embed<T> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Embed<T> {
+        Embed(self.0.map_vars(on_var, on_binder))
+    }
+}
+
+impl<N, P: MapVars<N>> MapVars<N> for Nest<P> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Nest<P> {
+        Nest {
+            unsafe_patterns: self.unsafe_patterns.map_vars(on_var, on_binder),
+        }
+    }
+}
+
+impl<N, P1: MapVars<N>, P2: MapVars<N>> MapVars<N> for Rebind<P1, P2> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Rebind<P1, P2> {
+        Rebind {
+            unsafe_left: self.unsafe_left.map_vars(on_var, on_binder),
+            unsafe_right: self.unsafe_right.map_vars(on_var, on_binder),
+        }
+    }
+}
+
+impl<N, P: MapVars<N>> MapVars<N> for Rec<P> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Rec<P> {
+        Rec {
+            unsafe_pattern: self.unsafe_pattern.map_vars(on_var, on_binder),
+        }
+    }
+}
+
+impl<N, L: Clone, P: MapVars<N>> MapVars<N> for Record<L, P> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Record<L, P> {
+        Record {
+            unsafe_fields: self
+                .unsafe_fields
+                .iter()
+                .map(|field| (field.0.clone(), field.1.map_vars(on_var, on_binder)))
+                .collect(),
+        }
+    }
+}
+
+impl<N, P: MapVars<N>, T: MapVars<N>> MapVars<N> for Scope<P, T> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Scope<P, T> {
+        Scope {
+            unsafe_pattern: self.unsafe_pattern.map_vars(on_var, on_binder),
+            unsafe_body: self.unsafe_body.map_vars(on_var, on_binder),
+        }
+    }
+}
+
+impl<N, S: Clone, T: MapVars<N>> MapVars<N> for Spanned<S, T> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Spanned<S, T> {
+        Spanned {
+            span: self.span.clone(),
+            inner: self.inner.map_vars(on_var, on_binder),
+        }
+    }
+}
+
+// Implementations for common types
+
+macro_rules! impl_map_vars_clone {
+    ([$($lt:lifetime),*] $T:ty) => {
+        impl<$($lt,)* N> MapVars<N> for $T {
+            fn map_vars(
+                &self,
+                _: &mut impl FnMut(&Var<N>) -> Var<N>,
+                _: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+            ) -> $T {
+                self.clone()
+            }
+        }
+    };
+    ($T:ty) => {
+        impl_map_vars_clone!([] $T);
+    };
+}
+
+impl_map_vars_clone!(());
+impl_map_vars_clone!(String);
+impl_map_vars_clone!(['a] &'a str);
+impl_map_vars_clone!(Arc<str>);
+impl_map_vars_clone!(char);
+impl_map_vars_clone!(bool);
+impl_map_vars_clone!(u8);
+impl_map_vars_clone!(u16);
+impl_map_vars_clone!(u32);
+impl_map_vars_clone!(u64);
+impl_map_vars_clone!(usize);
+impl_map_vars_clone!(i8);
+impl_map_vars_clone!(i16);
+impl_map_vars_clone!(i32);
+impl_map_vars_clone!(i64);
+impl_map_vars_clone!(isize);
+impl_map_vars_clone!(f32);
+impl_map_vars_clone!(f64);
+
+impl<N, T: MapVars<N>> MapVars<N> for Option<T> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Option<T> {
+        self.as_ref().map(|inner| inner.map_vars(on_var, on_binder))
+    }
+}
+
+impl<N, T: MapVars<N>> MapVars<N> for Box<T> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Box<T> {
+        Box::new(T::map_vars(self, on_var, on_binder))
+    }
+}
+
+impl<N, T: MapVars<N>> MapVars<N> for Rc<T> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Rc<T> {
+        Rc::new(T::map_vars(self, on_var, on_binder))
+    }
+}
+
+impl<N, T: MapVars<N>> MapVars<N> for Arc<T> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Arc<T> {
+        Arc::new(T::map_vars(self, on_var, on_binder))
+    }
+}
+
+impl<N, T1: MapVars<N>, T2: MapVars<N>> MapVars<N> for (T1, T2) {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> (T1, T2) {
+        (
+            self.0.map_vars(on_var, on_binder),
+            self.1.map_vars(on_var, on_binder),
+        )
+    }
+}
+
+impl<N, T1, T2, T3> MapVars<N> for (T1, T2, T3)
+where
+    T1: MapVars<N>,
+    T2: MapVars<N>,
+    T3: MapVars<N>,
+{
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> (T1, T2, T3) {
+        (
+            self.0.map_vars(on_var, on_binder),
+            self.1.map_vars(on_var, on_binder),
+            self.2.map_vars(on_var, on_binder),
+        )
+    }
+}
+
+impl<N, T1, T2, T3, T4> MapVars<N> for (T1, T2, T3, T4)
+where
+    T1: MapVars<N>,
+    T2: MapVars<N>,
+    T3: MapVars<N>,
+    T4: MapVars<N>,
+{
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> (T1, T2, T3, T4) {
+        (
+            self.0.map_vars(on_var, on_binder),
+            self.1.map_vars(on_var, on_binder),
+            self.2.map_vars(on_var, on_binder),
+            self.3.map_vars(on_var, on_binder),
+        )
+    }
+}
+
+impl<N, T1, T2, T3, T4, T5> MapVars<N> for (T1, T2, T3, T4, T5)
+where
+    T1: MapVars<N>,
+    T2: MapVars<N>,
+    T3: MapVars<N>,
+    T4: MapVars<N>,
+    T5: MapVars<N>,
+{
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> (T1, T2, T3, T4, T5) {
+        (
+            self.0.map_vars(on_var, on_binder),
+            self.1.map_vars(on_var, on_binder),
+            self.2.map_vars(on_var, on_binder),
+            self.3.map_vars(on_var, on_binder),
+            self.4.map_vars(on_var, on_binder),
+        )
+    }
+}
+
+impl<N, T: MapVars<N>> MapVars<N> for Vec<T> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> Vec<T> {
+        self.iter()
+            .map(|elem| elem.map_vars(on_var, on_binder))
+            .collect()
+    }
+}
+
+impl<N, T: MapVars<N>> MapVars<N> for SmallVec<T> {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> SmallVec<T> {
+        self.iter()
+            .map(|elem| elem.map_vars(on_var, on_binder))
+            .collect()
+    }
+}
+
+impl<N, T: MapVars<N>, const LEN: usize> MapVars<N> for [T; LEN] {
+    fn map_vars(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> Var<N>,
+        on_binder: &mut impl FnMut(&Binder<N>) -> Binder<N>,
+    ) -> [T; LEN] {
+        self.each_ref().map(|elem| elem.map_vars(on_var, on_binder))
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use alpha_mismatch::PathSegment;
+use binder::Binder;
+use bound::{BinderStack, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use subst::Subst;
+use unique_id::UniqueId;
+use var::Var;
+
+/// A metavariable, standing in for a term that is not yet known
+///
+/// Metavariables are useful when implementing unification-based type
+/// checkers. Unlike free variables they are unaffected by binding, so they
+/// may be freely moved between scopes, and are only replaced with their
+/// solutions when calling `Instantiate::instantiate`.
+#[derive(Debug, Clone)]
+pub struct MetaVar<N> {
+    /// A generated id
+    pub unique_id: UniqueId,
+    /// programmer-provided name for pretty-printing
+    pub pretty_name: Option<N>,
+}
+
+impl<N> MetaVar<N> {
+    /// Create a fresh metavariable, with an optional name hint for pretty printing
+    pub fn fresh(pretty_name: Option<N>) -> MetaVar<N> {
+        MetaVar {
+            unique_id: UniqueId::new(),
+            pretty_name,
+        }
+    }
+
+    /// Create a fresh metavariable, with no name hint
+    pub fn fresh_unnamed() -> MetaVar<N> {
+        MetaVar::fresh(None)
+    }
+
+    /// Create a fresh metavariable, with a name hint for pretty printing
+    pub fn fresh_named(pretty_name: impl Into<N>) -> MetaVar<N> {
+        MetaVar::fresh(Some(pretty_name.into()))
+    }
+}
+
+impl<N> PartialEq for MetaVar<N>
+where
+    N: PartialEq,
+{
+    fn eq(&self, other: &MetaVar<N>) -> bool {
+        self.unique_id == other.unique_id
+    }
+}
+
+impl<N> Eq for MetaVar<N> where N: Eq {}
+
+impl<N> Hash for MetaVar<N>
+where
+    N: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.unique_id.hash(state);
+    }
+}
+
+impl<N: fmt::Display> fmt::Display for MetaVar<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.pretty_name {
+            None => write!(f, "?${}", self.unique_id),
+            Some(ref pretty_name) => write!(f, "?{}${}", pretty_name, self.unique_id),
+        }
+    }
+}
+
+impl<N: Clone + PartialEq> BoundTerm<N> for MetaVar<N> {
+    fn term_eq(&self, other: &MetaVar<N>) -> bool {
+        self == other
+    }
+
+    fn close_term(&mut self, _: ScopeState, _: &impl OnFreeFn<N>) {}
+
+    fn open_term(&mut self, _: ScopeState, _: &impl OnBoundFn<N>) {}
+
+    fn visit_vars(&self, _: &mut impl FnMut(&Var<N>)) {}
+
+    fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
+
+    fn visit_vars_with_binders(
+        &self,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+    }
+
+    fn visit_scopes(
+        &self,
+        _: &mut Vec<PathSegment<N>>,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+    }
+
+    fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
+}
+
+impl<N: Clone, T> Subst<N, T> for MetaVar<N> {
+    fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> MetaVar<N> {
+        self.clone()
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> MetaVar<N> {
+        self.clone()
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, _: &V, _: &T) {}
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, _: &[(V, T)]) {}
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Option<MetaVar<N>> {
+        None
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Option<MetaVar<N>> {
+        None
+    }
+}
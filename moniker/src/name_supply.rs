@@ -0,0 +1,155 @@
+#[cfg(feature = "u64-ids")]
+use std::collections::hash_map::DefaultHasher;
+#[cfg(feature = "u64-ids")]
+use std::hash::{Hash, Hasher};
+
+use unique_id::{RawId, UniqueId};
+
+/// The top two bits of an id, which are used to keep the ids of the supplies
+/// apart: they are clear in the ids of the global supply, `10` in the ids of
+/// `SeededNameSupply`, and `11` in the ids of `StableNameSupply`
+const TAG_BITS: RawId = 0b11 << (UniqueId::BITS - 2);
+
+/// The tag of the ids generated by `SeededNameSupply`
+const SEEDED_TAG: RawId = 0b10 << (UniqueId::BITS - 2);
+
+/// The tag of the ids generated by `StableNameSupply`
+#[cfg(feature = "u64-ids")]
+const STABLE_TAG: RawId = 0b11 << (UniqueId::BITS - 2);
+
+/// A source of unique ids for fresh variables
+///
+/// `FreeVar::fresh` and `Scope::unbind` draw their ids from a global counter,
+/// so the ids that a program generates depend on everything else that has
+/// been freshened before it, including in other threads. Passing an explicit
+/// supply to `FreeVar::fresh_from`, `Binder::fresh_from`, or one of the
+/// `_from` variants of the methods that unbind scopes, like
+/// `Scope::unbind_from`, instead makes the generated ids deterministic, which
+/// is useful for reproducible output and for replaying a session when
+/// debugging.
+pub trait NameSupply {
+    /// Generate the next id
+    fn next_id(&mut self) -> UniqueId;
+}
+
+impl<S: NameSupply + ?Sized> NameSupply for &mut S {
+    fn next_id(&mut self) -> UniqueId {
+        S::next_id(self)
+    }
+}
+
+/// The global supply of ids, which is used by `FreeVar::fresh`
+#[derive(Debug, Copy, Clone, Default)]
+pub struct GlobalNameSupply;
+
+impl NameSupply for GlobalNameSupply {
+    fn next_id(&mut self) -> UniqueId {
+        UniqueId::new()
+    }
+}
+
+/// A supply that numbers ids consecutively, starting from a seed
+///
+/// Supplies that are created with the same seed generate the same ids in the
+/// same order. The numbers are stored below a tag in the top two bits of the
+/// ids, which keeps them apart from the ids generated by the global supply
+/// and by `StableNameSupply`. Supplies with different seeds can still
+/// generate the same ids once one of them has counted up to the seed of the
+/// other.
+#[derive(Debug, Clone)]
+pub struct SeededNameSupply {
+    /// The number of the next id, without the tag
+    next_number: RawId,
+}
+
+impl SeededNameSupply {
+    /// Create a supply that starts numbering ids from `seed`
+    ///
+    /// The seed should fit in the bits below the tag, which leaves 30 bits,
+    /// or 62 bits with the `u64-ids` feature.
+    pub fn new(seed: RawId) -> SeededNameSupply {
+        SeededNameSupply { next_number: seed }
+    }
+}
+
+impl NameSupply for SeededNameSupply {
+    /// # Panics
+    ///
+    /// Panics if the supply runs out of numbers below the tag.
+    fn next_id(&mut self) -> UniqueId {
+        let number = Some(self.next_number)
+            .filter(|number| number & TAG_BITS == 0)
+            .expect("cannot generate any more ids from this supply");
+        self.next_number = number + 1;
+        UniqueId::from_raw(number | SEEDED_TAG)
+    }
+}
+
+/// A supply that derives its ids from a stable key, like the file and path of
+/// the definition that is being elaborated
+///
+/// Supplies that are created from the same key generate the same ids in the
+/// same order, even in different runs of the program, so re-elaborating an
+/// unchanged definition results in the same free variables. This lets caches
+/// that are keyed by variables stay valid between runs of an incremental
+/// compiler. The ids are hashes, with their top two bits set to keep them
+/// apart from the ids generated by the global supply and by
+/// `SeededNameSupply`, but they are only unlikely to clash with the ids from
+/// other keys, not guaranteed not to.
+/// The hashes are computed using `DefaultHasher`, which may change between
+/// releases of Rust, so ids should not be persisted across compiler upgrades.
+///
+/// This is enabled by the `u64-ids` feature, which leaves 62 bits for the
+/// hashes. Clashes then only become likely once billions of ids have been
+/// generated, but callers that need to rule them out entirely should check
+/// the ids that they generate against each other. With 32-bit ids the 30
+/// bits that are left would be expected to clash after tens of thousands of
+/// ids, which is too few to be useful.
+#[cfg(feature = "u64-ids")]
+#[derive(Debug, Clone)]
+pub struct StableNameSupply {
+    /// The hash of the key
+    seed: u64,
+    /// The number of ids that have been generated so far
+    next_index: u64,
+}
+
+#[cfg(feature = "u64-ids")]
+impl StableNameSupply {
+    /// Create a supply that derives its ids from `key`
+    pub fn new<K: Hash + ?Sized>(key: &K) -> StableNameSupply {
+        StableNameSupply {
+            seed: hash_of(&(0u64, key)),
+            next_index: 0,
+        }
+    }
+
+    /// Create a supply for a part of the key of this one, like a definition
+    /// that is nested inside of another
+    ///
+    /// The ids of the new supply only depend on the keys, not on how many ids
+    /// have been generated by this one.
+    pub fn child<K: Hash + ?Sized>(&self, key: &K) -> StableNameSupply {
+        StableNameSupply {
+            seed: hash_of(&(self.seed, key)),
+            next_index: 0,
+        }
+    }
+}
+
+#[cfg(feature = "u64-ids")]
+fn hash_of<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut state = DefaultHasher::new();
+    value.hash(&mut state);
+    state.finish()
+}
+
+#[cfg(feature = "u64-ids")]
+impl NameSupply for StableNameSupply {
+    fn next_id(&mut self) -> UniqueId {
+        let hash = hash_of(&(self.seed, self.next_index));
+        self.next_index += 1;
+        // Replace the top two bits with the tag, leaving the rest of the hash
+        UniqueId::from_raw((hash as RawId & !TAG_BITS) | STABLE_TAG)
+    }
+}
@@ -1,11 +1,16 @@
+use std::ops::ControlFlow;
+use std::slice;
+
+use alpha_mismatch::PathSegment;
 use binder::Binder;
-use bound::{BoundPattern, OnBoundFn, OnFreeFn, ScopeState};
+use bound::{BinderStack, BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use subst::Subst;
 use var::Var;
 
 /// Nested binding patterns
 ///
 /// Contrast with `Multi`
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Nest<P> {
     pub unsafe_patterns: Vec<P>,
 }
@@ -50,6 +55,58 @@ impl<P> Nest<P> {
 
         unnested_patterns
     }
+
+    /// The number of patterns in the nest
+    pub fn len(&self) -> usize {
+        self.unsafe_patterns.len()
+    }
+
+    /// Returns `true` if the nest contains no patterns
+    pub fn is_empty(&self) -> bool {
+        self.unsafe_patterns.is_empty()
+    }
+
+    /// Iterate over the patterns in the nest
+    ///
+    /// The patterns are returned as they are stored, so references to the
+    /// binders of earlier patterns will be bound variables. Prefer calling
+    /// `Nest::unnest` if you need to inspect them.
+    pub fn iter(&self) -> slice::Iter<'_, P> {
+        self.unsafe_patterns.iter()
+    }
+
+    /// Add a pattern to the end of the nest, binding the binders of the
+    /// existing patterns in it
+    pub fn push<N>(&mut self, mut pattern: P)
+    where
+        N: Clone + PartialEq,
+        P: BoundPattern<N>,
+    {
+        let binders = self
+            .unsafe_patterns
+            .iter()
+            .map(P::binders)
+            .collect::<Vec<_>>();
+
+        pattern.close_pattern(ScopeState::new(), &binders);
+        self.unsafe_patterns.push(pattern);
+    }
+
+    /// Append the patterns of another nest to the end of this one, binding
+    /// the binders of this nest in each of them
+    pub fn concat<N>(mut self, other: Nest<P>) -> Nest<P>
+    where
+        N: Clone + PartialEq,
+        P: BoundPattern<N>,
+    {
+        self.unsafe_patterns.reserve(other.len());
+        // Open the patterns first, so that the references between them are
+        // shifted past the patterns of this nest when they are closed again
+        for pattern in other.unnest() {
+            self.push(pattern);
+        }
+        self
+    }
 }
 
 impl<N, P> BoundPattern<N> for Nest<P>
@@ -79,10 +136,52 @@ where
         <[P]>::visit_vars(&self.unsafe_patterns, on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        <[P]>::try_visit_vars(&self.unsafe_patterns, on_var)
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         <[P]>::visit_mut_vars(&mut self.unsafe_patterns, on_var);
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        // Later patterns refer to the binders of the earlier patterns in the
+        // order that they were introduced, so each set of binders is entered
+        // below the ones that came before it
+        let depth = binders.depth();
+        for (i, elem) in self.unsafe_patterns.iter().enumerate() {
+            elem.visit_vars_with_binders(binders, on_var);
+            binders.insert(i, elem.binders());
+        }
+        binders.truncate(depth);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        // Later patterns refer to the binders of the earlier patterns in the
+        // order that they were introduced, so each set of binders is entered
+        // below the ones that came before it
+        let depth = binders.depth();
+        for (i, elem) in self.unsafe_patterns.iter().enumerate() {
+            path.push(PathSegment::Index(i));
+            elem.visit_scopes(path, binders, on_scope);
+            path.pop();
+            binders.insert(i, elem.binders());
+        }
+        binders.truncate(depth);
+    }
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         <[P]>::visit_binders(&self.unsafe_patterns, on_binder);
     }
@@ -90,4 +189,49 @@ where
     fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
         <[P]>::visit_mut_binders(&mut self.unsafe_patterns, on_binder);
     }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        <[P]>::order_binders(&mut self.unsafe_patterns, body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        <[P]>::visit_nodes(&self.unsafe_patterns, depth, on_node);
+    }
+}
+
+impl<N, T, P> Subst<N, T> for Nest<P>
+where
+    P: Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Nest<P> {
+        Nest {
+            unsafe_patterns: self.unsafe_patterns.subst(name, replacement),
+        }
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Nest<P> {
+        Nest {
+            unsafe_patterns: self.unsafe_patterns.substs(mappings),
+        }
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        self.unsafe_patterns.subst_mut(name, replacement);
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        self.unsafe_patterns.substs_mut(mappings);
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Option<Nest<P>> {
+        self.unsafe_patterns
+            .subst_changed(name, replacement)
+            .map(|unsafe_patterns| Nest { unsafe_patterns })
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<Nest<P>> {
+        self.unsafe_patterns
+            .substs_changed(mappings)
+            .map(|unsafe_patterns| Nest { unsafe_patterns })
+    }
 }
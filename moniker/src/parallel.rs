@@ -0,0 +1,58 @@
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use bound::BoundTerm;
+use free_var::FreeVar;
+
+/// The number of terms below which a slice is traversed on the current
+/// thread, and the smallest number of terms that are handed to each task in
+/// the thread pool
+const MIN_CHUNK_LEN: usize = 64;
+
+/// Compare two slices of terms for alpha equivalence, splitting the work
+/// between the threads of the `rayon` thread pool
+///
+/// This returns the same result as `BoundTerm::term_eq` on the slices, but
+/// compares the elements in parallel. It is intended for the wide nodes of
+/// large terms, like the definitions of a whole program, where each element
+/// is expensive to compare. Short slices are compared on the current thread.
+pub fn par_term_eq<N, T>(lhs: &[T], rhs: &[T]) -> bool
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N> + Sync,
+{
+    if lhs.len() != rhs.len() {
+        false
+    } else if lhs.len() < MIN_CHUNK_LEN {
+        <[T]>::term_eq(lhs, rhs)
+    } else {
+        lhs.par_iter()
+            .zip(rhs.par_iter())
+            .with_min_len(MIN_CHUNK_LEN)
+            .all(|(lhs, rhs)| T::term_eq(lhs, rhs))
+    }
+}
+
+/// Collect the free variables of a slice of terms, splitting the work between
+/// the threads of the `rayon` thread pool
+///
+/// This returns the same set as `BoundTerm::free_vars` on the slice. Short
+/// slices are traversed on the current thread.
+pub fn par_free_vars<N, T>(terms: &[T]) -> HashSet<FreeVar<N>>
+where
+    N: Clone + Eq + Hash + Send,
+    T: BoundTerm<N> + Sync,
+{
+    if terms.len() < MIN_CHUNK_LEN {
+        return terms.free_vars();
+    }
+
+    terms
+        .par_chunks(MIN_CHUNK_LEN)
+        .map(|chunk| chunk.free_vars())
+        .reduce(HashSet::new, |mut free_vars, chunk_free_vars| {
+            free_vars.extend(chunk_free_vars);
+            free_vars
+        })
+}
@@ -0,0 +1,285 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+use binder::Binder;
+use bound::{BoundTerm, ScopeState};
+use bound_var::{BoundVar, ScopeOffset};
+use free_var::FreeVar;
+use instantiate::Instantiate;
+use meta_var::MetaVar;
+use scope::Scope;
+use var::Var;
+use zip::{Zip, Zipper};
+
+/// Unification of terms in the higher-order pattern fragment
+///
+/// A pattern is a metavariable applied to distinct variables, like `?F x y`.
+/// Unifying a pattern with another term solves the metavariable with a
+/// function of its arguments, like `?F := \a b => f b a` when unifying
+/// `?F x y` with `f y x`. The solutions are stored as scopes that bind each of
+/// the arguments in turn.
+///
+/// The `as_pattern` function recognises the terms that are metavariables
+/// applied to variables, returning the metavariable and its arguments. A
+/// metavariable that is not applied to anything is a pattern with no
+/// arguments. Terms containing metavariables outside of the pattern fragment
+/// are compared structurally.
+///
+/// The arguments of a pattern may be bound by the surrounding terms, or may be
+/// free variables that were introduced when unbinding them. Solutions may only
+/// refer to the bound variables that are passed as arguments.
+#[derive(Debug, Clone)]
+pub struct PatternUnifier<N, T, F> {
+    as_pattern: F,
+    solutions: HashMap<MetaVar<N>, Scope<Vec<Binder<N>>, T>>,
+}
+
+impl<N, T, F> PatternUnifier<N, T, F>
+where
+    N: Clone + Eq + Hash,
+    T: BoundTerm<N> + Instantiate<N, T> + Zip<N, T> + Clone,
+    F: Fn(&T) -> Option<(MetaVar<N>, Vec<Var<N>>)>,
+{
+    /// Create a unifier with no solutions, using `as_pattern` to recognise
+    /// the patterns in the unified terms
+    pub fn new(as_pattern: F) -> PatternUnifier<N, T, F> {
+        PatternUnifier {
+            as_pattern,
+            solutions: HashMap::new(),
+        }
+    }
+
+    /// The solutions that have been found so far
+    ///
+    /// The solutions may refer to metavariables that were solved after them.
+    /// Use `PatternUnifier::instantiate` to replace these.
+    pub fn solutions(&self) -> &HashMap<MetaVar<N>, Scope<Vec<Binder<N>>, T>> {
+        &self.solutions
+    }
+
+    /// Lookup the solution to `meta`, with the other solutions applied to it
+    pub fn solution(&self, meta: &MetaVar<N>) -> Option<Scope<Vec<Binder<N>>, T>> {
+        let (binders, body) = self.solutions.get(meta)?.clone().unbind();
+        Some(Scope::new(binders, self.instantiate(&body)))
+    }
+
+    /// Unify two terms, adding the solutions to the metavariables
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the terms can't be unified. The solutions that
+    /// were found before the error was encountered are kept.
+    pub fn unify<U: Zip<N, T>>(&mut self, lhs: &U, rhs: &U) -> Result<(), PatternUnifyError<N>> {
+        let mut unify = Unify {
+            unifier: self,
+            error: None,
+        };
+        match lhs.zip_with(rhs, &mut unify) {
+            Some(_) => Ok(()),
+            None => Err(unify.error.unwrap_or(PatternUnifyError::Mismatch)),
+        }
+    }
+
+    /// Replace each solved pattern in the term with its solution
+    pub fn instantiate<U: Zip<N, T> + Clone>(&self, term: &U) -> U {
+        term.zip_with(term, &mut Instantiator { unifier: self })
+            .unwrap_or_else(|| term.clone())
+    }
+
+    /// Replace the pattern at the head of the term with its solution
+    fn instantiate_head(&self, term: &T) -> T {
+        let mut term = term.clone();
+        while let Some((meta, args)) = (self.as_pattern)(&term) {
+            match self.solutions.get(&meta) {
+                Some(solution) if solution.binder_count() == args.len() => {
+                    term = apply_solution(solution, &args);
+                },
+                Some(_) | None => break,
+            }
+        }
+        term
+    }
+
+    fn solve(
+        &mut self,
+        meta: MetaVar<N>,
+        args: Vec<Var<N>>,
+        term: &T,
+    ) -> Result<T, PatternUnifyError<N>> {
+        for (i, arg) in args.iter().enumerate() {
+            if args[..i].contains(arg) {
+                return Err(PatternUnifyError::NotPattern(meta));
+            }
+        }
+
+        let mut term = self.instantiate(term);
+        let mut occurs = false;
+        term.visit_metas(&mut |m| occurs |= *m == meta);
+        if occurs {
+            return Err(PatternUnifyError::Occurs(meta));
+        }
+
+        let binders = args
+            .iter()
+            .map(|arg| match *arg {
+                Var::Free(ref free_var) => Binder(free_var.clone()),
+                Var::Bound(ref bound_var) => Binder(FreeVar::fresh(bound_var.pretty_name.clone())),
+            })
+            .collect::<Vec<_>>();
+
+        // Replace the bound variables that refer to the arguments with the
+        // binders of the solution, and make sure no other variables escape
+        let solved = term.clone();
+        let escapes = Cell::new(false);
+        term.open_term(
+            ScopeState::new(),
+            &|state: ScopeState, bound_var: &BoundVar<N>| {
+                let ScopeOffset(depth) = state.depth();
+                if bound_var.scope().0 < depth {
+                    return None;
+                }
+                let escaping = Var::Bound(BoundVar::new(
+                    ScopeOffset(bound_var.scope().0 - depth),
+                    bound_var.binder(),
+                    bound_var.pretty_name.clone(),
+                ));
+                match args.iter().position(|arg| *arg == escaping) {
+                    Some(index) => Some(binders[index].0.clone()),
+                    None => {
+                        escapes.set(true);
+                        None
+                    },
+                }
+            },
+        );
+        if escapes.get() {
+            return Err(PatternUnifyError::Escape(meta));
+        }
+
+        self.solutions.insert(meta, Scope::new(binders, term));
+        Ok(solved)
+    }
+}
+
+/// Apply a solution to the arguments of a pattern
+fn apply_solution<N, T>(solution: &Scope<Vec<Binder<N>>, T>, args: &[Var<N>]) -> T
+where
+    N: Clone + Eq + Hash,
+    T: BoundTerm<N> + Clone,
+{
+    let (binders, mut body) = solution.clone().unbind();
+    for (binder, arg) in binders.iter().zip(args) {
+        if let Var::Free(ref free_var) = *arg {
+            body.rename_free_var(&binder.0, free_var);
+        }
+    }
+    body.close_term(
+        ScopeState::new(),
+        &|state: ScopeState, free_var: &FreeVar<N>| {
+            let index = binders.iter().position(|binder| binder.0 == *free_var)?;
+            match args[index] {
+                Var::Bound(ref bound_var) => Some(bound_var.clone().shift(state.depth().0)),
+                Var::Free(_) => None,
+            }
+        },
+    );
+    body
+}
+
+struct Unify<'a, N: 'a, T: 'a, F: 'a> {
+    unifier: &'a mut PatternUnifier<N, T, F>,
+    error: Option<PatternUnifyError<N>>,
+}
+
+impl<'a, N, T, F> Zipper<N, T> for Unify<'a, N, T, F>
+where
+    N: Clone + Eq + Hash,
+    T: BoundTerm<N> + Instantiate<N, T> + Zip<N, T> + Clone,
+    F: Fn(&T) -> Option<(MetaVar<N>, Vec<Var<N>>)>,
+{
+    fn zip_terms(&mut self, lhs: &T, rhs: &T) -> Option<T> {
+        let lhs = self.unifier.instantiate_head(lhs);
+        let rhs = self.unifier.instantiate_head(rhs);
+        if T::term_eq(&lhs, &rhs) {
+            return Some(lhs);
+        }
+
+        let solved = match (
+            (self.unifier.as_pattern)(&lhs),
+            (self.unifier.as_pattern)(&rhs),
+        ) {
+            (Some((meta, args)), _) => self.unifier.solve(meta, args, &rhs),
+            (None, Some((meta, args))) => self.unifier.solve(meta, args, &lhs),
+            (None, None) => return lhs.zip_structure(&rhs, self),
+        };
+        match solved {
+            Ok(term) => Some(term),
+            Err(error) => {
+                self.error = Some(error);
+                None
+            },
+        }
+    }
+}
+
+struct Instantiator<'a, N: 'a, T: 'a, F: 'a> {
+    unifier: &'a PatternUnifier<N, T, F>,
+}
+
+impl<'a, N, T, F> Zipper<N, T> for Instantiator<'a, N, T, F>
+where
+    N: Clone + Eq + Hash,
+    T: BoundTerm<N> + Instantiate<N, T> + Zip<N, T> + Clone,
+    F: Fn(&T) -> Option<(MetaVar<N>, Vec<Var<N>>)>,
+{
+    fn zip_terms(&mut self, term: &T, _: &T) -> Option<T> {
+        let term = self.unifier.instantiate_head(term);
+        Some(term.zip_structure(&term, self).unwrap_or(term))
+    }
+}
+
+/// An error produced when unifying two terms with a `PatternUnifier`
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternUnifyError<N> {
+    /// The terms have a different structure
+    Mismatch,
+    /// The metavariable would need to be solved with a term that contains it
+    Occurs(MetaVar<N>),
+    /// The metavariable would need to be solved with a term that refers to
+    /// bound variables that are not arguments of the pattern
+    Escape(MetaVar<N>),
+    /// The metavariable is applied to the same variable more than once
+    NotPattern(MetaVar<N>),
+}
+
+impl<N: fmt::Display> fmt::Display for PatternUnifyError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PatternUnifyError::Mismatch => {
+                write!(f, "cannot unify terms with a different structure")
+            },
+            PatternUnifyError::Occurs(ref meta) => write!(
+                f,
+                "cannot solve the metavariable `{}` with a term that contains it",
+                meta,
+            ),
+            PatternUnifyError::Escape(ref meta) => write!(
+                f,
+                "cannot solve the metavariable `{}` with a term that refers to bound \
+                 variables that are not its arguments",
+                meta,
+            ),
+            PatternUnifyError::NotPattern(ref meta) => write!(
+                f,
+                "cannot solve the metavariable `{}` when it is applied to the same \
+                 variable more than once",
+                meta,
+            ),
+        }
+    }
+}
+
+impl<N: fmt::Debug + fmt::Display> Error for PatternUnifyError<N> {}
@@ -0,0 +1,60 @@
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Smart pointers that subterms can be stored behind
+///
+/// `BoundTerm`, `BoundPattern`, and the other traits in this crate are
+/// implemented for `Box`, `Rc`, and `Arc`, so the pointer used by a syntax
+/// tree is usually picked with a type alias, like `type Ptr<T> = Arc<T>`.
+/// This trait allows helper functions to be written once for any of these
+/// pointers, instead of calling `Rc::new` or `Arc::make_mut` directly.
+pub trait Pointer<T>: Deref<Target = T> + Sized {
+    /// Move a value behind a new pointer
+    fn new(inner: T) -> Self;
+
+    /// Get a mutable reference to the value behind the pointer, cloning it if
+    /// it is shared with other pointers
+    fn make_mut(this: &mut Self) -> &mut T
+    where
+        T: Clone;
+}
+
+impl<T> Pointer<T> for Box<T> {
+    fn new(inner: T) -> Box<T> {
+        Box::new(inner)
+    }
+
+    fn make_mut(this: &mut Box<T>) -> &mut T
+    where
+        T: Clone,
+    {
+        this
+    }
+}
+
+impl<T> Pointer<T> for Rc<T> {
+    fn new(inner: T) -> Rc<T> {
+        Rc::new(inner)
+    }
+
+    fn make_mut(this: &mut Rc<T>) -> &mut T
+    where
+        T: Clone,
+    {
+        Rc::make_mut(this)
+    }
+}
+
+impl<T> Pointer<T> for Arc<T> {
+    fn new(inner: T) -> Arc<T> {
+        Arc::new(inner)
+    }
+
+    fn make_mut(this: &mut Arc<T>) -> &mut T
+    where
+        T: Clone,
+    {
+        Arc::make_mut(this)
+    }
+}
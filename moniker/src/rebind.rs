@@ -0,0 +1,196 @@
+use std::ops::ControlFlow;
+
+use alpha_mismatch::PathSegment;
+use binder::Binder;
+use bound::{BinderStack, BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use subst::Subst;
+use var::Var;
+
+/// A pair of patterns, where the second pattern is in the scope of the
+/// binders of the first
+///
+/// This is like a `Nest` of two patterns that may have different types. The
+/// binders of both patterns are bound by the enclosing scope.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rebind<P1, P2> {
+    pub unsafe_left: P1,
+    pub unsafe_right: P2,
+}
+
+impl<P1, P2> Rebind<P1, P2> {
+    /// Bind the binders of the left pattern in the right pattern
+    pub fn new<N>(left: P1, mut right: P2) -> Rebind<P1, P2>
+    where
+        N: Clone + PartialEq,
+        P1: BoundPattern<N>,
+        P2: BoundPattern<N>,
+    {
+        right.close_pattern(ScopeState::new(), &left.binders());
+        Rebind {
+            unsafe_left: left,
+            unsafe_right: right,
+        }
+    }
+
+    /// Unbind the patterns, returning the right pattern with its references
+    /// to the binders of the left pattern as free variables again
+    pub fn unrebind<N>(self) -> (P1, P2)
+    where
+        N: Clone + PartialEq,
+        P1: BoundPattern<N>,
+        P2: BoundPattern<N>,
+    {
+        let Rebind {
+            unsafe_left: left,
+            unsafe_right: mut right,
+        } = self;
+        right.open_pattern(ScopeState::new(), &left.binders());
+        (left, right)
+    }
+}
+
+impl<N, P1, P2> BoundPattern<N> for Rebind<P1, P2>
+where
+    N: Clone + PartialEq,
+    P1: BoundPattern<N>,
+    P2: BoundPattern<N>,
+{
+    fn pattern_eq(&self, other: &Rebind<P1, P2>) -> bool {
+        P1::pattern_eq(&self.unsafe_left, &other.unsafe_left)
+            && P2::pattern_eq(&self.unsafe_right, &other.unsafe_right)
+    }
+
+    fn close_pattern(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        self.unsafe_left.close_pattern(state, on_free);
+        self.unsafe_right.close_pattern(state.incr(), on_free);
+    }
+
+    fn open_pattern(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        self.unsafe_left.open_pattern(state, on_bound);
+        self.unsafe_right.open_pattern(state.incr(), on_bound);
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        self.unsafe_left.visit_vars(on_var);
+        self.unsafe_right.visit_vars(on_var);
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.unsafe_left.try_visit_vars(on_var)?;
+        self.unsafe_right.try_visit_vars(on_var)?;
+        ControlFlow::Continue(())
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        self.unsafe_left.visit_mut_vars(on_var);
+        self.unsafe_right.visit_mut_vars(on_var);
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.unsafe_left.visit_vars_with_binders(binders, on_var);
+        binders.push(self.unsafe_left.binders());
+        self.unsafe_right.visit_vars_with_binders(binders, on_var);
+        binders.pop();
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        self.unsafe_left.visit_scopes(path, binders, on_scope);
+        binders.push(self.unsafe_left.binders());
+        self.unsafe_right.visit_scopes(path, binders, on_scope);
+        binders.pop();
+    }
+
+    fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
+        self.unsafe_left.visit_binders(on_binder);
+        self.unsafe_right.visit_binders(on_binder);
+    }
+
+    fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
+        self.unsafe_left.visit_mut_binders(on_binder);
+        self.unsafe_right.visit_mut_binders(on_binder);
+    }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        self.unsafe_left.order_binders(body);
+        self.unsafe_right.order_binders(body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.unsafe_left.visit_nodes(depth, on_node);
+        self.unsafe_right.visit_nodes(depth, on_node);
+    }
+}
+
+impl<N, T, P1, P2> Subst<N, T> for Rebind<P1, P2>
+where
+    P1: Subst<N, T>,
+    P2: Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Rebind<P1, P2> {
+        Rebind {
+            unsafe_left: self.unsafe_left.subst(name, replacement),
+            unsafe_right: self.unsafe_right.subst(name, replacement),
+        }
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Rebind<P1, P2> {
+        Rebind {
+            unsafe_left: self.unsafe_left.substs(mappings),
+            unsafe_right: self.unsafe_right.substs(mappings),
+        }
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        self.unsafe_left.subst_mut(name, replacement);
+        self.unsafe_right.subst_mut(name, replacement);
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        self.unsafe_left.substs_mut(mappings);
+        self.unsafe_right.substs_mut(mappings);
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(
+        &self,
+        name: &V,
+        replacement: &T,
+    ) -> Option<Rebind<P1, P2>> {
+        match (
+            self.unsafe_left.subst_changed(name, replacement),
+            self.unsafe_right.subst_changed(name, replacement),
+        ) {
+            (None, None) => None,
+            (unsafe_left, unsafe_right) => Some(Rebind {
+                unsafe_left: unsafe_left
+                    .unwrap_or_else(|| self.unsafe_left.subst(name, replacement)),
+                unsafe_right: unsafe_right
+                    .unwrap_or_else(|| self.unsafe_right.subst(name, replacement)),
+            }),
+        }
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<Rebind<P1, P2>> {
+        match (
+            self.unsafe_left.substs_changed(mappings),
+            self.unsafe_right.substs_changed(mappings),
+        ) {
+            (None, None) => None,
+            (unsafe_left, unsafe_right) => Some(Rebind {
+                unsafe_left: unsafe_left.unwrap_or_else(|| self.unsafe_left.substs(mappings)),
+                unsafe_right: unsafe_right.unwrap_or_else(|| self.unsafe_right.substs(mappings)),
+            }),
+        }
+    }
+}
@@ -1,17 +1,22 @@
+use std::ops::ControlFlow;
+
+use alpha_mismatch::PathSegment;
 use binder::Binder;
-use bound::{BoundPattern, OnBoundFn, OnFreeFn, ScopeState};
+use bound::{BinderStack, BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use subst::Subst;
 use var::Var;
 
 /// Recursively bind a pattern in itself
 ///
 /// Mutually recursive bindings can be modelled by combining this type with
 /// the pattern implementations for `Vec<P>` and `(P1, P2)`.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Rec<P> {
     pub unsafe_pattern: P,
 }
 
 impl<P> Rec<P> {
+    /// Bind the binders of the pattern in the terms that are embedded in it
     pub fn new<N>(mut pattern: P) -> Rec<P>
     where
         N: Clone + PartialEq,
@@ -24,6 +29,8 @@ impl<P> Rec<P> {
         }
     }
 
+    /// Unbind the pattern, returning it with the embedded terms referring to
+    /// its binders as free variables again
     pub fn unrec<N>(mut self) -> P
     where
         N: Clone + PartialEq,
@@ -56,10 +63,40 @@ where
         self.unsafe_pattern.visit_vars(on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.unsafe_pattern.try_visit_vars(on_var)
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         self.unsafe_pattern.visit_mut_vars(on_var);
     }
 
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        // The binders of the pattern are in scope in its own embedded terms
+        binders.push(self.unsafe_pattern.binders());
+        self.unsafe_pattern.visit_vars_with_binders(binders, on_var);
+        binders.pop();
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        // The binders of the pattern are in scope in its own embedded terms
+        binders.push(self.unsafe_pattern.binders());
+        self.unsafe_pattern.visit_scopes(path, binders, on_scope);
+        binders.pop();
+    }
+
     fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
         self.unsafe_pattern.visit_binders(on_binder);
     }
@@ -67,4 +104,49 @@ where
     fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
         self.unsafe_pattern.visit_mut_binders(on_binder);
     }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        self.unsafe_pattern.order_binders(body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.unsafe_pattern.visit_nodes(depth, on_node);
+    }
+}
+
+impl<N, T, P> Subst<N, T> for Rec<P>
+where
+    P: Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Rec<P> {
+        Rec {
+            unsafe_pattern: self.unsafe_pattern.subst(name, replacement),
+        }
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Rec<P> {
+        Rec {
+            unsafe_pattern: self.unsafe_pattern.substs(mappings),
+        }
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        self.unsafe_pattern.subst_mut(name, replacement);
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        self.unsafe_pattern.substs_mut(mappings);
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Option<Rec<P>> {
+        self.unsafe_pattern
+            .subst_changed(name, replacement)
+            .map(|unsafe_pattern| Rec { unsafe_pattern })
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<Rec<P>> {
+        self.unsafe_pattern
+            .substs_changed(mappings)
+            .map(|unsafe_pattern| Rec { unsafe_pattern })
+    }
 }
@@ -0,0 +1,236 @@
+use std::ops::ControlFlow;
+use std::slice;
+
+use alpha_mismatch::PathSegment;
+use binder::Binder;
+use bound::{BinderStack, BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use subst::{changed_elems, Subst};
+use var::Var;
+
+/// A pattern made up of labelled fields, where the order that the fields were
+/// given in does not matter for alpha equality
+///
+/// This is useful for record and module patterns, like `{ x = a, y = b }`,
+/// where fields are matched by their labels rather than by their positions.
+/// The fields are sorted by their labels when the record is created, so
+/// each binder is always given the same index.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Record<L, P> {
+    /// The fields of the record, sorted by their labels
+    ///
+    /// You can access this directly, but only if you understand what you are
+    /// doing! Bound variables refer to the binders by their positions, so the
+    /// order must not be changed.
+    pub unsafe_fields: Vec<(L, P)>,
+}
+
+impl<L: Ord, P> Record<L, P> {
+    /// Create a record pattern from the given fields
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than one field has the same label.
+    pub fn new(fields: impl IntoIterator<Item = (L, P)>) -> Record<L, P> {
+        let mut fields = fields.into_iter().collect::<Vec<_>>();
+        fields.sort_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+
+        assert!(
+            fields.windows(2).all(|pair| pair[0].0 != pair[1].0),
+            "cannot create a record pattern with duplicate labels",
+        );
+
+        Record {
+            unsafe_fields: fields,
+        }
+    }
+
+    /// Lookup the pattern of the field with the given label
+    pub fn get(&self, label: &L) -> Option<&P> {
+        self.unsafe_fields
+            .binary_search_by(|field| field.0.cmp(label))
+            .ok()
+            .map(|index| &self.unsafe_fields[index].1)
+    }
+}
+
+impl<L, P> Record<L, P> {
+    /// The number of fields in the record
+    pub fn len(&self) -> usize {
+        self.unsafe_fields.len()
+    }
+
+    /// Returns `true` if the record has no fields
+    pub fn is_empty(&self) -> bool {
+        self.unsafe_fields.is_empty()
+    }
+
+    /// Iterate over the fields of the record, sorted by their labels
+    pub fn iter(&self) -> slice::Iter<'_, (L, P)> {
+        self.unsafe_fields.iter()
+    }
+
+    /// Returns the fields of the record, sorted by their labels
+    pub fn into_fields(self) -> Vec<(L, P)> {
+        self.unsafe_fields
+    }
+}
+
+impl<N, L, P> BoundPattern<N> for Record<L, P>
+where
+    N: Clone + PartialEq,
+    L: PartialEq,
+    P: BoundPattern<N>,
+{
+    fn pattern_eq(&self, other: &Record<L, P>) -> bool {
+        self.unsafe_fields.len() == other.unsafe_fields.len()
+            && <_>::zip(self.unsafe_fields.iter(), other.unsafe_fields.iter())
+                .all(|(lhs, rhs)| lhs.0 == rhs.0 && P::pattern_eq(&lhs.1, &rhs.1))
+    }
+
+    fn close_pattern(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        for field in &mut self.unsafe_fields {
+            field.1.close_pattern(state, on_free);
+        }
+    }
+
+    fn open_pattern(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        for field in &mut self.unsafe_fields {
+            field.1.open_pattern(state, on_bound);
+        }
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        for field in &self.unsafe_fields {
+            field.1.visit_vars(on_var);
+        }
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        for field in &self.unsafe_fields {
+            field.1.try_visit_vars(on_var)?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        for field in &mut self.unsafe_fields {
+            field.1.visit_mut_vars(on_var);
+        }
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        for field in &self.unsafe_fields {
+            field.1.visit_vars_with_binders(binders, on_var);
+        }
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        for (index, field) in self.unsafe_fields.iter().enumerate() {
+            path.push(PathSegment::Index(index));
+            field.1.visit_scopes(path, binders, on_scope);
+            path.pop();
+        }
+    }
+
+    fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
+        for field in &self.unsafe_fields {
+            field.1.visit_binders(on_binder);
+        }
+    }
+
+    fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
+        for field in &mut self.unsafe_fields {
+            field.1.visit_mut_binders(on_binder);
+        }
+    }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        for field in &mut self.unsafe_fields {
+            field.1.order_binders(body);
+        }
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        for field in &self.unsafe_fields {
+            field.1.visit_nodes(depth, on_node);
+        }
+    }
+}
+
+impl<N, T, L, P> Subst<N, T> for Record<L, P>
+where
+    L: Clone,
+    P: Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Record<L, P> {
+        Record {
+            unsafe_fields: self
+                .unsafe_fields
+                .iter()
+                .map(|field| (field.0.clone(), field.1.subst(name, replacement)))
+                .collect(),
+        }
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Record<L, P> {
+        Record {
+            unsafe_fields: self
+                .unsafe_fields
+                .iter()
+                .map(|field| (field.0.clone(), field.1.substs(mappings)))
+                .collect(),
+        }
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        for field in &mut self.unsafe_fields {
+            field.1.subst_mut(name, replacement);
+        }
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        for field in &mut self.unsafe_fields {
+            field.1.substs_mut(mappings);
+        }
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(
+        &self,
+        name: &V,
+        replacement: &T,
+    ) -> Option<Record<L, P>> {
+        let unsafe_fields = changed_elems(
+            &self.unsafe_fields,
+            |field| {
+                let value = field.1.subst_changed(name, replacement)?;
+                Some((field.0.clone(), value))
+            },
+            |field| (field.0.clone(), field.1.subst(name, replacement)),
+        )?;
+        Some(Record { unsafe_fields })
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<Record<L, P>> {
+        let unsafe_fields = changed_elems(
+            &self.unsafe_fields,
+            |field| {
+                let value = field.1.substs_changed(mappings)?;
+                Some((field.0.clone(), value))
+            },
+            |field| (field.0.clone(), field.1.substs(mappings)),
+        )?;
+        Some(Record { unsafe_fields })
+    }
+}
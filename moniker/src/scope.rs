@@ -1,34 +1,58 @@
+use std::error::Error;
+use std::fmt;
 use std::hash::Hash;
+use std::ops::ControlFlow;
 
+use alpha_mismatch::{AlphaMismatch, Mismatched, PathSegment};
 use binder::Binder;
-use bound::{BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use bound::{BinderStack, BinderTable, BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use bound_var::BoundVar;
 use free_var::FreeVar;
+use name_supply::{GlobalNameSupply, NameSupply};
+use subst::Subst;
 use var::Var;
 
+/// The number of binders that a pattern can have before `Scope::new` looks up
+/// the free variables of the body using a `BinderTable`, rather than scanning
+/// the binders
+const MAX_LINEAR_BINDERS: usize = 16;
+
 /// A bound scope
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// The pattern and body are kept private, because the bound variables in the
+/// body refer to the binders of the pattern by position. They can be taken
+/// apart using `Scope::unbind`, or rewritten in place using `Scope::map_body`
+/// and `Scope::map_pattern`. For the rare cases where direct access is needed,
+/// `Scope::from_raw_parts` and `Scope::into_raw_parts` are also provided.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Scope<P, T> {
     /// The pattern that binds the body of the scope
-    ///
-    /// You can access this directly, but only if you understand what you are
-    /// doing! Prefer calling `Scope::unbind` instead.
-    pub unsafe_pattern: P,
-    /// The body of the scope
-    ///
-    /// You can access this directly, but only if you understand what you are
-    /// doing! Prefer calling `Scope::unbind` instead.
-    pub unsafe_body: T,
+    pub(crate) unsafe_pattern: P,
+    /// The body of the scope, with its bound variables referring to the
+    /// binders of the pattern
+    pub(crate) unsafe_body: T,
 }
 
 impl<P, T> Scope<P, T> {
     /// Create a new scope by binding a term with the given pattern
-    pub fn new<N>(pattern: P, mut body: T) -> Scope<P, T>
+    ///
+    /// If the pattern binds the same free variable more than once, its
+    /// occurrences in the body are bound to the first of the binders, leaving
+    /// the later binders unused. Use `Scope::try_new` to reject these patterns
+    /// instead.
+    pub fn new<N>(mut pattern: P, mut body: T) -> Scope<P, T>
     where
         N: Clone + PartialEq,
         P: BoundPattern<N>,
         T: BoundTerm<N>,
     {
-        body.close_term(ScopeState::new(), &pattern.binders());
+        pattern.order_binders(&body);
+        let binders = pattern.binders();
+        if binders.len() > MAX_LINEAR_BINDERS {
+            body.close_term(ScopeState::new(), &BinderTable::new(&binders));
+        } else {
+            body.close_term(ScopeState::new(), &binders);
+        }
 
         Scope {
             unsafe_pattern: pattern,
@@ -36,8 +60,56 @@ impl<P, T> Scope<P, T> {
         }
     }
 
+    /// Create a new scope by binding a term with the given pattern, checking
+    /// that the pattern does not bind the same free variable more than once
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first binder that was repeated in the
+    /// pattern.
+    pub fn try_new<N>(pattern: P, body: T) -> Result<Scope<P, T>, DuplicateBinderError<N>>
+    where
+        N: Clone + PartialEq,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        check_distinct_binders(&pattern)?;
+        Ok(Scope::new(pattern, body))
+    }
+
     /// Unbind a term, returning the freshened pattern and body
     pub fn unbind<N>(self) -> (P, T)
+    where
+        N: Clone + Eq + Hash,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        self.unbind_from(&mut GlobalNameSupply)
+    }
+
+    /// Unbind a term, drawing the ids of the fresh binders from the given
+    /// supply
+    ///
+    /// Unbinding the same scope using supplies in the same state produces the
+    /// same binders, which is useful for making the output of a program
+    /// reproducible.
+    pub fn unbind_from<N>(self, supply: &mut impl NameSupply) -> (P, T)
+    where
+        N: Clone + Eq + Hash,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        self.unbind_with(|binder| Binder::fresh_from(supply, binder.0.pretty_name.clone()))
+    }
+
+    /// Unbind a term, using a function to generate the fresh binders
+    ///
+    /// The function is called with each of the binders of the pattern, in the
+    /// order that they appear. This lets type checkers control the numbering
+    /// of variables, for example by using a counter for each definition. The
+    /// returned binders must be distinct from each other, and from the other
+    /// free variables in the body.
+    pub fn unbind_with<N>(self, mut fresh: impl FnMut(&Binder<N>) -> Binder<N>) -> (P, T)
     where
         N: Clone + Eq + Hash,
         P: BoundPattern<N>,
@@ -47,19 +119,108 @@ impl<P, T> Scope<P, T> {
         let mut body = self.unsafe_body;
 
         // Freshen the pattern in preparation for opening
-        pattern.visit_mut_binders(&mut |binder| {
-            *binder = Binder(FreeVar::fresh(binder.0.pretty_name.clone()));
-        });
+        pattern.visit_mut_binders(&mut |binder| *binder = fresh(binder));
         // Use the freshened binders when opening the body
         body.open_term(ScopeState::new(), &pattern.binders());
 
         (pattern, body)
     }
 
+    /// Unbind a term without consuming or cloning the scope
+    ///
+    /// Fresh binders are generated for the pattern as in `Scope::unbind`, but
+    /// the body is left untouched, so its bound variables have to be looked
+    /// up using `UnboundRef::lookup`. This is useful for read-only analyses
+    /// over large terms, which can call `UnboundRef::into_owned` if they find
+    /// that they need the opened body after all.
+    pub fn unbind_ref<N>(&self) -> UnboundRef<'_, N, P, T>
+    where
+        N: Clone + Eq + Hash,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        self.unbind_ref_from(&mut GlobalNameSupply)
+    }
+
+    /// Unbind a term without consuming or cloning the scope, drawing the ids
+    /// of the fresh binders from the given supply
+    pub fn unbind_ref_from<N>(&self, supply: &mut impl NameSupply) -> UnboundRef<'_, N, P, T>
+    where
+        N: Clone + Eq + Hash,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        let mut binders = self.unsafe_pattern.binders();
+        for binder in &mut binders {
+            *binder = Binder::fresh_from(supply, binder.0.pretty_name.clone());
+        }
+
+        UnboundRef {
+            pattern: &self.unsafe_pattern,
+            binders,
+            body: &self.unsafe_body,
+        }
+    }
+
+    /// Unbind a term without freshening the pattern, returning a guard that
+    /// only generates fresh binders if the pattern and body escape it
+    ///
+    /// The body is opened using the binders that the pattern already has, so
+    /// code that unbinds a scope, rewrites the body, and binds it again using
+    /// `LazyUnbound::rebind` avoids generating fresh names and renaming the
+    /// body. If the binders of the pattern occur free in the body, for example
+    /// after substituting a term that mentions them, the pattern is freshened
+    /// first to avoid capturing them.
+    ///
+    /// Terms from outside of the scope that mention the original binders must
+    /// not be moved into the body before it is rebound, because they would be
+    /// captured by the pattern. Use `LazyUnbound::into_fresh` to get a pattern
+    /// and body that can be used anywhere.
+    pub fn unbind_lazy<N>(self) -> LazyUnbound<P, T>
+    where
+        N: Clone + Eq + Hash,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        self.unbind_lazy_from(&mut GlobalNameSupply)
+    }
+
+    /// Unbind a term without freshening the pattern, drawing the ids of the
+    /// fresh binders from the given supply if the pattern has to be freshened
+    /// up front
+    pub fn unbind_lazy_from<N>(self, supply: &mut impl NameSupply) -> LazyUnbound<P, T>
+    where
+        N: Clone + Eq + Hash,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        let mut pattern = self.unsafe_pattern;
+        let mut body = self.unsafe_body;
+
+        let captures = occurs_free(&pattern.binders(), &body);
+        if captures {
+            pattern.visit_mut_binders(&mut |binder| {
+                *binder = Binder::fresh_from(supply, binder.0.pretty_name.clone());
+            });
+        }
+        body.open_term(ScopeState::new(), &pattern.binders());
+
+        LazyUnbound {
+            pattern,
+            body,
+            is_fresh: captures,
+        }
+    }
+
     /// Simultaneously unbind two terms
     ///
     /// The fresh names in the first pattern with be used for the second pattern
-    pub fn unbind2<N, P2, T2>(self, other: Scope<P2, T2>) -> (P, T, P2, T2)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the patterns bind differing numbers of variables,
+    /// for example when they are alternative variants of an enum pattern.
+    pub fn unbind2<N, P2, T2>(self, other: Scope<P2, T2>) -> Result<(P, T, P2, T2), UnbindError>
     where
         N: Clone + Eq + Hash,
         P: BoundPattern<N>,
@@ -67,58 +228,723 @@ impl<P, T> Scope<P, T> {
         P2: BoundPattern<N>,
         T2: BoundTerm<N>,
     {
-        use std::collections::HashMap;
-
-        // FIXME: Just porting the behavior of Unbound here. Is there _any_ way
-        // we can make this faster and less allocation-heavy? :/
-
-        let mut self_pattern = self.unsafe_pattern;
-        let mut other_pattern = other.unsafe_pattern;
-        let mut self_body = self.unsafe_body;
-        let mut other_body = other.unsafe_body;
-
-        // Freshen the patterns
-        {
-            let self_binders = self_pattern.binders();
-            let other_binders = other_pattern.binders();
-
-            // Can't simultaneously unbind patterns of differing lengths!
-            // TODO: return an error rather than panicking?
-            assert_eq!(self_binders.len(), other_binders.len());
-
-            // Get the permutation that takes us from from the binders in
-            // `other_pattern` to the binders `self_pattern`
-            let other_to_self =
-                <_>::zip(other_binders.iter().cloned(), self_binders.iter().cloned())
-                    .collect::<HashMap<_, _>>();
-
-            // Freshen `self_pattern` while also getting the permutation that
-            // takes us from from the binders in `self_pattern` to the fresh
-            // binders
-            let mut self_to_fresh = HashMap::new();
-            self_pattern.visit_mut_binders(&mut |binder| {
-                let fresh = Binder(FreeVar::fresh(binder.0.pretty_name.clone()));
-                self_to_fresh.insert(binder.clone(), fresh.clone());
-                *binder = fresh;
-            });
+        check_binder_counts(
+            &self.unsafe_pattern,
+            &[other.unsafe_pattern.binders().len()],
+        )?;
 
-            // Apply the composition of the permutations in order to freshen
-            // the binders in `other_pattern`
-            other_pattern.visit_mut_binders(&mut |binder| {
-                if let Some(new_binder) = other_to_self.get(binder) {
-                    *binder = new_binder.clone();
-                }
-                if let Some(new_binder) = self_to_fresh.get(binder) {
-                    *binder = new_binder.clone();
+        let (self_pattern, self_body) = self.unbind();
+        let fresh_binders = self_pattern.binders();
+        let (other_pattern, other_body) = other.unbind_like(&fresh_binders);
+
+        Ok((self_pattern, self_body, other_pattern, other_body))
+    }
+
+    /// Simultaneously unbind three terms
+    ///
+    /// The fresh names in the first pattern will be used for the other patterns
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the patterns bind differing numbers of variables.
+    pub fn unbind3<N, P2, T2, P3, T3>(
+        self,
+        other2: Scope<P2, T2>,
+        other3: Scope<P3, T3>,
+    ) -> Result<(P, T, P2, T2, P3, T3), UnbindError>
+    where
+        N: Clone + Eq + Hash,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+        P2: BoundPattern<N>,
+        T2: BoundTerm<N>,
+        P3: BoundPattern<N>,
+        T3: BoundTerm<N>,
+    {
+        check_binder_counts(
+            &self.unsafe_pattern,
+            &[
+                other2.unsafe_pattern.binders().len(),
+                other3.unsafe_pattern.binders().len(),
+            ],
+        )?;
+
+        let (pattern1, body1) = self.unbind();
+        let fresh_binders = pattern1.binders();
+        let (pattern2, body2) = other2.unbind_like(&fresh_binders);
+        let (pattern3, body3) = other3.unbind_like(&fresh_binders);
+
+        Ok((pattern1, body1, pattern2, body2, pattern3, body3))
+    }
+
+    /// Simultaneously unbind any number of terms of the same type
+    ///
+    /// The fresh names in the first pattern will be used for the other patterns
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the patterns bind differing numbers of variables.
+    pub fn unbind_all<N>(
+        scopes: impl IntoIterator<Item = Scope<P, T>>,
+    ) -> Result<Vec<(P, T)>, UnbindError>
+    where
+        N: Clone + Eq + Hash,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        let mut scopes = scopes.into_iter();
+        let first = match scopes.next() {
+            None => return Ok(Vec::new()),
+            Some(first) => first,
+        };
+        let others = scopes.collect::<Vec<_>>();
+        let counts = others
+            .iter()
+            .map(|scope| scope.unsafe_pattern.binders().len())
+            .collect::<Vec<_>>();
+        check_binder_counts(&first.unsafe_pattern, &counts)?;
+
+        let (pattern, body) = first.unbind();
+        let fresh_binders = pattern.binders();
+        let mut unbound = vec![(pattern, body)];
+        unbound.extend(
+            others
+                .into_iter()
+                .map(|scope| scope.unbind_like(&fresh_binders)),
+        );
+
+        Ok(unbound)
+    }
+
+    /// Unbind a term using binders that have already been freshened, replacing
+    /// the binders of the pattern in the order that they appear
+    fn unbind_like<N>(self, fresh_binders: &[Binder<N>]) -> (P, T)
+    where
+        N: Clone + Eq + Hash,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        let mut fresh_binders = fresh_binders.iter();
+        self.unbind_with(|_| fresh_binders.next().unwrap().clone())
+    }
+}
+
+impl<P, T> Scope<P, T> {
+    /// Create a scope from a pattern and a body that has already been closed
+    /// over the binders of the pattern
+    ///
+    /// Only use this if you understand what you are doing! No variables are
+    /// bound in the body, so any variables that should refer to the pattern
+    /// must already be bound. This is useful for constructing terms that would
+    /// be awkward to build using `Scope::new`, like bodies that refer to
+    /// binders outside of the scope. Prefer calling `Scope::new` instead.
+    pub fn from_raw_parts(pattern: P, body: T) -> Scope<P, T> {
+        Scope {
+            unsafe_pattern: pattern,
+            unsafe_body: body,
+        }
+    }
+
+    /// Take apart a scope without unbinding it, returning the pattern and the
+    /// body with its bound variables still referring to the pattern
+    ///
+    /// Only use this if you understand what you are doing! The body can be
+    /// put back together with the pattern using `Scope::from_raw_parts`, but
+    /// its bound variables are meaningless on their own. Prefer calling
+    /// `Scope::unbind` instead.
+    pub fn into_raw_parts(self) -> (P, T) {
+        (self.unsafe_pattern, self.unsafe_body)
+    }
+
+    /// Apply a function to the body of the scope, without unbinding it
+    ///
+    /// This avoids generating fresh names for simple rewrites, like
+    /// substituting free variables. The body is passed to the function with
+    /// its bound variables still referring to the pattern, so the function must
+    /// not move the body's variables into other scopes, or return parts of the
+    /// body outside of the new scope.
+    pub fn map_body<U>(self, f: impl FnOnce(T) -> U) -> Scope<P, U> {
+        Scope {
+            unsafe_pattern: self.unsafe_pattern,
+            unsafe_body: f(self.unsafe_body),
+        }
+    }
+
+    /// Apply a function to the pattern of the scope, without unbinding it
+    ///
+    /// This is useful for rewriting the terms embedded in the pattern, like
+    /// type annotations. The bound variables in the body refer to the binders
+    /// of the pattern by position, so the function must leave the binders of
+    /// the pattern as they were.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function changes the binders of the pattern.
+    pub fn map_pattern<N, Q>(self, f: impl FnOnce(P) -> Q) -> Scope<Q, T>
+    where
+        N: Clone + PartialEq,
+        P: BoundPattern<N>,
+        Q: BoundPattern<N>,
+    {
+        let binders = self.unsafe_pattern.binders();
+        let pattern = f(self.unsafe_pattern);
+
+        assert!(
+            binders == pattern.binders(),
+            "cannot change the binders of a pattern when mapping over it",
+        );
+
+        Scope {
+            unsafe_pattern: pattern,
+            unsafe_body: self.unsafe_body,
+        }
+    }
+
+    /// Returns the number of variables bound by the pattern, without
+    /// unbinding the scope
+    ///
+    /// This is useful for arity checks, like when applying a function to some
+    /// arguments.
+    pub fn binder_count<N>(&self) -> usize
+    where
+        N: Clone + PartialEq,
+        P: BoundPattern<N>,
+    {
+        let mut count = 0;
+        self.unsafe_pattern.visit_binders(&mut |_| count += 1);
+        count
+    }
+
+    /// Returns the pretty names of the binders in the pattern, in the order
+    /// that they appear, without unbinding the scope
+    ///
+    /// Unlike `Scope::unbind`, this neither freshens the pattern nor opens the
+    /// body, which makes it cheap enough for pretty printers that only need
+    /// the names of the parameters. The names are only hints, so they might
+    /// clash with each other or with the free variables in the body.
+    pub fn binder_hints<N>(&self) -> Vec<Option<N>>
+    where
+        N: Clone + PartialEq,
+        P: BoundPattern<N>,
+    {
+        let mut hints = Vec::new();
+        self.unsafe_pattern.visit_binders(&mut |binder| {
+            hints.push(binder.0.pretty_name.clone());
+        });
+        hints
+    }
+
+    /// Returns how many times each of the binders of the pattern is used in
+    /// the body, in the order that they appear in the pattern
+    ///
+    /// This does not unbind the scope, and works for any body that implements
+    /// `BoundTerm`, so it can be used for reporting unused variables, checking
+    /// that variables are used linearly, or deciding whether to inline a
+    /// definition. Occurrences in the terms embedded in the pattern are not
+    /// included.
+    pub fn binder_usage<N>(&self) -> Vec<Usage>
+    where
+        N: Clone + PartialEq,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        let mut counts = vec![0; self.binder_count()];
+        let mut binders = BinderStack::new();
+        binders.push(self.unsafe_pattern.binders());
+
+        self.unsafe_body
+            .visit_vars_with_binders(&mut binders, &mut |binders, var| {
+                // Skip the variables that refer to the scopes inside the body
+                if let Var::Bound(ref bound_var) = *var {
+                    if bound_var.scope().0 as usize + 1 == binders.depth() {
+                        if let Some(count) = counts.get_mut(bound_var.binder().to_usize()) {
+                            *count += 1;
+                        }
+                    }
                 }
-            })
+            });
+
+        counts.into_iter().map(Usage::from_count).collect()
+    }
+
+    /// Replace the variables bound by the pattern with the given arguments,
+    /// returning the body
+    ///
+    /// The arguments are matched up with the binders in the order that they
+    /// appear in the pattern. This is like calling `Scope::unbind` and then
+    /// `Subst::substs` on the body, but avoids generating fresh names for the
+    /// binders only to immediately replace them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of arguments differs from the number of variables
+    /// bound by the pattern.
+    pub fn instantiates<N, R>(self, args: impl IntoIterator<Item = R>) -> T
+    where
+        N: Clone + PartialEq,
+        P: BoundPattern<N>,
+        T: BoundTerm<N> + Subst<N, R>,
+    {
+        let binders = self.unsafe_pattern.binders();
+        let args = args.into_iter().collect::<Vec<_>>();
+
+        assert!(
+            binders.len() == args.len(),
+            "cannot instantiate a scope with differing numbers of arguments: \
+             the pattern binds {} variables, but {} arguments were given",
+            binders.len(),
+            args.len(),
+        );
+        let mut body = self.unsafe_body;
+        let binders = opening_binders(binders, &body);
+        body.open_term(ScopeState::new(), &binders);
+
+        let mappings = <_>::zip(binders.into_iter(), args).collect::<Vec<_>>();
+        body.substs(&mappings)
+    }
+}
+
+impl<N, T> Scope<Binder<N>, T> {
+    /// Replace the variable bound by the binder with the given argument,
+    /// returning the body
+    ///
+    /// This is handy for beta reduction, and is like calling `Scope::unbind`
+    /// and then `Subst::subst` on the body, but avoids generating a fresh name
+    /// for the binder only to immediately replace it.
+    pub fn instantiate<R>(self, arg: &R) -> T
+    where
+        N: Clone + PartialEq,
+        T: BoundTerm<N> + Subst<N, R>,
+    {
+        let mut body = self.unsafe_body;
+        let binders = opening_binders(vec![self.unsafe_pattern], &body);
+        body.open_term(ScopeState::new(), &binders);
+        body.subst(&binders[0], arg)
+    }
+}
+
+impl<N, T> Scope<Vec<Binder<N>>, T> {
+    /// Create a new scope by binding the given free variables in a term
+    ///
+    /// Bound variables refer to the free variables in the order that they
+    /// were given. This is useful for lambda lifting, and for generalizing
+    /// the types of definitions during type inference.
+    pub fn abstract_over(
+        free_vars: impl IntoIterator<Item = FreeVar<N>>,
+        body: T,
+    ) -> Scope<Vec<Binder<N>>, T>
+    where
+        N: Clone + Eq + Hash,
+        T: BoundTerm<N>,
+    {
+        Scope::new(free_vars.into_iter().map(Binder).collect(), body)
+    }
+}
+
+/// Scopes that are directly nested inside other scopes, like the parameters
+/// of a curried function
+impl<P1, P2, T> Scope<P1, Scope<P2, T>> {
+    /// Create a pair of nested scopes in a single traversal of the body
+    ///
+    /// This is equivalent to `Scope::new(outer, Scope::new(inner, body))`,
+    /// which would close the body once for each of the patterns. The binders
+    /// of the inner pattern shadow the binders of the outer pattern.
+    pub fn new_nested<N>(mut outer: P1, mut inner: P2, body: T) -> Scope<P1, Scope<P2, T>>
+    where
+        N: Clone + PartialEq,
+        P1: BoundPattern<N>,
+        P2: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        inner.order_binders(&body);
+        // Neither the inner pattern nor the body have been closed yet, so the
+        // binders of the outer pattern are found wherever they occur in them,
+        // including where they are shadowed by the inner pattern
+        let nested = Scope {
+            unsafe_pattern: inner,
+            unsafe_body: body,
+        };
+        outer.order_binders(&nested);
+        let Scope {
+            unsafe_pattern: mut inner,
+            unsafe_body: mut body,
+        } = nested;
+
+        let outer_binders = outer.binders();
+        // The binders of each scope, starting from the innermost one
+        let binders = vec![inner.binders(), outer_binders.clone()];
+
+        inner.close_pattern(ScopeState::new(), &outer_binders);
+        body.close_term(ScopeState::new(), &binders);
+
+        Scope {
+            unsafe_pattern: outer,
+            unsafe_body: Scope {
+                unsafe_pattern: inner,
+                unsafe_body: body,
+            },
+        }
+    }
+
+    /// Unbind a pair of nested scopes in a single traversal of the body,
+    /// returning the freshened patterns and body
+    ///
+    /// This is equivalent to unbinding the outer scope, and then unbinding the
+    /// inner scope, which would open the body once for each of the patterns.
+    pub fn unbind_nested<N>(self) -> (P1, P2, T)
+    where
+        N: Clone + Eq + Hash,
+        P1: BoundPattern<N>,
+        P2: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        self.unbind_nested_from(&mut GlobalNameSupply)
+    }
+
+    /// Unbind a pair of nested scopes in a single traversal of the body,
+    /// drawing the ids of the fresh binders from the given supply
+    pub fn unbind_nested_from<N>(self, supply: &mut impl NameSupply) -> (P1, P2, T)
+    where
+        N: Clone + Eq + Hash,
+        P1: BoundPattern<N>,
+        P2: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        let mut fresh = |binder: &mut Binder<N>| {
+            *binder = Binder::fresh_from(supply, binder.0.pretty_name.clone());
+        };
+
+        let mut outer = self.unsafe_pattern;
+        let mut inner = self.unsafe_body.unsafe_pattern;
+        let mut body = self.unsafe_body.unsafe_body;
+
+        outer.visit_mut_binders(&mut fresh);
+        let outer_binders = outer.binders();
+        inner.open_pattern(ScopeState::new(), &outer_binders);
+        inner.visit_mut_binders(&mut fresh);
+
+        // The binders of each scope, starting from the innermost one
+        let binders = vec![inner.binders(), outer_binders];
+        body.open_term(ScopeState::new(), &binders);
+
+        (outer, inner, body)
+    }
+}
+
+/// Scopes that bind a single pattern over multiple bodies
+impl<P, T> Scope<P, Vec<T>> {
+    /// Unbind one of the bodies, returning the freshened pattern and body
+    /// without opening the other bodies
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn unbind_body<N>(mut self, index: usize) -> (P, T)
+    where
+        N: Clone + Eq + Hash,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        let body = self.unsafe_body.swap_remove(index);
+        Scope {
+            unsafe_pattern: self.unsafe_pattern,
+            unsafe_body: body,
+        }
+        .unbind()
+    }
+
+    /// Apply a function to each of the bodies, rebinding the results with the
+    /// same pattern
+    ///
+    /// The bodies are all unbound using the same fresh names, which are passed
+    /// to the function in the freshened pattern.
+    pub fn map_bodies<N, U>(self, mut f: impl FnMut(&P, T) -> U) -> Scope<P, Vec<U>>
+    where
+        N: Clone + Eq + Hash,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+        U: BoundTerm<N>,
+    {
+        let (pattern, bodies) = self.unbind();
+        let bodies = bodies.into_iter().map(|body| f(&pattern, body)).collect();
+        Scope::new(pattern, bodies)
+    }
+}
+
+/// Returns `true` if any of the `binders` occur free in `body`, for example
+/// after substituting a term that mentions them into the scope
+fn occurs_free<N, T>(binders: &[Binder<N>], body: &T) -> bool
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N>,
+{
+    let occurrence = body.try_visit_vars(&mut |var| match *var {
+        Var::Free(ref free_var) if binders.iter().any(|binder| binder.0 == *free_var) => {
+            ControlFlow::Break(())
+        },
+        Var::Free(_) | Var::Bound(_) => ControlFlow::Continue(()),
+    });
+    occurrence.is_break()
+}
+
+/// The binders to open `body` with before substituting for them
+///
+/// Opening the body with the binders of the pattern avoids generating fresh
+/// names, but if the binders also occur free in the body then the
+/// substitution would replace those occurrences too, so fresh binders are
+/// returned instead.
+pub(crate) fn opening_binders<N, T>(binders: Vec<Binder<N>>, body: &T) -> Vec<Binder<N>>
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N>,
+{
+    if !occurs_free(&binders, body) {
+        return binders;
+    }
+    binders
+        .into_iter()
+        .map(|binder| Binder(FreeVar::fresh(binder.0.pretty_name)))
+        .collect()
+}
+
+/// Check that each of the other patterns bind the same number of variables as
+/// the first pattern
+fn check_binder_counts<N, P>(first: &P, others: &[usize]) -> Result<(), UnbindError>
+where
+    N: Clone + PartialEq,
+    P: BoundPattern<N>,
+{
+    let first = first.binders().len();
+    match others.iter().find(|&&other| other != first) {
+        None => Ok(()),
+        Some(&second) => Err(UnbindError::BinderCountMismatch { first, second }),
+    }
+}
+
+/// Check that none of the binders of the pattern are repeated
+fn check_distinct_binders<N, P>(pattern: &P) -> Result<(), DuplicateBinderError<N>>
+where
+    N: Clone + PartialEq,
+    P: BoundPattern<N>,
+{
+    let binders = pattern.binders();
+    for (second, binder) in binders.iter().enumerate() {
+        if let Some(first) = binders[..second].iter().position(|other| other == binder) {
+            return Err(DuplicateBinderError {
+                binder: binder.clone(),
+                first,
+                second,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// How many times a binder is used, returned by `Scope::binder_usage`
+///
+/// The variants are ordered by the number of uses, so affine usage can be
+/// checked with `usage <= Usage::Once`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Usage {
+    /// The binder is never used
+    Unused,
+    /// The binder is used exactly once
+    Once,
+    /// The binder is used more than once
+    Many,
+}
+
+impl Usage {
+    /// The usage corresponding to a number of occurrences
+    pub fn from_count(count: usize) -> Usage {
+        match count {
+            0 => Usage::Unused,
+            1 => Usage::Once,
+            _ => Usage::Many,
+        }
+    }
+}
+
+/// An error produced when a pattern binds the same variable more than once,
+/// returned by `Scope::try_new`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateBinderError<N> {
+    /// The binder that was repeated
+    pub binder: Binder<N>,
+    /// The position of the first occurrence of the binder in the pattern
+    pub first: usize,
+    /// The position of the repeated occurrence of the binder in the pattern
+    pub second: usize,
+}
+
+impl<N: fmt::Display> fmt::Display for DuplicateBinderError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "cannot bind `{}` more than once in the same pattern: \
+             it is bound by both binder {} and binder {}",
+            self.binder, self.first, self.second,
+        )
+    }
+}
+
+impl<N: fmt::Debug + fmt::Display> Error for DuplicateBinderError<N> {}
+
+/// An error produced when simultaneously unbinding scopes
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnbindError {
+    /// The patterns of the scopes bind differing numbers of variables
+    BinderCountMismatch {
+        /// The number of variables bound by the first pattern
+        first: usize,
+        /// The number of variables bound by the other pattern that differed
+        /// from the first pattern
+        second: usize,
+    },
+}
+
+impl fmt::Display for UnbindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UnbindError::BinderCountMismatch { first, second } => write!(
+                f,
+                "cannot simultaneously unbind patterns with differing binders: \
+                 the first pattern binds {} variables, but the second binds {}",
+                first, second,
+            ),
+        }
+    }
+}
+
+impl Error for UnbindError {}
+
+/// A scope that has been unbound without cloning it, returned by
+/// `Scope::unbind_ref`
+#[derive(Debug, Clone)]
+pub struct UnboundRef<'a, N, P: 'a, T: 'a> {
+    /// The pattern of the scope, which still refers to the original binders
+    pub pattern: &'a P,
+    /// The fresh binders, in the order that they appear in the pattern
+    pub binders: Vec<Binder<N>>,
+    /// The body of the scope, which still refers to the pattern using bound
+    /// variables
+    pub body: &'a T,
+}
+
+impl<'a, N, P, T> UnboundRef<'a, N, P, T> {
+    /// Look up the fresh variable that a bound variable in the body refers to
+    ///
+    /// `state` should be incremented each time a scope is entered in the body,
+    /// in the same way as when opening terms. Returns `None` if the variable
+    /// is bound by another scope.
+    pub fn lookup(&self, state: ScopeState, bound_var: &BoundVar<N>) -> Option<FreeVar<N>>
+    where
+        N: Clone + PartialEq,
+    {
+        OnBoundFn::call(&self.binders, state, bound_var)
+    }
+
+    /// Clone the pattern and the body, opening them using the fresh binders
+    pub fn into_owned(self) -> (P, T)
+    where
+        N: Clone + PartialEq,
+        P: BoundPattern<N> + Clone,
+        T: BoundTerm<N> + Clone,
+    {
+        let mut pattern = self.pattern.clone();
+        let mut fresh_binders = self.binders.iter();
+        pattern.visit_mut_binders(&mut |binder| {
+            *binder = fresh_binders.next().unwrap().clone();
+        });
+
+        let mut body = self.body.clone();
+        body.open_term(ScopeState::new(), &self.binders);
+
+        (pattern, body)
+    }
+}
+
+/// A scope that has been unbound without freshening its pattern, returned by
+/// `Scope::unbind_lazy`
+#[derive(Debug, Clone)]
+pub struct LazyUnbound<P, T> {
+    /// The pattern of the scope
+    pattern: P,
+    /// The body of the scope, opened using the binders of the pattern
+    body: T,
+    /// Whether the binders of the pattern have already been freshened
+    is_fresh: bool,
+}
+
+impl<P, T> LazyUnbound<P, T> {
+    /// The pattern that the body was unbound with
+    pub fn pattern(&self) -> &P {
+        &self.pattern
+    }
+
+    /// The opened body
+    pub fn body(&self) -> &T {
+        &self.body
+    }
+
+    /// The opened body, for rewriting it before it is rebound
+    pub fn body_mut(&mut self) -> &mut T {
+        &mut self.body
+    }
+
+    /// Bind the body with the pattern again, without freshening it
+    pub fn rebind<N>(self) -> Scope<P, T>
+    where
+        N: Clone + PartialEq,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        Scope::new(self.pattern, self.body)
+    }
+
+    /// Freshen the pattern, returning the pattern and body as if they had been
+    /// unbound using `Scope::unbind`
+    pub fn into_fresh<N>(self) -> (P, T)
+    where
+        N: Clone + Eq + Hash,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        self.into_fresh_from(&mut GlobalNameSupply)
+    }
+
+    /// Freshen the pattern, drawing the ids of the fresh binders from the
+    /// given supply
+    pub fn into_fresh_from<N>(self, supply: &mut impl NameSupply) -> (P, T)
+    where
+        N: Clone + Eq + Hash,
+        P: BoundPattern<N>,
+        T: BoundTerm<N>,
+    {
+        let mut pattern = self.pattern;
+        let mut body = self.body;
+        if self.is_fresh {
+            return (pattern, body);
         }
 
-        // Finally, use the binders from the freshened patterns to open the body terms
-        self_body.open_term(ScopeState::new(), &self_pattern.binders());
-        other_body.open_term(ScopeState::new(), &other_pattern.binders());
+        let binders = pattern.binders();
+        pattern.visit_mut_binders(&mut |binder| {
+            *binder = Binder::fresh_from(supply, binder.0.pretty_name.clone());
+        });
+        let fresh_binders = pattern.binders();
+
+        // The body was opened using the original binders, so their occurrences
+        // are renamed to the fresh binders in the same position
+        body.visit_mut_vars(&mut |var| {
+            if let Var::Free(ref mut free_var) = *var {
+                if let Some(index) = binders.iter().position(|binder| binder.0 == *free_var) {
+                    *free_var = fresh_binders[index].0.clone();
+                }
+            }
+        });
 
-        (self_pattern, self_body, other_pattern, other_body)
+        (pattern, body)
     }
 }
 
@@ -133,6 +959,22 @@ where
             && T::term_eq(&self.unsafe_body, &other.unsafe_body)
     }
 
+    fn term_eq_check(&self, other: &Scope<P, T>) -> Result<(), AlphaMismatch<N>> {
+        if !P::pattern_eq(&self.unsafe_pattern, &other.unsafe_pattern) {
+            let lhs_len = self.unsafe_pattern.binders().len();
+            let rhs_len = other.unsafe_pattern.binders().len();
+            let mismatch = if lhs_len == rhs_len {
+                AlphaMismatch::new(Mismatched::Value, Mismatched::Value)
+            } else {
+                AlphaMismatch::new(Mismatched::Binders(lhs_len), Mismatched::Binders(rhs_len))
+            };
+            return Err(mismatch.within(PathSegment::Pattern));
+        }
+
+        T::term_eq_check(&self.unsafe_body, &other.unsafe_body)
+            .map_err(|mismatch| mismatch.within(PathSegment::Body(self.unsafe_pattern.binders())))
+    }
+
     fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
         self.unsafe_pattern.close_pattern(state, on_free);
         self.unsafe_body.close_term(state.incr(), on_free);
@@ -148,8 +990,116 @@ where
         self.unsafe_body.visit_vars(on_var);
     }
 
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.unsafe_pattern.try_visit_vars(on_var)?;
+        self.unsafe_body.try_visit_vars(on_var)?;
+        ControlFlow::Continue(())
+    }
+
     fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
         self.unsafe_pattern.visit_mut_vars(on_var);
         self.unsafe_body.visit_mut_vars(on_var);
     }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.unsafe_pattern.visit_vars_with_binders(binders, on_var);
+        binders.push(self.unsafe_pattern.binders());
+        self.unsafe_body.visit_vars_with_binders(binders, on_var);
+        binders.pop();
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        let pattern_binders = self.unsafe_pattern.binders();
+        on_scope(path, binders, &pattern_binders);
+
+        path.push(PathSegment::Pattern);
+        self.unsafe_pattern.visit_scopes(path, binders, on_scope);
+        path.pop();
+
+        path.push(PathSegment::Body(pattern_binders.clone()));
+        binders.push(pattern_binders);
+        self.unsafe_body.visit_scopes(path, binders, on_scope);
+        binders.pop();
+        path.pop();
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.unsafe_pattern.visit_nodes(depth, on_node);
+        self.unsafe_body.visit_nodes(depth, on_node);
+    }
+}
+
+impl<N, R, P, T> Subst<N, R> for Scope<P, T>
+where
+    P: Subst<N, R>,
+    T: Subst<N, R>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &R) -> Scope<P, T> {
+        Scope {
+            unsafe_pattern: self.unsafe_pattern.subst(name, replacement),
+            unsafe_body: self.unsafe_body.subst(name, replacement),
+        }
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, R)]) -> Scope<P, T> {
+        Scope {
+            unsafe_pattern: self.unsafe_pattern.substs(mappings),
+            unsafe_body: self.unsafe_body.substs(mappings),
+        }
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &R) {
+        self.unsafe_pattern.subst_mut(name, replacement);
+        self.unsafe_body.subst_mut(name, replacement);
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, R)]) {
+        self.unsafe_pattern.substs_mut(mappings);
+        self.unsafe_body.substs_mut(mappings);
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(
+        &self,
+        name: &V,
+        replacement: &R,
+    ) -> Option<Scope<P, T>> {
+        match (
+            self.unsafe_pattern.subst_changed(name, replacement),
+            self.unsafe_body.subst_changed(name, replacement),
+        ) {
+            (None, None) => None,
+            (unsafe_pattern, unsafe_body) => Some(Scope {
+                unsafe_pattern: unsafe_pattern
+                    .unwrap_or_else(|| self.unsafe_pattern.subst(name, replacement)),
+                unsafe_body: unsafe_body
+                    .unwrap_or_else(|| self.unsafe_body.subst(name, replacement)),
+            }),
+        }
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, R)]) -> Option<Scope<P, T>> {
+        match (
+            self.unsafe_pattern.substs_changed(mappings),
+            self.unsafe_body.substs_changed(mappings),
+        ) {
+            (None, None) => None,
+            (unsafe_pattern, unsafe_body) => Some(Scope {
+                unsafe_pattern: unsafe_pattern
+                    .unwrap_or_else(|| self.unsafe_pattern.substs(mappings)),
+                unsafe_body: unsafe_body.unwrap_or_else(|| self.unsafe_body.substs(mappings)),
+            }),
+        }
+    }
 }
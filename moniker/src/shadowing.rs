@@ -0,0 +1,26 @@
+use alpha_mismatch::PathSegment;
+use binder::Binder;
+use free_var::FreeVar;
+
+/// A binder that shadows an earlier binding with the same pretty name,
+/// returned by `BoundTerm::shadowing`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shadowing<N> {
+    /// The path from the root of the term to the scope whose pattern contains
+    /// the binder
+    pub path: Vec<PathSegment<N>>,
+    /// The binder that shadows the earlier binding
+    pub binder: Binder<N>,
+    /// The binding that is shadowed
+    pub shadowed: Shadowed<N>,
+}
+
+/// A binding that has been shadowed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shadowed<N> {
+    /// A binder of an enclosing scope, or an earlier binder in the same
+    /// pattern
+    Binder(Binder<N>),
+    /// One of the free variables that the term was checked against
+    FreeVar(FreeVar<N>),
+}
@@ -0,0 +1,184 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::slice;
+use std::vec;
+
+/// A vector that stores up to two elements inline
+///
+/// Most patterns only bind one or two variables, so using
+/// `Scope<SmallVec<Binder<N>>, T>` in place of `Scope<Vec<Binder<N>>, T>`
+/// avoids allocating a vector for each function or let binding, while still
+/// supporting any number of binders. Elements are accessed through the slice
+/// that this dereferences to.
+#[derive(Clone)]
+pub struct SmallVec<T> {
+    repr: Repr<T>,
+}
+
+#[derive(Clone)]
+enum Repr<T> {
+    /// A single element, stored inline
+    One([T; 1]),
+    /// Two elements, stored inline
+    Two([T; 2]),
+    /// Any other number of elements, stored on the heap
+    ///
+    /// Empty vectors do not allocate, so they are stored here too.
+    Heap(Vec<T>),
+}
+
+impl<T> SmallVec<T> {
+    /// Create an empty vector
+    pub fn new() -> SmallVec<T> {
+        SmallVec {
+            repr: Repr::Heap(Vec::new()),
+        }
+    }
+
+    /// Append an element to the end of the vector, moving the elements onto
+    /// the heap if there is no more room for them inline
+    pub fn push(&mut self, elem: T) {
+        self.repr = match mem::replace(&mut self.repr, Repr::Heap(Vec::new())) {
+            Repr::Heap(ref elems) if elems.is_empty() => Repr::One([elem]),
+            Repr::One([first]) => Repr::Two([first, elem]),
+            Repr::Two([first, second]) => Repr::Heap(vec![first, second, elem]),
+            Repr::Heap(mut elems) => {
+                elems.push(elem);
+                Repr::Heap(elems)
+            },
+        };
+    }
+
+    /// Returns `true` if the elements are stored inline, without allocating
+    pub fn is_inline(&self) -> bool {
+        match self.repr {
+            Repr::One(_) | Repr::Two(_) => true,
+            Repr::Heap(ref elems) => elems.capacity() == 0,
+        }
+    }
+
+    /// Move the elements into a `Vec`
+    pub fn into_vec(self) -> Vec<T> {
+        match self.repr {
+            Repr::One(elems) => Vec::from(elems),
+            Repr::Two(elems) => Vec::from(elems),
+            Repr::Heap(elems) => elems,
+        }
+    }
+}
+
+impl<T> Default for SmallVec<T> {
+    fn default() -> SmallVec<T> {
+        SmallVec::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SmallVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// The comparisons are implemented using the slices of elements, so that they
+// do not depend on how the elements are stored
+
+impl<T: PartialEq> PartialEq for SmallVec<T> {
+    fn eq(&self, other: &SmallVec<T>) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: Eq> Eq for SmallVec<T> {}
+
+impl<T: PartialOrd> PartialOrd for SmallVec<T> {
+    fn partial_cmp(&self, other: &SmallVec<T>) -> Option<Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<T: Ord> Ord for SmallVec<T> {
+    fn cmp(&self, other: &SmallVec<T>) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<T: Hash> Hash for SmallVec<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+impl<T> Deref for SmallVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self.repr {
+            Repr::One(ref elems) => elems,
+            Repr::Two(ref elems) => elems,
+            Repr::Heap(ref elems) => elems,
+        }
+    }
+}
+
+impl<T> DerefMut for SmallVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        match self.repr {
+            Repr::One(ref mut elems) => elems,
+            Repr::Two(ref mut elems) => elems,
+            Repr::Heap(ref mut elems) => elems,
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for SmallVec<T> {
+    fn from(src: Vec<T>) -> SmallVec<T> {
+        if src.len() > 2 {
+            SmallVec {
+                repr: Repr::Heap(src),
+            }
+        } else {
+            src.into_iter().collect()
+        }
+    }
+}
+
+impl<T> FromIterator<T> for SmallVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> SmallVec<T> {
+        let mut elems = SmallVec::new();
+        for elem in iter {
+            elems.push(elem);
+        }
+        elems
+    }
+}
+
+impl<T> IntoIterator for SmallVec<T> {
+    type Item = T;
+    type IntoIter = vec::IntoIter<T>;
+
+    fn into_iter(self) -> vec::IntoIter<T> {
+        self.into_vec().into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SmallVec<T> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut SmallVec<T> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> slice::IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
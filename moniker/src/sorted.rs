@@ -0,0 +1,230 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use alpha_mismatch::PathSegment;
+use binder::Binder;
+use bound::{BinderStack, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use subst::Subst;
+use var::Var;
+
+/// A name that is tagged with the sort of variable that it names
+///
+/// Languages with more than one namespace, like separate term and type
+/// variables, can give each namespace its own marker type:
+///
+/// ```rust
+/// use moniker::{FreeVar, Sorted};
+///
+/// enum TermSort {}
+/// enum TypeSort {}
+///
+/// type TermName = Sorted<String, TermSort>;
+/// type TypeName = Sorted<String, TypeSort>;
+///
+/// let x = FreeVar::<TermName>::fresh_named("x");
+/// let a = FreeVar::<TypeName>::fresh_named("a");
+/// ```
+///
+/// A `FreeVar<TermName>` then cannot be confused with a `FreeVar<TypeName>`,
+/// and because terms are only bound over one type of name at a time, binding
+/// and unbinding a `Scope<Binder<TypeName>, T>` only touches the type
+/// variables of `T`. Terms of one sort can be embedded in the terms of
+/// another sort using `OtherSort`.
+pub struct Sorted<N, S> {
+    /// The name of the variable
+    pub name: N,
+    sort: PhantomData<fn() -> S>,
+}
+
+impl<N, S> Sorted<N, S> {
+    /// Tag a name with a sort
+    pub fn new(name: N) -> Sorted<N, S> {
+        Sorted {
+            name,
+            sort: PhantomData,
+        }
+    }
+}
+
+impl<N: fmt::Debug, S> fmt::Debug for Sorted<N, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.name, f)
+    }
+}
+
+impl<N: fmt::Display, S> fmt::Display for Sorted<N, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.name, f)
+    }
+}
+
+impl<N: Clone, S> Clone for Sorted<N, S> {
+    fn clone(&self) -> Sorted<N, S> {
+        Sorted::new(self.name.clone())
+    }
+}
+
+impl<N: Copy, S> Copy for Sorted<N, S> {}
+
+impl<N: PartialEq, S> PartialEq for Sorted<N, S> {
+    fn eq(&self, other: &Sorted<N, S>) -> bool {
+        self.name == other.name
+    }
+}
+
+impl<N: Eq, S> Eq for Sorted<N, S> {}
+
+impl<N: PartialOrd, S> PartialOrd for Sorted<N, S> {
+    fn partial_cmp(&self, other: &Sorted<N, S>) -> Option<Ordering> {
+        self.name.partial_cmp(&other.name)
+    }
+}
+
+impl<N: Ord, S> Ord for Sorted<N, S> {
+    fn cmp(&self, other: &Sorted<N, S>) -> Ordering {
+        self.name.cmp(&other.name)
+    }
+}
+
+impl<N: Hash, S> Hash for Sorted<N, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
+
+impl<'a, S> From<&'a str> for Sorted<String, S> {
+    fn from(src: &'a str) -> Sorted<String, S> {
+        Sorted::new(String::from(src))
+    }
+}
+
+impl<S> From<String> for Sorted<String, S> {
+    fn from(src: String) -> Sorted<String, S> {
+        Sorted::new(src)
+    }
+}
+
+/// A term whose variables are named by `M`, embedded in a term whose
+/// variables are named by some other type of name
+///
+/// For example, the type annotations of a term can be stored as
+/// `OtherSort<TypeName, RcType>` in a term over `TermName`. Binding,
+/// unbinding, and substituting term variables skips over the annotations,
+/// which are still compared using their own alpha equivalence. This must
+/// only be used between different sorts, because the variables in the
+/// embedded term are never bound by the scopes of the enclosing term.
+pub struct OtherSort<M, T> {
+    /// The embedded term
+    pub term: T,
+    sort: PhantomData<fn() -> M>,
+}
+
+impl<M, T> OtherSort<M, T> {
+    /// Embed a term of another sort
+    pub fn new(term: T) -> OtherSort<M, T> {
+        OtherSort {
+            term,
+            sort: PhantomData,
+        }
+    }
+
+    /// Returns the embedded term
+    pub fn into_inner(self) -> T {
+        self.term
+    }
+}
+
+impl<M, T: fmt::Debug> fmt::Debug for OtherSort<M, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("OtherSort").field(&self.term).finish()
+    }
+}
+
+impl<M, T: Clone> Clone for OtherSort<M, T> {
+    fn clone(&self) -> OtherSort<M, T> {
+        OtherSort::new(self.term.clone())
+    }
+}
+
+impl<M, T: PartialEq> PartialEq for OtherSort<M, T> {
+    fn eq(&self, other: &OtherSort<M, T>) -> bool {
+        self.term == other.term
+    }
+}
+
+impl<M, T: Eq> Eq for OtherSort<M, T> {}
+
+impl<M, T: Hash> Hash for OtherSort<M, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.term.hash(state);
+    }
+}
+
+impl<M, T> Deref for OtherSort<M, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.term
+    }
+}
+
+impl<N, M, T> BoundTerm<N> for OtherSort<M, T>
+where
+    N: Clone + PartialEq,
+    M: Clone + PartialEq,
+    T: BoundTerm<M>,
+{
+    fn term_eq(&self, other: &OtherSort<M, T>) -> bool {
+        T::term_eq(&self.term, &other.term)
+    }
+
+    fn close_term(&mut self, _: ScopeState, _: &impl OnFreeFn<N>) {}
+
+    fn open_term(&mut self, _: ScopeState, _: &impl OnBoundFn<N>) {}
+
+    fn visit_vars(&self, _: &mut impl FnMut(&Var<N>)) {}
+
+    fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
+
+    fn visit_vars_with_binders(
+        &self,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+    }
+
+    fn visit_scopes(
+        &self,
+        _: &mut Vec<PathSegment<N>>,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+    }
+
+    fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
+}
+
+impl<N, M, R, T: Clone> Subst<N, R> for OtherSort<M, T> {
+    fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &R) -> OtherSort<M, T> {
+        self.clone()
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, R)]) -> OtherSort<M, T> {
+        self.clone()
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, _: &V, _: &R) {}
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, _: &[(V, R)]) {}
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, _: &V, _: &R) -> Option<OtherSort<M, T>> {
+        None
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, _: &[(V, R)]) -> Option<OtherSort<M, T>> {
+        None
+    }
+}
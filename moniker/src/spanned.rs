@@ -0,0 +1,200 @@
+use std::ops::ControlFlow;
+
+use alpha_mismatch::{AlphaMismatch, PathSegment};
+use binder::Binder;
+use bound::{BinderStack, BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use subst::Subst;
+use var::Var;
+
+/// A term or pattern that is annotated with a source span
+///
+/// Like `Ignore<T>`, the span does not participate in name binding, so two
+/// spanned terms are alpha equal whenever their inner terms are. Unlike
+/// `Ignore<T>` the inner term is bound as usual, and the span is preserved
+/// when binding, unbinding, and substituting.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Spanned<S, T> {
+    /// The source span
+    pub span: S,
+    /// The term or pattern that was found at the span
+    pub inner: T,
+}
+
+impl<S, T> Spanned<S, T> {
+    /// Annotate a term or pattern with a span
+    pub fn new(span: S, inner: T) -> Spanned<S, T> {
+        Spanned { span, inner }
+    }
+}
+
+impl<N, S, T> BoundTerm<N> for Spanned<S, T>
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N>,
+{
+    fn term_eq(&self, other: &Spanned<S, T>) -> bool {
+        T::term_eq(&self.inner, &other.inner)
+    }
+
+    fn term_eq_check(&self, other: &Spanned<S, T>) -> Result<(), AlphaMismatch<N>> {
+        T::term_eq_check(&self.inner, &other.inner)
+    }
+
+    fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        self.inner.close_term(state, on_free);
+    }
+
+    fn open_term(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        self.inner.open_term(state, on_bound);
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        self.inner.visit_vars(on_var);
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.inner.try_visit_vars(on_var)
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        self.inner.visit_mut_vars(on_var);
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.inner.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        self.inner.visit_scopes(path, binders, on_scope);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.inner.visit_nodes(depth, on_node);
+    }
+}
+
+impl<N, S, P> BoundPattern<N> for Spanned<S, P>
+where
+    N: Clone + PartialEq,
+    P: BoundPattern<N>,
+{
+    fn pattern_eq(&self, other: &Spanned<S, P>) -> bool {
+        P::pattern_eq(&self.inner, &other.inner)
+    }
+
+    fn close_pattern(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        self.inner.close_pattern(state, on_free);
+    }
+
+    fn open_pattern(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        self.inner.open_pattern(state, on_bound);
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        self.inner.visit_vars(on_var);
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.inner.try_visit_vars(on_var)
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        self.inner.visit_mut_vars(on_var);
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.inner.visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        self.inner.visit_scopes(path, binders, on_scope);
+    }
+
+    fn visit_binders(&self, on_binder: &mut impl FnMut(&Binder<N>)) {
+        self.inner.visit_binders(on_binder);
+    }
+
+    fn visit_mut_binders(&mut self, on_binder: &mut impl FnMut(&mut Binder<N>)) {
+        self.inner.visit_mut_binders(on_binder);
+    }
+
+    fn order_binders(&mut self, body: &impl BoundTerm<N>) {
+        self.inner.order_binders(body);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.inner.visit_nodes(depth, on_node);
+    }
+}
+
+impl<N, R, S, T> Subst<N, R> for Spanned<S, T>
+where
+    S: Clone,
+    T: Subst<N, R>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &R) -> Spanned<S, T> {
+        Spanned {
+            span: self.span.clone(),
+            inner: self.inner.subst(name, replacement),
+        }
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, R)]) -> Spanned<S, T> {
+        Spanned {
+            span: self.span.clone(),
+            inner: self.inner.substs(mappings),
+        }
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &R) {
+        self.inner.subst_mut(name, replacement);
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, R)]) {
+        self.inner.substs_mut(mappings);
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(
+        &self,
+        name: &V,
+        replacement: &R,
+    ) -> Option<Spanned<S, T>> {
+        self.inner
+            .subst_changed(name, replacement)
+            .map(|inner| Spanned {
+                span: self.span.clone(),
+                inner,
+            })
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, R)]) -> Option<Spanned<S, T>> {
+        self.inner.substs_changed(mappings).map(|inner| Spanned {
+            span: self.span.clone(),
+            inner,
+        })
+    }
+}
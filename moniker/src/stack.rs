@@ -0,0 +1,64 @@
+use std::panic;
+use std::thread;
+
+/// The amount of stack that must be left over for `maybe_grow_stack` to call
+/// its function on the current stack segment
+#[cfg(feature = "stacker")]
+const RED_ZONE: usize = 128 * 1024;
+
+/// The size of each new stack segment that `maybe_grow_stack` allocates
+#[cfg(feature = "stacker")]
+const SEGMENT_SIZE: usize = 4 * 1024 * 1024;
+
+/// Call `f`, growing the stack first if it is running low, and return its
+/// result
+///
+/// The traversals performed by `BoundTerm`, `BoundPattern`, and `Subst`
+/// recurse into each subterm in turn, so they need stack space in proportion
+/// to the depth of the term. The impls generated by `moniker-derive` call
+/// this at each node. With the `stacker` feature enabled, a new stack segment
+/// is allocated on the current thread whenever the stack is running low, so
+/// that machine generated terms that are nested tens of thousands of nodes
+/// deep can be traversed without overflowing it. Without the feature, this
+/// just calls `f`. Other recursive functions over these terms, like
+/// evaluators, can call it in the same way.
+#[inline]
+pub fn maybe_grow_stack<R>(f: impl FnOnce() -> R) -> R {
+    #[cfg(feature = "stacker")]
+    {
+        stacker::maybe_grow(RED_ZONE, SEGMENT_SIZE, f)
+    }
+    #[cfg(not(feature = "stacker"))]
+    {
+        f()
+    }
+}
+
+/// Run a function on a new thread with a stack of at least `size` bytes,
+/// returning its result
+///
+/// Without the `stacker` feature the traversals performed by this crate do
+/// not grow the stack, and other recursive functions over deep terms might
+/// not either. Dropping a deep term recurses too, as do the `Debug` and
+/// `Display` impls of most terms. Running the compiler, or the part of it that
+/// works on these terms, inside `with_stack_size` gives them more room.
+///
+/// Terms that are not `Send`, like those using `Rc`, must be built, used, and
+/// dropped within `f`.
+///
+/// # Panics
+///
+/// Panics if the thread cannot be spawned, for example on targets without
+/// thread support. If `f` panics, the panic is resumed on the calling thread.
+pub fn with_stack_size<R: Send>(size: usize, f: impl FnOnce() -> R + Send) -> R {
+    thread::scope(|scope| {
+        let handle = thread::Builder::new()
+            .stack_size(size)
+            .spawn_scoped(scope, f)
+            .expect("cannot spawn a thread for the traversal");
+
+        handle
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload))
+    })
+}
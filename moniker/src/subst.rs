@@ -0,0 +1,857 @@
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use free_var::FreeVar;
+use small_vec::SmallVec;
+use var::Var;
+
+/// Capture-avoiding substitution of terms for free variables
+///
+/// `T` is the type of the replacement terms. This will usually be the type
+/// that contains the variable constructor of your language, or the reference
+/// counted wrapper around it.
+///
+/// Because bound variables are stored as Debruijn indices inside scopes, free
+/// variables in the replacement terms can never be captured by the binders
+/// that they are substituted under.
+#[diagnostic::on_unimplemented(
+    message = "variables in `{Self}` can not be substituted with `{T}`",
+    label = "the trait `Subst<{N}, {T}>` is not implemented for `{Self}`",
+    note = "data that does not contain variables can be wrapped in `Ignore<T>`, \
+            or the field can be skipped with `#[moniker(ignore)]`"
+)]
+pub trait Subst<N, T>: Sized {
+    /// Returns the variable if this term is one that can be replaced during
+    /// substitution
+    fn as_var(&self) -> Option<&Var<N>> {
+        None
+    }
+
+    /// Substitute `replacement` for each free occurrence of `name` in the term
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Self;
+
+    /// Simultaneously substitute each of the `mappings` in a single traversal
+    /// of the term
+    ///
+    /// The replacements are not themselves substituted into, so free
+    /// variables in them will not be affected by the other mappings.
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Self;
+
+    /// Substitute `replacement` for each free occurrence of `name` in place
+    ///
+    /// This avoids rebuilding the parts of the term that are uniquely owned,
+    /// like the contents of a `Box`, or of an `Rc` that is not shared. The
+    /// default implementation replaces the term with the result of
+    /// `Subst::subst`.
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        *self = self.subst(name, replacement);
+    }
+
+    /// Simultaneously substitute each of the `mappings` in place
+    ///
+    /// The default implementation replaces the term with the result of
+    /// `Subst::substs`.
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        *self = self.substs(mappings);
+    }
+
+    /// Substitute `replacement` for each free occurrence of `name` in the
+    /// term, returning `None` if the term would be left unchanged
+    ///
+    /// This lets reference counted terms share the subterms that do not
+    /// mention `name` with the original term, rather than rebuilding them.
+    /// The default implementation always returns the result of
+    /// `Subst::subst`.
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Option<Self> {
+        Some(self.subst(name, replacement))
+    }
+
+    /// Simultaneously substitute each of the `mappings`, returning `None` if
+    /// the term would be left unchanged
+    ///
+    /// The default implementation always returns the result of
+    /// `Subst::substs`.
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<Self> {
+        Some(self.substs(mappings))
+    }
+}
+
+/// Terms that replace themselves with the replacement term when they are a
+/// variable that is being substituted for
+///
+/// This is implemented by `#[derive(Subst)]` for the types with a
+/// `#[subst(var)]` field that are substituted with themselves, like the
+/// reference counted wrapper around the variable constructor of a language.
+/// Types that are substituted with some other type of term using
+/// `#[subst(replacement = "...")]` never replace their own variables, so the
+/// derive requires the replacement type to implement this trait. Otherwise
+/// substitution would silently leave the variables alone:
+///
+/// ```rust,compile_fail
+/// #[macro_use]
+/// extern crate moniker;
+///
+/// use std::rc::Rc;
+/// use moniker::Var;
+///
+/// // `Rc<Expr>` is never replaced, so variables would never be substituted
+/// #[derive(Debug, Clone, BoundTerm, Subst)]
+/// #[subst(replacement = "Rc<Expr>")]
+/// pub enum Expr {
+///     #[subst(var)]
+///     Var(Var<String>),
+///     App(Rc<Expr>, Rc<Expr>),
+/// }
+/// # fn main() {}
+/// ```
+///
+/// Hand-written implementations of `Subst` that replace the term with the
+/// replacement should also implement this trait.
+#[diagnostic::on_unimplemented(
+    message = "variables in terms can not be substituted with `{Self}`",
+    label = "`{Self}` is never replaced during substitution",
+    note = "the replacement should be a type with a `#[subst(var)]` field that is substituted \
+            with itself, like a newtype marked with `#[moniker(transparent)]`"
+)]
+pub trait SubstVar<N> {}
+
+/// Substitute into the elements of a slice, returning `None` if none of them
+/// were changed
+///
+/// The elements that are not changed are rebuilt using `subst`, which lets
+/// them share their reference counted subterms with the original elements.
+pub(crate) fn changed_elems<U>(
+    elems: &[U],
+    changed: impl Fn(&U) -> Option<U>,
+    subst: impl Fn(&U) -> U,
+) -> Option<Vec<U>> {
+    let (index, first) = elems
+        .iter()
+        .enumerate()
+        .find_map(|(index, elem)| changed(elem).map(|elem| (index, elem)))?;
+
+    let mut result = Vec::with_capacity(elems.len());
+    result.extend(elems[..index].iter().map(&subst));
+    result.push(first);
+    result.extend(elems[index + 1..].iter().map(&subst));
+    Some(result)
+}
+
+impl<N: Clone, T> Subst<N, T> for Var<N> {
+    fn as_var(&self) -> Option<&Var<N>> {
+        Some(self)
+    }
+
+    fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Var<N> {
+        self.clone()
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Var<N> {
+        self.clone()
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, _: &V, _: &T) {}
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, _: &[(V, T)]) {}
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Option<Var<N>> {
+        None
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Option<Var<N>> {
+        None
+    }
+}
+
+impl<N: Clone, T> Subst<N, T> for FreeVar<N> {
+    fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> FreeVar<N> {
+        self.clone()
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> FreeVar<N> {
+        self.clone()
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, _: &V, _: &T) {}
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, _: &[(V, T)]) {}
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Option<FreeVar<N>> {
+        None
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Option<FreeVar<N>> {
+        None
+    }
+}
+
+// Implementations for common types
+
+macro_rules! impl_subst_clone {
+    ([$($lt:lifetime),*] $T:ty) => {
+        impl<$($lt,)* N, T> Subst<N, T> for $T {
+            fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> $T {
+                self.clone()
+            }
+
+            fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> $T {
+                self.clone()
+            }
+
+            fn subst_mut<V: PartialEq<Var<N>>>(&mut self, _: &V, _: &T) {}
+
+            fn substs_mut<V: PartialEq<Var<N>>>(&mut self, _: &[(V, T)]) {}
+
+            fn subst_changed<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Option<$T> {
+                None
+            }
+
+            fn substs_changed<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Option<$T> {
+                None
+            }
+        }
+    };
+    ($T:ty) => {
+        impl_subst_clone!([] $T);
+    };
+}
+
+impl_subst_clone!(());
+impl_subst_clone!(String);
+impl_subst_clone!(['a] &'a str);
+impl_subst_clone!(Arc<str>);
+impl_subst_clone!(char);
+impl_subst_clone!(bool);
+impl_subst_clone!(u8);
+impl_subst_clone!(u16);
+impl_subst_clone!(u32);
+impl_subst_clone!(u64);
+impl_subst_clone!(usize);
+impl_subst_clone!(i8);
+impl_subst_clone!(i16);
+impl_subst_clone!(i32);
+impl_subst_clone!(i64);
+impl_subst_clone!(isize);
+impl_subst_clone!(f32);
+impl_subst_clone!(f64);
+
+impl<N, T, U> Subst<N, T> for Option<U>
+where
+    U: Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Option<U> {
+        self.as_ref().map(|inner| inner.subst(name, replacement))
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<U> {
+        self.as_ref().map(|inner| inner.substs(mappings))
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        if let Some(ref mut inner) = *self {
+            inner.subst_mut(name, replacement);
+        }
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        if let Some(ref mut inner) = *self {
+            inner.substs_mut(mappings);
+        }
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Option<Option<U>> {
+        self.as_ref()
+            .and_then(|inner| inner.subst_changed(name, replacement))
+            .map(Some)
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<Option<U>> {
+        self.as_ref()
+            .and_then(|inner| inner.substs_changed(mappings))
+            .map(Some)
+    }
+}
+
+impl<N, T, U> Subst<N, T> for Box<U>
+where
+    U: Subst<N, T>,
+{
+    fn as_var(&self) -> Option<&Var<N>> {
+        U::as_var(self)
+    }
+
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Box<U> {
+        Box::new(U::subst(self, name, replacement))
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Box<U> {
+        Box::new(U::substs(self, mappings))
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        U::subst_mut(self, name, replacement);
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        U::substs_mut(self, mappings);
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Option<Box<U>> {
+        U::subst_changed(self, name, replacement).map(Box::new)
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<Box<U>> {
+        U::substs_changed(self, mappings).map(Box::new)
+    }
+}
+
+impl<N, T, U> Subst<N, T> for Rc<U>
+where
+    U: Subst<N, T>,
+{
+    fn as_var(&self) -> Option<&Var<N>> {
+        U::as_var(self)
+    }
+
+    /// The original reference is returned if the term would be unchanged
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Rc<U> {
+        match U::subst_changed(self, name, replacement) {
+            Some(inner) => Rc::new(inner),
+            None => self.clone(),
+        }
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Rc<U> {
+        match U::substs_changed(self, mappings) {
+            Some(inner) => Rc::new(inner),
+            None => self.clone(),
+        }
+    }
+
+    /// The term is only substituted into in place if this is the only
+    /// reference to it, otherwise it is rebuilt as in `Subst::subst`, and left
+    /// shared if it would be unchanged
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        match Rc::get_mut(self) {
+            Some(inner) => inner.subst_mut(name, replacement),
+            None => {
+                if let Some(inner) = U::subst_changed(self, name, replacement) {
+                    *self = Rc::new(inner);
+                }
+            },
+        }
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        match Rc::get_mut(self) {
+            Some(inner) => inner.substs_mut(mappings),
+            None => {
+                if let Some(inner) = U::substs_changed(self, mappings) {
+                    *self = Rc::new(inner);
+                }
+            },
+        }
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Option<Rc<U>> {
+        U::subst_changed(self, name, replacement).map(Rc::new)
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<Rc<U>> {
+        U::substs_changed(self, mappings).map(Rc::new)
+    }
+}
+
+impl<N, T, U> Subst<N, T> for Arc<U>
+where
+    U: Subst<N, T>,
+{
+    fn as_var(&self) -> Option<&Var<N>> {
+        U::as_var(self)
+    }
+
+    /// The original reference is returned if the term would be unchanged
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Arc<U> {
+        match U::subst_changed(self, name, replacement) {
+            Some(inner) => Arc::new(inner),
+            None => self.clone(),
+        }
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Arc<U> {
+        match U::substs_changed(self, mappings) {
+            Some(inner) => Arc::new(inner),
+            None => self.clone(),
+        }
+    }
+
+    /// The term is only substituted into in place if this is the only
+    /// reference to it, otherwise it is rebuilt as in `Subst::subst`, and left
+    /// shared if it would be unchanged
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        match Arc::get_mut(self) {
+            Some(inner) => inner.subst_mut(name, replacement),
+            None => {
+                if let Some(inner) = U::subst_changed(self, name, replacement) {
+                    *self = Arc::new(inner);
+                }
+            },
+        }
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        match Arc::get_mut(self) {
+            Some(inner) => inner.substs_mut(mappings),
+            None => {
+                if let Some(inner) = U::substs_changed(self, mappings) {
+                    *self = Arc::new(inner);
+                }
+            },
+        }
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Option<Arc<U>> {
+        U::subst_changed(self, name, replacement).map(Arc::new)
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<Arc<U>> {
+        U::substs_changed(self, mappings).map(Arc::new)
+    }
+}
+
+impl<N, T, U1, U2> Subst<N, T> for (U1, U2)
+where
+    U1: Subst<N, T>,
+    U2: Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> (U1, U2) {
+        (
+            self.0.subst(name, replacement),
+            self.1.subst(name, replacement),
+        )
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> (U1, U2) {
+        (self.0.substs(mappings), self.1.substs(mappings))
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        self.0.subst_mut(name, replacement);
+        self.1.subst_mut(name, replacement);
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        self.0.substs_mut(mappings);
+        self.1.substs_mut(mappings);
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Option<(U1, U2)> {
+        match (
+            self.0.subst_changed(name, replacement),
+            self.1.subst_changed(name, replacement),
+        ) {
+            (None, None) => None,
+            (elem0, elem1) => Some((
+                elem0.unwrap_or_else(|| self.0.subst(name, replacement)),
+                elem1.unwrap_or_else(|| self.1.subst(name, replacement)),
+            )),
+        }
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<(U1, U2)> {
+        match (
+            self.0.substs_changed(mappings),
+            self.1.substs_changed(mappings),
+        ) {
+            (None, None) => None,
+            (elem0, elem1) => Some((
+                elem0.unwrap_or_else(|| self.0.substs(mappings)),
+                elem1.unwrap_or_else(|| self.1.substs(mappings)),
+            )),
+        }
+    }
+}
+
+impl<N, T, U1, U2, U3> Subst<N, T> for (U1, U2, U3)
+where
+    U1: Subst<N, T>,
+    U2: Subst<N, T>,
+    U3: Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> (U1, U2, U3) {
+        (
+            self.0.subst(name, replacement),
+            self.1.subst(name, replacement),
+            self.2.subst(name, replacement),
+        )
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> (U1, U2, U3) {
+        (
+            self.0.substs(mappings),
+            self.1.substs(mappings),
+            self.2.substs(mappings),
+        )
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        self.0.subst_mut(name, replacement);
+        self.1.subst_mut(name, replacement);
+        self.2.subst_mut(name, replacement);
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        self.0.substs_mut(mappings);
+        self.1.substs_mut(mappings);
+        self.2.substs_mut(mappings);
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(
+        &self,
+        name: &V,
+        replacement: &T,
+    ) -> Option<(U1, U2, U3)> {
+        match (
+            self.0.subst_changed(name, replacement),
+            self.1.subst_changed(name, replacement),
+            self.2.subst_changed(name, replacement),
+        ) {
+            (None, None, None) => None,
+            (elem0, elem1, elem2) => Some((
+                elem0.unwrap_or_else(|| self.0.subst(name, replacement)),
+                elem1.unwrap_or_else(|| self.1.subst(name, replacement)),
+                elem2.unwrap_or_else(|| self.2.subst(name, replacement)),
+            )),
+        }
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<(U1, U2, U3)> {
+        match (
+            self.0.substs_changed(mappings),
+            self.1.substs_changed(mappings),
+            self.2.substs_changed(mappings),
+        ) {
+            (None, None, None) => None,
+            (elem0, elem1, elem2) => Some((
+                elem0.unwrap_or_else(|| self.0.substs(mappings)),
+                elem1.unwrap_or_else(|| self.1.substs(mappings)),
+                elem2.unwrap_or_else(|| self.2.substs(mappings)),
+            )),
+        }
+    }
+}
+
+impl<N, T, U1, U2, U3, U4> Subst<N, T> for (U1, U2, U3, U4)
+where
+    U1: Subst<N, T>,
+    U2: Subst<N, T>,
+    U3: Subst<N, T>,
+    U4: Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> (U1, U2, U3, U4) {
+        (
+            self.0.subst(name, replacement),
+            self.1.subst(name, replacement),
+            self.2.subst(name, replacement),
+            self.3.subst(name, replacement),
+        )
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> (U1, U2, U3, U4) {
+        (
+            self.0.substs(mappings),
+            self.1.substs(mappings),
+            self.2.substs(mappings),
+            self.3.substs(mappings),
+        )
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        self.0.subst_mut(name, replacement);
+        self.1.subst_mut(name, replacement);
+        self.2.subst_mut(name, replacement);
+        self.3.subst_mut(name, replacement);
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        self.0.substs_mut(mappings);
+        self.1.substs_mut(mappings);
+        self.2.substs_mut(mappings);
+        self.3.substs_mut(mappings);
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(
+        &self,
+        name: &V,
+        replacement: &T,
+    ) -> Option<(U1, U2, U3, U4)> {
+        match (
+            self.0.subst_changed(name, replacement),
+            self.1.subst_changed(name, replacement),
+            self.2.subst_changed(name, replacement),
+            self.3.subst_changed(name, replacement),
+        ) {
+            (None, None, None, None) => None,
+            (elem0, elem1, elem2, elem3) => Some((
+                elem0.unwrap_or_else(|| self.0.subst(name, replacement)),
+                elem1.unwrap_or_else(|| self.1.subst(name, replacement)),
+                elem2.unwrap_or_else(|| self.2.subst(name, replacement)),
+                elem3.unwrap_or_else(|| self.3.subst(name, replacement)),
+            )),
+        }
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(
+        &self,
+        mappings: &[(V, T)],
+    ) -> Option<(U1, U2, U3, U4)> {
+        match (
+            self.0.substs_changed(mappings),
+            self.1.substs_changed(mappings),
+            self.2.substs_changed(mappings),
+            self.3.substs_changed(mappings),
+        ) {
+            (None, None, None, None) => None,
+            (elem0, elem1, elem2, elem3) => Some((
+                elem0.unwrap_or_else(|| self.0.substs(mappings)),
+                elem1.unwrap_or_else(|| self.1.substs(mappings)),
+                elem2.unwrap_or_else(|| self.2.substs(mappings)),
+                elem3.unwrap_or_else(|| self.3.substs(mappings)),
+            )),
+        }
+    }
+}
+
+impl<N, T, U1, U2, U3, U4, U5> Subst<N, T> for (U1, U2, U3, U4, U5)
+where
+    U1: Subst<N, T>,
+    U2: Subst<N, T>,
+    U3: Subst<N, T>,
+    U4: Subst<N, T>,
+    U5: Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> (U1, U2, U3, U4, U5) {
+        (
+            self.0.subst(name, replacement),
+            self.1.subst(name, replacement),
+            self.2.subst(name, replacement),
+            self.3.subst(name, replacement),
+            self.4.subst(name, replacement),
+        )
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> (U1, U2, U3, U4, U5) {
+        (
+            self.0.substs(mappings),
+            self.1.substs(mappings),
+            self.2.substs(mappings),
+            self.3.substs(mappings),
+            self.4.substs(mappings),
+        )
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        self.0.subst_mut(name, replacement);
+        self.1.subst_mut(name, replacement);
+        self.2.subst_mut(name, replacement);
+        self.3.subst_mut(name, replacement);
+        self.4.subst_mut(name, replacement);
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        self.0.substs_mut(mappings);
+        self.1.substs_mut(mappings);
+        self.2.substs_mut(mappings);
+        self.3.substs_mut(mappings);
+        self.4.substs_mut(mappings);
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(
+        &self,
+        name: &V,
+        replacement: &T,
+    ) -> Option<(U1, U2, U3, U4, U5)> {
+        match (
+            self.0.subst_changed(name, replacement),
+            self.1.subst_changed(name, replacement),
+            self.2.subst_changed(name, replacement),
+            self.3.subst_changed(name, replacement),
+            self.4.subst_changed(name, replacement),
+        ) {
+            (None, None, None, None, None) => None,
+            (elem0, elem1, elem2, elem3, elem4) => Some((
+                elem0.unwrap_or_else(|| self.0.subst(name, replacement)),
+                elem1.unwrap_or_else(|| self.1.subst(name, replacement)),
+                elem2.unwrap_or_else(|| self.2.subst(name, replacement)),
+                elem3.unwrap_or_else(|| self.3.subst(name, replacement)),
+                elem4.unwrap_or_else(|| self.4.subst(name, replacement)),
+            )),
+        }
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(
+        &self,
+        mappings: &[(V, T)],
+    ) -> Option<(U1, U2, U3, U4, U5)> {
+        match (
+            self.0.substs_changed(mappings),
+            self.1.substs_changed(mappings),
+            self.2.substs_changed(mappings),
+            self.3.substs_changed(mappings),
+            self.4.substs_changed(mappings),
+        ) {
+            (None, None, None, None, None) => None,
+            (elem0, elem1, elem2, elem3, elem4) => Some((
+                elem0.unwrap_or_else(|| self.0.substs(mappings)),
+                elem1.unwrap_or_else(|| self.1.substs(mappings)),
+                elem2.unwrap_or_else(|| self.2.substs(mappings)),
+                elem3.unwrap_or_else(|| self.3.substs(mappings)),
+                elem4.unwrap_or_else(|| self.4.substs(mappings)),
+            )),
+        }
+    }
+}
+
+impl<N, T, U> Subst<N, T> for Vec<U>
+where
+    U: Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Vec<U> {
+        self.iter()
+            .map(|elem| elem.subst(name, replacement))
+            .collect()
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Vec<U> {
+        self.iter().map(|elem| elem.substs(mappings)).collect()
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        for elem in self {
+            elem.subst_mut(name, replacement);
+        }
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        for elem in self {
+            elem.substs_mut(mappings);
+        }
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Option<Vec<U>> {
+        changed_elems(
+            self,
+            |elem| elem.subst_changed(name, replacement),
+            |elem| elem.subst(name, replacement),
+        )
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<Vec<U>> {
+        changed_elems(
+            self,
+            |elem| elem.substs_changed(mappings),
+            |elem| elem.substs(mappings),
+        )
+    }
+}
+
+impl<N, T, U> Subst<N, T> for SmallVec<U>
+where
+    U: Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> SmallVec<U> {
+        self.iter()
+            .map(|elem| elem.subst(name, replacement))
+            .collect()
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> SmallVec<U> {
+        self.iter().map(|elem| elem.substs(mappings)).collect()
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        for elem in self {
+            elem.subst_mut(name, replacement);
+        }
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        for elem in self {
+            elem.substs_mut(mappings);
+        }
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(
+        &self,
+        name: &V,
+        replacement: &T,
+    ) -> Option<SmallVec<U>> {
+        changed_elems(
+            self,
+            |elem| elem.subst_changed(name, replacement),
+            |elem| elem.subst(name, replacement),
+        )
+        .map(SmallVec::from)
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<SmallVec<U>> {
+        changed_elems(
+            self,
+            |elem| elem.substs_changed(mappings),
+            |elem| elem.substs(mappings),
+        )
+        .map(SmallVec::from)
+    }
+}
+
+impl<N, T, U, const LEN: usize> Subst<N, T> for [U; LEN]
+where
+    U: Subst<N, T>,
+{
+    fn subst<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> [U; LEN] {
+        self.each_ref().map(|elem| elem.subst(name, replacement))
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> [U; LEN] {
+        self.each_ref().map(|elem| elem.substs(mappings))
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, name: &V, replacement: &T) {
+        for elem in self {
+            elem.subst_mut(name, replacement);
+        }
+    }
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, mappings: &[(V, T)]) {
+        for elem in self {
+            elem.substs_mut(mappings);
+        }
+    }
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, name: &V, replacement: &T) -> Option<[U; LEN]> {
+        changed_elems(
+            self,
+            |elem| elem.subst_changed(name, replacement),
+            |elem| elem.subst(name, replacement),
+        )
+        .map(|elems| {
+            <[U; LEN]>::try_from(elems)
+                .ok()
+                .expect("`changed_elems` returns one element for each element of the array")
+        })
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, mappings: &[(V, T)]) -> Option<[U; LEN]> {
+        changed_elems(
+            self,
+            |elem| elem.substs_changed(mappings),
+            |elem| elem.substs(mappings),
+        )
+        .map(|elems| {
+            <[U; LEN]>::try_from(elems)
+                .ok()
+                .expect("`changed_elems` returns one element for each element of the array")
+        })
+    }
+}
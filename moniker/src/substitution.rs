@@ -0,0 +1,163 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::iter::FromIterator;
+use std::slice;
+
+use free_var::FreeVar;
+use subst::Subst;
+
+/// A mapping from free variables to the terms that should replace them
+///
+/// This is useful when the replacements are built up over time, for example
+/// when solving constraints during unification-based type checking. The
+/// mappings are applied to a term simultaneously, in a single traversal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Substitution<N, T> {
+    mappings: Vec<(FreeVar<N>, T)>,
+}
+
+impl<N, T> Substitution<N, T> {
+    /// Create an empty substitution
+    pub fn new() -> Substitution<N, T> {
+        Substitution {
+            mappings: Vec::new(),
+        }
+    }
+
+    /// The number of variables that are replaced by the substitution
+    pub fn len(&self) -> usize {
+        self.mappings.len()
+    }
+
+    /// Returns `true` if the substitution does not replace any variables
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Iterate over the variables and their replacements, in the order that
+    /// they were inserted
+    pub fn iter(&self) -> slice::Iter<'_, (FreeVar<N>, T)> {
+        self.mappings.iter()
+    }
+
+    /// The mappings in the substitution, in the form expected by
+    /// `Subst::substs`
+    pub fn as_slice(&self) -> &[(FreeVar<N>, T)] {
+        &self.mappings
+    }
+}
+
+impl<N: PartialEq, T> Substitution<N, T> {
+    /// Replace `free_var` with `replacement`, returning the previous
+    /// replacement for the variable if there was one
+    pub fn insert(&mut self, free_var: FreeVar<N>, replacement: T) -> Option<T> {
+        match self.mappings.iter_mut().find(|(fv, _)| *fv == free_var) {
+            Some((_, old)) => Some(::std::mem::replace(old, replacement)),
+            None => {
+                self.mappings.push((free_var, replacement));
+                None
+            },
+        }
+    }
+
+    /// Lookup the replacement for `free_var`
+    pub fn get(&self, free_var: &FreeVar<N>) -> Option<&T> {
+        self.mappings
+            .iter()
+            .find(|(fv, _)| fv == free_var)
+            .map(|(_, replacement)| replacement)
+    }
+
+    /// Returns `true` if the substitution replaces `free_var`
+    pub fn contains(&self, free_var: &FreeVar<N>) -> bool {
+        self.get(free_var).is_some()
+    }
+
+    /// Stop replacing `free_var`, returning its replacement if there was one
+    pub fn remove(&mut self, free_var: &FreeVar<N>) -> Option<T> {
+        let index = self.mappings.iter().position(|(fv, _)| fv == free_var)?;
+        Some(self.mappings.remove(index).1)
+    }
+
+    /// Apply the substitution to `term`, replacing all of the variables in a
+    /// single traversal
+    pub fn apply<U>(&self, term: &U) -> U
+    where
+        U: Subst<N, T>,
+    {
+        term.substs(&self.mappings)
+    }
+
+    /// Compose two substitutions, returning a substitution that is the same
+    /// as applying `other` and then `self`
+    ///
+    /// This is often written as `self ∘ other`.
+    pub fn compose(&self, other: &Substitution<N, T>) -> Substitution<N, T>
+    where
+        N: Clone,
+        T: Clone + Subst<N, T>,
+    {
+        let mut mappings = other
+            .mappings
+            .iter()
+            .map(|(fv, replacement)| (fv.clone(), self.apply(replacement)))
+            .collect::<Vec<_>>();
+
+        for (fv, replacement) in &self.mappings {
+            if !other.contains(fv) {
+                mappings.push((fv.clone(), replacement.clone()));
+            }
+        }
+
+        Substitution { mappings }
+    }
+
+    /// Only keep the replacements for the variables in `free_vars`
+    pub fn restrict(mut self, free_vars: &HashSet<FreeVar<N>>) -> Substitution<N, T>
+    where
+        N: Eq + Hash,
+    {
+        self.mappings.retain(|(fv, _)| free_vars.contains(fv));
+        self
+    }
+}
+
+impl<N, T> Default for Substitution<N, T> {
+    fn default() -> Substitution<N, T> {
+        Substitution::new()
+    }
+}
+
+impl<N: PartialEq, T> FromIterator<(FreeVar<N>, T)> for Substitution<N, T> {
+    fn from_iter<I: IntoIterator<Item = (FreeVar<N>, T)>>(iter: I) -> Substitution<N, T> {
+        let mut substitution = Substitution::new();
+        substitution.extend(iter);
+        substitution
+    }
+}
+
+impl<N: PartialEq, T> Extend<(FreeVar<N>, T)> for Substitution<N, T> {
+    fn extend<I: IntoIterator<Item = (FreeVar<N>, T)>>(&mut self, iter: I) {
+        for (free_var, replacement) in iter {
+            self.insert(free_var, replacement);
+        }
+    }
+}
+
+impl<N, T> IntoIterator for Substitution<N, T> {
+    type Item = (FreeVar<N>, T);
+    type IntoIter = ::std::vec::IntoIter<(FreeVar<N>, T)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.mappings.into_iter()
+    }
+}
+
+impl<'a, N, T> IntoIterator for &'a Substitution<N, T> {
+    type Item = &'a (FreeVar<N>, T);
+    type IntoIter = slice::Iter<'a, (FreeVar<N>, T)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.mappings.iter()
+    }
+}
@@ -0,0 +1,215 @@
+use std::ops::ControlFlow;
+use std::sync::OnceLock;
+
+use alpha_mismatch::{AlphaMismatch, PathSegment};
+use binder::Binder;
+use bound::{BinderStack, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use free_var::FreeVar;
+use scope::{opening_binders, Scope};
+use subst::Subst;
+use var::Var;
+
+/// A term with a suspended substitution
+///
+/// Substituting into a `Suspended<N, T, R>` only records the mapping, leaving
+/// the term untouched until it is forced. Later substitutions are applied to
+/// the replacements of the earlier ones, so forcing the term performs all of
+/// the pending substitutions in a single traversal. This is useful for
+/// evaluators that perform many beta reductions in a row, which would
+/// otherwise traverse and rebuild the body of each function once per
+/// argument.
+///
+/// The operations of `BoundTerm` force the term first, so that a suspended
+/// term is always treated as if the substitutions had already been applied.
+/// Forcing through a shared reference caches the result, so the pending
+/// substitutions are only applied once however many operations are
+/// performed.
+#[derive(Debug, Clone)]
+pub struct Suspended<N, T, R> {
+    /// The term, before the pending substitutions have been applied
+    term: T,
+    /// The pending substitutions, in a form that can be applied to the term
+    /// simultaneously
+    mappings: Vec<(FreeVar<N>, R)>,
+    /// The term after the pending substitutions have been applied, if it has
+    /// been forced through a shared reference
+    forced: OnceLock<T>,
+}
+
+impl<N, T, R> Suspended<N, T, R> {
+    /// Wrap a term, with no pending substitutions
+    pub fn new(term: T) -> Suspended<N, T, R> {
+        Suspended {
+            term,
+            mappings: Vec::new(),
+            forced: OnceLock::new(),
+        }
+    }
+
+    /// The substitutions that have yet to be applied to the term
+    pub fn pending(&self) -> &[(FreeVar<N>, R)] {
+        &self.mappings
+    }
+
+    /// Suspend the substitution of `replacement` for each free occurrence of
+    /// `free_var` in the term
+    ///
+    /// This only traverses the replacements of the pending substitutions, and
+    /// not the term itself.
+    pub fn subst(mut self, free_var: FreeVar<N>, replacement: R) -> Suspended<N, T, R>
+    where
+        N: PartialEq,
+        R: Subst<N, R>,
+    {
+        for mapping in &mut self.mappings {
+            mapping.1 = mapping.1.subst(&free_var, &replacement);
+        }
+        // Occurrences of a variable that is already being substituted for will
+        // have been replaced by the time that this substitution is applied
+        if self.mappings.iter().all(|mapping| mapping.0 != free_var) {
+            self.mappings.push((free_var, replacement));
+        }
+        self.forced = OnceLock::new();
+        self
+    }
+
+    /// Apply the pending substitutions, returning a reference to the resulting
+    /// term
+    ///
+    /// The result is cached, so this only traverses the term the first time it
+    /// is called.
+    pub fn force_ref(&self) -> &T
+    where
+        N: PartialEq,
+        T: Subst<N, R>,
+    {
+        if self.mappings.is_empty() {
+            &self.term
+        } else {
+            self.forced.get_or_init(|| self.term.substs(&self.mappings))
+        }
+    }
+
+    /// Apply the pending substitutions, returning the resulting term
+    pub fn force(&self) -> T
+    where
+        N: PartialEq,
+        T: Subst<N, R> + Clone,
+    {
+        self.force_ref().clone()
+    }
+
+    /// Apply the pending substitutions in place, returning a mutable reference
+    /// to the resulting term
+    pub fn force_mut(&mut self) -> &mut T
+    where
+        N: PartialEq,
+        T: Subst<N, R>,
+    {
+        if !self.mappings.is_empty() {
+            self.term = match self.forced.take() {
+                Some(forced) => forced,
+                None => self.term.substs(&self.mappings),
+            };
+            self.mappings.clear();
+        }
+        &mut self.term
+    }
+
+    /// Apply the pending substitutions, consuming the suspended term
+    pub fn into_forced(mut self) -> T
+    where
+        N: PartialEq,
+        T: Subst<N, R>,
+    {
+        self.force_mut();
+        self.term
+    }
+}
+
+impl<N, T> Scope<Binder<N>, T> {
+    /// Replace the variable bound by the binder with the given argument,
+    /// suspending the substitution
+    ///
+    /// This is like `Scope::instantiate`, but only opens the body, leaving the
+    /// substitution to be applied when the result is forced.
+    pub fn instantiate_suspended<R>(self, arg: R) -> Suspended<N, T, R>
+    where
+        N: Clone + PartialEq,
+        T: BoundTerm<N>,
+    {
+        let mut body = self.unsafe_body;
+        let mut binders = opening_binders(vec![self.unsafe_pattern], &body);
+        body.open_term(ScopeState::new(), &binders);
+
+        Suspended {
+            term: body,
+            mappings: vec![(binders.remove(0).0, arg)],
+            forced: OnceLock::new(),
+        }
+    }
+}
+
+impl<N, T, R> From<T> for Suspended<N, T, R> {
+    fn from(src: T) -> Suspended<N, T, R> {
+        Suspended::new(src)
+    }
+}
+
+impl<N, T, R> BoundTerm<N> for Suspended<N, T, R>
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N> + Subst<N, R>,
+{
+    fn term_eq(&self, other: &Suspended<N, T, R>) -> bool {
+        T::term_eq(self.force_ref(), other.force_ref())
+    }
+
+    fn term_eq_check(&self, other: &Suspended<N, T, R>) -> Result<(), AlphaMismatch<N>> {
+        T::term_eq_check(self.force_ref(), other.force_ref())
+    }
+
+    fn close_term(&mut self, state: ScopeState, on_free: &impl OnFreeFn<N>) {
+        self.force_mut().close_term(state, on_free);
+    }
+
+    fn open_term(&mut self, state: ScopeState, on_bound: &impl OnBoundFn<N>) {
+        self.force_mut().open_term(state, on_bound);
+    }
+
+    fn visit_vars(&self, on_var: &mut impl FnMut(&Var<N>)) {
+        self.force_ref().visit_vars(on_var);
+    }
+
+    fn try_visit_vars<B>(
+        &self,
+        on_var: &mut impl FnMut(&Var<N>) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        self.force_ref().try_visit_vars(on_var)
+    }
+
+    fn visit_mut_vars(&mut self, on_var: &mut impl FnMut(&mut Var<N>)) {
+        self.force_mut().visit_mut_vars(on_var);
+    }
+
+    fn visit_vars_with_binders(
+        &self,
+        binders: &mut BinderStack<N>,
+        on_var: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+        self.force_ref().visit_vars_with_binders(binders, on_var);
+    }
+
+    fn visit_scopes(
+        &self,
+        path: &mut Vec<PathSegment<N>>,
+        binders: &mut BinderStack<N>,
+        on_scope: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+        self.force_ref().visit_scopes(path, binders, on_scope);
+    }
+
+    fn visit_nodes(&self, depth: usize, on_node: &mut impl FnMut(usize)) {
+        self.force_ref().visit_nodes(depth, on_node);
+    }
+}
@@ -0,0 +1,195 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+#[cfg(any(feature = "lasso", feature = "string-interner"))]
+use std::hash::BuildHasher;
+use std::hash::{Hash, Hasher};
+use std::ptr;
+use std::sync::Mutex;
+
+/// An interned string, for use as the names of variables
+///
+/// Each distinct string is only ever stored once, so symbols can be copied,
+/// compared, and hashed in constant time, without touching the characters of
+/// the string. Using `Symbol` in place of `String` avoids allocating each time
+/// that a `FreeVar` or `Binder` is cloned, which happens often when unbinding
+/// scopes and substituting into large terms.
+///
+/// Interned strings are never freed, so symbols should only be created for
+/// the names that appear in the source code, rather than for generated names.
+#[derive(Copy, Clone)]
+pub struct Symbol(&'static str);
+
+impl Symbol {
+    /// Intern a string, returning the symbol that refers to it
+    pub fn intern(name: &str) -> Symbol {
+        // `HashSet::new` cannot be called in a constant, so the set is created
+        // on first use
+        static SYMBOLS: Mutex<Option<HashSet<&'static str>>> = Mutex::new(None);
+
+        let mut symbols = SYMBOLS.lock().unwrap_or_else(|error| error.into_inner());
+        let symbols = symbols.get_or_insert_with(HashSet::new);
+        match symbols.get(name) {
+            Some(&interned) => Symbol(interned),
+            None => {
+                let interned = Box::leak(name.to_owned().into_boxed_str());
+                symbols.insert(interned);
+                Symbol(interned)
+            },
+        }
+    }
+
+    /// The string that the symbol refers to
+    pub fn as_str(self) -> &'static str {
+        self.0
+    }
+}
+
+/// Symbols are equal if they point to the same interned string
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Symbol) -> bool {
+        ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_ptr().hash(state);
+    }
+}
+
+/// Symbols are ordered by the strings that they refer to, so the ordering
+/// does not depend on the order in which they were interned
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Symbol) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Symbol) -> Ordering {
+        if self == other {
+            Ordering::Equal
+        } else {
+            str::cmp(self.0, other.0)
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Symbol {
+    fn from(src: &'a str) -> Symbol {
+        Symbol::intern(src)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(src: String) -> Symbol {
+        Symbol::intern(&src)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.0, f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.0, f)
+    }
+}
+
+/// A table of interned strings, for use as the names of variables
+///
+/// This lets the names of variables be interned using some other interner
+/// than the global one used by `Symbol`, for example one that can be freed
+/// once a compilation unit has been checked. Variables can then be named with
+/// the interner's symbols, like `FreeVar<I::Symbol>`, and given names from
+/// strings using `FreeVar::fresh_interned`.
+///
+/// This is implemented for `lasso::Rodeo` when the `lasso` feature is
+/// enabled, and for `string_interner::StringInterner` when the
+/// `string-interner` feature is enabled.
+pub trait Interner {
+    /// The symbols that refer to the interned strings
+    type Symbol: Copy + Eq + Hash;
+
+    /// Intern a string, returning the symbol that refers to it
+    fn intern(&mut self, name: &str) -> Self::Symbol;
+
+    /// The string that a symbol refers to
+    fn resolve(&self, symbol: Self::Symbol) -> &str;
+}
+
+impl<I: Interner + ?Sized> Interner for &mut I {
+    type Symbol = I::Symbol;
+
+    fn intern(&mut self, name: &str) -> I::Symbol {
+        I::intern(self, name)
+    }
+
+    fn resolve(&self, symbol: I::Symbol) -> &str {
+        I::resolve(self, symbol)
+    }
+}
+
+/// The global interner that `Symbol::intern` uses
+#[derive(Debug, Copy, Clone, Default)]
+pub struct GlobalInterner;
+
+impl Interner for GlobalInterner {
+    type Symbol = Symbol;
+
+    fn intern(&mut self, name: &str) -> Symbol {
+        Symbol::intern(name)
+    }
+
+    fn resolve(&self, symbol: Symbol) -> &str {
+        symbol.as_str()
+    }
+}
+
+#[cfg(feature = "lasso")]
+impl<K, S> Interner for lasso::Rodeo<K, S>
+where
+    K: lasso::Key + Hash,
+    S: BuildHasher,
+{
+    type Symbol = K;
+
+    fn intern(&mut self, name: &str) -> K {
+        self.get_or_intern(name)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the symbol was interned by another interner.
+    fn resolve(&self, symbol: K) -> &str {
+        lasso::Rodeo::resolve(self, &symbol)
+    }
+}
+
+#[cfg(feature = "string-interner")]
+impl<B, H> Interner for string_interner::StringInterner<B, H>
+where
+    B: string_interner::backend::Backend,
+    B::Symbol: Hash,
+    H: BuildHasher,
+{
+    type Symbol = B::Symbol;
+
+    fn intern(&mut self, name: &str) -> B::Symbol {
+        self.get_or_intern(name)
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the symbol was interned by another interner.
+    fn resolve(&self, symbol: B::Symbol) -> &str {
+        string_interner::StringInterner::resolve(self, symbol)
+            .expect("cannot resolve a symbol that was interned by another interner")
+    }
+}
@@ -1,20 +1,231 @@
+use std::cell::Cell;
 use std::fmt;
+use std::mem;
+#[cfg(not(feature = "u64-ids"))]
+use std::sync::atomic::AtomicU32 as AtomicRawId;
+#[cfg(feature = "u64-ids")]
+use std::sync::atomic::AtomicU64 as AtomicRawId;
+use std::sync::atomic::Ordering;
+
+use display::{replace_displayed_names, DisplayedNames};
+
+/// The number that a `UniqueId` is represented with
+///
+/// This is a `u32` by default, or a `u64` if the `u64-ids` feature is
+/// enabled. Wider ids make the containing variables larger, but are needed
+/// by long running programs that would otherwise run out of ids.
+#[cfg(not(feature = "u64-ids"))]
+pub type RawId = u32;
+
+/// The number that a `UniqueId` is represented with
+///
+/// This is a `u32` by default, or a `u64` if the `u64-ids` feature is
+/// enabled. Wider ids make the containing variables larger, but are needed
+/// by long running programs that would otherwise run out of ids.
+#[cfg(feature = "u64-ids")]
+pub type RawId = u64;
 
 /// A generated id
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub struct UniqueId(u32);
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UniqueId(RawId);
+
+/// The strategy that `UniqueId::new` uses to generate ids
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IdStrategy {
+    /// Each id is taken from a single global atomic counter
+    Atomic,
+    /// Each thread takes blocks of ids from the global counter, and numbers
+    /// the ids in its current block using a thread-local counter
+    ///
+    /// This is enabled by the `thread-local-ids` feature. Ids are still
+    /// unique across threads, but are no longer consecutive, and generating
+    /// an id only synchronizes with other threads once per block.
+    ThreadLocal,
+}
+
+/// What `UniqueId::new` does once every id has been generated
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IdOverflow {
+    /// Panic, so that an id is never generated twice
+    Panic,
+    /// Wrap around and start again from zero
+    ///
+    /// This is enabled by the `wrapping-ids` feature. Ids are then only
+    /// guaranteed to be distinct from the last `2^UniqueId::BITS` ids that
+    /// were generated before them, which is enough for programs that
+    /// eventually throw away the variables that they create, like language
+    /// servers.
+    Wrap,
+}
+
+/// The next id, or the start of the next block of ids, to be generated
+///
+/// This is initialized at compile time, so no synchronization is needed to
+/// set it up.
+static NEXT_ID: AtomicRawId = AtomicRawId::new(0);
+
+/// Take `count` consecutive ids from the global counter, returning the first
+#[cfg(not(feature = "wrapping-ids"))]
+fn take_ids(count: RawId) -> RawId {
+    NEXT_ID
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |id| {
+            id.checked_add(count)
+        })
+        .expect("cannot generate any more unique ids")
+}
+
+/// Take `count` consecutive ids from the global counter, returning the first
+#[cfg(feature = "wrapping-ids")]
+fn take_ids(count: RawId) -> RawId {
+    NEXT_ID.fetch_add(count, Ordering::Relaxed)
+}
+
+#[cfg(feature = "thread-local-ids")]
+fn next_global_id() -> RawId {
+    /// The number of ids that a thread takes from the global counter at a time
+    const BLOCK_LEN: RawId = 1024;
+
+    thread_local! {
+        /// The next id in the current block, and the end of the block
+        static BLOCK: Cell<(RawId, RawId)> = const { Cell::new((0, 0)) };
+    }
+
+    BLOCK.with(|block| {
+        let (mut id, mut end) = block.get();
+        if id == end {
+            id = take_ids(BLOCK_LEN);
+            // The blocks are aligned, so the last block ends at zero
+            end = id.wrapping_add(BLOCK_LEN);
+        }
+        block.set((id.wrapping_add(1), end));
+        id
+    })
+}
+
+#[cfg(not(feature = "thread-local-ids"))]
+fn next_global_id() -> RawId {
+    take_ids(1)
+}
+
+thread_local! {
+    /// The next id to be generated by `with_isolated_ids` on this thread, if
+    /// it is being called
+    static ISOLATED_NEXT_ID: Cell<Option<RawId>> = const { Cell::new(None) };
+}
+
+fn next_id() -> RawId {
+    let isolated_id = ISOLATED_NEXT_ID.with(|next_id| {
+        let id = next_id.get()?;
+        next_id.set(Some(
+            id.checked_add(1)
+                .expect("cannot generate any more isolated ids"),
+        ));
+        Some(id)
+    });
+    isolated_id.unwrap_or_else(next_global_id)
+}
+
+/// Call `f`, numbering the ids that it generates on the current thread
+/// from zero, separately from the global counter
+///
+/// This makes the names generated by tests and examples the same each time
+/// they are run, no matter which other tests have run before them. The names
+/// that the `Display` impls of variables choose are also forgotten while `f`
+/// runs, so the first variable with a given name hint that `f` displays is
+/// shown without a suffix. The ids
+/// are not unique with respect to the ids generated outside of `f`, so terms
+/// containing them should not be mixed with other terms. Nested calls start
+/// numbering from zero again, and the outer numbering resumes once they
+/// return.
+pub fn with_isolated_ids<T>(f: impl FnOnce() -> T) -> T {
+    /// Restores the previous numbering and names, even if `f` panics
+    struct Restore(Option<RawId>, DisplayedNames);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            ISOLATED_NEXT_ID.with(|next_id| next_id.set(self.0));
+            replace_displayed_names(mem::take(&mut self.1));
+        }
+    }
+
+    let _restore = Restore(
+        ISOLATED_NEXT_ID.with(|next_id| next_id.replace(Some(0))),
+        replace_displayed_names(DisplayedNames::default()),
+    );
+    f()
+}
 
 impl UniqueId {
+    /// The strategy that is used to generate ids, as selected by the
+    /// `thread-local-ids` feature
+    #[cfg(feature = "thread-local-ids")]
+    pub const STRATEGY: IdStrategy = IdStrategy::ThreadLocal;
+
+    /// The strategy that is used to generate ids, as selected by the
+    /// `thread-local-ids` feature
+    #[cfg(not(feature = "thread-local-ids"))]
+    pub const STRATEGY: IdStrategy = IdStrategy::Atomic;
+
+    /// The number of bits in an id, as selected by the `u64-ids` feature
+    #[cfg(not(feature = "u64-ids"))]
+    pub const BITS: u32 = 32;
+
+    /// The number of bits in an id, as selected by the `u64-ids` feature
+    #[cfg(feature = "u64-ids")]
+    pub const BITS: u32 = 64;
+
+    /// What happens once every id has been generated, as selected by the
+    /// `wrapping-ids` feature
+    #[cfg(not(feature = "wrapping-ids"))]
+    pub const OVERFLOW: IdOverflow = IdOverflow::Panic;
+
+    /// What happens once every id has been generated, as selected by the
+    /// `wrapping-ids` feature
+    #[cfg(feature = "wrapping-ids")]
+    pub const OVERFLOW: IdOverflow = IdOverflow::Wrap;
+
     /// Generate a new, globally unique id
+    ///
+    /// Ids that are generated inside of `with_isolated_ids` are numbered
+    /// separately, and are only unique within the call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the global supply of ids runs out, unless the `wrapping-ids`
+    /// feature is enabled.
     pub fn new() -> UniqueId {
-        use std::sync::atomic::{AtomicUsize, Ordering};
+        UniqueId(next_id())
+    }
 
-        lazy_static! {
-            static ref NEXT_ID: AtomicUsize = AtomicUsize::new(0);
-        }
+    /// The number of ids that have been taken from the global supply
+    ///
+    /// When the `thread-local-ids` feature is enabled this counts the ids in
+    /// the blocks that have been handed out to threads, including the ones
+    /// that have not been used yet. When the `wrapping-ids` feature is
+    /// enabled this starts again from zero each time the ids wrap around.
+    pub fn issued() -> RawId {
+        NEXT_ID.load(Ordering::Relaxed)
+    }
+
+    /// Create an id from a number, for when the numbering of variables needs
+    /// to be controlled, for example when replaying a type checking session
+    ///
+    /// Ids that are created in this way are not guaranteed to be unique, and
+    /// may clash with the ids generated by `UniqueId::new`, so care must be
+    /// taken not to mix the two.
+    pub fn from_raw(id: RawId) -> UniqueId {
+        UniqueId(id)
+    }
+
+    /// The number that the id was created with
+    pub fn to_raw(self) -> RawId {
+        self.0
+    }
+}
 
-        // FIXME: check for integer overflow
-        UniqueId(NEXT_ID.fetch_add(1, Ordering::SeqCst) as u32)
+impl Default for UniqueId {
+    fn default() -> UniqueId {
+        UniqueId::new()
     }
 }
 
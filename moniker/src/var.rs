@@ -24,6 +24,30 @@ impl<N> Var<N> {
 
 impl<N> Eq for Var<N> where N: Eq {}
 
+/// Compares the name hint of the variable, whether it is free or bound, like
+/// the impl for `FreeVar<String>`
+impl PartialEq<str> for Var<String> {
+    fn eq(&self, other: &str) -> bool {
+        self.pretty_name()
+            .is_some_and(|pretty_name| pretty_name == other)
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Var<String> {
+    fn eq(&self, other: &&'a str) -> bool {
+        *self == **other
+    }
+}
+
+/// Creates a fresh free variable with the string as its name hint
+impl<'a> From<&'a str> for Var<String> {
+    fn from(src: &'a str) -> Var<String> {
+        Var::Free(FreeVar::from(src))
+    }
+}
+
+/// Displays free variables in the same way as `FreeVar`, and bound variables
+/// using their name hints and positions, like `x@0.0`
 impl<N: fmt::Display> fmt::Display for Var<N> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
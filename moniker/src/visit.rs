@@ -0,0 +1,276 @@
+use std::rc::Rc;
+use std::sync::Arc;
+
+use ann_binder::AnnBinder;
+use bind_set::BindSet;
+use binder::Binder;
+use closed::Closed;
+use embed::Embed;
+use free_var::FreeVar;
+use ignore::Ignore;
+use meta_var::MetaVar;
+use nest::Nest;
+use rebind::Rebind;
+use rec::Rec;
+use record::Record;
+use scope::Scope;
+use small_vec::SmallVec;
+use spanned::Spanned;
+use var::Var;
+use wildcard::Wildcard;
+
+/// Callbacks that are made when a visitor moves in and out of scopes
+///
+/// The depth of the scopes that have been entered corresponds to the scope
+/// offsets of any bound variables that are encountered along the way.
+pub trait ScopeVisitor {
+    /// Called before visiting the body of a scope
+    fn enter_scope(&mut self) {}
+
+    /// Called after visiting the body of a scope
+    fn exit_scope(&mut self) {}
+}
+
+/// Walk over a syntax tree using a visitor
+///
+/// This can be derived for your own types using `#[derive(Visit)]`, which will
+/// also generate a visitor trait with a hook for each variant.
+pub trait Visit<V: ?Sized> {
+    /// Visit each part of the term, calling the hooks of the `visitor` along
+    /// the way
+    fn visit(&self, visitor: &mut V);
+}
+
+impl<V: ?Sized, N> Visit<V> for Var<N> {
+    fn visit(&self, _: &mut V) {}
+}
+
+impl<V: ?Sized, N> Visit<V> for FreeVar<N> {
+    fn visit(&self, _: &mut V) {}
+}
+
+impl<V: ?Sized, N> Visit<V> for MetaVar<N> {
+    fn visit(&self, _: &mut V) {}
+}
+
+impl<V: ?Sized, N> Visit<V> for Binder<N> {
+    fn visit(&self, _: &mut V) {}
+}
+
+impl<V: ?Sized, N, A> Visit<V> for AnnBinder<N, A> {
+    fn visit(&self, _: &mut V) {}
+}
+
+impl<V: ?Sized, N> Visit<V> for BindSet<N> {
+    fn visit(&self, _: &mut V) {}
+}
+
+impl<V: ?Sized, T> Visit<V> for Ignore<T> {
+    fn visit(&self, _: &mut V) {}
+}
+
+impl<V: ?Sized> Visit<V> for Wildcard {
+    fn visit(&self, _: &mut V) {}
+}
+
+impl<V: ?Sized, T: Visit<V>> Visit<V> for Closed<T> {
+    fn visit(&self, visitor: &mut V) {
+        self.unsafe_term.visit(visitor);
+    }
+}
+
+impl<V: ?Sized, T: Visit<V>> Visit<V> for Embed<T> {
+    fn visit(&self, visitor: &mut V) {
+        self.0.visit(visitor);
+    }
+}
+
+impl<V: ?Sized + ScopeVisitor, P: Visit<V>> Visit<V> for Nest<P> {
+    fn visit(&self, visitor: &mut V) {
+        // Each pattern is nested in the scope of the previous patterns
+        for pattern in &self.unsafe_patterns {
+            pattern.visit(visitor);
+            visitor.enter_scope();
+        }
+        for _ in &self.unsafe_patterns {
+            visitor.exit_scope();
+        }
+    }
+}
+
+impl<V, P1, P2> Visit<V> for Rebind<P1, P2>
+where
+    V: ?Sized + ScopeVisitor,
+    P1: Visit<V>,
+    P2: Visit<V>,
+{
+    fn visit(&self, visitor: &mut V) {
+        // The right pattern is nested in the scope of the left pattern
+        self.unsafe_left.visit(visitor);
+        visitor.enter_scope();
+        self.unsafe_right.visit(visitor);
+        visitor.exit_scope();
+    }
+}
+
+impl<V: ?Sized, P: Visit<V>> Visit<V> for Rec<P> {
+    fn visit(&self, visitor: &mut V) {
+        self.unsafe_pattern.visit(visitor);
+    }
+}
+
+impl<V: ?Sized, L, P: Visit<V>> Visit<V> for Record<L, P> {
+    fn visit(&self, visitor: &mut V) {
+        for field in &self.unsafe_fields {
+            field.1.visit(visitor);
+        }
+    }
+}
+
+impl<V: ?Sized + ScopeVisitor, P: Visit<V>, T: Visit<V>> Visit<V> for Scope<P, T> {
+    fn visit(&self, visitor: &mut V) {
+        self.unsafe_pattern.visit(visitor);
+        visitor.enter_scope();
+        self.unsafe_body.visit(visitor);
+        visitor.exit_scope();
+    }
+}
+
+impl<V: ?Sized, S, T: Visit<V>> Visit<V> for Spanned<S, T> {
+    fn visit(&self, visitor: &mut V) {
+        self.inner.visit(visitor);
+    }
+}
+
+// Implementations for common types
+
+macro_rules! impl_visit_leaf {
+    ([$($lt:lifetime),*] $T:ty) => {
+        impl<$($lt,)* V: ?Sized> Visit<V> for $T {
+            fn visit(&self, _: &mut V) {}
+        }
+    };
+    ($T:ty) => {
+        impl_visit_leaf!([] $T);
+    };
+}
+
+impl_visit_leaf!(());
+impl_visit_leaf!(String);
+impl_visit_leaf!(str);
+impl_visit_leaf!(['a] &'a str);
+impl_visit_leaf!(char);
+impl_visit_leaf!(bool);
+impl_visit_leaf!(u8);
+impl_visit_leaf!(u16);
+impl_visit_leaf!(u32);
+impl_visit_leaf!(u64);
+impl_visit_leaf!(usize);
+impl_visit_leaf!(i8);
+impl_visit_leaf!(i16);
+impl_visit_leaf!(i32);
+impl_visit_leaf!(i64);
+impl_visit_leaf!(isize);
+impl_visit_leaf!(f32);
+impl_visit_leaf!(f64);
+
+impl<V: ?Sized, T: Visit<V>> Visit<V> for Option<T> {
+    fn visit(&self, visitor: &mut V) {
+        if let Some(ref inner) = *self {
+            inner.visit(visitor);
+        }
+    }
+}
+
+impl<V: ?Sized, T: Visit<V> + ?Sized> Visit<V> for Box<T> {
+    fn visit(&self, visitor: &mut V) {
+        T::visit(self, visitor);
+    }
+}
+
+impl<V: ?Sized, T: Visit<V> + ?Sized> Visit<V> for Rc<T> {
+    fn visit(&self, visitor: &mut V) {
+        T::visit(self, visitor);
+    }
+}
+
+impl<V: ?Sized, T: Visit<V> + ?Sized> Visit<V> for Arc<T> {
+    fn visit(&self, visitor: &mut V) {
+        T::visit(self, visitor);
+    }
+}
+
+impl<V: ?Sized, T1: Visit<V>, T2: Visit<V>> Visit<V> for (T1, T2) {
+    fn visit(&self, visitor: &mut V) {
+        self.0.visit(visitor);
+        self.1.visit(visitor);
+    }
+}
+
+impl<V: ?Sized, T1: Visit<V>, T2: Visit<V>, T3: Visit<V>> Visit<V> for (T1, T2, T3) {
+    fn visit(&self, visitor: &mut V) {
+        self.0.visit(visitor);
+        self.1.visit(visitor);
+        self.2.visit(visitor);
+    }
+}
+
+impl<V, T1, T2, T3, T4> Visit<V> for (T1, T2, T3, T4)
+where
+    V: ?Sized,
+    T1: Visit<V>,
+    T2: Visit<V>,
+    T3: Visit<V>,
+    T4: Visit<V>,
+{
+    fn visit(&self, visitor: &mut V) {
+        self.0.visit(visitor);
+        self.1.visit(visitor);
+        self.2.visit(visitor);
+        self.3.visit(visitor);
+    }
+}
+
+impl<V, T1, T2, T3, T4, T5> Visit<V> for (T1, T2, T3, T4, T5)
+where
+    V: ?Sized,
+    T1: Visit<V>,
+    T2: Visit<V>,
+    T3: Visit<V>,
+    T4: Visit<V>,
+    T5: Visit<V>,
+{
+    fn visit(&self, visitor: &mut V) {
+        self.0.visit(visitor);
+        self.1.visit(visitor);
+        self.2.visit(visitor);
+        self.3.visit(visitor);
+        self.4.visit(visitor);
+    }
+}
+
+impl<V: ?Sized, T: Visit<V>> Visit<V> for [T] {
+    fn visit(&self, visitor: &mut V) {
+        for elem in self {
+            elem.visit(visitor);
+        }
+    }
+}
+
+impl<V: ?Sized, T: Visit<V>, const LEN: usize> Visit<V> for [T; LEN] {
+    fn visit(&self, visitor: &mut V) {
+        <[T]>::visit(self, visitor);
+    }
+}
+
+impl<V: ?Sized, T: Visit<V>> Visit<V> for Vec<T> {
+    fn visit(&self, visitor: &mut V) {
+        <[T]>::visit(self, visitor);
+    }
+}
+
+impl<V: ?Sized, T: Visit<V>> Visit<V> for SmallVec<T> {
+    fn visit(&self, visitor: &mut V) {
+        <[T]>::visit(self, visitor);
+    }
+}
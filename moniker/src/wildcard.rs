@@ -0,0 +1,77 @@
+use alpha_mismatch::PathSegment;
+use binder::Binder;
+use bound::{BinderStack, BoundPattern, BoundTerm, OnBoundFn, OnFreeFn, ScopeState};
+use subst::Subst;
+use var::Var;
+
+/// A pattern that binds no variables, like the `_` parameters found in many
+/// surface languages
+///
+/// Wildcards are always alpha equal to each other, in the same way as
+/// binders. Because they bind nothing, they are skipped when numbering the
+/// binders of a pattern, so in a pattern like `(x, _, y)` the binder `y`
+/// will be given the index `1`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Wildcard;
+
+impl<N> BoundPattern<N> for Wildcard
+where
+    N: Clone + PartialEq,
+{
+    fn pattern_eq(&self, _: &Wildcard) -> bool {
+        true
+    }
+
+    fn close_pattern(&mut self, _: ScopeState, _: &impl OnFreeFn<N>) {}
+
+    fn open_pattern(&mut self, _: ScopeState, _: &impl OnBoundFn<N>) {}
+
+    fn visit_vars(&self, _: &mut impl FnMut(&Var<N>)) {}
+
+    fn visit_mut_vars(&mut self, _: &mut impl FnMut(&mut Var<N>)) {}
+
+    fn visit_vars_with_binders(
+        &self,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&BinderStack<N>, &Var<N>),
+    ) {
+    }
+
+    fn visit_scopes(
+        &self,
+        _: &mut Vec<PathSegment<N>>,
+        _: &mut BinderStack<N>,
+        _: &mut impl FnMut(&[PathSegment<N>], &BinderStack<N>, &[Binder<N>]),
+    ) {
+    }
+
+    fn visit_nodes(&self, _: usize, _: &mut impl FnMut(usize)) {}
+
+    fn visit_binders(&self, _: &mut impl FnMut(&Binder<N>)) {}
+
+    fn visit_mut_binders(&mut self, _: &mut impl FnMut(&mut Binder<N>)) {}
+
+    fn order_binders(&mut self, _: &impl BoundTerm<N>) {}
+}
+
+impl<N, T> Subst<N, T> for Wildcard {
+    fn subst<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Wildcard {
+        Wildcard
+    }
+
+    fn substs<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Wildcard {
+        Wildcard
+    }
+
+    fn subst_mut<V: PartialEq<Var<N>>>(&mut self, _: &V, _: &T) {}
+
+    fn substs_mut<V: PartialEq<Var<N>>>(&mut self, _: &[(V, T)]) {}
+
+    fn subst_changed<V: PartialEq<Var<N>>>(&self, _: &V, _: &T) -> Option<Wildcard> {
+        None
+    }
+
+    fn substs_changed<V: PartialEq<Var<N>>>(&self, _: &[(V, T)]) -> Option<Wildcard> {
+        None
+    }
+}
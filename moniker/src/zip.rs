@@ -0,0 +1,692 @@
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use ann_binder::AnnBinder;
+use bind_set::BindSet;
+use binder::Binder;
+use bound::{BinderStack, BoundTerm};
+use closed::Closed;
+use embed::Embed;
+use free_var::FreeVar;
+use ignore::Ignore;
+use meta_var::MetaVar;
+use nest::Nest;
+use rebind::Rebind;
+use rec::Rec;
+use record::Record;
+use scope::Scope;
+use small_vec::SmallVec;
+use spanned::Spanned;
+use subst::Subst;
+use substitution::Substitution;
+use var::Var;
+use wildcard::Wildcard;
+
+/// Traversal of two terms in lock-step
+///
+/// `T` is the type of the subterms that are handed to the [`Zipper`], in the
+/// same way as the replacement terms of `Subst`. This can be derived, and is
+/// used to implement operations that compare the structure of two terms up to
+/// alpha equivalence, like [`Zip::alpha_match`].
+///
+/// The names of binders are never compared, so the terms are zipped together
+/// without needing to be unbound.
+///
+/// [`Zipper`]: trait.Zipper.html
+/// [`Zip::alpha_match`]: trait.Zip.html#method.alpha_match
+pub trait Zip<N, T>: Sized {
+    /// Zip this term with `other`, handing it to the `zipper` if it has the
+    /// same type as the zipped subterms
+    fn zip_with(&self, other: &Self, zipper: &mut impl Zipper<N, T>) -> Option<Self> {
+        self.zip_structure(other, zipper)
+    }
+
+    /// Zip the fields of this term with the fields of `other`, returning
+    /// `None` if the terms have a different shape
+    fn zip_structure(&self, other: &Self, zipper: &mut impl Zipper<N, T>) -> Option<Self>;
+
+    /// Match this term against a `subject` term, treating the free variables
+    /// in `holes` as pattern variables
+    ///
+    /// Returns the substitution that makes this term alpha equivalent to the
+    /// subject, or `None` if there is no such substitution. Each hole is
+    /// matched with a subterm of the subject that does not refer to any
+    /// variables bound outside the subterm, and each occurrence of a hole
+    /// must match an alpha equivalent subterm.
+    fn alpha_match(&self, subject: &Self, holes: &[FreeVar<N>]) -> Option<Substitution<N, T>>
+    where
+        N: Clone + PartialEq,
+        T: BoundTerm<N> + Subst<N, T> + Zip<N, T> + Clone,
+    {
+        let mut matcher = Matcher {
+            holes,
+            substitution: Substitution::new(),
+        };
+        self.zip_with(subject, &mut matcher)?;
+        Some(matcher.substitution)
+    }
+
+    /// Find the most specific generalization of this term and `other`
+    ///
+    /// Subterms that differ between the two terms are replaced with fresh
+    /// variables, constructed with `mk_var`, and the returned substitutions
+    /// map these variables back to the subterms from this term and from
+    /// `other` respectively. Each pair of differing subterms is generalized
+    /// to the same variable wherever it occurs, and subterms that refer to
+    /// variables bound outside of them are never generalized.
+    ///
+    /// Returns `None` if the terms differ in a place that can't be replaced
+    /// with a variable.
+    #[allow(clippy::type_complexity)]
+    fn anti_unify(
+        &self,
+        other: &Self,
+        mk_var: impl FnMut(FreeVar<N>) -> T,
+    ) -> Option<(Self, Substitution<N, T>, Substitution<N, T>)>
+    where
+        N: Clone + PartialEq,
+        T: BoundTerm<N> + Zip<N, T> + Clone,
+    {
+        let mut generalizer = Generalizer {
+            mk_var,
+            generalized: Vec::new(),
+        };
+        let term = self.zip_with(other, &mut generalizer)?;
+
+        let mut lhs_substitution = Substitution::new();
+        let mut rhs_substitution = Substitution::new();
+        for (free_var, lhs, rhs) in generalizer.generalized {
+            lhs_substitution.insert(free_var.clone(), lhs);
+            rhs_substitution.insert(free_var, rhs);
+        }
+
+        Some((term, lhs_substitution, rhs_substitution))
+    }
+
+    /// Unify this term with `other`, treating the free variables in `metas`
+    /// as metavariables that may be solved
+    ///
+    /// Returns the most general substitution of the metavariables that makes
+    /// the terms alpha equivalent. The solutions in the substitution have
+    /// already had the other solutions applied to them, so the substitution
+    /// only needs to be applied to a term once.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the terms have a different structure, if a
+    /// metavariable would need to be solved with a term that contains it, or
+    /// if a metavariable would need to be solved with a term that refers to
+    /// variables that were bound outside of it.
+    fn unify(&self, other: &Self, metas: &[FreeVar<N>]) -> Result<Substitution<N, T>, UnifyError<N>>
+    where
+        N: Clone + PartialEq,
+        T: BoundTerm<N> + Subst<N, T> + Zip<N, T> + Clone,
+    {
+        let mut unifier = Unifier {
+            metas,
+            substitution: Substitution::new(),
+            error: None,
+        };
+        match self.zip_with(other, &mut unifier) {
+            Some(_) => Ok(unifier.substitution),
+            None => Err(unifier.error.unwrap_or(UnifyError::Mismatch)),
+        }
+    }
+}
+
+/// Combines the subterms that are found at the same position in two terms
+/// that are being zipped together
+pub trait Zipper<N, T> {
+    /// Zip two subterms, returning `None` if they can't be combined
+    ///
+    /// The subterms can be compared structurally by zipping them with
+    /// `Zip::zip_structure`, passing this zipper along.
+    fn zip_terms(&mut self, lhs: &T, rhs: &T) -> Option<T>;
+}
+
+/// Returns `true` if the term does not refer to any variables that were bound
+/// outside of it
+pub(crate) fn is_locally_closed<N, T>(term: &T) -> bool
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N>,
+{
+    let mut is_locally_closed = true;
+    term.visit_vars_with_binders(&mut BinderStack::new(), &mut |binders, var| {
+        if let Var::Bound(ref bound_var) = *var {
+            if binders.lookup(bound_var).is_none() {
+                is_locally_closed = false;
+            }
+        }
+    });
+    is_locally_closed
+}
+
+struct Matcher<'a, N: 'a, T> {
+    holes: &'a [FreeVar<N>],
+    substitution: Substitution<N, T>,
+}
+
+impl<'a, N, T> Zipper<N, T> for Matcher<'a, N, T>
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N> + Subst<N, T> + Zip<N, T> + Clone,
+{
+    fn zip_terms(&mut self, lhs: &T, rhs: &T) -> Option<T> {
+        match lhs.as_var() {
+            Some(Var::Free(hole)) if self.holes.contains(hole) => {
+                if !is_locally_closed(rhs) {
+                    return None;
+                }
+                match self.substitution.get(hole) {
+                    Some(matched) if !T::term_eq(matched, rhs) => return None,
+                    Some(_) => {},
+                    None => {
+                        self.substitution.insert(hole.clone(), rhs.clone());
+                    },
+                }
+                Some(rhs.clone())
+            },
+            _ => lhs.zip_structure(rhs, self),
+        }
+    }
+}
+
+struct Generalizer<N, T, F> {
+    mk_var: F,
+    generalized: Vec<(FreeVar<N>, T, T)>,
+}
+
+impl<N, T, F> Zipper<N, T> for Generalizer<N, T, F>
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N> + Zip<N, T> + Clone,
+    F: FnMut(FreeVar<N>) -> T,
+{
+    fn zip_terms(&mut self, lhs: &T, rhs: &T) -> Option<T> {
+        // Forget the generalizations made while zipping the subterms if the
+        // subterms turn out to have a different shape
+        let len = self.generalized.len();
+        if let Some(term) = lhs.zip_structure(rhs, self) {
+            return Some(term);
+        }
+        self.generalized.truncate(len);
+
+        if !is_locally_closed(lhs) || !is_locally_closed(rhs) {
+            return None;
+        }
+
+        let existing = self
+            .generalized
+            .iter()
+            .find(|(_, prev_lhs, prev_rhs)| T::term_eq(prev_lhs, lhs) && T::term_eq(prev_rhs, rhs))
+            .map(|(free_var, _, _)| free_var.clone());
+        let free_var = match existing {
+            Some(free_var) => free_var,
+            None => {
+                let free_var = FreeVar::fresh_unnamed();
+                let generalized = (free_var.clone(), lhs.clone(), rhs.clone());
+                self.generalized.push(generalized);
+                free_var
+            },
+        };
+
+        Some((self.mk_var)(free_var))
+    }
+}
+
+/// An error produced when unifying two terms
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnifyError<N> {
+    /// The terms have a different structure
+    Mismatch,
+    /// The metavariable would need to be solved with a term that contains it
+    Occurs(FreeVar<N>),
+    /// The metavariable would need to be solved with a term that refers to
+    /// variables that were bound outside of it
+    Escape(FreeVar<N>),
+}
+
+impl<N: fmt::Display> fmt::Display for UnifyError<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UnifyError::Mismatch => write!(f, "cannot unify terms with a different structure"),
+            UnifyError::Occurs(ref meta) => write!(
+                f,
+                "cannot solve the metavariable `{}` with a term that contains it",
+                meta,
+            ),
+            UnifyError::Escape(ref meta) => write!(
+                f,
+                "cannot solve the metavariable `{}` with a term that refers to bound variables",
+                meta,
+            ),
+        }
+    }
+}
+
+impl<N: fmt::Debug + fmt::Display> Error for UnifyError<N> {}
+
+struct Unifier<'a, N: 'a, T> {
+    metas: &'a [FreeVar<N>],
+    substitution: Substitution<N, T>,
+    error: Option<UnifyError<N>>,
+}
+
+impl<'a, N, T> Unifier<'a, N, T>
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N> + Subst<N, T> + Zip<N, T> + Clone,
+{
+    fn as_meta(&self, term: &T) -> Option<FreeVar<N>> {
+        match term.as_var() {
+            Some(Var::Free(free_var)) if self.metas.contains(free_var) => Some(free_var.clone()),
+            Some(_) | None => None,
+        }
+    }
+
+    fn solve(&mut self, meta: FreeVar<N>, term: T) -> Option<T> {
+        if term.as_var().is_some_and(|var| *var == meta) {
+            return Some(term);
+        }
+        if term.count_occurrences(&meta) > 0 {
+            self.error = Some(UnifyError::Occurs(meta));
+            return None;
+        }
+        if !is_locally_closed(&term) {
+            self.error = Some(UnifyError::Escape(meta));
+            return None;
+        }
+
+        // Keep the substitution idempotent by applying the new solution to
+        // the existing solutions
+        let mut solution = Substitution::new();
+        solution.insert(meta, term.clone());
+        self.substitution = solution.compose(&self.substitution);
+
+        Some(term)
+    }
+}
+
+impl<'a, N, T> Zipper<N, T> for Unifier<'a, N, T>
+where
+    N: Clone + PartialEq,
+    T: BoundTerm<N> + Subst<N, T> + Zip<N, T> + Clone,
+{
+    fn zip_terms(&mut self, lhs: &T, rhs: &T) -> Option<T> {
+        let lhs = self.substitution.apply(lhs);
+        let rhs = self.substitution.apply(rhs);
+
+        if let Some(meta) = self.as_meta(&lhs) {
+            return self.solve(meta, rhs);
+        }
+        if let Some(meta) = self.as_meta(&rhs) {
+            return self.solve(meta, lhs);
+        }
+        lhs.zip_structure(&rhs, self)
+    }
+}
+
+macro_rules! impl_zip_term_eq {
+    ($T:ty) => {
+        impl<N: Clone + PartialEq, T> Zip<N, T> for $T {
+            fn zip_structure(&self, other: &$T, _: &mut impl Zipper<N, T>) -> Option<$T> {
+                if BoundTerm::<N>::term_eq(self, other) {
+                    Some(self.clone())
+                } else {
+                    None
+                }
+            }
+        }
+    };
+}
+
+impl_zip_term_eq!(Var<N>);
+impl_zip_term_eq!(FreeVar<N>);
+impl_zip_term_eq!(MetaVar<N>);
+
+impl<N: Clone, T> Zip<N, T> for Binder<N> {
+    fn zip_structure(&self, _: &Binder<N>, _: &mut impl Zipper<N, T>) -> Option<Binder<N>> {
+        Some(self.clone())
+    }
+}
+
+impl<N: Clone, T> Zip<N, T> for BindSet<N> {
+    fn zip_structure(&self, other: &BindSet<N>, _: &mut impl Zipper<N, T>) -> Option<BindSet<N>> {
+        if self.len() == other.len() {
+            Some(self.clone())
+        } else {
+            None
+        }
+    }
+}
+
+impl<N, T, U: Clone> Zip<N, T> for Ignore<U> {
+    fn zip_structure(&self, _: &Ignore<U>, _: &mut impl Zipper<N, T>) -> Option<Ignore<U>> {
+        Some(self.clone())
+    }
+}
+
+impl<N, T> Zip<N, T> for Wildcard {
+    fn zip_structure(&self, _: &Wildcard, _: &mut impl Zipper<N, T>) -> Option<Wildcard> {
+        Some(*self)
+    }
+}
+
+impl<N, T, U: Zip<N, T>> Zip<N, T> for Embed<U> {
+    fn zip_structure(&self, other: &Embed<U>, zipper: &mut impl Zipper<N, T>) -> Option<Embed<U>> {
+        Some(Embed(self.0.zip_with(&other.0, zipper)?))
+    }
+}
+
+impl<N, T, P: Zip<N, T>> Zip<N, T> for Nest<P> {
+    fn zip_structure(&self, other: &Nest<P>, zipper: &mut impl Zipper<N, T>) -> Option<Nest<P>> {
+        Some(Nest {
+            unsafe_patterns: self
+                .unsafe_patterns
+                .zip_with(&other.unsafe_patterns, zipper)?,
+        })
+    }
+}
+
+impl<N, T, P1, P2> Zip<N, T> for Rebind<P1, P2>
+where
+    P1: Zip<N, T>,
+    P2: Zip<N, T>,
+{
+    fn zip_structure(
+        &self,
+        other: &Rebind<P1, P2>,
+        zipper: &mut impl Zipper<N, T>,
+    ) -> Option<Rebind<P1, P2>> {
+        Some(Rebind {
+            unsafe_left: self.unsafe_left.zip_with(&other.unsafe_left, zipper)?,
+            unsafe_right: self.unsafe_right.zip_with(&other.unsafe_right, zipper)?,
+        })
+    }
+}
+
+impl<N, T, P: Zip<N, T>> Zip<N, T> for Rec<P> {
+    fn zip_structure(&self, other: &Rec<P>, zipper: &mut impl Zipper<N, T>) -> Option<Rec<P>> {
+        Some(Rec {
+            unsafe_pattern: self
+                .unsafe_pattern
+                .zip_with(&other.unsafe_pattern, zipper)?,
+        })
+    }
+}
+
+impl<N, T, L, P> Zip<N, T> for Record<L, P>
+where
+    L: Clone + PartialEq,
+    P: Zip<N, T>,
+{
+    fn zip_structure(
+        &self,
+        other: &Record<L, P>,
+        zipper: &mut impl Zipper<N, T>,
+    ) -> Option<Record<L, P>> {
+        if self.unsafe_fields.len() != other.unsafe_fields.len() {
+            return None;
+        }
+
+        let mut unsafe_fields = Vec::with_capacity(self.unsafe_fields.len());
+        for (lhs, rhs) in self.unsafe_fields.iter().zip(&other.unsafe_fields) {
+            if lhs.0 != rhs.0 {
+                return None;
+            }
+            unsafe_fields.push((lhs.0.clone(), lhs.1.zip_with(&rhs.1, zipper)?));
+        }
+
+        Some(Record { unsafe_fields })
+    }
+}
+
+impl<N, T, P, U> Zip<N, T> for Scope<P, U>
+where
+    P: Zip<N, T>,
+    U: Zip<N, T>,
+{
+    fn zip_structure(
+        &self,
+        other: &Scope<P, U>,
+        zipper: &mut impl Zipper<N, T>,
+    ) -> Option<Scope<P, U>> {
+        Some(Scope {
+            unsafe_pattern: self
+                .unsafe_pattern
+                .zip_with(&other.unsafe_pattern, zipper)?,
+            unsafe_body: self.unsafe_body.zip_with(&other.unsafe_body, zipper)?,
+        })
+    }
+}
+
+impl<N, T, U: Zip<N, T>> Zip<N, T> for Closed<U> {
+    fn zip_structure(
+        &self,
+        other: &Closed<U>,
+        zipper: &mut impl Zipper<N, T>,
+    ) -> Option<Closed<U>> {
+        Some(Closed {
+            unsafe_term: self.unsafe_term.zip_with(&other.unsafe_term, zipper)?,
+        })
+    }
+}
+
+impl<N: Clone, T, A: Zip<N, T>> Zip<N, T> for AnnBinder<N, A> {
+    fn zip_structure(
+        &self,
+        other: &AnnBinder<N, A>,
+        zipper: &mut impl Zipper<N, T>,
+    ) -> Option<AnnBinder<N, A>> {
+        Some(AnnBinder {
+            binder: self.binder.clone(),
+            annotation: self.annotation.zip_with(&other.annotation, zipper)?,
+        })
+    }
+}
+
+impl<N, T, S: Clone, U: Zip<N, T>> Zip<N, T> for Spanned<S, U> {
+    fn zip_structure(
+        &self,
+        other: &Spanned<S, U>,
+        zipper: &mut impl Zipper<N, T>,
+    ) -> Option<Spanned<S, U>> {
+        Some(Spanned {
+            span: self.span.clone(),
+            inner: self.inner.zip_with(&other.inner, zipper)?,
+        })
+    }
+}
+
+// Implementations for common types
+
+macro_rules! impl_zip_eq {
+    ([$($param:tt)*] $T:ty) => {
+        impl<$($param)*, T> Zip<N, T> for $T
+        where
+            $T: Clone + PartialEq,
+        {
+            fn zip_structure(&self, other: &$T, _: &mut impl Zipper<N, T>) -> Option<$T> {
+                if self == other {
+                    Some(self.clone())
+                } else {
+                    None
+                }
+            }
+        }
+    };
+    ($T:ty) => {
+        impl_zip_eq!([N] $T);
+    };
+}
+
+impl_zip_eq!(());
+impl_zip_eq!(String);
+impl_zip_eq!(['a, N] &'a str);
+impl_zip_eq!([N] Arc<str>);
+impl_zip_eq!(char);
+impl_zip_eq!(bool);
+impl_zip_eq!(u8);
+impl_zip_eq!(u16);
+impl_zip_eq!(u32);
+impl_zip_eq!(u64);
+impl_zip_eq!(usize);
+impl_zip_eq!(i8);
+impl_zip_eq!(i16);
+impl_zip_eq!(i32);
+impl_zip_eq!(i64);
+impl_zip_eq!(isize);
+impl_zip_eq!(f32);
+impl_zip_eq!(f64);
+
+impl<N, T, U: Zip<N, T>> Zip<N, T> for Option<U> {
+    fn zip_structure(
+        &self,
+        other: &Option<U>,
+        zipper: &mut impl Zipper<N, T>,
+    ) -> Option<Option<U>> {
+        match (self, other) {
+            (Some(lhs), Some(rhs)) => Some(Some(lhs.zip_with(rhs, zipper)?)),
+            (None, None) => Some(None),
+            (_, _) => None,
+        }
+    }
+}
+
+impl<N, T, U: Zip<N, T>> Zip<N, T> for Box<U> {
+    fn zip_structure(&self, other: &Box<U>, zipper: &mut impl Zipper<N, T>) -> Option<Box<U>> {
+        Some(Box::new(U::zip_with(self, other, zipper)?))
+    }
+}
+
+impl<N, T, U: Zip<N, T>> Zip<N, T> for Rc<U> {
+    fn zip_structure(&self, other: &Rc<U>, zipper: &mut impl Zipper<N, T>) -> Option<Rc<U>> {
+        Some(Rc::new(U::zip_with(self, other, zipper)?))
+    }
+}
+
+impl<N, T, U: Zip<N, T>> Zip<N, T> for Arc<U> {
+    fn zip_structure(&self, other: &Arc<U>, zipper: &mut impl Zipper<N, T>) -> Option<Arc<U>> {
+        Some(Arc::new(U::zip_with(self, other, zipper)?))
+    }
+}
+
+impl<N, T, U1, U2> Zip<N, T> for (U1, U2)
+where
+    U1: Zip<N, T>,
+    U2: Zip<N, T>,
+{
+    fn zip_structure(&self, other: &(U1, U2), zipper: &mut impl Zipper<N, T>) -> Option<(U1, U2)> {
+        Some((
+            self.0.zip_with(&other.0, zipper)?,
+            self.1.zip_with(&other.1, zipper)?,
+        ))
+    }
+}
+
+impl<N, T, U1, U2, U3> Zip<N, T> for (U1, U2, U3)
+where
+    U1: Zip<N, T>,
+    U2: Zip<N, T>,
+    U3: Zip<N, T>,
+{
+    fn zip_structure(
+        &self,
+        other: &(U1, U2, U3),
+        zipper: &mut impl Zipper<N, T>,
+    ) -> Option<(U1, U2, U3)> {
+        Some((
+            self.0.zip_with(&other.0, zipper)?,
+            self.1.zip_with(&other.1, zipper)?,
+            self.2.zip_with(&other.2, zipper)?,
+        ))
+    }
+}
+
+impl<N, T, U1, U2, U3, U4> Zip<N, T> for (U1, U2, U3, U4)
+where
+    U1: Zip<N, T>,
+    U2: Zip<N, T>,
+    U3: Zip<N, T>,
+    U4: Zip<N, T>,
+{
+    fn zip_structure(
+        &self,
+        other: &(U1, U2, U3, U4),
+        zipper: &mut impl Zipper<N, T>,
+    ) -> Option<(U1, U2, U3, U4)> {
+        Some((
+            self.0.zip_with(&other.0, zipper)?,
+            self.1.zip_with(&other.1, zipper)?,
+            self.2.zip_with(&other.2, zipper)?,
+            self.3.zip_with(&other.3, zipper)?,
+        ))
+    }
+}
+
+impl<N, T, U1, U2, U3, U4, U5> Zip<N, T> for (U1, U2, U3, U4, U5)
+where
+    U1: Zip<N, T>,
+    U2: Zip<N, T>,
+    U3: Zip<N, T>,
+    U4: Zip<N, T>,
+    U5: Zip<N, T>,
+{
+    fn zip_structure(
+        &self,
+        other: &(U1, U2, U3, U4, U5),
+        zipper: &mut impl Zipper<N, T>,
+    ) -> Option<(U1, U2, U3, U4, U5)> {
+        Some((
+            self.0.zip_with(&other.0, zipper)?,
+            self.1.zip_with(&other.1, zipper)?,
+            self.2.zip_with(&other.2, zipper)?,
+            self.3.zip_with(&other.3, zipper)?,
+            self.4.zip_with(&other.4, zipper)?,
+        ))
+    }
+}
+
+impl<N, T, U: Zip<N, T>> Zip<N, T> for Vec<U> {
+    fn zip_structure(&self, other: &Vec<U>, zipper: &mut impl Zipper<N, T>) -> Option<Vec<U>> {
+        if self.len() != other.len() {
+            return None;
+        }
+
+        self.iter()
+            .zip(other)
+            .map(|(lhs, rhs)| lhs.zip_with(rhs, zipper))
+            .collect()
+    }
+}
+
+impl<N, T, U: Zip<N, T>> Zip<N, T> for SmallVec<U> {
+    fn zip_structure(
+        &self,
+        other: &SmallVec<U>,
+        zipper: &mut impl Zipper<N, T>,
+    ) -> Option<SmallVec<U>> {
+        if self.len() != other.len() {
+            return None;
+        }
+
+        self.iter()
+            .zip(other)
+            .map(|(lhs, rhs)| lhs.zip_with(rhs, zipper))
+            .collect()
+    }
+}
+
+impl<N, T, U: Zip<N, T>, const LEN: usize> Zip<N, T> for [U; LEN] {
+    fn zip_structure(&self, other: &[U; LEN], zipper: &mut impl Zipper<N, T>) -> Option<[U; LEN]> {
+        let mut elems = Vec::with_capacity(LEN);
+        for (lhs, rhs) in self.iter().zip(other) {
+            elems.push(lhs.zip_with(rhs, zipper)?);
+        }
+        elems.try_into().ok()
+    }
+}